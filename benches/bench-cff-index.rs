@@ -0,0 +1,57 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use allsorts::binary::read::ReadScope;
+use allsorts::cff::{Index, LazyIndex};
+
+const NUM_OBJECTS: usize = 20_000;
+
+/// Build an INDEX of `NUM_OBJECTS` objects of varying length, similar to a CharStrings INDEX.
+fn index_data() -> Vec<u8> {
+    let lengths = (0..NUM_OBJECTS).map(|i| 20 + i % 80).collect::<Vec<_>>();
+    let mut data = Vec::new();
+    data.extend_from_slice(&(NUM_OBJECTS as u16).to_be_bytes());
+    data.push(3); // off_size
+    let mut offset = 1u32;
+    data.extend_from_slice(&offset.to_be_bytes()[1..]);
+    for length in &lengths {
+        offset += *length as u32;
+        data.extend_from_slice(&offset.to_be_bytes()[1..]);
+    }
+    for (i, length) in lengths.iter().enumerate() {
+        data.extend(std::iter::repeat(i as u8).take(*length));
+    }
+    data
+}
+
+/// Ten glyph ids spread over the INDEX.
+fn glyph_ids() -> Vec<usize> {
+    (0..10).map(|i| (i * 7919) % NUM_OBJECTS).collect()
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let data = index_data();
+    let glyph_ids = glyph_ids();
+
+    c.bench_function("Index read 10 objects", |b| {
+        b.iter(|| {
+            let index = ReadScope::new(&data).read::<Index<'_>>().unwrap();
+            glyph_ids
+                .iter()
+                .map(|&glyph_id| index.read_object(glyph_id).unwrap().len())
+                .sum::<usize>()
+        })
+    });
+
+    c.bench_function("LazyIndex read 10 objects", |b| {
+        b.iter(|| {
+            let index = ReadScope::new(&data).read::<LazyIndex<'_>>().unwrap();
+            glyph_ids
+                .iter()
+                .map(|&glyph_id| index.read_object(glyph_id).unwrap().len())
+                .sum::<usize>()
+        })
+    });
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+use allsorts::binary::read::ReadScope;
+use allsorts::font_data_impl::CmapCache;
+use allsorts::tables::cmap::{read_cmap_subtable, Cmap};
+use allsorts::tables::{FontTableProvider, OpenTypeFile};
+use allsorts::tag;
+
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn text() -> String {
+    "The quick brown fox jumps over the lazy dog. ".repeat(100)
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fonts/arabic/amiri-regular.ttf");
+    let buffer = std::fs::read(path).unwrap();
+    let fontfile = ReadScope::new(&buffer).read::<OpenTypeFile>().unwrap();
+    let provider = fontfile.font_provider(0).unwrap();
+    let cmap_data = provider.read_table_data(tag::CMAP).unwrap();
+    let cmap = ReadScope::new(&cmap_data).read::<Cmap>().unwrap();
+    let text = text();
+
+    c.bench_function("map_glyph uncached", |b| {
+        b.iter(|| {
+            let _glyphs = text
+                .chars()
+                .map(|ch| {
+                    let (_, cmap_subtable) = read_cmap_subtable(&cmap).unwrap().unwrap();
+                    cmap_subtable.map_glyph(u32::from(ch)).unwrap()
+                })
+                .collect::<Vec<_>>();
+        })
+    });
+
+    c.bench_function("map_glyph cached", |b| {
+        b.iter(|| {
+            let cmap_cache = CmapCache::new(&cmap).unwrap().unwrap();
+            let _glyphs = text
+                .chars()
+                .map(|ch| cmap_cache.map_glyph(ch).unwrap())
+                .collect::<Vec<_>>();
+        })
+    });
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
@@ -1,10 +1,10 @@
 use allsorts::binary::read::ReadScope;
 use allsorts::error::{ParseError, ShapingError};
-use allsorts::font_data_impl::read_cmap_subtable;
-use allsorts::gpos::{gpos_apply, Info};
+use allsorts::gpos::{gpos_apply, kern_apply, Info};
 use allsorts::gsub::{gsub_apply_default, GlyphOrigin, GsubFeatureMask, RawGlyph};
 use allsorts::layout::{new_layout_cache, GDEFTable, LayoutTable, GPOS, GSUB};
-use allsorts::tables::cmap::{Cmap, CmapSubtable};
+use allsorts::tables::cmap::{read_cmap_subtable, Cmap, CmapSubtable};
+use allsorts::tables::kern::KernTable;
 use allsorts::tables::{MaxpTable, OffsetTable, OpenTypeFile, OpenTypeFont, TTCHeader};
 use allsorts::tag;
 
@@ -115,12 +115,19 @@ fn shape_ttf<'a>(
                     &gpos_cache,
                     opt_gdef_table.as_ref(),
                     kerning,
+                    None,
                     script_tag,
                     opt_lang_tag,
                     &mut infos,
                 )?;
             }
-            None => {}
+            None => {
+                if let Some(kern_scope) = ttf.read_table(&scope, tag::KERN)? {
+                    let kern_table = kern_scope.read::<KernTable<'_>>()?;
+                    let mut infos = Info::init_from_glyphs(opt_gdef_table.as_ref(), glyphs)?;
+                    kern_apply(&kern_table, &mut infos);
+                }
+            }
         }
     } else {
         println!("no GSUB table");
@@ -149,6 +156,7 @@ fn make_glyph(ch: char, glyph_index: u16) -> RawGlyph<()> {
         unicodes: tiny_vec![[char; 1] => ch],
         glyph_index: glyph_index,
         liga_component_pos: 0,
+        cluster: 0,
         glyph_origin: GlyphOrigin::Char(ch),
         small_caps: false,
         multi_subst_dup: false,
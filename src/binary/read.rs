@@ -8,14 +8,20 @@
 
 use crate::binary::{I16Be, I32Be, I64Be, U16Be, U24Be, U32Be, I8, U8};
 use crate::error::ParseError;
+#[cfg(feature = "std")]
 use crate::layout::{LayoutCache, LayoutTableType};
 use crate::size;
-use std::borrow::Cow;
-use std::cmp;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp;
+use core::fmt;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
 use std::collections::hash_map::Entry;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::fmt;
-use std::marker::PhantomData;
+#[cfg(feature = "std")]
 use std::rc::Rc;
 
 #[derive(Debug, Copy, Clone)]
@@ -58,6 +64,7 @@ pub struct ReadCtxt<'a> {
     offset: usize,
 }
 
+#[cfg(feature = "std")]
 pub struct ReadCache<T> {
     map: HashMap<usize, Rc<T>>,
 }
@@ -253,8 +260,21 @@ impl<'a> ReadScope<'a> {
         ReadScope { base, data }
     }
 
+    /// Returns the scope starting at `offset`, which is checked to be within this scope.
+    ///
+    /// Offsets read from font data may be corrupt, so `ParseError::BadOffset` is returned if
+    /// `offset` is not within this scope. Offsets into a structure of known length should be
+    /// taken from a scope bounded to that length with `offset_length`.
+    pub fn offset_checked(&self, offset: usize) -> Result<ReadScope<'a>, ParseError> {
+        if offset < self.data.len() {
+            Ok(self.offset(offset))
+        } else {
+            Err(ParseError::BadOffset)
+        }
+    }
+
     pub fn offset_length(&self, offset: usize, length: usize) -> Result<ReadScope<'a>, ParseError> {
-        if offset < self.data.len() || length == 0 {
+        if offset < self.data.len() || (length == 0 && offset == self.data.len()) {
             let data = &self.data[offset..];
             if length <= data.len() {
                 let base = self.base + offset;
@@ -280,6 +300,7 @@ impl<'a> ReadScope<'a> {
         self.ctxt().read_dep::<T>(args)
     }
 
+    #[cfg(feature = "std")]
     pub fn read_cache<'b, T>(
         &self,
         cache: &mut ReadCache<T::HostType>,
@@ -296,6 +317,7 @@ impl<'a> ReadScope<'a> {
         }
     }
 
+    #[cfg(feature = "std")]
     pub fn read_cache_state<'b, T, Table>(
         &self,
         cache: &mut ReadCache<T::HostType>,
@@ -315,6 +337,7 @@ impl<'a> ReadScope<'a> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<T> ReadCache<T> {
     pub fn new() -> Self {
         let map = HashMap::new();
@@ -922,4 +945,28 @@ mod tests {
         let scope = ReadScope::new(&[1, 2, 3]);
         assert_eq!(scope.read::<U24Be>().unwrap(), 0x10203);
     }
+
+    #[test]
+    fn test_offset_checked() {
+        let scope = ReadScope::new(&[1, 2, 3, 4]);
+        let offset_scope = scope.offset_checked(1).unwrap();
+        assert_eq!(offset_scope.data(), &[2, 3, 4]);
+        assert_eq!(offset_scope.ctxt().read_u16be().unwrap(), 0x0203);
+        assert_eq!(scope.offset_checked(4).err(), Some(ParseError::BadOffset));
+
+        // A structure of known length bounds the offsets into it
+        let structure = scope.offset_length(0, 3).unwrap();
+        assert_eq!(structure.offset_checked(2).unwrap().data(), &[3]);
+        assert_eq!(
+            structure.offset_checked(3).err(),
+            Some(ParseError::BadOffset)
+        );
+    }
+
+    #[test]
+    fn test_offset_length_empty() {
+        let scope = ReadScope::new(&[1, 2, 3]);
+        assert!(scope.offset_length(3, 0).unwrap().data().is_empty());
+        assert_eq!(scope.offset_length(4, 0).err(), Some(ParseError::BadOffset));
+    }
 }
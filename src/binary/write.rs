@@ -2,8 +2,9 @@
 
 //! Write binary data
 
-use std::iter;
-use std::marker::PhantomData;
+use alloc::vec::Vec;
+use core::iter;
+use core::marker::PhantomData;
 
 use crate::binary::read::{ReadArray, ReadArrayCow, ReadScope, ReadUnchecked};
 use crate::binary::{I16Be, I32Be, I64Be, U16Be, U24Be, U32Be, I8, U8};
@@ -155,6 +156,34 @@ pub trait WriteContext {
     ) -> Result<T::Output, WriteError>
     where
         T: WriteBinary<HostType>;
+
+    /// Return a placeholder for a big-endian `u16`, to be filled in later with `patch_u16`.
+    fn reserve_u16(&mut self) -> Result<Placeholder<U16Be, u16>, WriteError> {
+        self.placeholder::<U16Be, u16>()
+    }
+
+    /// Return a placeholder for a big-endian `u32`, to be filled in later with `patch_u32`.
+    fn reserve_u32(&mut self) -> Result<Placeholder<U32Be, u32>, WriteError> {
+        self.placeholder::<U32Be, u32>()
+    }
+
+    /// Consumes the `u16` placeholder and writes `value` into it.
+    fn patch_u16(
+        &mut self,
+        placeholder: Placeholder<U16Be, u16>,
+        value: u16,
+    ) -> Result<(), WriteError> {
+        self.write_placeholder(placeholder, value)
+    }
+
+    /// Consumes the `u32` placeholder and writes `value` into it.
+    fn patch_u32(
+        &mut self,
+        placeholder: Placeholder<U32Be, u32>,
+        value: u32,
+    ) -> Result<(), WriteError> {
+        self.write_placeholder(placeholder, value)
+    }
 }
 
 /// Write `T` into a `WriteBuffer` and return it
@@ -477,6 +506,7 @@ impl WriteCounter {
 mod tests {
     use super::*;
     use crate::tag;
+    use std::convert::TryFrom;
 
     struct TestTable {
         tag: u32,
@@ -537,6 +567,18 @@ mod tests {
         assert_eq!(ctxt.bytes(), &[1, 0, 2, 3]);
     }
 
+    #[test]
+    fn test_reserve_and_patch() {
+        let mut ctxt = WriteBuffer::new();
+        let length = ctxt.reserve_u32().unwrap();
+        let count = ctxt.reserve_u16().unwrap();
+        ctxt.write_bytes(b"data").unwrap();
+        let written = u32::try_from(ctxt.bytes_written()).unwrap();
+        ctxt.patch_u16(count, 4).unwrap();
+        ctxt.patch_u32(length, written).unwrap();
+        assert_eq!(ctxt.bytes(), &[0, 0, 0, 10, 0, 4, b'd', b'a', b't', b'a']);
+    }
+
     #[test]
     fn test_write_placeholder_overflow() {
         // Test that trying to write more data than reserved results in an error
@@ -3,6 +3,7 @@
 //! Refer to [Technical Note #5176](http://wwwimages.adobe.com/content/dam/Adobe/en/devnet/font/pdfs/5176.CFF.pdf)
 //! for more information.
 
+use std::collections::{BTreeSet, HashMap};
 use std::convert::{TryFrom, TryInto};
 use std::iter;
 use std::marker::PhantomData;
@@ -11,6 +12,7 @@ use std::mem;
 use byteorder::{BigEndian, ByteOrder};
 use itertools::Itertools;
 use lazy_static::lazy_static;
+use log::{debug, log_enabled, Level};
 use num_traits as num;
 
 use crate::binary::read::{
@@ -21,8 +23,12 @@ use crate::binary::write::{WriteBinary, WriteBinaryDep, WriteBuffer, WriteContex
 use crate::binary::{I16Be, I32Be, U16Be, U24Be, U32Be, U8};
 use crate::error::{ParseError, WriteError};
 
+pub mod charstring;
+
 // CFF Spec: An operator may be preceded by up to a maximum of 48 operands.
 const MAX_OPERANDS: usize = 48;
+/// The maximum stack depth of a CFF2 DICT, which is larger to allow for blend operands
+const MAX_CFF2_OPERANDS: usize = 513;
 
 const OPERAND_ZERO: [Operand; 1] = [Operand::Integer(0)];
 const OFFSET_ZERO: [Operand; 1] = [Operand::Offset(0)];
@@ -68,13 +74,24 @@ const IDENTITY: &[u8] = b"Identity";
 #[derive(Clone)]
 pub struct CFF<'a> {
     pub header: Header,
-    pub name_index: Index<'a>,
+    pub name_index: MaybeOwnedIndex<'a>,
     pub string_index: MaybeOwnedIndex<'a>,
     pub global_subr_index: Index<'a>,
     pub fonts: Vec<Font<'a>>,
 }
 
+/// Map of PostScript glyph names to glyph ids for a Type 1 font within a CFF font set
+///
+/// Built once from a `CFF` for repeated lookups. The map is not updated if the `CFF` it was built
+/// from is modified afterwards.
+#[derive(Clone, Debug)]
+pub struct GlyphNameMap {
+    names: HashMap<String, u16>,
+}
+
 /// CFF Font Header described in Section 6 of Technical Note #5176
+///
+/// When written, `hdr_size` and `off_size` are always 4.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Header {
     pub major: u8,
@@ -92,12 +109,29 @@ pub struct Index<'a> {
     data_array: &'a [u8],
 }
 
+/// A CFF INDEX that checks the offsets of each object as it is read
+///
+/// Reading an `Index` checks that the data covers every object, which are then sliced without
+/// further checks. A `LazyIndex` only checks the last offset up front, which suits reading a few
+/// objects from a large INDEX, such as the CharStrings of a handful of glyphs, and corrupt offsets
+/// only affect the objects they belong to.
+#[derive(Clone)]
+pub struct LazyIndex<'a> {
+    pub count: usize,
+    off_size: u8,
+    offset_array: &'a [u8],
+    /// The data following the offset array, up to the last offset
+    data: &'a [u8],
+}
+
 /// A single font within a CFF file
 #[derive(Clone)]
 pub struct Font<'a> {
     pub top_dict: TopDict,
     pub char_strings_index: MaybeOwnedIndex<'a>,
     pub charset: Charset<'a>,
+    /// The format of the CharStrings, from the `CharstringType` Top DICT operator
+    pub charstring_type: i32,
     pub data: CFFVariant<'a>,
 }
 
@@ -159,6 +193,7 @@ mod owned {
 pub enum CFFVariant<'a> {
     CID(CIDData<'a>),
     Type1(Type1Data<'a>),
+    Synthetic(SyntheticData<'a>),
 }
 
 #[derive(Clone)]
@@ -167,6 +202,8 @@ pub struct CIDData<'a> {
     pub private_dicts: Vec<PrivateDict>,
     pub local_subr_indices: Vec<Option<Index<'a>>>,
     pub fd_select: FDSelect<'a>,
+    /// The `CIDFontType` from the Top DICT, 0 unless the font uses Type 1 based CharStrings
+    pub cid_font_type: i32,
 }
 
 pub struct CIDDataOffsets {
@@ -181,6 +218,17 @@ pub struct Type1Data<'a> {
     pub local_subr_index: Option<Index<'a>>,
 }
 
+/// Data for a synthetic font, which is derived from a base font in the same font set.
+///
+/// Synthetic fonts share the CharStrings, charset, and Private DICT of their base font so the
+/// `char_strings_index` and `charset` of a synthetic `Font` are empty.
+#[derive(Clone)]
+pub struct SyntheticData<'a> {
+    /// The index of the base font within the font set
+    pub base_font_index: usize,
+    pub encoding: Encoding<'a>,
+}
+
 pub struct Type1DataOffsets {
     pub custom_encoding: Option<usize>,
     pub private_dict: usize,
@@ -237,6 +285,32 @@ pub struct Range<F, N> {
     pub n_left: N,
 }
 
+/// Glyphs with identical CharStrings, as found by `Font::dedupe_charstrings`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DuplicateCharStrings {
+    /// `(glyph_id, first_glyph_id)` pairs, where the CharString of `glyph_id` is identical to that
+    /// of the earlier glyph `first_glyph_id`
+    pub glyphs: Vec<(u16, u16)>,
+    /// The total length of the duplicated CharStrings in bytes
+    pub bytes: usize,
+}
+
+/// Common font metadata from a Top DICT, as returned by `Font::font_info`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontInfo {
+    pub version: Option<String>,
+    pub notice: Option<String>,
+    pub copyright: Option<String>,
+    pub full_name: Option<String>,
+    pub family_name: Option<String>,
+    pub weight: Option<String>,
+    pub is_fixed_pitch: bool,
+    pub italic_angle: f64,
+    pub underline_position: f64,
+    pub underline_thickness: f64,
+    pub font_matrix: [f64; 6],
+}
+
 /// A CFF DICT described in Section 4 of Technical Note #5176
 #[derive(Debug, PartialEq, Clone)]
 pub struct Dict<T>
@@ -284,6 +358,8 @@ pub enum FDSelect<'a> {
     },
     // Formats 1 and 2 are not defined
     Format3 {
+        /// `first` is the first glyph of the range and `n_left` is its Font DICT index. The range
+        /// extends up to the `first` glyph of the next range, or `sentinel` for the last range.
         ranges: ReadArrayCow<'a, Range<u16, u8>>,
         sentinel: u16,
     },
@@ -334,6 +410,12 @@ pub enum Operator {
     Subrs = 19,
     DefaultWidthX = 20,
     NominalWidthX = 21,
+    /// CFF2 only
+    VSIndex = 22,
+    /// CFF2 only
+    Blend = 23,
+    /// CFF2 only
+    VStore = 24,
     Copyright = op2(0),
     IsFixedPitch = op2(1),
     ItalicAngle = op2(2),
@@ -387,25 +469,42 @@ impl<'a> ReadBinary<'a> for CFF<'a> {
 
         let mut fonts = Vec::with_capacity(name_index.count);
         for font_index in 0..name_index.count {
+            let name = name_index
+                .read_object(font_index)
+                .ok_or(ParseError::BadIndex)?;
+            ctxt.check(is_valid_font_name(name))?;
+
             let top_dict = top_dict_index.read::<TopDict>(font_index)?;
+            let charstring_type = read_charstring_type(&top_dict)?;
+
+            if let Some(Operator::SyntheticBase) = top_dict.first_operator() {
+                let synthetic_data =
+                    read_synthetic_data(&scope, &top_dict, font_index, name_index.count)?;
+                fonts.push(Font {
+                    top_dict,
+                    char_strings_index: MaybeOwnedIndex::Owned(owned::Index { data: Vec::new() }),
+                    charset: Charset::ISOAdobe,
+                    charstring_type,
+                    data: CFFVariant::Synthetic(synthetic_data),
+                });
+                continue;
+            }
 
             // CharStrings index
             let offset = top_dict
                 .get_i32(Operator::CharStrings)
                 .unwrap_or(Err(ParseError::MissingValue))?;
-            let char_strings_index = scope.offset(usize::try_from(offset)?).read::<Index<'_>>()?;
+            let char_strings_index =
+                offset_scope(&scope, usize::try_from(offset)?)?.read::<Index<'_>>()?;
 
             // The Top DICT begins with the SyntheticBase and ROS operators
             // for synthetic and CIDFonts, respectively. Regular Type 1 fonts
-            // begin with some other operator.
+            // begin with some other operator. Synthetic fonts are handled above.
             let data = match top_dict.first_operator() {
                 Some(Operator::ROS) => {
                     let cid_data = read_cid_data(&scope, &top_dict, char_strings_index.count)?;
                     CFFVariant::CID(cid_data)
                 }
-                Some(Operator::SyntheticBase) => {
-                    return Err(ParseError::NotImplemented);
-                }
                 Some(_) => {
                     let (private_dict, private_dict_offset) = top_dict.read_private_dict(&scope)?;
                     let local_subr_index =
@@ -427,13 +526,14 @@ impl<'a> ReadBinary<'a> for CFF<'a> {
                 top_dict,
                 char_strings_index: MaybeOwnedIndex::Borrowed(char_strings_index),
                 charset,
+                charstring_type,
                 data,
             });
         }
 
         Ok(CFF {
             header,
-            name_index,
+            name_index: MaybeOwnedIndex::Borrowed(name_index),
             string_index: MaybeOwnedIndex::Borrowed(string_index),
             global_subr_index,
             fonts,
@@ -446,7 +546,7 @@ impl<'a> WriteBinary<&Self> for CFF<'a> {
 
     fn write<C: WriteContext>(ctxt: &mut C, cff: &CFF<'a>) -> Result<(), WriteError> {
         Header::write(ctxt, &cff.header)?;
-        Index::write(ctxt, &cff.name_index)?;
+        MaybeOwnedIndex::write(ctxt, &cff.name_index)?;
         let top_dicts = cff.fonts.iter().map(|font| &font.top_dict).collect_vec();
         let top_dict_index_length =
             Index::calculate_size::<TopDict, _>(top_dicts.as_slice(), DictDelta::new())?;
@@ -457,17 +557,21 @@ impl<'a> WriteBinary<&Self> for CFF<'a> {
         // Collect Top DICT deltas now that we know the offsets to other items in the DICT
         let mut top_dict_deltas = vec![DictDelta::new(); cff.fonts.len()];
         for (font, mut top_dict_delta) in cff.fonts.iter().zip(top_dict_deltas.iter_mut()) {
-            top_dict_delta.push_offset(Operator::CharStrings, i32::try_from(ctxt.bytes_written())?);
-            MaybeOwnedIndex::write(ctxt, &font.char_strings_index)?;
-
-            match &font.charset {
-                Charset::ISOAdobe => top_dict_delta.push_offset(Operator::Charset, 0),
-                Charset::Expert => top_dict_delta.push_offset(Operator::Charset, 1),
-                Charset::ExpertSubset => top_dict_delta.push_offset(Operator::Charset, 2),
-                Charset::Custom(custom) => {
-                    top_dict_delta
-                        .push_offset(Operator::Charset, i32::try_from(ctxt.bytes_written())?);
-                    CustomCharset::write(ctxt, custom)?;
+            // Synthetic fonts use the CharStrings and charset of their base font
+            if !font.is_synthetic() {
+                top_dict_delta
+                    .push_offset(Operator::CharStrings, i32::try_from(ctxt.bytes_written())?);
+                MaybeOwnedIndex::write(ctxt, &font.char_strings_index)?;
+
+                match &font.charset {
+                    Charset::ISOAdobe => top_dict_delta.push_offset(Operator::Charset, 0),
+                    Charset::Expert => top_dict_delta.push_offset(Operator::Charset, 1),
+                    Charset::ExpertSubset => top_dict_delta.push_offset(Operator::Charset, 2),
+                    Charset::Custom(custom) => {
+                        top_dict_delta
+                            .push_offset(Operator::Charset, i32::try_from(ctxt.bytes_written())?);
+                        CustomCharset::write(ctxt, custom)?;
+                    }
                 }
             }
             write_cff_variant(ctxt, &font.data, &mut top_dict_delta)?;
@@ -496,38 +600,201 @@ impl<'a> WriteBinary<&Self> for CFF<'a> {
     }
 }
 
+impl GlyphNameMap {
+    /// Build the map of glyph names for the font at `font_index` in `cff`
+    ///
+    /// Returns `None` if there is no font at `font_index` or it is not a Type 1 font. The charset
+    /// of a CID-keyed font maps glyphs to CIDs, not names. When several glyphs have the same name
+    /// the lowest glyph id is used.
+    pub fn new(cff: &CFF<'_>, font_index: usize) -> Option<Self> {
+        let font = cff.fonts.get(font_index)?;
+        match font.data {
+            CFFVariant::Type1(_) => {}
+            CFFVariant::CID(_) | CFFVariant::Synthetic(_) => return None,
+        }
+        let mut names = HashMap::new();
+        for glyph_id in (0..=std::u16::MAX).take(font.char_strings_index.len()) {
+            if let Some(name) = cff.glyph_name(font, glyph_id) {
+                names.entry(name).or_insert(glyph_id);
+            }
+        }
+        Some(GlyphNameMap { names })
+    }
+
+    /// Look up the glyph id of the glyph with the PostScript name `name`
+    pub fn gid_for_name(&self, name: &str) -> Option<u16> {
+        self.names.get(name).copied()
+    }
+}
+
 impl<'a> CFF<'a> {
     /// Read a string with the given SID from the String INDEX
     pub fn read_string(&self, sid: SID) -> Result<String, ParseError> {
         read_string_index_string(&self.string_index, sid)
     }
 
+    /// Returns the Registry, Ordering, and Supplement of the CID-keyed font at `font_index`
+    ///
+    /// Returns `None` if there is no font at `font_index` or it is not CID-keyed.
+    pub fn ros(&self, font_index: usize) -> Option<Result<(String, String, u16), ParseError>> {
+        let font = self.fonts.get(font_index)?;
+        if !font.is_cid_keyed() {
+            return None;
+        }
+
+        let ros = match font.top_dict.get(Operator::ROS) {
+            Some(ros) => ros,
+            None => return Some(Err(ParseError::MissingValue)),
+        };
+        Some(self.read_ros(ros))
+    }
+
+    /// Set the `Notice` string in the Top DICT of the fonts in this font set
+    ///
+    /// `text` is added to the String INDEX if not already present. An empty `text` removes the
+    /// `Notice` instead.
+    pub fn set_notice(&mut self, text: &str) -> Result<(), ParseError> {
+        self.set_top_dict_string(Operator::Notice, text)
+    }
+
+    /// Set the `Copyright` string in the Top DICT of the fonts in this font set
+    ///
+    /// `text` is added to the String INDEX if not already present. An empty `text` removes the
+    /// `Copyright` instead.
+    pub fn set_copyright(&mut self, text: &str) -> Result<(), ParseError> {
+        self.set_top_dict_string(Operator::Copyright, text)
+    }
+
+    fn set_top_dict_string(&mut self, operator: Operator, text: &str) -> Result<(), ParseError> {
+        if text.is_empty() {
+            for font in &mut self.fonts {
+                font.top_dict.remove(operator);
+            }
+            return Ok(());
+        }
+
+        let sid = string_sid(&mut self.string_index, text.as_bytes())?;
+        for font in &mut self.fonts {
+            font.top_dict
+                .set(operator, vec![Operand::Integer(i32::from(sid))]);
+        }
+        Ok(())
+    }
+
+    /// Returns the number of glyphs in the font at `font_index`
+    ///
+    /// Returns `None` if there is no font at `font_index`.
+    pub fn glyph_count(&self, font_index: usize) -> Option<usize> {
+        self.fonts
+            .get(font_index)
+            .map(|font| font.char_strings_index.len())
+    }
+
+    /// Returns whether the font at `font_index` is CID-keyed
+    ///
+    /// Returns `None` if there is no font at `font_index`.
+    pub fn is_cid(&self, font_index: usize) -> Option<bool> {
+        self.fonts.get(font_index).map(Font::is_cid_keyed)
+    }
+
+    /// Returns the PostScript name of the font at `font_index` from the Name INDEX
+    ///
+    /// Returns `None` if there is no name for `font_index`, the font has been deleted from the
+    /// font set, or the name contains characters that are not allowed in a font name.
+    pub fn font_name(&self, font_index: usize) -> Option<String> {
+        let name = self.name_index.read_object(font_index)?;
+        if name.first() == Some(&0) || !is_valid_font_name(name) {
+            return None;
+        }
+        String::from_utf8(name.to_vec()).ok()
+    }
+
+    fn read_ros(&self, operands: &[Operand]) -> Result<(String, String, u16), ParseError> {
+        match operands {
+            [Operand::Integer(registry), Operand::Integer(ordering), Operand::Integer(supplement)] =>
+            {
+                let registry = self.read_string(SID::try_from(*registry)?)?;
+                let ordering = self.read_string(SID::try_from(*ordering)?)?;
+                let supplement = u16::try_from(*supplement)?;
+                Ok((registry, ordering, supplement))
+            }
+            _ => Err(ParseError::BadValue),
+        }
+    }
+
+    /// Look up the glyph id of the glyph with the PostScript name `name` in the first font.
+    ///
+    /// Returns `None` if there is no glyph with that name, or if the font is not a Type 1 font.
+    /// The charset of a CID-keyed font maps glyphs to CIDs, not names. Each call scans the
+    /// charset, use a `GlyphNameMap` for repeated lookups.
+    pub fn gid_for_name(&self, name: &str) -> Option<u16> {
+        let font = self.fonts.first()?;
+        match font.data {
+            CFFVariant::Type1(_) => {}
+            CFFVariant::CID(_) | CFFVariant::Synthetic(_) => return None,
+        }
+        (0..=std::u16::MAX)
+            .take(font.char_strings_index.len())
+            .find(|&glyph_id| {
+                self.glyph_name(font, glyph_id)
+                    .map_or(false, |glyph_name| glyph_name == name)
+            })
+    }
+
+    /// The name of glyph `glyph_id` in `font`, a Type 1 font in this font set
+    fn glyph_name(&self, font: &Font<'a>, glyph_id: u16) -> Option<String> {
+        let sid = font.charset.id_for_glyph(glyph_id)?;
+        self.read_string(sid).ok()
+    }
+
     /// The `Vec<u16>` in the output is a mapping from new to old glyph id.
     ///
+    /// `font_index` is the index of the font within the CFF font set to subset. The output
+    /// contains only this font.
+    ///
     /// `glpyh_ids` contains the ids of the glyphs to retain.
     /// When subsetting a Type 1 CFF font and retaining more than 255 glyphs the
     /// `convert_cff_to_cid_if_more_than_255_glyphs` argument controls whether the Type 1 font
     /// is converted to a CID keyed font in the process. The primary motivation for this is
     /// broader compatibility, especially if the subset font is embedded in a PDF.
     ///
+    /// If the font has the default `FontBBox` of `[0 0 0 0]` the bounding box of the retained
+    /// glyph outlines is written to the Top DICT of the subset font instead.
+    ///
     /// **Known Limitations**
     ///
     /// Currently the subsetting process does not produce the smallest possible output font.
     /// There are various parts of the source font that are copied to the output font as-is.
-    /// Specifically the subsetting process does not subset the String INDEX, or the Local or
-    /// Global subroutines.
+    /// Specifically the subsetting process does not subset the Local or Global subroutines.
     ///
-    /// Subsetting the String INDEX requires updating all String IDs (SID) in the font so
-    /// that they point at their new position in the String INDEX. Subsetting the subroutines
-    /// requires parsing the CharStrings, which describe the glyph outlines. The CharStrings
-    /// format is non-trivial so this has been left for now.
+    /// Subsetting the subroutines requires parsing the CharStrings, which describe the glyph
+    /// outlines. The CharStrings format is non-trivial so this has been left for now.
+    ///
+    /// Subsetting synthetic fonts is not supported.
     pub fn subset(
         &self,
+        font_index: usize,
         glyph_ids: &[u16],
         convert_cff_to_cid_if_more_than_255_glyphs: bool,
     ) -> Result<(Self, Vec<u16>), ParseError> {
-        let mut cff = self.to_owned();
+        let font = self.fonts.get(font_index).ok_or(ParseError::BadIndex)?;
+        let name = self
+            .name_index
+            .read_object(font_index)
+            .ok_or(ParseError::BadIndex)?;
+        let mut cff = CFF {
+            header: self.header.clone(),
+            name_index: MaybeOwnedIndex::Owned(owned::Index {
+                data: vec![name.to_owned()],
+            }),
+            string_index: self.string_index.clone(),
+            global_subr_index: self.global_subr_index.clone(),
+            fonts: vec![font.clone()],
+        };
         let font: &mut Font<'_> = &mut cff.fonts[0];
+        if font.is_synthetic() {
+            return Err(ParseError::NotImplemented);
+        }
         let mut charset = Vec::with_capacity(glyph_ids.len());
         let mut fd_select = Vec::with_capacity(glyph_ids.len());
         let mut new_to_old_id = Vec::with_capacity(glyph_ids.len());
@@ -553,15 +820,13 @@ impl<'a> CFF<'a> {
             match &font.data {
                 CFFVariant::CID(cid) => {
                     // Find out which font DICT this glyph maps to if it's a CID font
-                    // Need to know which font DICT applies to each glyph, then ideally work out which FDSelect
-                    // format is the best to use. For now it's probably good enough to just use format 0
                     let fd_index = cid
                         .fd_select
                         .font_dict_index(glyph_id)
                         .ok_or(ParseError::BadIndex)?;
                     fd_select.push(fd_index);
                 }
-                CFFVariant::Type1(_type1) => {}
+                CFFVariant::Type1(_) | CFFVariant::Synthetic(_) => {}
             }
         }
 
@@ -570,17 +835,11 @@ impl<'a> CFF<'a> {
         if font.is_cid_keyed() {
             // Update CID/Type 1 specific structures
             match &mut font.data {
-                CFFVariant::CID(cid) => {
-                    cid.fd_select = FDSelect::Format0 {
-                        glyph_font_dict_indices: ReadArrayCow::Owned(fd_select),
-                    }
-                }
-                CFFVariant::Type1(_type1) => {}
+                CFFVariant::CID(cid) => cid.fd_select = FDSelect::from_glyph_indices(&fd_select),
+                CFFVariant::Type1(_) | CFFVariant::Synthetic(_) => {}
             }
 
-            font.charset = Charset::Custom(CustomCharset::Format0 {
-                glyphs: ReadArrayCow::Owned(charset),
-            });
+            font.charset = Charset::Custom(CustomCharset::from_sids(&charset));
         } else if convert_cff_to_cid_if_more_than_255_glyphs && font.char_strings_index.len() > 255
         {
             font.charset = convert_type1_to_cid(&mut cff.string_index, font)?;
@@ -595,14 +854,163 @@ impl<'a> CFF<'a> {
                 // fonts. So this branch is only taken for Type 1 fonts.
                 font.charset = Charset::ISOAdobe;
             } else {
-                font.charset = Charset::Custom(CustomCharset::Format0 {
-                    glyphs: ReadArrayCow::Owned(charset),
-                });
+                font.charset = Charset::Custom(CustomCharset::from_sids(&charset));
+            }
+        }
+
+        // Many fonts have the default FontBBox of [0 0 0 0], in which case the actual value is
+        // computed from the outlines of the retained glyphs
+        if font.top_dict.get_with_default(Operator::FontBBox) == Some(DEFAULT_BBOX.as_ref()) {
+            if let Ok(bbox) = font.compute_font_bbox(&cff.global_subr_index) {
+                font.top_dict.remove(Operator::FontBBox);
+                font.top_dict.inner_mut().push((
+                    Operator::FontBBox,
+                    vec![
+                        Operand::Integer(i32::from(bbox.x_min)),
+                        Operand::Integer(i32::from(bbox.y_min)),
+                        Operand::Integer(i32::from(bbox.x_max)),
+                        Operand::Integer(i32::from(bbox.y_max)),
+                    ],
+                ));
+            }
+        }
+
+        if log_enabled!(Level::Debug) {
+            let duplicates = cff.fonts[0].dedupe_charstrings();
+            if !duplicates.glyphs.is_empty() {
+                debug!(
+                    "subset contains {} duplicate CharStrings totalling {} bytes",
+                    duplicates.glyphs.len(),
+                    duplicates.bytes
+                );
             }
         }
 
+        cff.subset_string_index()?;
+
         Ok((cff, new_to_old_id))
     }
+
+    /// Rebuild the String INDEX so that it only contains the strings referenced by the fonts.
+    ///
+    /// SIDs are referenced from the Top DICT, Font DICTs, Private DICTs, and for Type 1 fonts
+    /// the charset. All of these are updated to point at the new position of the string.
+    fn subset_string_index(&mut self) -> Result<(), ParseError> {
+        let mut sids = Vec::new();
+        for font in &self.fonts {
+            sids.extend(font.top_dict.sids()?);
+            match &font.data {
+                CFFVariant::CID(cid) => {
+                    for i in 0..cid.font_dict_index.len() {
+                        sids.extend(cid.font_dict(i)?.sids()?);
+                    }
+                    for private_dict in &cid.private_dicts {
+                        sids.extend(private_dict.sids()?);
+                    }
+                }
+                CFFVariant::Type1(type1) => {
+                    sids.extend(type1.private_dict.sids()?);
+                    // The charset of a CID-keyed font contains CIDs, not SIDs
+                    if let Charset::Custom(custom) = &font.charset {
+                        for glyph_id in 1..u16::try_from(font.char_strings_index.len())? {
+                            sids.push(custom.id_for_glyph(glyph_id).ok_or(ParseError::BadIndex)?);
+                        }
+                    }
+                }
+                CFFVariant::Synthetic(_) => {}
+            }
+        }
+
+        let sid_map = remap_sids(&sids);
+        let mut new_strings = vec![Vec::new(); sid_map.len()];
+        for (&old_sid, &new_sid) in &sid_map {
+            let string = self
+                .string_index
+                .read_object(usize::from(old_sid) - STANDARD_STRINGS.len())
+                .ok_or(ParseError::BadIndex)?;
+            new_strings[usize::from(new_sid) - STANDARD_STRINGS.len()] = string.to_owned();
+        }
+
+        for font in &mut self.fonts {
+            font.top_dict.remap_sids(&sid_map)?;
+            match &mut font.data {
+                CFFVariant::CID(cid) => {
+                    let mut font_dicts = Vec::with_capacity(cid.font_dict_index.len());
+                    for i in 0..cid.font_dict_index.len() {
+                        let mut font_dict = cid.font_dict(i)?;
+                        font_dict.remap_sids(&sid_map)?;
+                        let mut buffer = WriteBuffer::new();
+                        FontDict::write_dep(&mut buffer, &font_dict, DictDelta::new())
+                            .map_err(|_err| ParseError::BadValue)?;
+                        font_dicts.push(buffer.into_inner());
+                    }
+                    cid.font_dict_index = MaybeOwnedIndex::Owned(owned::Index { data: font_dicts });
+                    for private_dict in &mut cid.private_dicts {
+                        private_dict.remap_sids(&sid_map)?;
+                    }
+                }
+                CFFVariant::Type1(type1) => {
+                    type1.private_dict.remap_sids(&sid_map)?;
+                    if let Charset::Custom(custom) = &font.charset {
+                        // .notdef is implied so the charset starts at glyph 1
+                        let glyphs = (1..u16::try_from(font.char_strings_index.len())?)
+                            .map(|glyph_id| {
+                                let sid =
+                                    custom.id_for_glyph(glyph_id).ok_or(ParseError::BadIndex)?;
+                                remap_sid(&sid_map, sid)
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        font.charset = Charset::Custom(CustomCharset::from_sids(&glyphs));
+                    }
+                }
+                CFFVariant::Synthetic(_) => {}
+            }
+        }
+
+        self.string_index = MaybeOwnedIndex::Owned(owned::Index { data: new_strings });
+
+        Ok(())
+    }
+}
+
+/// Build a mapping from the old to new SID for the custom strings referenced by `sids`.
+///
+/// Standard strings (SID < 391) are not present in the String INDEX so they are not included in
+/// the mapping. The relative order of the custom strings is preserved.
+fn remap_sids(sids: &[SID]) -> HashMap<SID, SID> {
+    let n_std_strings = STANDARD_STRINGS.len() as SID;
+    sids.iter()
+        .copied()
+        .filter(|&sid| sid >= n_std_strings)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .zip(n_std_strings..)
+        .collect()
+}
+
+/// Look up the new value of `sid` in `sid_map`, standard strings are returned unchanged.
+fn remap_sid(sid_map: &HashMap<SID, SID>, sid: SID) -> Result<SID, ParseError> {
+    if usize::from(sid) < STANDARD_STRINGS.len() {
+        Ok(sid)
+    } else {
+        sid_map.get(&sid).copied().ok_or(ParseError::BadIndex)
+    }
+}
+
+/// Returns whether `name` is a valid entry in the Name INDEX
+///
+/// Section 7 of Technical Note #5176 restricts font names to printable ASCII, excluding
+/// whitespace and the PostScript delimiters `[](){}<>/%`. A name starting with a 0 byte marks a
+/// font that has been deleted from the font set and may contain anything after it.
+fn is_valid_font_name(name: &[u8]) -> bool {
+    match name.split_first() {
+        Some((0, _)) => true,
+        _ => name.iter().all(|&b| match b {
+            b'[' | b']' | b'(' | b')' | b'{' | b'}' | b'<' | b'>' | b'/' | b'%' => false,
+            33..=126 => true,
+            _ => false,
+        }),
+    }
 }
 
 /// Read a string with the given SID from the String INDEX
@@ -626,6 +1034,23 @@ fn read_string_index_string<'a>(
     }
 }
 
+/// Returns the SID of `string`, adding it to the end of `string_index` if it's not a standard
+/// string or in the String INDEX already.
+fn string_sid(string_index: &mut MaybeOwnedIndex<'_>, string: &[u8]) -> Result<SID, ParseError> {
+    if let Some(sid) = STANDARD_STRINGS
+        .iter()
+        .position(|standard| standard.as_bytes() == string)
+    {
+        return Ok(SID::try_from(sid)?);
+    }
+
+    let index = match string_index.index(string) {
+        Some(index) => index,
+        None => string_index.push(string.to_owned()),
+    };
+    Ok(SID::try_from(STANDARD_STRINGS.len() + index)?)
+}
+
 fn convert_type1_to_cid<'a>(
     string_index: &mut MaybeOwnedIndex<'a>,
     font: &mut Font<'a>,
@@ -634,16 +1059,8 @@ fn convert_type1_to_cid<'a>(
 
     // Retrieve the SIDs of Adobe and Identity, adding them if they're not in the String INDEX
     // already.
-    let (adobe_sid, identity_sid) = match (string_index.index(ADOBE), string_index.index(IDENTITY))
-    {
-        (Some(adobe_sid), Some(identity_sid)) => (adobe_sid, identity_sid),
-        (Some(adobe_sid), None) => (adobe_sid, string_index.push(IDENTITY.to_owned())),
-        (None, Some(identity_sid)) => (string_index.push(ADOBE.to_owned()), identity_sid),
-        (None, None) => (
-            string_index.push(ADOBE.to_owned()),
-            string_index.push(IDENTITY.to_owned()),
-        ),
-    };
+    let adobe_sid = string_sid(string_index, ADOBE)?;
+    let identity_sid = string_sid(string_index, IDENTITY)?;
 
     // Build Font DICT
     let mut font_dict = FontDict::new();
@@ -672,15 +1089,16 @@ fn convert_type1_to_cid<'a>(
         private_dicts: Vec::new(),
         local_subr_indices: Vec::new(),
         fd_select,
+        cid_font_type: 0,
     });
 
     // Swap Type1 data with CID data
     let type1_data = match mem::replace(&mut font.data, cid_data) {
         CFFVariant::Type1(data) => data,
-        CFFVariant::CID(_) => unreachable!(),
+        CFFVariant::CID(_) | CFFVariant::Synthetic(_) => unreachable!(),
     };
     match &mut font.data {
-        CFFVariant::Type1(_type1) => unreachable!(),
+        CFFVariant::Type1(_) | CFFVariant::Synthetic(_) => unreachable!(),
         CFFVariant::CID(cid) => {
             cid.private_dicts = vec![type1_data.private_dict];
             cid.local_subr_indices = vec![type1_data.local_subr_index];
@@ -689,8 +1107,8 @@ fn convert_type1_to_cid<'a>(
 
     // Update the Top DICT
     // Add ROS
-    let registry = Operand::Integer(i32::try_from(adobe_sid)?);
-    let ordering = Operand::Integer(i32::try_from(identity_sid)?);
+    let registry = Operand::Integer(i32::from(adobe_sid));
+    let ordering = Operand::Integer(i32::from(identity_sid));
     let supplement = Operand::Integer(0);
     let ros = (Operator::ROS, vec![registry, ordering, supplement]);
     font.top_dict.inner_mut().insert(0, ros);
@@ -769,7 +1187,10 @@ impl WriteBinary<&Self> for Header {
         // Any data between the header and the Name INDEX will have been discarded.
         // So the size will always be 4 bytes.
         U8::write(ctxt, 4)?; // hdr_size
-        U8::write(ctxt, header.off_size)?;
+
+        // The absolute offsets in DICTs are always written as 4 byte integers, so the size of the
+        // offsets in the original font is not carried over.
+        U8::write(ctxt, 4)?; // off_size
 
         Ok(())
     }
@@ -778,39 +1199,46 @@ impl WriteBinary<&Self> for Header {
 impl<'a> ReadBinary<'a> for Index<'a> {
     type HostType = Self;
 
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let count = usize::from(ctxt.read_u16be()?);
+        read_index_data(ctxt, count)
+    }
+}
+
+impl<'a> ReadBinary<'a> for LazyIndex<'a> {
+    type HostType = Self;
+
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
         let count = usize::from(ctxt.read_u16be()?);
 
         if count > 0 {
             let off_size = ctxt.read_u8()?;
-            if off_size < 1 || off_size > 4 {
+            if !(1..=4).contains(&off_size) {
                 return Err(ParseError::BadValue);
             }
 
             let offset_array_size = (count + 1) * usize::from(off_size);
             let offset_array = ctxt.read_slice(offset_array_size)?;
 
-            let last_offset_index = lookup_offset_index(off_size, offset_array, count);
-            if last_offset_index < 1 {
+            // Skip over the data so that the INDEX can be followed by other structures
+            let last_offset = lookup_offset_index(off_size, offset_array, count);
+            if last_offset < 1 {
                 return Err(ParseError::BadValue);
             }
+            let data = ctxt.read_slice(last_offset - 1)?;
 
-            let data_array_size = last_offset_index - 1;
-            let data_array = ctxt.read_slice(data_array_size)?;
-
-            Ok(Index {
+            Ok(LazyIndex {
                 count,
                 off_size,
                 offset_array,
-                data_array,
+                data,
             })
         } else {
-            // count == 0
-            Ok(Index {
+            Ok(LazyIndex {
                 count,
                 off_size: 1,
                 offset_array: &[],
-                data_array: &[],
+                data: &[],
             })
         }
     }
@@ -851,30 +1279,103 @@ where
     type HostType = Self;
 
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self::HostType, ParseError> {
-        let mut dict = Vec::new();
-        let mut operands = Vec::new();
+        let dict = read_dict_entries(ctxt, None)?;
+
+        Ok(Dict {
+            dict,
+            default: PhantomData,
+        })
+    }
+}
+
+impl<T> Dict<T>
+where
+    T: DictDefault,
+{
+    /// Read a CFF2 DICT, which may contain the `vsindex` and `blend` operators
+    ///
+    /// `region_counts` is the number of regions of each Item Variation Data subtable of the
+    /// Variation Store, as selected by `vsindex`. Blended values are resolved to their value in
+    /// the default instance and the `blend` operator is not retained.
+    pub fn read_cff2(scope: ReadScope<'_>, region_counts: &[usize]) -> Result<Self, ParseError> {
+        let dict = read_dict_entries(&mut scope.ctxt(), Some(region_counts))?;
 
-        while ctxt.bytes_available() {
-            match Op::read(ctxt)? {
-                Op::Operator(operator) => {
+        Ok(Dict {
+            dict,
+            default: PhantomData,
+        })
+    }
+}
+
+/// Read the operators of a DICT along with their operands
+///
+/// `region_counts` is `Some` when reading a CFF2 DICT, see the `ReadBinaryDep` impl of `Dict`.
+fn read_dict_entries(
+    ctxt: &mut ReadCtxt<'_>,
+    region_counts: Option<&[usize]>,
+) -> Result<Vec<(Operator, Vec<Operand>)>, ParseError> {
+    let max_operands = if region_counts.is_some() {
+        MAX_CFF2_OPERANDS
+    } else {
+        MAX_OPERANDS
+    };
+    let mut dict = Vec::new();
+    let mut operands = Vec::new();
+    let mut vsindex = 0;
+
+    while ctxt.bytes_available() {
+        match Op::read(ctxt)? {
+            Op::Operator(operator) => match (operator, region_counts) {
+                // The CFF2 operators are reserved in CFF
+                (Operator::VSIndex, None) | (Operator::Blend, None) | (Operator::VStore, None) => {
+                    return Err(ParseError::BadValue)
+                }
+                (Operator::Blend, Some(region_counts)) => {
+                    let region_count = region_counts.get(vsindex).ok_or(ParseError::BadIndex)?;
+                    blend_default(&mut operands, *region_count)?;
+                }
+                _ => {
+                    if operator == Operator::VSIndex {
+                        vsindex = match operands.as_slice() {
+                            [Operand::Integer(index)] => usize::try_from(*index)?,
+                            _ => return Err(ParseError::BadValue),
+                        };
+                    }
                     integer_to_offset(operator, &mut operands);
                     dict.push((operator, operands.clone()));
                     operands.clear();
                 }
-                Op::Operand(operand) => {
-                    operands.push(operand);
-                    if operands.len() > MAX_OPERANDS {
-                        return Err(ParseError::LimitExceeded);
-                    }
+            },
+            Op::Operand(operand) => {
+                operands.push(operand);
+                if operands.len() > max_operands {
+                    return Err(ParseError::LimitExceeded);
                 }
             }
         }
-
-        Ok(Dict {
-            dict,
-            default: PhantomData,
-        })
     }
+
+    Ok(dict)
+}
+
+/// Replace the operands of a `blend` operator on `operands` with their default values
+///
+/// The operands of `blend` are `n` default values, `n * region_count` deltas, and `n`.
+fn blend_default(operands: &mut Vec<Operand>, region_count: usize) -> Result<(), ParseError> {
+    let n = match operands.pop() {
+        Some(Operand::Integer(n)) => usize::try_from(n)?,
+        _ => return Err(ParseError::BadValue),
+    };
+    let deltas_len = n
+        .checked_mul(region_count)
+        .ok_or(ParseError::LimitExceeded)?;
+    let defaults_start = operands
+        .len()
+        .checked_sub(n + deltas_len)
+        .ok_or(ParseError::BadValue)?;
+    operands.truncate(defaults_start + n);
+
+    Ok(())
 }
 
 fn offset_size(value: usize) -> Option<u8> {
@@ -899,7 +1400,8 @@ fn integer_to_offset(operator: Operator, operands: &mut [Operand]) {
         | (Operator::CharStrings, [Operand::Integer(offset)])
         | (Operator::Subrs, [Operand::Integer(offset)])
         | (Operator::FDArray, [Operand::Integer(offset)])
-        | (Operator::FDSelect, [Operand::Integer(offset)]) => {
+        | (Operator::FDSelect, [Operand::Integer(offset)])
+        | (Operator::VStore, [Operand::Integer(offset)]) => {
             operands[0] = Operand::Offset(*offset);
         }
         (Operator::Private, [Operand::Integer(length), Operand::Integer(offset)]) => {
@@ -957,7 +1459,7 @@ impl<'a> ReadBinary<'a> for Op {
         let b0 = ctxt.read_u8()?;
 
         match b0 {
-            0..=11 | 13..=21 => ok_operator(u16::from(b0).try_into().unwrap()), // NOTE(unwrap): Safe due to pattern
+            0..=11 | 13..=24 => ok_operator(u16::from(b0).try_into().unwrap()), // NOTE(unwrap): Safe due to pattern
             12 => ok_operator(u16::from(op2(ctxt.read_u8()?)).try_into()?),
             28 => {
                 let num = ctxt.read_i16be()?;
@@ -974,7 +1476,7 @@ impl<'a> ReadBinary<'a> for Op {
                 let b1 = ctxt.read_u8()?;
                 ok_int(-(i32::from(b0) - 251) * 256 - i32::from(b1) - 108)
             }
-            22..=27 | 31 | 255 => Err(ParseError::BadValue), // reserved
+            25..=27 | 31 | 255 => Err(ParseError::BadValue), // reserved
         }
     }
 }
@@ -1119,6 +1621,8 @@ where
 
 // TODO: Make these generic. Requires Rust stabilisation of the Step trait or its replacement.
 // https://doc.rust-lang.org/core/iter/trait.Step.html
+//
+// SID is an alias of u16 so this impl also covers Range<u16, u8>.
 impl<'a> Range<SID, u8> {
     pub fn iter(&self) -> impl Iterator<Item = SID> {
         let last = self.first + SID::from(self.n_left);
@@ -1174,6 +1678,50 @@ impl<'a> WriteBinary<&Self> for CustomEncoding<'a> {
     }
 }
 
+impl<'a> Encoding<'a> {
+    /// Returns the glyph id of the glyph mapped to the character `code`
+    ///
+    /// The Standard and Expert encodings map codes to SIDs, which are resolved to a glyph id
+    /// using the font's `charset`. Custom encodings map codes to glyph ids directly.
+    pub fn code_to_gid(&self, code: u8, charset: &Charset<'_>) -> Option<u16> {
+        let sid = match self {
+            Encoding::Standard => STANDARD_ENCODING[usize::from(code)],
+            Encoding::Expert => EXPERT_ENCODING[usize::from(code)],
+            Encoding::Custom(custom) => return custom.code_to_gid(code),
+        };
+
+        // SID 0 (.notdef) indicates that the code is not encoded
+        if sid == 0 {
+            None
+        } else {
+            charset.glyph_id_for_id(sid)
+        }
+    }
+}
+
+impl<'a> CustomEncoding<'a> {
+    /// Returns the glyph id of the glyph mapped to the character `code`
+    pub fn code_to_gid(&self, code: u8) -> Option<u16> {
+        // The encoding arrays begin with GID 1 as .notdef is not encoded
+        match self {
+            CustomEncoding::Format0 { codes } => codes
+                .iter()
+                .position(|glyph_code| glyph_code == code)
+                .and_then(|index| u16::try_from(index + 1).ok()),
+            CustomEncoding::Format1 { ranges } => {
+                let mut glyph_id = 1;
+                for range in ranges.iter() {
+                    if code >= range.first && usize::from(code - range.first) < range.len() {
+                        return u16::try_from(glyph_id + usize::from(code - range.first)).ok();
+                    }
+                    glyph_id += range.len();
+                }
+                None
+            }
+        }
+    }
+}
+
 impl<'a> Charset<'a> {
     /// Returns the id of the SID (Type 1 font) or CID (CID keyed font) of the name of the supplied glyph
     pub fn id_for_glyph(&self, glyph_id: u16) -> Option<u16> {
@@ -1191,6 +1739,38 @@ impl<'a> Charset<'a> {
             Charset::Custom(custom) => custom.id_for_glyph(glyph_id),
         }
     }
+
+    /// Returns the glyph id of the glyph with the supplied SID (Type 1 font) or CID (CID keyed
+    /// font)
+    fn glyph_id_for_id(&self, id: u16) -> Option<u16> {
+        match self {
+            Charset::ISOAdobe => {
+                if id <= ISO_ADOBE_LAST_SID {
+                    Some(id)
+                } else {
+                    None
+                }
+            }
+            Charset::Expert => EXPERT_CHARSET
+                .iter()
+                .position(|&sid| sid == id)
+                .and_then(|glyph_id| u16::try_from(glyph_id).ok()),
+            Charset::ExpertSubset => EXPERT_SUBSET_CHARSET
+                .iter()
+                .position(|&sid| sid == id)
+                .and_then(|glyph_id| u16::try_from(glyph_id).ok()),
+            Charset::Custom(custom) => {
+                let mut glyph_id = 0;
+                while let Some(glyph_id_id) = custom.id_for_glyph(glyph_id) {
+                    if glyph_id_id == id {
+                        return Some(glyph_id);
+                    }
+                    glyph_id = glyph_id.checked_add(1)?;
+                }
+                None
+            }
+        }
+    }
 }
 
 impl<'a> ReadBinaryDep<'a> for CustomCharset<'a> {
@@ -1250,6 +1830,62 @@ impl<'a> WriteBinary<&Self> for CustomCharset<'a> {
 }
 
 impl<'a> CustomCharset<'a> {
+    /// Build a `CustomCharset` from the SID (Type 1 font) or CID (CID keyed font) of each glyph
+    ///
+    /// `sids` starts at glyph 1 as the .notdef glyph is implied. Runs of consecutive SIDs are
+    /// stored as ranges (Format 1 or 2) when that is smaller than the per-glyph Format 0
+    /// representation.
+    pub fn from_sids(sids: &[SID]) -> Self {
+        let mut ranges: Vec<Range<SID, u16>> = Vec::new();
+        for &sid in sids {
+            match ranges.last_mut() {
+                Some(range)
+                    if u32::from(range.first) + u32::from(range.n_left) + 1 == u32::from(sid) =>
+                {
+                    range.n_left += 1
+                }
+                _ => ranges.push(Range {
+                    first: sid,
+                    n_left: 0,
+                }),
+            }
+        }
+
+        // Format 1 ranges can only cover 256 glyphs so longer runs are split
+        let format1_ranges = ranges
+            .iter()
+            .flat_map(|range| {
+                (0..=range.n_left / 256).map(move |i| {
+                    let offset = i * 256;
+                    Range {
+                        first: range.first + offset,
+                        n_left: (range.n_left - offset).min(255) as u8,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Format 0: 2 bytes per glyph
+        // Format 1: 3 bytes per range
+        // Format 2: 4 bytes per range
+        let format0_size = 2 * sids.len();
+        let format1_size = 3 * format1_ranges.len();
+        let format2_size = 4 * ranges.len();
+        if format1_size < format0_size && format1_size <= format2_size {
+            CustomCharset::Format1 {
+                ranges: ReadArrayCow::Owned(format1_ranges),
+            }
+        } else if format2_size < format0_size {
+            CustomCharset::Format2 {
+                ranges: ReadArrayCow::Owned(ranges),
+            }
+        } else {
+            CustomCharset::Format0 {
+                glyphs: ReadArrayCow::Owned(sids.to_vec()),
+            }
+        }
+    }
+
     pub fn iter(&'a self) -> Box<dyn Iterator<Item = u16> + 'a> {
         let notdef: Box<dyn Iterator<Item = u16>> = Box::new(iter::once(0));
         match &self {
@@ -1404,6 +2040,39 @@ impl<'a> PartialEq for FDSelect<'a> {
 }
 
 impl<'a> FDSelect<'a> {
+    /// Build an `FDSelect` from the Font DICT index of each glyph
+    ///
+    /// Format 3 is used if its ranges are smaller than the per-glyph Format 0 representation.
+    pub fn from_glyph_indices(glyph_font_dict_indices: &[u8]) -> Self {
+        let mut ranges: Vec<Range<u16, u8>> = Vec::new();
+        for (glyph_id, &fd_index) in glyph_font_dict_indices.iter().enumerate() {
+            match ranges.last() {
+                Some(range) if range.n_left == fd_index => {}
+                _ => match u16::try_from(glyph_id) {
+                    Ok(first) => ranges.push(Range {
+                        first,
+                        n_left: fd_index,
+                    }),
+                    Err(_) => break,
+                },
+            }
+        }
+
+        // Format 0: format + 1 byte per glyph
+        // Format 3: format + nRanges + 3 bytes per range + sentinel
+        let format0_size = 1 + glyph_font_dict_indices.len();
+        let format3_size = 1 + 2 + 3 * ranges.len() + 2;
+        match u16::try_from(glyph_font_dict_indices.len()) {
+            Ok(sentinel) if format3_size < format0_size => FDSelect::Format3 {
+                ranges: ReadArrayCow::Owned(ranges),
+                sentinel,
+            },
+            _ => FDSelect::Format0 {
+                glyph_font_dict_indices: ReadArrayCow::Owned(glyph_font_dict_indices.to_vec()),
+            },
+        }
+    }
+
     /// Returns the index of the Font DICT for the supplied `glyph_id`
     pub fn font_dict_index(&self, glyph_id: u16) -> Option<u8> {
         let index = usize::from(glyph_id);
@@ -1434,8 +2103,37 @@ impl<'a> FDSelect<'a> {
     }
 }
 
+impl<'a> LazyIndex<'a> {
+    /// Returns the object at `index`
+    ///
+    /// Returns `ParseError::BadIndex` if `index` is out of range, or `ParseError::BadOffset` or
+    /// `ParseError::BadEof` if the offsets of the object are invalid.
+    pub fn read_object(&self, index: usize) -> Result<&'a [u8], ParseError> {
+        if index >= self.count {
+            return Err(ParseError::BadIndex);
+        }
+
+        // Offsets are relative to the byte preceding the data so the first offset is 1
+        let start = lookup_offset_index(self.off_size, self.offset_array, index);
+        let end = lookup_offset_index(self.off_size, self.offset_array, index + 1);
+        if start < 1 || end < start {
+            return Err(ParseError::BadOffset);
+        }
+        self.data.get(start - 1..end - 1).ok_or(ParseError::BadEof)
+    }
+
+    pub fn read<T: ReadBinaryDep<'a, Args = ()>>(
+        &self,
+        index: usize,
+    ) -> Result<T::HostType, ParseError> {
+        let data = self.read_object(index)?;
+        ReadScope::new(data).read_dep::<T>(())
+    }
+}
+
 impl<'a> Index<'a> {
-    fn read_object(&self, index: usize) -> Option<&[u8]> {
+    /// Returns the object at `index`, or `None` if `index` is out of range
+    pub fn read_object(&self, index: usize) -> Option<&[u8]> {
         if index < self.count {
             let start_index = lookup_offset_index(self.off_size, self.offset_array, index) - 1;
             let end_index = lookup_offset_index(self.off_size, self.offset_array, index + 1) - 1;
@@ -1653,6 +2351,15 @@ where
         })
     }
 
+    /// Returns the f64 value of this operator if the operands hold a single Integer or Real.
+    pub fn get_f64(&self, key: Operator) -> Option<Result<f64, ParseError>> {
+        self.get_with_default(key).map(|operands| match operands {
+            [Operand::Integer(number)] => Ok(f64::from(*number)),
+            [Operand::Real(real)] => real.to_f64(),
+            _ => Err(ParseError::BadValue),
+        })
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &(Operator, Vec<Operand>)> {
         self.dict.iter()
     }
@@ -1691,6 +2398,23 @@ where
         self.dict.len()
     }
 
+    /// Set the operands of `operator`, replacing any existing entry for it.
+    ///
+    /// Operators not already present are appended, except for `ROS` and `SyntheticBase`, which are
+    /// inserted at the start of the DICT as they are used to identify CID-keyed and synthetic
+    /// fonts when reading.
+    pub fn set(&mut self, operator: Operator, operands: Vec<Operand>) {
+        if let Some((_, existing)) = self.dict.iter_mut().find(|(op, _)| *op == operator) {
+            *existing = operands;
+            return;
+        }
+
+        match operator {
+            Operator::ROS | Operator::SyntheticBase => self.dict.insert(0, (operator, operands)),
+            _ => self.dict.push((operator, operands)),
+        }
+    }
+
     fn inner_mut(&mut self) -> &mut Vec<(Operator, Vec<Operand>)> {
         &mut self.dict
     }
@@ -1700,6 +2424,36 @@ where
             self.dict.remove(index);
         }
     }
+
+    /// Returns the SIDs referenced by the operands of this DICT.
+    fn sids(&self) -> Result<Vec<SID>, ParseError> {
+        let mut sids = Vec::new();
+        for (operator, operands) in self.iter() {
+            for operand in sid_operands(*operator, operands) {
+                match operand {
+                    Operand::Integer(sid) => sids.push(SID::try_from(*sid)?),
+                    _ => return Err(ParseError::BadValue),
+                }
+            }
+        }
+        Ok(sids)
+    }
+
+    /// Replace the SIDs referenced by the operands of this DICT with their value in `sid_map`.
+    fn remap_sids(&mut self, sid_map: &HashMap<SID, SID>) -> Result<(), ParseError> {
+        for (operator, operands) in self.inner_mut() {
+            let count = sid_operands(*operator, operands).len();
+            for operand in operands.iter_mut().take(count) {
+                match operand {
+                    Operand::Integer(sid) => {
+                        *sid = i32::from(remap_sid(sid_map, SID::try_from(*sid)?)?)
+                    }
+                    _ => return Err(ParseError::BadValue),
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl DictDelta {
@@ -1806,27 +2560,201 @@ impl TryFrom<u16> for Operator {
                 19 => Ok(Operator::Subrs),
                 20 => Ok(Operator::DefaultWidthX),
                 21 => Ok(Operator::NominalWidthX),
+                22 => Ok(Operator::VSIndex),
+                23 => Ok(Operator::Blend),
+                24 => Ok(Operator::VStore),
                 _ => Err(ParseError::BadValue),
             }
         }
     }
-}
+}
+
+/// Returns the operands of `operator` that are SIDs.
+fn sid_operands(operator: Operator, operands: &[Operand]) -> &[Operand] {
+    match operator {
+        Operator::Version
+        | Operator::Notice
+        | Operator::Copyright
+        | Operator::FullName
+        | Operator::FamilyName
+        | Operator::Weight
+        | Operator::PostScript
+        | Operator::BaseFontName
+        | Operator::FontName => &operands[..operands.len().min(1)],
+        // Registry and Ordering are SIDs, Supplement is a number
+        Operator::ROS => &operands[..operands.len().min(2)],
+        _ => &[],
+    }
+}
+
+impl Operand {
+    pub fn is_offset(&self) -> bool {
+        match self {
+            Operand::Offset(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Real {
+    /// Decode the nibble encoded value of this `Real` into an `f64`.
+    ///
+    /// Refer to Table 5 Nibble Definitions in section 4 of Technical Note #5176 for details of the
+    /// encoding.
+    pub fn to_f64(&self) -> Result<f64, ParseError> {
+        let mut number = String::with_capacity(self.0.len() * 2);
+        let nibbles = self
+            .0
+            .iter()
+            .flat_map(|&byte| iter::once(byte >> 4).chain(iter::once(byte & 0xF)));
+        for nibble in nibbles {
+            match nibble {
+                0..=9 => number.push(char::from(b'0' + nibble)),
+                0xA => number.push('.'),
+                0xB => number.push('E'),
+                0xC => number.push_str("E-"),
+                0xE => number.push('-'),
+                0xF => break,
+                _ => return Err(ParseError::BadValue), // 0xD is reserved
+            }
+        }
+
+        number.parse().map_err(|_err| ParseError::BadValue)
+    }
+}
+
+impl<'a> Font<'a> {
+    /// Resolve the common metadata operators of the Top DICT of this font
+    ///
+    /// `cff` is the `CFF` this font belongs to, its String INDEX is used to look up the string
+    /// values. Operators that are absent take their default value, absent strings are `None`.
+    pub fn font_info(&self, cff: &CFF<'_>) -> Result<FontInfo, ParseError> {
+        let string = |operator| -> Result<Option<String>, ParseError> {
+            match self.top_dict.get(operator) {
+                Some([Operand::Integer(sid)]) => cff.read_string(SID::try_from(*sid)?).map(Some),
+                Some(_) => Err(ParseError::BadValue),
+                None => Ok(None),
+            }
+        };
+        let number = |operator| {
+            self.top_dict
+                .get_f64(operator)
+                .unwrap_or(Err(ParseError::MissingValue))
+        };
+
+        let mut font_matrix = [0.; 6];
+        match self.top_dict.get_with_default(Operator::FontMatrix) {
+            Some(operands) if operands.len() == font_matrix.len() => {
+                for (value, operand) in font_matrix.iter_mut().zip(operands) {
+                    *value = match operand {
+                        Operand::Integer(number) => f64::from(*number),
+                        Operand::Real(real) => real.to_f64()?,
+                        Operand::Offset(_) => return Err(ParseError::BadValue),
+                    };
+                }
+            }
+            _ => return Err(ParseError::BadValue),
+        }
+
+        Ok(FontInfo {
+            version: string(Operator::Version)?,
+            notice: string(Operator::Notice)?,
+            copyright: string(Operator::Copyright)?,
+            full_name: string(Operator::FullName)?,
+            family_name: string(Operator::FamilyName)?,
+            weight: string(Operator::Weight)?,
+            is_fixed_pitch: number(Operator::IsFixedPitch)? != 0.,
+            italic_angle: number(Operator::ItalicAngle)?,
+            underline_position: number(Operator::UnderlinePosition)?,
+            underline_thickness: number(Operator::UnderlineThickness)?,
+            font_matrix,
+        })
+    }
+
+    pub fn is_cid_keyed(&self) -> bool {
+        match self.data {
+            CFFVariant::CID(_) => true,
+            CFFVariant::Type1(_) | CFFVariant::Synthetic(_) => false,
+        }
+    }
 
-impl Operand {
-    pub fn is_offset(&self) -> bool {
-        match self {
-            Operand::Offset(_) => true,
-            _ => false,
+    pub fn is_synthetic(&self) -> bool {
+        match self.data {
+            CFFVariant::Synthetic(_) => true,
+            CFFVariant::CID(_) | CFFVariant::Type1(_) => false,
+        }
+    }
+
+    /// Iterate over the CharStrings of this font along with their glyph ids.
+    pub fn char_strings(&self) -> impl Iterator<Item = (u16, &[u8])> {
+        // The number of glyphs in a font is limited to 65535 so all CharStrings have a u16 id
+        (0..=std::u16::MAX).zip(self.char_strings_index.iter())
+    }
+
+    /// Find the glyphs that have the same CharString as an earlier glyph.
+    ///
+    /// Each object in the CharStrings INDEX is stored separately, so the duplicated data can't be
+    /// shared between glyphs. The result reports how much data could be saved by referring to the
+    /// first glyph instead, such as from a composite glyph in a `glyf` table.
+    pub fn dedupe_charstrings(&self) -> DuplicateCharStrings {
+        let mut first_glyph_ids = HashMap::new();
+        let mut duplicates = DuplicateCharStrings::default();
+        for (glyph_id, char_string) in self.char_strings() {
+            match first_glyph_ids.get(char_string) {
+                Some(&first_glyph_id) => {
+                    duplicates.glyphs.push((glyph_id, first_glyph_id));
+                    duplicates.bytes += char_string.len();
+                }
+                None => {
+                    first_glyph_ids.insert(char_string, glyph_id);
+                }
+            }
         }
+        duplicates
     }
 }
 
-impl<'a> Font<'a> {
-    pub fn is_cid_keyed(&self) -> bool {
-        match self.data {
-            CFFVariant::CID(_) => true,
-            CFFVariant::Type1(_) => false,
+/// Read the `offSize`, offset array, and data of an INDEX with `count` objects
+///
+/// The count is 16-bit in CFF and 32-bit in CFF2, the remainder of the INDEX is the same.
+pub(crate) fn read_index_data<'a>(
+    ctxt: &mut ReadCtxt<'a>,
+    count: usize,
+) -> Result<Index<'a>, ParseError> {
+    if count > 0 {
+        let off_size = ctxt.read_u8()?;
+        if off_size < 1 || off_size > 4 {
+            return Err(ParseError::BadValue);
+        }
+
+        // The count of a CFF2 INDEX is 32-bit so this may overflow on 32-bit platforms
+        let offset_array_size = (count + 1)
+            .checked_mul(usize::from(off_size))
+            .ok_or(ParseError::LimitExceeded)?;
+        let offset_array = ctxt.read_slice(offset_array_size)?;
+
+        let last_offset_index = lookup_offset_index(off_size, offset_array, count);
+        if last_offset_index < 1 {
+            return Err(ParseError::BadValue);
         }
+
+        let data_array_size = last_offset_index - 1;
+        let data_array = ctxt.read_slice(data_array_size)?;
+
+        Ok(Index {
+            count,
+            off_size,
+            offset_array,
+            data_array,
+        })
+    } else {
+        // count == 0
+        Ok(Index {
+            count,
+            off_size: 1,
+            offset_array: &[],
+            data_array: &[],
+        })
     }
 }
 
@@ -1887,6 +2815,13 @@ fn write_cff_variant<'a, C: WriteContext>(
                 ],
             );
         }
+        CFFVariant::Synthetic(synthetic_data) => {
+            if let Encoding::Custom(ref custom_encoding) = synthetic_data.encoding {
+                top_dict_delta
+                    .push_offset(Operator::Encoding, i32::try_from(ctxt.bytes_written())?);
+                CustomEncoding::write(ctxt, custom_encoding)?;
+            }
+        }
     }
 
     Ok(())
@@ -1916,14 +2851,13 @@ fn read_cid_data<'a>(
     let offset = top_dict
         .get_i32(Operator::FDArray)
         .ok_or(ParseError::MissingValue)??;
-    let font_dict_index = scope.offset(usize::try_from(offset)?).read::<Index<'a>>()?;
+    let font_dict_index = offset_scope(scope, usize::try_from(offset)?)?.read::<Index<'a>>()?;
 
     let offset = top_dict
         .get_i32(Operator::FDSelect)
         .ok_or(ParseError::MissingValue)??;
-    let fd_select = scope
-        .offset(usize::try_from(offset)?)
-        .read_dep::<FDSelect<'a>>(n_glyphs)?;
+    let fd_select =
+        offset_scope(scope, usize::try_from(offset)?)?.read_dep::<FDSelect<'a>>(n_glyphs)?;
 
     let mut private_dicts = Vec::with_capacity(font_dict_index.count);
     let mut local_subr_indices = Vec::with_capacity(font_dict_index.count);
@@ -1936,11 +2870,44 @@ fn read_cid_data<'a>(
         local_subr_indices.push(local_subr_index);
     }
 
+    let cid_font_type = match top_dict.get_with_default(Operator::CIDFontType) {
+        Some([Operand::Integer(cid_font_type)]) => *cid_font_type,
+        _ => return Err(ParseError::BadValue),
+    };
+
     Ok(CIDData {
         font_dict_index: MaybeOwnedIndex::Borrowed(font_dict_index),
         private_dicts,
         local_subr_indices,
         fd_select,
+        cid_font_type,
+    })
+}
+
+fn read_synthetic_data<'a>(
+    scope: &ReadScope<'a>,
+    top_dict: &TopDict,
+    font_index: usize,
+    n_fonts: usize,
+) -> Result<SyntheticData<'a>, ParseError> {
+    let base_font_index = top_dict
+        .get_i32(Operator::SyntheticBase)
+        .ok_or(ParseError::MissingValue)??;
+    let base_font_index = usize::try_from(base_font_index)?;
+    if base_font_index >= n_fonts || base_font_index == font_index {
+        return Err(ParseError::BadIndex);
+    }
+
+    // Synthetic fonts are expected to take these from the base font
+    if top_dict.get(Operator::CharStrings).is_some() || top_dict.get(Operator::Private).is_some() {
+        return Err(ParseError::NotImplemented);
+    }
+
+    let encoding = read_encoding(scope, top_dict)?;
+
+    Ok(SyntheticData {
+        base_font_index,
+        encoding,
     })
 }
 
@@ -2079,15 +3046,20 @@ fn read_encoding<'a>(
         0 => Encoding::Standard,
         1 => Encoding::Expert,
         _ => Encoding::Custom(
-            scope
-                .offset(usize::try_from(offset)?)
-                .read::<CustomEncoding<'_>>()?,
+            offset_scope(scope, usize::try_from(offset)?)?.read::<CustomEncoding<'_>>()?,
         ),
     };
 
     Ok(encoding)
 }
 
+fn read_charstring_type(top_dict: &TopDict) -> Result<i32, ParseError> {
+    match top_dict.get_with_default(Operator::CharstringType) {
+        Some([Operand::Integer(charstring_type)]) => Ok(*charstring_type),
+        _ => Err(ParseError::BadValue),
+    }
+}
+
 fn read_charset<'a>(
     scope: &ReadScope<'a>,
     top_dict: &TopDict,
@@ -2101,8 +3073,7 @@ fn read_charset<'a>(
         1 => Charset::Expert,
         2 => Charset::ExpertSubset,
         _ => Charset::Custom(
-            scope
-                .offset(usize::try_from(offset)?)
+            offset_scope(scope, usize::try_from(offset)?)?
                 .read_dep::<CustomCharset<'_>>(char_strings_count)?,
         ),
     };
@@ -2123,14 +3094,22 @@ fn read_local_subr_index<'a>(
         .get_i32(Operator::Subrs)
         .transpose()?
         .map(|offset| {
-            let offset = usize::try_from(offset)?;
-            scope
-                .offset(private_dict_offset + offset)
-                .read::<Index<'_>>()
+            let offset = private_dict_offset
+                .checked_add(usize::try_from(offset)?)
+                .ok_or(ParseError::BadOffset)?;
+            offset_scope(scope, offset)?.read::<Index<'_>>()
         })
         .transpose()
 }
 
+/// Returns the scope starting at `offset` within `scope`.
+///
+/// The offsets in DICTs come from the font data so they are checked to be within `scope`,
+/// returning `ParseError::BadOffset` if not.
+fn offset_scope<'a>(scope: &ReadScope<'a>, offset: usize) -> Result<ReadScope<'a>, ParseError> {
+    scope.offset_checked(offset)
+}
+
 /// Serialise the offsets using an optimal `off_size`, returning that and the serialised data.
 fn serialise_offset_array(offsets: Vec<usize>) -> Result<(u8, Vec<u8>), WriteError> {
     if offsets.is_empty() {
@@ -2159,6 +3138,21 @@ fn serialise_offset_array(offsets: Vec<usize>) -> Result<(u8, Vec<u8>), WriteErr
     Ok((off_size, offset_array.into_inner()))
 }
 
+// Maps character codes to SIDs, from Appendix B of Technical Note #5176
+const STANDARD_ENCODING: [u16; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
+    51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 69, 70, 71, 72, 73, 74,
+    75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 96,
+    97, 98, 99, 100, 101, 102, 103, 104, 105, 106, 107, 108, 109, 110, 0, 111, 112, 113, 114, 0,
+    115, 116, 117, 118, 119, 120, 121, 122, 0, 123, 0, 124, 125, 126, 127, 128, 129, 130, 131, 0,
+    132, 133, 0, 134, 135, 136, 137, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 138, 0, 139,
+    0, 0, 0, 0, 140, 141, 142, 143, 0, 0, 0, 0, 0, 144, 0, 0, 0, 145, 0, 0, 146, 147, 148, 149, 0,
+    0, 0, 0,
+];
+
 const STANDARD_STRINGS: [&str; 391] = [
     ".notdef",
     "space",
@@ -2553,6 +3547,22 @@ const STANDARD_STRINGS: [&str; 391] = [
     "Semibold",
 ];
 
+// Maps character codes to SIDs, from Appendix B of Technical Note #5176
+const EXPERT_ENCODING: [u16; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    1, 229, 230, 0, 231, 232, 233, 234, 235, 236, 237, 238, 13, 14, 15, 99, 239, 240, 241, 242,
+    243, 244, 245, 246, 247, 248, 27, 28, 249, 250, 251, 252, 0, 253, 254, 255, 256, 257, 0, 0, 0,
+    258, 0, 0, 259, 260, 261, 262, 0, 0, 263, 264, 265, 0, 266, 109, 110, 267, 268, 269, 0, 270,
+    271, 272, 273, 274, 275, 276, 277, 278, 279, 280, 281, 282, 283, 284, 285, 286, 287, 288, 289,
+    290, 291, 292, 293, 294, 295, 296, 297, 298, 299, 300, 301, 302, 303, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 304, 305, 306, 0,
+    0, 307, 308, 309, 310, 311, 0, 312, 0, 0, 313, 0, 0, 314, 315, 0, 0, 316, 317, 318, 0, 0, 0,
+    158, 155, 163, 319, 320, 321, 322, 323, 324, 325, 0, 0, 326, 150, 164, 169, 327, 328, 329, 330,
+    331, 332, 333, 334, 335, 336, 337, 338, 339, 340, 341, 342, 343, 344, 345, 346, 347, 348, 349,
+    350, 351, 352, 353, 354, 355, 356, 357, 358, 359, 360, 361, 362, 363, 364, 365, 366, 367, 368,
+    369, 370, 371, 372, 373, 374, 375, 376, 377, 378,
+];
+
 const EXPERT_CHARSET: [u16; 166] = [
     0,   // .notdef
     1,   // space
@@ -2816,6 +3826,9 @@ const EXPERT_SUBSET_CHARSET: [u16; 87] = [
 mod tests {
     use super::*;
     use crate::binary::read::ReadScope;
+    use crate::tables::{FontTableProvider, OpenTypeFile};
+    use crate::tag;
+    use crate::tests::read_fixture;
 
     #[test]
     fn test_iter_index() {
@@ -2831,6 +3844,87 @@ mod tests {
         assert_eq!(index.iter().collect::<Vec<_>>(), vec![[4], [5]]);
     }
 
+    #[test]
+    fn test_lazy_index() {
+        let data = [
+            0, 3, // count
+            1, // off_size
+            1, 2, 4, 5, // offset array
+            6, 7, 8, 9, // data
+        ];
+        let index = ReadScope::new(&data).read::<LazyIndex<'_>>().unwrap();
+        assert_eq!(index.read_object(0), Ok(&[6][..]));
+        assert_eq!(index.read_object(1), Ok(&[7, 8][..]));
+        assert_eq!(index.read_object(2), Ok(&[9][..]));
+        assert_eq!(index.read_object(3), Err(ParseError::BadIndex));
+
+        let empty = ReadScope::new(&[0, 0]).read::<LazyIndex<'_>>().unwrap();
+        assert_eq!(empty.read_object(0), Err(ParseError::BadIndex));
+    }
+
+    #[test]
+    fn test_lazy_index_bad_offsets() {
+        let data = [
+            0, 3, // count
+            1, // off_size
+            1, 9, 4, 5, // offset array
+            6, 7, 8, 9, // data
+        ];
+        let index = ReadScope::new(&data).read::<LazyIndex<'_>>().unwrap();
+        assert_eq!(index.read_object(0), Err(ParseError::BadEof));
+        assert_eq!(index.read_object(1), Err(ParseError::BadOffset));
+        assert_eq!(index.read_object(2), Ok(&[9][..]));
+    }
+
+    #[test]
+    fn test_lazy_index_followed_by_data() {
+        let data = [
+            0, 2, // count
+            1, // off_size
+            1, 2, 3, // offset array
+            6, 7,    // data
+            0xAB, // following byte
+        ];
+        let mut ctxt = ReadScope::new(&data).ctxt();
+        let index = ctxt.read::<LazyIndex<'_>>().unwrap();
+        assert_eq!(index.read_object(1), Ok(&[7][..]));
+        assert_eq!(ctxt.read_u8().unwrap(), 0xAB);
+
+        // The last offset points past the end of the data
+        let truncated = [0, 2, 1, 1, 2, 4, 6, 7];
+        assert_eq!(
+            ReadScope::new(&truncated).read::<LazyIndex<'_>>().err(),
+            Some(ParseError::BadEof)
+        );
+    }
+
+    #[test]
+    fn test_lazy_index_char_strings() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let otf = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = otf.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+        let font = &cff.fonts[0];
+        let char_strings_offset = font
+            .top_dict
+            .get_i32(Operator::CharStrings)
+            .unwrap()
+            .unwrap();
+        let index = ReadScope::new(&cff_data)
+            .offset(usize::try_from(char_strings_offset).unwrap())
+            .read::<LazyIndex<'_>>()
+            .unwrap();
+
+        assert_eq!(index.count, font.char_strings_index.len());
+        for glyph_id in 0..index.count {
+            assert_eq!(
+                index.read_object(glyph_id).ok(),
+                font.char_strings_index.read_object(glyph_id)
+            );
+        }
+    }
+
     #[test]
     fn test_read_op1() {
         let mut ctxt = ReadScope::new(&[0, 0]).ctxt();
@@ -2926,6 +4020,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_real_to_f64() {
+        assert_eq!(Real(vec![0xe2, 0xa2, 0x5f]).to_f64(), Ok(-2.25));
+        assert_eq!(
+            Real(vec![0x0a, 0x14, 0x05, 0x41, 0xc3, 0xff]).to_f64(),
+            Ok(0.140541E-3)
+        );
+        assert_eq!(Real(vec![0x0a, 0x00, 0x1f]).to_f64(), Ok(0.001));
+        assert_eq!(Real(vec![0x1b, 0x2f]).to_f64(), Ok(1E2));
+        assert_eq!(Real(vec![0x1d, 0x2f]).to_f64(), Err(ParseError::BadValue));
+    }
+
+    #[test]
+    fn test_dict_get_f64() {
+        let mut private_dict = PrivateDict::new();
+        private_dict
+            .inner_mut()
+            .push((Operator::BlueShift, vec![Operand::Integer(5)]));
+        private_dict.inner_mut().push((
+            Operator::BlueValues,
+            vec![Operand::Integer(-10), Operand::Integer(0)],
+        ));
+
+        assert_eq!(private_dict.get_f64(Operator::BlueShift), Some(Ok(5.)));
+        // Default value
+        assert_eq!(
+            private_dict.get_f64(Operator::BlueScale),
+            Some(Ok(0.039625))
+        );
+        assert_eq!(
+            private_dict.get_f64(Operator::BlueValues),
+            Some(Err(ParseError::BadValue))
+        );
+        assert_eq!(private_dict.get_f64(Operator::StdHW), None);
+    }
+
     #[test]
     fn test_read_top_dict() {
         let expected = TopDict {
@@ -3103,6 +4233,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_custom_charset_from_sids() {
+        fn write_size(charset: &CustomCharset<'_>) -> usize {
+            let mut buffer = WriteBuffer::new();
+            CustomCharset::write(&mut buffer, charset).unwrap();
+            buffer.bytes_written()
+        }
+
+        // A run of consecutive SIDs is stored as a single range
+        let sids = (100..200).collect_vec();
+        let charset = CustomCharset::from_sids(&sids);
+        match &charset {
+            CustomCharset::Format1 { ranges } => assert_eq!(
+                ranges.iter().collect_vec(),
+                vec![Range {
+                    first: 100,
+                    n_left: 99
+                }]
+            ),
+            _ => panic!("expected CustomCharset::Format1 got something else"),
+        }
+        let format0 = CustomCharset::Format0 {
+            glyphs: ReadArrayCow::Owned(sids.clone()),
+        };
+        assert!(write_size(&charset) < write_size(&format0));
+        assert_eq!(charset.iter().collect_vec(), format0.iter().collect_vec());
+
+        // Runs too long for Format 1 use Format 2
+        let sids = (1..=1000).collect_vec();
+        let charset = CustomCharset::from_sids(&sids);
+        match &charset {
+            CustomCharset::Format2 { ranges } => assert_eq!(
+                ranges.iter().collect_vec(),
+                vec![Range {
+                    first: 1,
+                    n_left: 999
+                }]
+            ),
+            _ => panic!("expected CustomCharset::Format2 got something else"),
+        }
+        assert_eq!(charset.id_for_glyph(1000), Some(1000));
+        assert_eq!(charset.id_for_glyph(1001), None);
+
+        // Non-consecutive SIDs are smaller as Format 0
+        let charset = CustomCharset::from_sids(&[10, 5, 20, 15]);
+        match &charset {
+            CustomCharset::Format0 { glyphs } => {
+                assert_eq!(glyphs.iter().collect_vec(), vec![10, 5, 20, 15])
+            }
+            _ => panic!("expected CustomCharset::Format0 got something else"),
+        }
+    }
+
     #[test]
     fn test_read_write_index() {
         let mut count = vec![0, 1];
@@ -3217,6 +4400,95 @@ mod tests {
         assert_eq!(fd_select.font_dict_index(33), None);
     }
 
+    #[test]
+    fn test_range_u16_u8_iter() {
+        let range: Range<u16, u8> = Range {
+            first: 17,
+            n_left: 3,
+        };
+        assert_eq!(range.iter().collect_vec(), vec![17, 18, 19, 20]);
+        assert_eq!(range.len(), 4);
+    }
+
+    #[test]
+    fn test_fd_select_from_glyph_indices() {
+        fn write_size(fd_select: &FDSelect<'_>) -> usize {
+            let mut buffer = WriteBuffer::new();
+            FDSelect::write(&mut buffer, fd_select).unwrap();
+            buffer.bytes_written()
+        }
+
+        let mut glyph_font_dict_indices = vec![0; 100];
+        glyph_font_dict_indices.extend(vec![1; 100]);
+        glyph_font_dict_indices.extend(vec![0; 100]);
+        let format0 = FDSelect::Format0 {
+            glyph_font_dict_indices: ReadArrayCow::Owned(glyph_font_dict_indices.clone()),
+        };
+        let fd_select = FDSelect::from_glyph_indices(&glyph_font_dict_indices);
+
+        match &fd_select {
+            FDSelect::Format3 { ranges, sentinel } => {
+                assert_eq!(ranges.len(), 3);
+                assert_eq!(*sentinel, 300);
+            }
+            FDSelect::Format0 { .. } => panic!("expected FDSelect format 3"),
+        }
+        assert!(write_size(&fd_select) < write_size(&format0));
+        for glyph_id in 0..300 {
+            assert_eq!(
+                fd_select.font_dict_index(glyph_id),
+                format0.font_dict_index(glyph_id)
+            );
+        }
+
+        // Alternating indices are smaller as format 0
+        let fd_select = FDSelect::from_glyph_indices(&[0, 1, 0, 1, 0, 1]);
+        assert_eq!(
+            fd_select,
+            FDSelect::Format0 {
+                glyph_font_dict_indices: ReadArrayCow::Owned(vec![0, 1, 0, 1, 0, 1]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_encoding_code_to_gid_pre_defined_encodings() {
+        // A -> SID 34, which is glyph 34 in the ISOAdobe charset
+        assert_eq!(
+            Encoding::Standard.code_to_gid(b'A', &Charset::ISOAdobe),
+            Some(34)
+        );
+        assert_eq!(Encoding::Standard.code_to_gid(0, &Charset::ISOAdobe), None);
+        // exclamsmall -> SID 229, which is glyph 2 in the Expert charset
+        assert_eq!(Encoding::Expert.code_to_gid(33, &Charset::Expert), Some(2));
+        assert_eq!(Encoding::Expert.code_to_gid(33, &Charset::ISOAdobe), None);
+        assert_eq!(Encoding::Expert.code_to_gid(35, &Charset::Expert), None);
+    }
+
+    #[test]
+    fn test_encoding_code_to_gid_custom_encodings() {
+        let format0 = ReadScope::new(&[0, 2, 66, 65])
+            .read::<CustomEncoding<'_>>()
+            .unwrap();
+        assert_eq!(format0.code_to_gid(65), Some(2));
+        assert_eq!(format0.code_to_gid(66), Some(1));
+        assert_eq!(format0.code_to_gid(67), None);
+
+        // Ranges 65..=66 and 97..=99
+        let format1 = ReadScope::new(&[1, 2, 65, 1, 97, 2])
+            .read::<CustomEncoding<'_>>()
+            .unwrap();
+        assert_eq!(format1.code_to_gid(65), Some(1));
+        assert_eq!(format1.code_to_gid(66), Some(2));
+        assert_eq!(format1.code_to_gid(67), None);
+        assert_eq!(format1.code_to_gid(98), Some(4));
+        assert_eq!(format1.code_to_gid(99), Some(5));
+        assert_eq!(format1.code_to_gid(100), None);
+
+        let encoding = Encoding::Custom(format1);
+        assert_eq!(encoding.code_to_gid(99, &Charset::ISOAdobe), Some(5));
+    }
+
     #[test]
     fn test_charset_id_for_glyph_pre_defined_charsets() {
         assert_eq!(Charset::ISOAdobe.id_for_glyph(2), Some(2));
@@ -3355,4 +4627,457 @@ mod tests {
         );
         assert!(read_string_index_string(&string_index, 392).is_err());
     }
+
+    #[test]
+    fn test_remap_sids() {
+        let sids = [0, 395, 2, 391, 395, 400, 390];
+        let actual = remap_sids(&sids);
+        let mut expected = HashMap::new();
+        expected.insert(391, 391);
+        expected.insert(395, 392);
+        expected.insert(400, 393);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_dict_remap_sids() {
+        let mut top_dict = TopDict::new();
+        top_dict.inner_mut().push((
+            Operator::ROS,
+            vec![
+                Operand::Integer(395),
+                Operand::Integer(396),
+                Operand::Integer(0),
+            ],
+        ));
+        top_dict
+            .inner_mut()
+            .push((Operator::Notice, vec![Operand::Integer(400)]));
+        top_dict
+            .inner_mut()
+            .push((Operator::Weight, vec![Operand::Integer(388)]));
+        top_dict
+            .inner_mut()
+            .push((Operator::UniqueID, vec![Operand::Integer(500)]));
+
+        assert_eq!(top_dict.sids().unwrap(), vec![395, 396, 400, 388]);
+
+        let sid_map = remap_sids(&top_dict.sids().unwrap());
+        top_dict.remap_sids(&sid_map).unwrap();
+
+        assert_eq!(
+            top_dict.get(Operator::ROS),
+            Some(
+                [
+                    Operand::Integer(391),
+                    Operand::Integer(392),
+                    Operand::Integer(0)
+                ]
+                .as_ref()
+            )
+        );
+        assert_eq!(
+            top_dict.get(Operator::Notice),
+            Some([Operand::Integer(393)].as_ref())
+        );
+        assert_eq!(
+            top_dict.get(Operator::Weight),
+            Some([Operand::Integer(388)].as_ref())
+        );
+        assert_eq!(
+            top_dict.get(Operator::UniqueID),
+            Some([Operand::Integer(500)].as_ref())
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_font_name() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+
+        cff.name_index = MaybeOwnedIndex::Owned(owned::Index {
+            data: vec![b"Klei\0Bold".to_vec()],
+        });
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        let data = buffer.into_inner();
+        assert_eq!(
+            ReadScope::new(&data).read::<CFF<'_>>().err(),
+            Some(ParseError::BadValue)
+        );
+        assert_eq!(cff.font_name(0), None);
+
+        // A leading 0 marks a deleted font rather than an invalid name
+        cff.name_index = MaybeOwnedIndex::Owned(owned::Index {
+            data: vec![b"\0Klei".to_vec()],
+        });
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        let data = buffer.into_inner();
+        let cff = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+        assert_eq!(cff.font_name(0), None);
+    }
+
+    #[test]
+    fn test_is_valid_font_name() {
+        assert!(is_valid_font_name(b"NotoSansJP-Regular"));
+        assert!(!is_valid_font_name(b"Noto Sans"));
+        assert!(!is_valid_font_name(b"Noto/Sans"));
+        assert!(!is_valid_font_name(b"Noto\xC3\xA9"));
+    }
+
+    #[test]
+    fn test_read_write_synthetic_font() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+
+        // Add a synthetic oblique font based on the first font to the font set
+        let mut name_index = WriteBuffer::new();
+        owned::Index::write(
+            &mut name_index,
+            &owned::Index {
+                data: vec![
+                    cff.name_index.read_object(0).unwrap().to_owned(),
+                    b"Klei-Oblique".to_vec(),
+                ],
+            },
+        )
+        .unwrap();
+        let name_index = name_index.into_inner();
+        cff.name_index =
+            MaybeOwnedIndex::Borrowed(ReadScope::new(&name_index).read::<Index<'_>>().unwrap());
+        let mut top_dict = TopDict::new();
+        top_dict
+            .inner_mut()
+            .push((Operator::SyntheticBase, vec![Operand::Integer(0)]));
+        top_dict
+            .inner_mut()
+            .push((Operator::ItalicAngle, vec![Operand::Integer(-12)]));
+        cff.fonts.push(Font {
+            top_dict: top_dict.clone(),
+            char_strings_index: MaybeOwnedIndex::Owned(owned::Index { data: Vec::new() }),
+            charset: Charset::ISOAdobe,
+            charstring_type: 2,
+            data: CFFVariant::Synthetic(SyntheticData {
+                base_font_index: 0,
+                encoding: Encoding::Standard,
+            }),
+        });
+
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        let data = buffer.into_inner();
+        let cff = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+
+        assert_eq!(cff.fonts.len(), 2);
+        assert!(!cff.fonts[0].is_synthetic());
+        assert_eq!(cff.fonts[1].top_dict, top_dict);
+        match &cff.fonts[1].data {
+            CFFVariant::Synthetic(synthetic) => assert_eq!(synthetic.base_font_index, 0),
+            _ => panic!("expected synthetic font"),
+        }
+
+        // Writing again should produce the same data
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        assert_eq!(buffer.into_inner(), data);
+    }
+
+    #[test]
+    fn test_write_header() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+        cff.header.hdr_size = 5;
+        cff.header.off_size = 1;
+
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        let data = buffer.into_inner();
+        let cff = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+
+        assert_eq!(cff.header.hdr_size, 4);
+        assert_eq!(cff.header.off_size, 4);
+    }
+
+    #[test]
+    fn test_read_synthetic_font_bad_base() {
+        let mut top_dict = TopDict::new();
+        top_dict
+            .inner_mut()
+            .push((Operator::SyntheticBase, vec![Operand::Integer(1)]));
+        let scope = ReadScope::new(&[]);
+
+        assert!(read_synthetic_data(&scope, &top_dict, 0, 2).is_ok());
+        assert_eq!(
+            read_synthetic_data(&scope, &top_dict, 1, 2).err(),
+            Some(ParseError::BadIndex)
+        );
+        assert_eq!(
+            read_synthetic_data(&scope, &top_dict, 0, 1).err(),
+            Some(ParseError::BadIndex)
+        );
+    }
+
+    #[test]
+    fn test_subset_font_set() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+
+        // Make a font set containing two copies of the font
+        cff.name_index = MaybeOwnedIndex::Owned(owned::Index {
+            data: vec![b"Klei-A".to_vec(), b"Klei-B".to_vec()],
+        });
+        let font = cff.fonts[0].clone();
+        cff.fonts.push(font);
+
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        let data = buffer.into_inner();
+        let cff = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+        assert_eq!(cff.fonts.len(), 2);
+
+        let (subset, new_to_old_id) = cff.subset(1, &[0, 1, 2], true).unwrap();
+        assert_eq!(new_to_old_id, vec![0, 1, 2]);
+        assert_eq!(subset.name_index.len(), 1);
+        assert_eq!(subset.name_index.read_object(0), Some(b"Klei-B".as_ref()));
+        assert_eq!(subset.fonts.len(), 1);
+        assert_eq!(subset.fonts[0].char_strings_index.len(), 3);
+
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &subset).unwrap();
+        let data = buffer.into_inner();
+        let subset = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+        assert_eq!(subset.fonts.len(), 1);
+        assert_eq!(subset.name_index.read_object(0), Some(b"Klei-B".as_ref()));
+
+        assert_eq!(
+            cff.subset(2, &[0, 1, 2], true).err(),
+            Some(ParseError::BadIndex)
+        );
+    }
+
+    #[test]
+    fn test_subset_computes_default_font_bbox() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+
+        // Replace the stored FontBBox with the default, as found in many fonts
+        let top_dict = &mut cff.fonts[0].top_dict;
+        top_dict.remove(Operator::FontBBox);
+        top_dict
+            .inner_mut()
+            .push((Operator::FontBBox, DEFAULT_BBOX.to_vec()));
+
+        let glyph_o = cff.gid_for_name("O").unwrap();
+        let (subset, _) = cff.subset(0, &[0, glyph_o], true).unwrap();
+        let font = &subset.fonts[0];
+        let bbox = subset.compute_font_bbox(0).unwrap();
+        assert_ne!(bbox.x_max, 0);
+        assert_ne!(bbox.y_max, 0);
+        assert_eq!(
+            font.top_dict.get(Operator::FontBBox),
+            Some(
+                [
+                    Operand::Integer(i32::from(bbox.x_min)),
+                    Operand::Integer(i32::from(bbox.y_min)),
+                    Operand::Integer(i32::from(bbox.x_max)),
+                    Operand::Integer(i32::from(bbox.y_max)),
+                ]
+                .as_ref()
+            )
+        );
+    }
+
+    #[test]
+    fn test_read_cid_font_type() {
+        let buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+        match &cff.fonts[0].data {
+            CFFVariant::CID(cid) => assert_eq!(cid.cid_font_type, 0),
+            _ => panic!("expected CID font"),
+        }
+
+        cff.fonts[0]
+            .top_dict
+            .inner_mut()
+            .push((Operator::CIDFontType, vec![Operand::Integer(1)]));
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        let data = buffer.into_inner();
+        let cff = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+        let font = &cff.fonts[0];
+
+        match &font.data {
+            CFFVariant::CID(cid) => assert_eq!(cid.cid_font_type, 1),
+            _ => panic!("expected CID font"),
+        }
+        assert_eq!(cff.glyph_path(0, 1).err(), Some(ParseError::NotImplemented));
+    }
+
+    #[test]
+    fn test_read_charstring_type() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+        assert_eq!(cff.fonts[0].charstring_type, 2);
+
+        cff.fonts[0]
+            .top_dict
+            .inner_mut()
+            .push((Operator::CharstringType, vec![Operand::Integer(1)]));
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        let data = buffer.into_inner();
+        let cff = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+        let font = &cff.fonts[0];
+
+        assert_eq!(font.charstring_type, 1);
+        assert_eq!(cff.glyph_path(0, 1).err(), Some(ParseError::NotImplemented));
+        assert_eq!(
+            cff.compute_font_bbox(0).err(),
+            Some(ParseError::NotImplemented)
+        );
+    }
+
+    #[test]
+    fn test_char_strings() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+        let font = &cff.fonts[0];
+
+        let char_strings = font.char_strings().collect::<Vec<_>>();
+        assert_eq!(char_strings.len(), font.char_strings_index.len());
+        assert_eq!(char_strings[0].0, 0);
+        assert_eq!(
+            Some(char_strings[0].1),
+            font.char_strings_index.read_object(0)
+        );
+        let last = char_strings.len() - 1;
+        assert_eq!(usize::from(char_strings[last].0), last);
+    }
+
+    #[test]
+    fn test_dedupe_charstrings() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+
+        // Glyphs 141 and 205 have the same CharString
+        let (subset, _) = cff.subset(0, &[0, 141, 36, 205], false).unwrap();
+        let duplicates = subset.fonts[0].dedupe_charstrings();
+        assert_eq!(duplicates.glyphs, &[(3, 1)]);
+        assert_eq!(duplicates.bytes, 110);
+    }
+
+    #[test]
+    fn test_dict_set() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cff_data = provider.read_table_data(tag::CFF).unwrap();
+        let mut cff = ReadScope::new(&cff_data).read::<CFF<'_>>().unwrap();
+        let top_dict = &mut cff.fonts[0].top_dict;
+        let first_operator = top_dict.first_operator();
+        let len = top_dict.len();
+
+        top_dict.set(Operator::UnderlinePosition, vec![Operand::Integer(-150)]);
+        top_dict.set(Operator::UnderlinePosition, vec![Operand::Integer(-125)]);
+        assert_eq!(top_dict.len(), len + 1);
+        assert_eq!(top_dict.first_operator(), first_operator);
+
+        let mut buffer = WriteBuffer::new();
+        CFF::write(&mut buffer, &cff).unwrap();
+        let data = buffer.into_inner();
+        let cff = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+        assert_eq!(
+            cff.fonts[0].top_dict.get(Operator::UnderlinePosition),
+            Some([Operand::Integer(-125)].as_ref())
+        );
+        assert!(!cff.fonts[0].is_cid_keyed());
+    }
+
+    #[test]
+    fn test_dict_set_ros_first() {
+        let mut top_dict = TopDict::new();
+        top_dict.set(Operator::Weight, vec![Operand::Integer(391)]);
+        top_dict.set(
+            Operator::ROS,
+            vec![
+                Operand::Integer(391),
+                Operand::Integer(392),
+                Operand::Integer(0),
+            ],
+        );
+
+        assert_eq!(top_dict.first_operator(), Some(Operator::ROS));
+        assert_eq!(top_dict.len(), 2);
+    }
+
+    // Build a CFF font set containing a single font with the supplied Top DICT data
+    fn cff_with_top_dict(top_dict: &[u8]) -> Vec<u8> {
+        let mut data = vec![
+            1, 0, 4, 1, // header
+            0, 1, 1, 1, 2, b'A', // Name INDEX
+            0, 1, 1, 1, // Top DICT INDEX
+        ];
+        data.push(top_dict.len() as u8 + 1);
+        data.extend_from_slice(top_dict);
+        data.extend_from_slice(&[
+            0, 0, // String INDEX
+            0, 0, // Global Subr INDEX
+        ]);
+        data
+    }
+
+    #[test]
+    fn test_read_offset_past_end() {
+        // CharStrings offset 1000
+        let data = cff_with_top_dict(&[28, 0x03, 0xE8, 17]);
+        assert_eq!(
+            ReadScope::new(&data).read::<CFF<'_>>().err(),
+            Some(ParseError::BadOffset)
+        );
+
+        // CharStrings offset at the end of the data
+        let data = cff_with_top_dict(&[28, 0, 23, 17]);
+        assert_eq!(data.len(), 23);
+        assert_eq!(
+            ReadScope::new(&data).read::<CFF<'_>>().err(),
+            Some(ParseError::BadOffset)
+        );
+
+        // CharStrings offset 24, the empty Global Subr INDEX, and an empty Private DICT at
+        // offset 1000
+        let data = cff_with_top_dict(&[163, 17, 139, 28, 0x03, 0xE8, 18]);
+        assert_eq!(
+            ReadScope::new(&data).read::<CFF<'_>>().err(),
+            Some(ParseError::BadOffset)
+        );
+    }
 }
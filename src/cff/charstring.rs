@@ -0,0 +1,843 @@
+//! Type 2 CharString interpretation.
+//!
+//! Refer to [Technical Note #5177](https://wwwimages2.adobe.com/content/dam/acom/en/devnet/font/pdfs/5177.Type2.pdf)
+//! for more information.
+
+use std::convert::TryFrom;
+use std::mem;
+
+use crate::binary::read::{ReadCtxt, ReadScope};
+use crate::error::ParseError;
+use crate::tables::glyf::BoundingBox;
+
+use super::{op2, CFFVariant, Encoding, Font, Index, CFF};
+
+// Type 2 CharString Spec: The argument stack may hold up to 48 values.
+const MAX_OPERANDS: usize = 48;
+
+// Type 2 CharString Spec: Subroutines may be nested to a depth of 10.
+const MAX_SUBR_DEPTH: usize = 10;
+
+/// A point in font units
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// A segment of a glyph outline
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathSegment {
+    /// Begin a new contour at the point
+    MoveTo(Point),
+    /// Straight line to the point
+    LineTo(Point),
+    /// Cubic Bézier curve with two control points followed by the end point
+    CurveTo(Point, Point, Point),
+}
+
+/// The outline of a glyph described by its CharString.
+///
+/// Each contour begins with a `MoveTo` and is implicitly closed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CharStringPath {
+    pub segments: Vec<PathSegment>,
+}
+
+impl CharStringPath {
+    /// Iterate over the end and control points of the segments of the path
+    pub fn points(&self) -> impl Iterator<Item = &Point> {
+        self.segments.iter().flat_map(|segment| match segment {
+            PathSegment::MoveTo(point) | PathSegment::LineTo(point) => vec![point],
+            PathSegment::CurveTo(control1, control2, point) => vec![control1, control2, point],
+        })
+    }
+}
+
+/// How execution of a CharString or subroutine finished
+enum Flow {
+    Return,
+    EndChar,
+}
+
+struct Interpreter<'f, 'a> {
+    font: &'f Font<'a>,
+    global_subr_index: &'f Index<'a>,
+    local_subr_index: Option<&'f Index<'a>>,
+    stack: Vec<f32>,
+    subr_depth: usize,
+    n_stems: usize,
+    seen_width: bool,
+    x: f32,
+    y: f32,
+    segments: Vec<PathSegment>,
+}
+
+impl<'a> CFF<'a> {
+    /// Interpret the Type 2 CharString of `glyph_id` in the font at `font_index`, returning the
+    /// outline of the glyph.
+    ///
+    /// Only Type 2 CharStrings are supported, other CharString types and CIDFonts with a
+    /// non-zero `CIDFontType` return `ParseError::NotImplemented`.
+    pub fn glyph_path(
+        &self,
+        font_index: usize,
+        glyph_id: u16,
+    ) -> Result<CharStringPath, ParseError> {
+        self.font(font_index)?
+            .glyph_path(glyph_id, &self.global_subr_index)
+    }
+
+    /// Compute the bounding box of all glyph outlines in the font at `font_index`.
+    ///
+    /// The bounding box is the union of the extents of the control points of every glyph. This
+    /// can be used to repair fonts with a missing or incorrect `FontBBox` in the Top DICT. A
+    /// font without any outlines has an empty bounding box at the origin.
+    pub fn compute_font_bbox(&self, font_index: usize) -> Result<BoundingBox, ParseError> {
+        self.font(font_index)?
+            .compute_font_bbox(&self.global_subr_index)
+    }
+
+    /// Compute the bounding box of the outline of `glyph_id` in the font at `font_index`.
+    ///
+    /// Returns `None` if the glyph has no outline.
+    pub fn glyph_bounding_box(
+        &self,
+        font_index: usize,
+        glyph_id: u16,
+    ) -> Result<Option<BoundingBox>, ParseError> {
+        self.font(font_index)?
+            .glyph_bounding_box(glyph_id, &self.global_subr_index)
+    }
+
+    fn font(&self, font_index: usize) -> Result<&Font<'a>, ParseError> {
+        self.fonts.get(font_index).ok_or(ParseError::BadIndex)
+    }
+}
+
+impl<'a> Font<'a> {
+    /// Interpret the Type 2 CharString of `glyph_id`, returning the outline of the glyph.
+    ///
+    /// `global_subr_index` is the Global Subr INDEX of the `CFF` this font belongs to.
+    pub(crate) fn glyph_path(
+        &self,
+        glyph_id: u16,
+        global_subr_index: &Index<'a>,
+    ) -> Result<CharStringPath, ParseError> {
+        if self.charstring_type != 2 {
+            return Err(ParseError::NotImplemented);
+        }
+        if let CFFVariant::CID(cid) = &self.data {
+            if cid.cid_font_type != 0 {
+                return Err(ParseError::NotImplemented);
+            }
+        }
+
+        let mut interpreter = Interpreter {
+            font: self,
+            global_subr_index,
+            local_subr_index: None,
+            stack: Vec::new(),
+            subr_depth: 0,
+            n_stems: 0,
+            seen_width: false,
+            x: 0.,
+            y: 0.,
+            segments: Vec::new(),
+        };
+        interpreter.glyph(glyph_id, 0., 0., true)?;
+
+        Ok(CharStringPath {
+            segments: interpreter.segments,
+        })
+    }
+
+    /// Compute the bounding box of all glyph outlines in this font.
+    ///
+    /// `global_subr_index` is the Global Subr INDEX of the `CFF` this font belongs to.
+    pub(crate) fn compute_font_bbox(
+        &self,
+        global_subr_index: &Index<'a>,
+    ) -> Result<BoundingBox, ParseError> {
+        let mut extents: Option<(f32, f32, f32, f32)> = None;
+        for glyph_id in 0..self.char_strings_index.len() {
+            let path = self.glyph_path(u16::try_from(glyph_id)?, global_subr_index)?;
+            extents = path_extents(&path, extents);
+        }
+
+        extents_to_bounding_box(extents.unwrap_or((0., 0., 0., 0.)))
+    }
+
+    /// Compute the bounding box of the outline of `glyph_id`.
+    ///
+    /// `global_subr_index` is the Global Subr INDEX of the `CFF` this font belongs to.
+    fn glyph_bounding_box(
+        &self,
+        glyph_id: u16,
+        global_subr_index: &Index<'a>,
+    ) -> Result<Option<BoundingBox>, ParseError> {
+        let path = self.glyph_path(glyph_id, global_subr_index)?;
+        path_extents(&path, None)
+            .map(extents_to_bounding_box)
+            .transpose()
+    }
+
+    /// Returns the Local Subr INDEX that applies to `glyph_id` if present.
+    fn local_subr_index(&self, glyph_id: u16) -> Result<Option<&Index<'a>>, ParseError> {
+        match &self.data {
+            CFFVariant::Type1(type1) => Ok(type1.local_subr_index.as_ref()),
+            CFFVariant::CID(cid) => {
+                let fd_index = cid
+                    .fd_select
+                    .font_dict_index(glyph_id)
+                    .ok_or(ParseError::BadIndex)?;
+                cid.local_subr_indices
+                    .get(usize::from(fd_index))
+                    .map(Option::as_ref)
+                    .ok_or(ParseError::BadIndex)
+            }
+            CFFVariant::Synthetic(_) => Err(ParseError::NotImplemented),
+        }
+    }
+
+    /// Returns the glyph id of the glyph with the supplied Standard Encoding `code`.
+    ///
+    /// Used to resolve the components of the `seac` form of `endchar`.
+    fn glyph_id_for_standard_code(&self, code: f32) -> Result<u16, ParseError> {
+        if self.is_cid_keyed() || !(0. ..=255.).contains(&code) {
+            return Err(ParseError::BadValue);
+        }
+        Encoding::Standard
+            .code_to_gid(code as u8, &self.charset)
+            .filter(|&glyph_id| usize::from(glyph_id) < self.char_strings_index.len())
+            .ok_or(ParseError::BadIndex)
+    }
+}
+
+/// Extend `extents`, (x_min, y_min, x_max, y_max), to include the points of `path`
+fn path_extents(
+    path: &CharStringPath,
+    extents: Option<(f32, f32, f32, f32)>,
+) -> Option<(f32, f32, f32, f32)> {
+    path.points().fold(extents, |extents, point| {
+        Some(match extents {
+            Some((x_min, y_min, x_max, y_max)) => (
+                x_min.min(point.x),
+                y_min.min(point.y),
+                x_max.max(point.x),
+                y_max.max(point.y),
+            ),
+            None => (point.x, point.y, point.x, point.y),
+        })
+    })
+}
+
+fn extents_to_bounding_box(
+    (x_min, y_min, x_max, y_max): (f32, f32, f32, f32),
+) -> Result<BoundingBox, ParseError> {
+    Ok(BoundingBox {
+        x_min: to_i16(x_min.floor())?,
+        x_max: to_i16(x_max.ceil())?,
+        y_min: to_i16(y_min.floor())?,
+        y_max: to_i16(y_max.ceil())?,
+    })
+}
+
+fn to_i16(value: f32) -> Result<i16, ParseError> {
+    num_traits::cast(value).ok_or(ParseError::BadValue)
+}
+
+impl<'f, 'a> Interpreter<'f, 'a> {
+    /// Interpret the CharString of `glyph_id` with its origin positioned at (`x`, `y`)
+    fn glyph(&mut self, glyph_id: u16, x: f32, y: f32, allow_seac: bool) -> Result<(), ParseError> {
+        let font = self.font;
+        let char_string = font
+            .char_strings_index
+            .read_object(usize::from(glyph_id))
+            .ok_or(ParseError::BadIndex)?;
+        self.local_subr_index = font.local_subr_index(glyph_id)?;
+        self.stack.clear();
+        self.n_stems = 0;
+        self.seen_width = false;
+        self.x = x;
+        self.y = y;
+
+        match self.execute(char_string)? {
+            Flow::EndChar => {}
+            Flow::Return => {}
+        }
+
+        // The seac form of endchar leaves the accent arguments on the stack
+        if self.stack.len() == 4 {
+            if !allow_seac {
+                return Err(ParseError::BadValue);
+            }
+            let (adx, ady) = (self.stack[0], self.stack[1]);
+            let base = font.glyph_id_for_standard_code(self.stack[2])?;
+            let accent = font.glyph_id_for_standard_code(self.stack[3])?;
+            self.glyph(base, x, y, false)?;
+            self.glyph(accent, x + adx, y + ady, false)?;
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, char_string: &[u8]) -> Result<Flow, ParseError> {
+        let mut ctxt = ReadScope::new(char_string).ctxt();
+
+        while ctxt.bytes_available() {
+            let b0 = ctxt.read_u8()?;
+            match b0 {
+                // Operands
+                28 => self.push(f32::from(ctxt.read_i16be()?))?,
+                32..=246 => self.push(f32::from(i16::from(b0) - 139))?,
+                247..=250 => {
+                    let b1 = ctxt.read_u8()?;
+                    self.push(f32::from((i16::from(b0) - 247) * 256 + i16::from(b1) + 108))?
+                }
+                251..=254 => {
+                    let b1 = ctxt.read_u8()?;
+                    self.push(f32::from(
+                        -(i16::from(b0) - 251) * 256 - i16::from(b1) - 108,
+                    ))?
+                }
+                255 => self.push(ctxt.read_i32be()? as f32 / 65536.)?,
+
+                // Hints
+                1 | 3 | 18 | 23 => self.stem()?, // hstem, vstem, hstemhm, vstemhm
+                19 | 20 => {
+                    // hintmask, cntrmask
+                    // Stem hints may precede the mask, in which case they're implied vstems
+                    self.stem()?;
+                    ctxt.read_slice((self.n_stems + 7) / 8)?;
+                }
+
+                // Path construction
+                21 => {
+                    // rmoveto
+                    self.width(2);
+                    self.check_len(2)?;
+                    self.move_to(self.stack[0], self.stack[1]);
+                    self.stack.clear();
+                }
+                22 => {
+                    // hmoveto
+                    self.width(1);
+                    self.check_len(1)?;
+                    self.move_to(self.stack[0], 0.);
+                    self.stack.clear();
+                }
+                4 => {
+                    // vmoveto
+                    self.width(1);
+                    self.check_len(1)?;
+                    self.move_to(0., self.stack[0]);
+                    self.stack.clear();
+                }
+                5 => {
+                    // rlineto
+                    let args = mem::replace(&mut self.stack, Vec::new());
+                    check(!args.is_empty() && args.len() % 2 == 0)?;
+                    for d in args.chunks(2) {
+                        self.line_to(d[0], d[1]);
+                    }
+                }
+                6 | 7 => {
+                    // hlineto, vlineto
+                    let args = mem::replace(&mut self.stack, Vec::new());
+                    check(!args.is_empty())?;
+                    let mut horizontal = b0 == 6;
+                    for &d in &args {
+                        if horizontal {
+                            self.line_to(d, 0.);
+                        } else {
+                            self.line_to(0., d);
+                        }
+                        horizontal = !horizontal;
+                    }
+                }
+                8 => {
+                    // rrcurveto
+                    let args = mem::replace(&mut self.stack, Vec::new());
+                    check(!args.is_empty() && args.len() % 6 == 0)?;
+                    for d in args.chunks(6) {
+                        self.curve_to(d);
+                    }
+                }
+                24 => {
+                    // rcurveline
+                    let args = mem::replace(&mut self.stack, Vec::new());
+                    check(args.len() >= 8 && (args.len() - 2) % 6 == 0)?;
+                    let (curves, line) = args.split_at(args.len() - 2);
+                    for d in curves.chunks(6) {
+                        self.curve_to(d);
+                    }
+                    self.line_to(line[0], line[1]);
+                }
+                25 => {
+                    // rlinecurve
+                    let args = mem::replace(&mut self.stack, Vec::new());
+                    check(args.len() >= 8 && args.len() % 2 == 0)?;
+                    let (lines, curve) = args.split_at(args.len() - 6);
+                    for d in lines.chunks(2) {
+                        self.line_to(d[0], d[1]);
+                    }
+                    self.curve_to(curve);
+                }
+                26 | 27 => {
+                    // vvcurveto, hhcurveto
+                    // An odd number of arguments indicates a leading dx1 (vv) or dy1 (hh)
+                    let args = mem::replace(&mut self.stack, Vec::new());
+                    check(args.len() >= 4 && args.len() % 4 <= 1)?;
+                    let (mut d1, curves) = if args.len() % 4 == 1 {
+                        (args[0], &args[1..])
+                    } else {
+                        (0., &args[..])
+                    };
+                    for d in curves.chunks(4) {
+                        if b0 == 26 {
+                            self.curve_to(&[d1, d[0], d[1], d[2], 0., d[3]]);
+                        } else {
+                            self.curve_to(&[d[0], d1, d[1], d[2], d[3], 0.]);
+                        }
+                        d1 = 0.;
+                    }
+                }
+                30 | 31 => {
+                    // vhcurveto, hvcurveto
+                    let args = mem::replace(&mut self.stack, Vec::new());
+                    check(args.len() >= 4 && args.len() % 4 <= 1)?;
+                    let mut horizontal = b0 == 31;
+                    let n_curves = args.len() / 4;
+                    for (i, d) in args.chunks_exact(4).enumerate() {
+                        // A trailing argument applies to the end point of the last curve
+                        let last = if i + 1 == n_curves && args.len() % 4 == 1 {
+                            args[args.len() - 1]
+                        } else {
+                            0.
+                        };
+                        if horizontal {
+                            self.curve_to(&[d[0], 0., d[1], d[2], last, d[3]]);
+                        } else {
+                            self.curve_to(&[0., d[0], d[1], d[2], d[3], last]);
+                        }
+                        horizontal = !horizontal;
+                    }
+                }
+
+                // Subroutines
+                10 => {
+                    // callsubr
+                    let local_subr_index = self.local_subr_index.ok_or(ParseError::BadIndex)?;
+                    if let Flow::EndChar = self.call_subr(local_subr_index)? {
+                        return Ok(Flow::EndChar);
+                    }
+                }
+                29 => {
+                    // callgsubr
+                    if let Flow::EndChar = self.call_subr(self.global_subr_index)? {
+                        return Ok(Flow::EndChar);
+                    }
+                }
+                11 => return Ok(Flow::Return), // return
+                14 => {
+                    // endchar
+                    // Either no arguments or 4 for the seac form, optionally preceded by the width
+                    if !self.seen_width && (self.stack.len() == 1 || self.stack.len() == 5) {
+                        self.stack.remove(0);
+                    }
+                    self.seen_width = true;
+                    check(self.stack.is_empty() || self.stack.len() == 4)?;
+                    return Ok(Flow::EndChar);
+                }
+                12 => self.escape(&mut ctxt)?,
+
+                // Reserved
+                0 | 2 | 9 | 13 | 15 | 16 | 17 => return Err(ParseError::BadValue),
+            }
+        }
+
+        Ok(Flow::Return)
+    }
+
+    /// Execute two byte operators
+    fn escape(&mut self, ctxt: &mut ReadCtxt<'_>) -> Result<(), ParseError> {
+        let b1 = ctxt.read_u8()?;
+        if b1 == 0 {
+            // dotsection is deprecated and treated as a no-op
+            self.stack.clear();
+            return Ok(());
+        }
+        let s = &self.stack;
+        let curves = match b1 {
+            35 => {
+                // flex
+                check(s.len() == 13)?;
+                [
+                    [s[0], s[1], s[2], s[3], s[4], s[5]],
+                    [s[6], s[7], s[8], s[9], s[10], s[11]],
+                ]
+            }
+            34 => {
+                // hflex
+                check(s.len() == 7)?;
+                [
+                    [s[0], 0., s[1], s[2], s[3], 0.],
+                    [s[4], 0., s[5], -s[2], s[6], 0.],
+                ]
+            }
+            36 => {
+                // hflex1
+                check(s.len() == 9)?;
+                [
+                    [s[0], s[1], s[2], s[3], s[4], 0.],
+                    [s[5], 0., s[6], s[7], s[8], -(s[1] + s[3] + s[7])],
+                ]
+            }
+            37 => {
+                // flex1
+                check(s.len() == 11)?;
+                let dx = s[0] + s[2] + s[4] + s[6] + s[8];
+                let dy = s[1] + s[3] + s[5] + s[7] + s[9];
+                let (dx6, dy6) = if dx.abs() > dy.abs() {
+                    (s[10], -dy)
+                } else {
+                    (-dx, s[10])
+                };
+                [
+                    [s[0], s[1], s[2], s[3], s[4], s[5]],
+                    [s[6], s[7], s[8], s[9], dx6, dy6],
+                ]
+            }
+            // The arithmetic and storage operators are not supported
+            3..=5 | 9..=12 | 14 | 15 | 18 | 20..=24 | 26..=30 => {
+                return Err(ParseError::UnsupportedCharstringOperator(op2(b1)))
+            }
+            _ => return Err(ParseError::BadValue),
+        };
+        for curve in &curves {
+            self.curve_to(curve);
+        }
+        self.stack.clear();
+
+        Ok(())
+    }
+
+    fn call_subr(&mut self, subr_index: &Index<'_>) -> Result<Flow, ParseError> {
+        let number = self.stack.pop().ok_or(ParseError::BadValue)? as i32;
+        let index = usize::try_from(number + subr_bias(subr_index.count))
+            .map_err(|_| ParseError::BadIndex)?;
+        let subr = subr_index.read_object(index).ok_or(ParseError::BadIndex)?;
+        if self.subr_depth >= MAX_SUBR_DEPTH {
+            return Err(ParseError::LimitExceeded);
+        }
+        self.subr_depth += 1;
+        let flow = self.execute(subr)?;
+        self.subr_depth -= 1;
+        Ok(flow)
+    }
+
+    fn push(&mut self, value: f32) -> Result<(), ParseError> {
+        if self.stack.len() >= MAX_OPERANDS {
+            return Err(ParseError::LimitExceeded);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Handle the stem hint operators, which take pairs of arguments
+    fn stem(&mut self) -> Result<(), ParseError> {
+        if !self.seen_width && self.stack.len() % 2 == 1 {
+            self.stack.remove(0);
+        }
+        self.seen_width = true;
+        check(self.stack.len() % 2 == 0)?;
+        self.n_stems += self.stack.len() / 2;
+        self.stack.clear();
+        Ok(())
+    }
+
+    /// Discard the width if present, which precedes the arguments of the first stack clearing
+    /// operator when there are more than `n_args` arguments.
+    fn width(&mut self, n_args: usize) {
+        if !self.seen_width && self.stack.len() > n_args {
+            self.stack.remove(0);
+        }
+        self.seen_width = true;
+    }
+
+    fn check_len(&self, n_args: usize) -> Result<(), ParseError> {
+        check(self.stack.len() == n_args)
+    }
+
+    fn move_to(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+        self.segments.push(PathSegment::MoveTo(Point {
+            x: self.x,
+            y: self.y,
+        }));
+    }
+
+    fn line_to(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+        self.segments.push(PathSegment::LineTo(Point {
+            x: self.x,
+            y: self.y,
+        }));
+    }
+
+    /// Append a curve from the relative coordinates `[dxa, dya, dxb, dyb, dxc, dyc]`
+    fn curve_to(&mut self, d: &[f32]) {
+        let a = Point {
+            x: self.x + d[0],
+            y: self.y + d[1],
+        };
+        let b = Point {
+            x: a.x + d[2],
+            y: a.y + d[3],
+        };
+        let c = Point {
+            x: b.x + d[4],
+            y: b.y + d[5],
+        };
+        self.x = c.x;
+        self.y = c.y;
+        self.segments.push(PathSegment::CurveTo(a, b, c));
+    }
+}
+
+fn check(cond: bool) -> Result<(), ParseError> {
+    if cond {
+        Ok(())
+    } else {
+        Err(ParseError::BadValue)
+    }
+}
+
+/// Returns the bias applied to subroutine numbers for an INDEX with `count` subroutines
+fn subr_bias(count: usize) -> i32 {
+    if count < 1240 {
+        107
+    } else if count < 33900 {
+        1131
+    } else {
+        32768
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::write::{WriteBinary, WriteBuffer};
+    use crate::cff::{owned, Charset, Encoding, MaybeOwnedIndex, PrivateDict, TopDict, Type1Data};
+
+    fn type1_font<'a>(char_strings: Vec<Vec<u8>>) -> Font<'a> {
+        Font {
+            top_dict: TopDict::new(),
+            char_strings_index: MaybeOwnedIndex::Owned(owned::Index { data: char_strings }),
+            charset: Charset::ISOAdobe,
+            charstring_type: 2,
+            data: CFFVariant::Type1(Type1Data {
+                encoding: Encoding::Standard,
+                private_dict: PrivateDict::new(),
+                local_subr_index: None,
+            }),
+        }
+    }
+
+    fn index_data(objects: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut buffer = WriteBuffer::new();
+        owned::Index::write(&mut buffer, &owned::Index { data: objects }).unwrap();
+        buffer.into_inner()
+    }
+
+    fn point(x: f32, y: f32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn test_lines() {
+        // 50 10 20 rmoveto 100 hlineto 100 vlineto -100 hlineto endchar
+        // 50 is the width
+        let char_string = vec![189, 149, 159, 21, 239, 6, 239, 7, 39, 6, 14];
+        let font = type1_font(vec![vec![14], char_string]);
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        let path = font.glyph_path(1, &global_subr_index).unwrap();
+        let expected = vec![
+            PathSegment::MoveTo(point(10., 20.)),
+            PathSegment::LineTo(point(110., 20.)),
+            PathSegment::LineTo(point(110., 120.)),
+            PathSegment::LineTo(point(10., 120.)),
+        ];
+        assert_eq!(path.segments, expected);
+    }
+
+    #[test]
+    fn test_curves() {
+        // 0 0 rmoveto 10 0 20 10 0 20 rrcurveto 10 20 30 40 5 hvcurveto endchar
+        let char_string = vec![
+            139, 139, 21, 149, 139, 159, 149, 139, 159, 8, 149, 159, 169, 179, 144, 31, 14,
+        ];
+        let font = type1_font(vec![vec![14], char_string]);
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        let path = font.glyph_path(1, &global_subr_index).unwrap();
+        let expected = vec![
+            PathSegment::MoveTo(point(0., 0.)),
+            PathSegment::CurveTo(point(10., 0.), point(30., 10.), point(30., 30.)),
+            PathSegment::CurveTo(point(40., 30.), point(60., 60.), point(65., 100.)),
+        ];
+        assert_eq!(path.segments, expected);
+    }
+
+    #[test]
+    fn test_subrs() {
+        // Local subr 0: 100 hlineto return
+        // Global subr 0: 100 vlineto return
+        // 10 10 rmoveto -107 callsubr -107 callgsubr endchar
+        let char_string = vec![149, 149, 21, 32, 10, 32, 29, 14];
+        let local_subrs = index_data(vec![vec![239, 6, 11]]);
+        let global_subrs = index_data(vec![vec![239, 7, 11]]);
+        let mut font = type1_font(vec![vec![14], char_string]);
+        match &mut font.data {
+            CFFVariant::Type1(type1) => {
+                type1.local_subr_index =
+                    Some(ReadScope::new(&local_subrs).read::<Index<'_>>().unwrap())
+            }
+            CFFVariant::CID(_) | CFFVariant::Synthetic(_) => unreachable!(),
+        }
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        let path = font.glyph_path(1, &global_subr_index).unwrap();
+        let expected = vec![
+            PathSegment::MoveTo(point(10., 10.)),
+            PathSegment::LineTo(point(110., 10.)),
+            PathSegment::LineTo(point(110., 110.)),
+        ];
+        assert_eq!(path.segments, expected);
+    }
+
+    #[test]
+    fn test_hintmask() {
+        // 1 2 3 4 hstemhm 10 20 hintmask 0xFF 0 0 rmoveto endchar
+        // There are 3 stems so the mask is one byte
+        let char_string = vec![140, 141, 142, 143, 18, 149, 159, 19, 0xFF, 139, 139, 21, 14];
+        let font = type1_font(vec![vec![14], char_string]);
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        let path = font.glyph_path(1, &global_subr_index).unwrap();
+        assert_eq!(path.segments, vec![PathSegment::MoveTo(point(0., 0.))]);
+    }
+
+    #[test]
+    fn test_seac() {
+        // With the ISOAdobe charset glyph ids are the same as SIDs.
+        // A (SID 34): 0 0 rmoveto 10 hlineto endchar
+        // acute (SID 125): 0 0 rmoveto 5 vlineto endchar
+        // Glyph 1: 100 200 65 194 endchar (A, acute in Standard Encoding)
+        let mut char_strings = vec![vec![14]; 126];
+        char_strings[34] = vec![139, 139, 21, 149, 6, 14];
+        char_strings[125] = vec![139, 139, 21, 144, 7, 14];
+        char_strings[1] = vec![239, 247, 92, 204, 247, 86, 14];
+        let font = type1_font(char_strings);
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        let path = font.glyph_path(1, &global_subr_index).unwrap();
+        let expected = vec![
+            PathSegment::MoveTo(point(0., 0.)),
+            PathSegment::LineTo(point(10., 0.)),
+            PathSegment::MoveTo(point(100., 200.)),
+            PathSegment::LineTo(point(100., 205.)),
+        ];
+        assert_eq!(path.segments, expected);
+    }
+
+    #[test]
+    fn test_unsupported_operator() {
+        // 1 2 add endchar
+        let char_string = vec![140, 141, 12, 10, 14];
+        let font = type1_font(vec![vec![14], char_string]);
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        assert_eq!(
+            font.glyph_path(1, &global_subr_index).err(),
+            Some(ParseError::UnsupportedCharstringOperator(0x0C0A))
+        );
+    }
+
+    #[test]
+    fn test_dotsection() {
+        // 0 0 rmoveto 1 2 dotsection 10 hlineto endchar
+        let char_string = vec![139, 139, 21, 140, 141, 12, 0, 149, 6, 14];
+        let font = type1_font(vec![vec![14], char_string]);
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        let path = font.glyph_path(1, &global_subr_index).unwrap();
+        let expected = vec![
+            PathSegment::MoveTo(point(0., 0.)),
+            PathSegment::LineTo(point(10., 0.)),
+        ];
+        assert_eq!(path.segments, expected);
+    }
+
+    #[test]
+    fn test_reserved_escape_operator() {
+        // 12 38 is reserved
+        let char_string = vec![12, 38, 14];
+        let font = type1_font(vec![vec![14], char_string]);
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        assert_eq!(
+            font.glyph_path(1, &global_subr_index).err(),
+            Some(ParseError::BadValue)
+        );
+    }
+
+    #[test]
+    fn test_recursive_subr() {
+        // Local subr 0: -107 callsubr
+        // -107 callsubr endchar
+        let char_string = vec![32, 10, 14];
+        let local_subrs = index_data(vec![vec![32, 10]]);
+        let mut font = type1_font(vec![vec![14], char_string]);
+        match &mut font.data {
+            CFFVariant::Type1(type1) => {
+                type1.local_subr_index =
+                    Some(ReadScope::new(&local_subrs).read::<Index<'_>>().unwrap())
+            }
+            CFFVariant::CID(_) | CFFVariant::Synthetic(_) => unreachable!(),
+        }
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        assert_eq!(
+            font.glyph_path(1, &global_subr_index),
+            Err(ParseError::LimitExceeded)
+        );
+    }
+
+    #[test]
+    fn test_operand_stack_limit() {
+        // 49 operands followed by endchar
+        let mut char_string = vec![139; MAX_OPERANDS + 1];
+        char_string.push(14);
+        let font = type1_font(vec![vec![14], char_string]);
+        let global_subrs = index_data(Vec::new());
+        let global_subr_index = ReadScope::new(&global_subrs).read::<Index<'_>>().unwrap();
+
+        assert_eq!(
+            font.glyph_path(1, &global_subr_index),
+            Err(ParseError::LimitExceeded)
+        );
+    }
+}
@@ -0,0 +1,559 @@
+//! CFF2 font format parsing.
+//!
+//! CFF2 is the format of the PostScript outlines in variable OpenType fonts. The structure of the
+//! table is parsed, including the Variation Store, but CharStrings are not interpreted.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/cff2>
+
+use std::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadBinaryDep, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::{U16Be, U32Be, U8};
+use crate::cff::{self, FontDict, Index, Operand, Operator, PrivateDict, Range, TopDict};
+use crate::error::ParseError;
+use crate::tables::F2Dot14;
+
+/// Flag in `ItemVariationData::word_delta_count` indicating 32-bit and 16-bit deltas
+const LONG_WORDS: u16 = 0x8000;
+
+/// Top level representation of a CFF2 table
+pub struct CFF2<'a> {
+    pub header: Header,
+    pub top_dict: TopDict,
+    pub global_subr_index: Index<'a>,
+    pub char_strings_index: Index<'a>,
+    /// The Variation Store, which is present in variable fonts
+    pub vstore: Option<ItemVariationStore<'a>>,
+    /// Maps glyphs to the Font DICTs of `fonts`, may be absent if there is only one
+    pub fd_select: Option<FDSelect<'a>>,
+    /// The Font DICTs of the FDArray
+    pub fonts: Vec<Font<'a>>,
+}
+
+/// CFF2 Header
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub major: u8,
+    pub minor: u8,
+    pub header_size: u8,
+    pub top_dict_length: u16,
+}
+
+/// A Font DICT of the FDArray along with its Private DICT and Local Subrs
+pub struct Font<'a> {
+    pub font_dict: FontDict,
+    /// The Private DICT, with any blended values resolved to the default instance
+    pub private_dict: PrivateDict,
+    pub local_subr_index: Option<Index<'a>>,
+}
+
+/// Reads a CFF2 INDEX, which has a 32-bit count, into an `Index`
+pub enum CFF2Index {}
+
+/// Maps glyph ids to Font DICTs
+pub enum FDSelect<'a> {
+    /// Formats 0 and 3, which are the same as in CFF
+    CFF(cff::FDSelect<'a>),
+    Format4 {
+        /// `first` is the first glyph of the range and `n_left` is its Font DICT index
+        ranges: ReadArray<'a, Range<u32, u16>>,
+        sentinel: u32,
+    },
+}
+
+/// Item Variation Store, holding the deltas of the variable values in the font
+pub struct ItemVariationStore<'a> {
+    pub format: u16,
+    pub variation_region_list: VariationRegionList<'a>,
+    pub item_variation_data: Vec<ItemVariationData<'a>>,
+}
+
+/// The regions of the design space that deltas apply to
+pub struct VariationRegionList<'a> {
+    pub axis_count: u16,
+    /// Each region holds `axis_count` records, one for each axis
+    pub variation_regions: Vec<ReadArray<'a, RegionAxisCoordinates>>,
+}
+
+/// The extent of a region along a single axis, in normalized coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RegionAxisCoordinates {
+    pub start_coord: F2Dot14,
+    pub peak_coord: F2Dot14,
+    pub end_coord: F2Dot14,
+}
+
+/// A set of regions and the deltas of the items that vary over them
+///
+/// In CFF2 the `vsindex` operator selects one of these and `blend` takes one delta per region.
+pub struct ItemVariationData<'a> {
+    pub item_count: u16,
+    /// The number of leading deltas in each delta set that are 16-bit, or 32-bit if the
+    /// `LONG_WORDS` flag is set. The remaining deltas are 8-bit, or 16-bit.
+    pub word_delta_count: u16,
+    pub region_indexes: ReadArray<'a, U16Be>,
+    delta_sets: &'a [u8],
+}
+
+impl<'a> ReadBinary<'a> for CFF2<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        // Offsets in the DICTs are from the start of the CFF2 data
+        let scope = ctxt.scope();
+
+        let header = ctxt.read::<Header>()?;
+        let top_dict_offset = usize::from(header.header_size);
+        let top_dict_length = usize::from(header.top_dict_length);
+        // The Top DICT does not contain blend so there are no region counts
+        let top_dict =
+            TopDict::read_cff2(scope.offset_length(top_dict_offset, top_dict_length)?, &[])?;
+
+        // The Global Subr INDEX immediately follows the Top DICT
+        let global_subr_index = scope
+            .offset(top_dict_offset + top_dict_length)
+            .read::<CFF2Index>()?;
+
+        let vstore = top_dict
+            .get_i32(Operator::VStore)
+            .transpose()?
+            .map(|offset| {
+                // The Variation Store data is preceded by its length
+                let mut ctxt = offset_scope(&scope, offset)?.ctxt();
+                let length = usize::from(ctxt.read_u16be()?);
+                ctxt.read_scope(length)?.read::<ItemVariationStore<'_>>()
+            })
+            .transpose()?;
+        let region_counts = match &vstore {
+            Some(vstore) => vstore
+                .item_variation_data
+                .iter()
+                .map(|data| data.region_indexes.len())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let offset = top_dict
+            .get_i32(Operator::CharStrings)
+            .ok_or(ParseError::MissingValue)??;
+        let char_strings_index = offset_scope(&scope, offset)?.read::<CFF2Index>()?;
+
+        let offset = top_dict
+            .get_i32(Operator::FDArray)
+            .ok_or(ParseError::MissingValue)??;
+        let font_dict_index = offset_scope(&scope, offset)?.read::<CFF2Index>()?;
+        let mut fonts = Vec::with_capacity(font_dict_index.count);
+        for object in font_dict_index.iter() {
+            let font_dict = FontDict::read_cff2(ReadScope::new(object), &region_counts)?;
+            fonts.push(read_font(&scope, font_dict, &region_counts)?);
+        }
+
+        let fd_select = top_dict
+            .get_i32(Operator::FDSelect)
+            .transpose()?
+            .map(|offset| {
+                offset_scope(&scope, offset)?.read_dep::<FDSelect<'_>>(char_strings_index.count)
+            })
+            .transpose()?;
+        if fd_select.is_none() && fonts.len() > 1 {
+            return Err(ParseError::MissingValue);
+        }
+
+        Ok(CFF2 {
+            header,
+            top_dict,
+            global_subr_index,
+            char_strings_index,
+            vstore,
+            fd_select,
+            fonts,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for Header {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let major = ctxt.read::<U8>()?;
+        ctxt.check_version(major == 2)?;
+        let minor = ctxt.read::<U8>()?;
+        let header_size = ctxt.read::<U8>()?;
+        let top_dict_length = ctxt.read::<U16Be>()?;
+        ctxt.check(header_size >= 5)?;
+
+        Ok(Header {
+            major,
+            minor,
+            header_size,
+            top_dict_length,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for CFF2Index {
+    type HostType = Index<'a>;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Index<'a>, ParseError> {
+        let count = usize::try_from(ctxt.read_u32be()?)?;
+        cff::read_index_data(ctxt, count)
+    }
+}
+
+impl<'a> ReadBinaryDep<'a> for FDSelect<'a> {
+    type Args = usize;
+    type HostType = Self;
+
+    fn read_dep(ctxt: &mut ReadCtxt<'a>, n_glyphs: usize) -> Result<Self, ParseError> {
+        match ctxt.scope().read::<U8>()? {
+            4 => {
+                let _format = ctxt.read_u8()?;
+                let n_ranges = usize::try_from(ctxt.read_u32be()?)?;
+                let ranges = ctxt.read_array(n_ranges)?;
+                let sentinel = ctxt.read_u32be()?;
+                Ok(FDSelect::Format4 { ranges, sentinel })
+            }
+            _ => ctxt
+                .read_dep::<cff::FDSelect<'_>>(n_glyphs)
+                .map(FDSelect::CFF),
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for Range<u32, u16> {
+    type ReadType = (U32Be, U16Be);
+    fn from((first, n_left): (u32, u16)) -> Self {
+        Range { first, n_left }
+    }
+}
+
+impl<'a> ReadBinary<'a> for ItemVariationStore<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let format = ctxt.read_u16be()?;
+        ctxt.check_version(format == 1)?;
+        let variation_region_list_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let item_variation_data_count = usize::from(ctxt.read_u16be()?);
+        let item_variation_data_offsets = ctxt.read_array::<U32Be>(item_variation_data_count)?;
+
+        let variation_region_list = scope
+            .offset_checked(variation_region_list_offset)?
+            .read::<VariationRegionList<'_>>()?;
+        let item_variation_data = item_variation_data_offsets
+            .iter()
+            .map(|offset| {
+                scope
+                    .offset_checked(usize::try_from(offset)?)?
+                    .read::<ItemVariationData<'_>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ItemVariationStore {
+            format,
+            variation_region_list,
+            item_variation_data,
+        })
+    }
+}
+
+impl<'a> ReadBinary<'a> for VariationRegionList<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let axis_count = ctxt.read_u16be()?;
+        let region_count = ctxt.read_u16be()?;
+        let variation_regions = (0..region_count)
+            .map(|_| ctxt.read_array(usize::from(axis_count)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(VariationRegionList {
+            axis_count,
+            variation_regions,
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for RegionAxisCoordinates {
+    type ReadType = (U16Be, U16Be, U16Be);
+    fn from((start_coord, peak_coord, end_coord): (u16, u16, u16)) -> Self {
+        RegionAxisCoordinates {
+            start_coord: F2Dot14::new(start_coord),
+            peak_coord: F2Dot14::new(peak_coord),
+            end_coord: F2Dot14::new(end_coord),
+        }
+    }
+}
+
+impl<'a> ReadBinary<'a> for ItemVariationData<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let item_count = ctxt.read_u16be()?;
+        let word_delta_count = ctxt.read_u16be()?;
+        let region_index_count = ctxt.read_u16be()?;
+        ctxt.check(word_delta_count & !LONG_WORDS <= region_index_count)?;
+        let region_indexes = ctxt.read_array(usize::from(region_index_count))?;
+        let row_size = delta_set_row_size(word_delta_count, region_index_count);
+        let delta_sets = ctxt.read_slice(usize::from(item_count) * row_size)?;
+
+        Ok(ItemVariationData {
+            item_count,
+            word_delta_count,
+            region_indexes,
+            delta_sets,
+        })
+    }
+}
+
+impl<'a> CFF2<'a> {
+    /// Returns the number of glyphs in the font
+    pub fn glyph_count(&self) -> usize {
+        self.char_strings_index.count
+    }
+
+    /// Returns the index into `fonts` of the Font DICT for `glyph_id`
+    ///
+    /// Returns `None` if `glyph_id` is not in the font.
+    pub fn font_dict_index(&self, glyph_id: u16) -> Option<usize> {
+        if usize::from(glyph_id) >= self.glyph_count() {
+            return None;
+        }
+
+        match &self.fd_select {
+            Some(fd_select) => fd_select.font_dict_index(glyph_id).map(usize::from),
+            None => Some(0),
+        }
+    }
+}
+
+impl<'a> FDSelect<'a> {
+    /// Returns the index of the Font DICT for the supplied `glyph_id`
+    pub fn font_dict_index(&self, glyph_id: u16) -> Option<u16> {
+        match self {
+            FDSelect::CFF(fd_select) => fd_select.font_dict_index(glyph_id).map(u16::from),
+            FDSelect::Format4 { ranges, sentinel } => {
+                let glyph_id = u32::from(glyph_id);
+                let ends = ranges
+                    .iter()
+                    .skip(1)
+                    .map(|range| range.first)
+                    .chain(std::iter::once(*sentinel));
+                ranges
+                    .iter()
+                    .zip(ends)
+                    .find(|(range, end)| glyph_id >= range.first && glyph_id < *end)
+                    .map(|(range, _)| range.n_left)
+            }
+        }
+    }
+}
+
+impl<'a> ItemVariationData<'a> {
+    /// Returns the deltas of the item at `item_index`, one for each region of `region_indexes`
+    pub fn delta_set(&self, item_index: u16) -> Option<Vec<i32>> {
+        if item_index >= self.item_count {
+            return None;
+        }
+
+        let region_index_count = u16::try_from(self.region_indexes.len()).ok()?;
+        let row_size = delta_set_row_size(self.word_delta_count, region_index_count);
+        let start = usize::from(item_index) * row_size;
+        let mut ctxt = ReadScope::new(&self.delta_sets[start..start + row_size]).ctxt();
+        let long_words = self.word_delta_count & LONG_WORDS != 0;
+        let word_count = self.word_delta_count & !LONG_WORDS;
+
+        (0..region_index_count)
+            .map(|index| match (long_words, index < word_count) {
+                (true, true) => ctxt.read_i32be(),
+                (true, false) => ctxt.read_i16be().map(i32::from),
+                (false, true) => ctxt.read_i16be().map(i32::from),
+                (false, false) => ctxt.read_i8().map(i32::from),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+    }
+}
+
+/// Read the Private DICT and Local Subrs of `font_dict`
+fn read_font<'a>(
+    scope: &ReadScope<'a>,
+    font_dict: FontDict,
+    region_counts: &[usize],
+) -> Result<Font<'a>, ParseError> {
+    let (private_dict_offset, private_dict_length) = match font_dict.get(Operator::Private) {
+        Some([Operand::Offset(length), Operand::Offset(offset)]) => {
+            (usize::try_from(*offset)?, usize::try_from(*length)?)
+        }
+        Some(_) => return Err(ParseError::BadValue),
+        None => return Err(ParseError::MissingValue),
+    };
+    let private_dict = PrivateDict::read_cff2(
+        scope.offset_length(private_dict_offset, private_dict_length)?,
+        region_counts,
+    )?;
+
+    // The Local Subrs offset is relative to the start of the Private DICT
+    let local_subr_index = private_dict
+        .get_i32(Operator::Subrs)
+        .transpose()?
+        .map(|offset| {
+            let offset = i32::try_from(private_dict_offset)?
+                .checked_add(offset)
+                .ok_or(ParseError::BadOffset)?;
+            offset_scope(scope, offset)?.read::<CFF2Index>()
+        })
+        .transpose()?;
+
+    Ok(Font {
+        font_dict,
+        private_dict,
+        local_subr_index,
+    })
+}
+
+/// Returns the scope starting at `offset` within `scope`, which must be in range.
+fn offset_scope<'a>(scope: &ReadScope<'a>, offset: i32) -> Result<ReadScope<'a>, ParseError> {
+    let offset = usize::try_from(offset).map_err(|_| ParseError::BadOffset)?;
+    scope.offset_checked(offset)
+}
+
+fn delta_set_row_size(word_delta_count: u16, region_index_count: u16) -> usize {
+    let word_count = usize::from(word_delta_count & !LONG_WORDS);
+    let region_index_count = usize::from(region_index_count);
+    let word_size = if word_delta_count & LONG_WORDS != 0 {
+        4
+    } else {
+        2
+    };
+    word_count * word_size + region_index_count.saturating_sub(word_count) * (word_size / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    const CFF2_TABLE: &[u8] = &[
+        // Header
+        2, 0, 5, 0, 26,
+        // Top DICT
+        29, 0, 0, 0, 78, 17,    // CharStrings
+        29, 0, 0, 0, 90, 12, 36, // FDArray
+        29, 0, 0, 0, 112, 12, 37, // FDSelect
+        29, 0, 0, 0, 35, 24,    // vstore
+        // Global Subr INDEX (31)
+        0, 0, 0, 0,
+        // Variation Store (35)
+        0, 41, // length
+        0, 1, // format
+        0, 0, 0, 12, // variationRegionListOffset
+        0, 1, // itemVariationDataCount
+        0, 0, 0, 28, // itemVariationDataOffsets
+        // VariationRegionList
+        0, 1, // axisCount
+        0, 2, // regionCount
+        0x00, 0x00, 0x40, 0x00, 0x40, 0x00, // region 0: 0, 1, 1
+        0xC0, 0x00, 0xC0, 0x00, 0x00, 0x00, // region 1: -1, -1, 0
+        // ItemVariationData
+        0, 1, // itemCount
+        0, 1, // wordDeltaCount
+        0, 2, // regionIndexCount
+        0, 0, 0, 1, // regionIndexes
+        0xFE, 0xD4, 0x14, // deltaSets: -300, 20
+        // CharStrings INDEX (78)
+        0, 0, 0, 3, 1, 1, 2, 3, 4, 0x0E, 0x0E, 0x0E,
+        // FDArray INDEX (90)
+        0, 0, 0, 2, 1, 1, 8, 15,
+        152, 29, 0, 0, 0, 133, 18, // Private: 13 bytes at 133
+        141, 29, 0, 0, 0, 154, 18, // Private: 2 bytes at 154
+        // FDSelect (112)
+        4, // format
+        0, 0, 0, 2, // nRanges
+        0, 0, 0, 0, 0, 0, // first: 0, fd: 0
+        0, 0, 0, 2, 0, 1, // first: 2, fd: 1
+        0, 0, 0, 3, // sentinel
+        // Private DICT 0 (133)
+        139, 22, // 0 vsindex
+        129, 139, 144, 134, 141, 142, 141, 23, 6, // -10 0 5 -5 2 3 2 blend BlueValues
+        152, 19, // Subrs
+        // Local Subr INDEX (146)
+        0, 0, 0, 1, 1, 1, 2, 0x0B,
+        // Private DICT 1 (154)
+        189, 10, // StdHW
+    ];
+
+    #[test]
+    fn read_cff2() {
+        let cff2 = ReadScope::new(CFF2_TABLE).read::<CFF2<'_>>().unwrap();
+
+        assert_eq!(cff2.header.major, 2);
+        assert_eq!(cff2.glyph_count(), 3);
+        assert_eq!(cff2.global_subr_index.count, 0);
+        assert_eq!(cff2.fonts.len(), 2);
+        assert_eq!(cff2.font_dict_index(1), Some(0));
+        assert_eq!(cff2.font_dict_index(2), Some(1));
+        assert_eq!(cff2.font_dict_index(3), None);
+
+        let font = &cff2.fonts[0];
+        assert_eq!(
+            font.private_dict.get(Operator::BlueValues),
+            Some([Operand::Integer(-10), Operand::Integer(0)].as_ref())
+        );
+        assert_eq!(font.private_dict.get(Operator::Blend), None);
+        assert_eq!(
+            font.local_subr_index.as_ref().map(|index| index.count),
+            Some(1)
+        );
+        let font = &cff2.fonts[1];
+        assert_eq!(
+            font.private_dict.get(Operator::StdHW),
+            Some([Operand::Integer(50)].as_ref())
+        );
+        assert!(font.local_subr_index.is_none());
+
+        let vstore = cff2.vstore.unwrap();
+        let region_list = &vstore.variation_region_list;
+        assert_eq!(region_list.axis_count, 1);
+        assert_eq!(region_list.variation_regions.len(), 2);
+        let coords = region_list.variation_regions[1].get_item(0);
+        assert_eq!(f32::from(coords.start_coord), -1.);
+        assert_eq!(f32::from(coords.peak_coord), -1.);
+        assert_eq!(f32::from(coords.end_coord), 0.);
+        let data = &vstore.item_variation_data[0];
+        assert_eq!(data.region_indexes.to_vec(), vec![0, 1]);
+        assert_eq!(data.delta_set(0), Some(vec![-300, 20]));
+        assert_eq!(data.delta_set(1), None);
+    }
+
+    #[test]
+    fn bad_version() {
+        let mut data = CFF2_TABLE.to_vec();
+        data[0] = 1;
+        match ReadScope::new(&data).read::<CFF2<'_>>() {
+            Err(ParseError::BadVersion) => {}
+            _ => panic!("expected ParseError::BadVersion"),
+        }
+    }
+
+    #[test]
+    fn blend_in_cff_dict() {
+        // 1 1 blend StdHW is not valid in a CFF DICT
+        let data = [140, 140, 23, 10];
+        match ReadScope::new(&data).read::<PrivateDict>() {
+            Err(ParseError::BadValue) => {}
+            _ => panic!("expected ParseError::BadValue"),
+        }
+    }
+
+    #[test]
+    fn blend_missing_deltas() {
+        // 10 5 1 blend StdHW with two regions is missing a delta
+        let data = [149, 144, 140, 23, 10];
+        match PrivateDict::read_cff2(ReadScope::new(&data), &[2]) {
+            Err(ParseError::BadValue) => {}
+            _ => panic!("expected ParseError::BadValue"),
+        }
+    }
+}
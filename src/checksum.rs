@@ -2,11 +2,21 @@
 
 //! Checksum calculation routines.
 
-use std::num::Wrapping;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::num::Wrapping;
 
 use crate::binary::read::ReadScope;
 use crate::binary::U32Be;
 use crate::error::ParseError;
+use crate::tables::{OffsetTable, OpenTypeFile, OpenTypeFont};
+use crate::tag;
+
+/// The value that the checksum of a whole font, including `checkSumAdjustment`, should equal.
+const CHECKSUM_MAGIC: u32 = 0xB1B0AFBA;
+
+/// Offset of `checkSumAdjustment` in the `head` table.
+const CHECKSUM_ADJUSTMENT_OFFSET: usize = 8;
 
 /// Calculate a checksum of `data` according to the OpenType table checksum algorithm
 ///
@@ -19,9 +29,99 @@ pub fn table_checksum(data: &[u8]) -> Result<Wrapping<u32>, ParseError> {
     Ok(array.iter().map(Wrapping).sum())
 }
 
+/// Verify the table checksums of the font in `data`, along with its `checkSumAdjustment`.
+///
+/// Returns the tags of the tables that do not match the checksum in the table directory. If all of
+/// the tables match but the `checkSumAdjustment` of the font is wrong the `head` tag is returned.
+/// The adjustment is not checked for font collections, where it is not well defined. The returned
+/// tags are empty for a font with correct checksums.
+pub fn verify_font(data: &[u8]) -> Result<Vec<u32>, ParseError> {
+    let scope = ReadScope::new(data);
+    let fontfile = scope.read::<OpenTypeFile<'_>>()?;
+    match fontfile.font {
+        OpenTypeFont::Single(offset_table) => {
+            let mut bad_tags = verify_tables(data, &offset_table)?;
+            if bad_tags.is_empty() && !verify_checksum_adjustment(data, &offset_table)? {
+                bad_tags.push(tag::HEAD);
+            }
+            Ok(bad_tags)
+        }
+        OpenTypeFont::Collection(ttc) => {
+            let mut bad_tags = Vec::new();
+            for offset in &ttc.offset_tables {
+                let offset_table = scope
+                    .offset(usize::try_from(offset)?)
+                    .read::<OffsetTable<'_>>()?;
+                for tag in verify_tables(data, &offset_table)? {
+                    if !bad_tags.contains(&tag) {
+                        bad_tags.push(tag);
+                    }
+                }
+            }
+            Ok(bad_tags)
+        }
+    }
+}
+
+fn verify_tables(data: &[u8], offset_table: &OffsetTable<'_>) -> Result<Vec<u32>, ParseError> {
+    let mut bad_tags = Vec::new();
+    for record in &offset_table.table_records {
+        let table = ReadScope::new(data)
+            .offset_length(
+                usize::try_from(record.offset)?,
+                usize::try_from(record.length)?,
+            )?
+            .data();
+        let mut checksum = padded_checksum(table)?;
+        if record.table_tag == tag::HEAD {
+            checksum -= read_u32(table, CHECKSUM_ADJUSTMENT_OFFSET)?;
+        }
+        if checksum.0 != record.checksum {
+            bad_tags.push(record.table_tag);
+        }
+    }
+    Ok(bad_tags)
+}
+
+fn verify_checksum_adjustment(
+    data: &[u8],
+    offset_table: &OffsetTable<'_>,
+) -> Result<bool, ParseError> {
+    let head_record = match offset_table.find_table_record(tag::HEAD) {
+        Some(head_record) => head_record,
+        None => return Ok(true),
+    };
+    let head_offset = usize::try_from(head_record.offset)?;
+    let adjustment = read_u32(data, head_offset + CHECKSUM_ADJUSTMENT_OFFSET)?;
+    // The checksum of the whole font is calculated with `checkSumAdjustment` set to zero
+    let font_checksum = padded_checksum(data)? - adjustment;
+    Ok((Wrapping(CHECKSUM_MAGIC) - font_checksum) == adjustment)
+}
+
+/// Calculate the checksum of `data`, padding it with zeros to a multiple of 4 bytes.
+fn padded_checksum(data: &[u8]) -> Result<Wrapping<u32>, ParseError> {
+    let aligned_len = data.len() - data.len() % 4;
+    let mut checksum = table_checksum(&data[..aligned_len])?;
+    if aligned_len < data.len() {
+        let mut tail = [0; 4];
+        tail[..data.len() - aligned_len].copy_from_slice(&data[aligned_len..]);
+        checksum += Wrapping(u32::from_be_bytes(tail));
+    }
+    Ok(checksum)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<Wrapping<u32>, ParseError> {
+    let value = ReadScope::new(data).offset(offset).ctxt().read_u32be()?;
+    Ok(Wrapping(value))
+}
+
 #[cfg(test)]
 mod tests {
     use super::Wrapping;
+    use crate::binary::read::ReadScope;
+    use crate::tables::{OpenTypeFile, OpenTypeFont};
+    use crate::tag;
+    use crate::tests::read_fixture;
 
     #[test]
     fn test_table_checksum() {
@@ -36,4 +136,40 @@ mod tests {
 
         assert_eq!(super::table_checksum(&data).unwrap(), Wrapping(1));
     }
+
+    fn table_offset(data: &[u8], table_tag: u32) -> usize {
+        let fontfile = ReadScope::new(data).read::<OpenTypeFile<'_>>().unwrap();
+        match fontfile.font {
+            OpenTypeFont::Single(offset_table) => {
+                offset_table.find_table_record(table_tag).unwrap().offset as usize
+            }
+            OpenTypeFont::Collection(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_verify_font() {
+        let buffer = read_fixture("tests/fonts/opentype/test-font.ttf");
+
+        assert_eq!(super::verify_font(&buffer).unwrap(), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_verify_font_corrupt_table() {
+        let mut buffer = read_fixture("tests/fonts/opentype/test-font.ttf");
+        // Change the ascender in the hhea table
+        let hhea_offset = table_offset(&buffer, tag::HHEA);
+        buffer[hhea_offset + 5] ^= 1;
+
+        assert_eq!(super::verify_font(&buffer).unwrap(), vec![tag::HHEA]);
+    }
+
+    #[test]
+    fn test_verify_font_corrupt_adjustment() {
+        let mut buffer = read_fixture("tests/fonts/opentype/test-font.ttf");
+        let head_offset = table_offset(&buffer, tag::HEAD);
+        buffer[head_offset + 11] ^= 1;
+
+        assert_eq!(super::verify_font(&buffer).unwrap(), vec![tag::HEAD]);
+    }
 }
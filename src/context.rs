@@ -4,7 +4,7 @@ use std::marker::PhantomData;
 use std::rc::Rc;
 
 use crate::gdef;
-use crate::layout::{ClassDef, Coverage, GDEFTable};
+use crate::layout::{ClassDef, Coverage, GDEFTable, LookupCacheItem};
 
 #[derive(Copy, Clone)]
 pub struct LookupFlag(pub u16);
@@ -21,6 +21,7 @@ pub struct MatchType {
     ignore_bases: bool,
     ignore_ligatures: bool,
     ignore_marks: IgnoreMarks,
+    mark_filtering_set: Option<u16>,
 }
 
 pub enum GlyphTable<'a> {
@@ -96,6 +97,10 @@ impl LookupFlag {
             IgnoreMarks::NoIgnoreMarks
         }
     }
+
+    pub fn get_use_mark_filtering_set(self) -> bool {
+        (self.0 & 0x0010) != 0
+    }
 }
 
 impl MatchType {
@@ -104,6 +109,7 @@ impl MatchType {
             ignore_bases: false,
             ignore_ligatures: false,
             ignore_marks: IgnoreMarks::IgnoreAllMarks,
+            mark_filtering_set: None,
         }
     }
 
@@ -112,6 +118,7 @@ impl MatchType {
             ignore_bases: true,
             ignore_ligatures: true,
             ignore_marks: IgnoreMarks::NoIgnoreMarks,
+            mark_filtering_set: None,
         }
     }
 
@@ -120,6 +127,15 @@ impl MatchType {
             ignore_bases: lookup_flag.get_ignore_bases(),
             ignore_ligatures: lookup_flag.get_ignore_ligatures(),
             ignore_marks: lookup_flag.get_ignore_marks(),
+            mark_filtering_set: None,
+        }
+    }
+
+    /// Build a `MatchType` from the lookup flag and mark filtering set of `lookup`
+    pub fn from_lookup<T>(lookup: &LookupCacheItem<T>) -> MatchType {
+        MatchType {
+            mark_filtering_set: lookup.mark_filtering_set,
+            ..MatchType::from_lookup_flag(lookup.lookup_flag)
         }
     }
 
@@ -127,6 +143,7 @@ impl MatchType {
         if !self.ignore_bases
             && !self.ignore_ligatures
             && self.ignore_marks == IgnoreMarks::NoIgnoreMarks
+            && self.mark_filtering_set.is_none()
         {
             // fast path that doesn't require checking glyph_class
             return true;
@@ -138,14 +155,29 @@ impl MatchType {
         if self.ignore_ligatures && glyph_class == 2 {
             return false;
         }
-        match self.ignore_marks {
+        if glyph_class != 3 {
+            return true;
+        }
+        let keep_mark = match self.ignore_marks {
             IgnoreMarks::NoIgnoreMarks => true,
-            IgnoreMarks::IgnoreAllMarks => glyph_class != 3,
+            IgnoreMarks::IgnoreAllMarks => false,
             IgnoreMarks::IgnoreMarksExcept(keep_class) => {
                 let mark_attach_class =
                     gdef::mark_attach_class(opt_gdef_table, glyph.get_glyph_index());
-                (glyph_class != 3) || (mark_attach_class == u16::from(keep_class))
+                mark_attach_class == u16::from(keep_class)
+            }
+        };
+        // Marks that are not in the mark filtering set are skipped
+        match self.mark_filtering_set {
+            Some(mark_glyph_set) => {
+                keep_mark
+                    && gdef::is_in_mark_glyph_set(
+                        opt_gdef_table,
+                        mark_glyph_set,
+                        glyph.get_glyph_index(),
+                    )
             }
+            None => keep_mark,
         }
     }
 
@@ -1,7 +1,7 @@
 //! Error types
 
 use crate::binary::read::ReadEof;
-use std::fmt;
+use core::fmt;
 
 /// Error returned from font shaping functions
 #[derive(Debug)]
@@ -22,8 +22,8 @@ impl From<ParseError> for ShapingError {
     }
 }
 
-impl From<std::num::TryFromIntError> for ShapingError {
-    fn from(_error: std::num::TryFromIntError) -> Self {
+impl From<core::num::TryFromIntError> for ShapingError {
+    fn from(_error: core::num::TryFromIntError) -> Self {
         ShapingError::Parse(ParseError::BadValue)
     }
 }
@@ -37,6 +37,7 @@ impl fmt::Display for ShapingError {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ShapingError {}
 
 /// Error returned from font shaping Indic scripts
@@ -61,6 +62,9 @@ pub enum ParseError {
     MissingValue,
     CompressionError,
     NotImplemented,
+    /// A valid CharString operator that is not supported by the interpreter. Two byte operators
+    /// are encoded as `12 << 8 | b1`, the same as CFF DICT operators.
+    UnsupportedCharstringOperator(u16),
 }
 
 impl From<ReadEof> for ParseError {
@@ -69,8 +73,8 @@ impl From<ReadEof> for ParseError {
     }
 }
 
-impl From<std::num::TryFromIntError> for ParseError {
-    fn from(_error: std::num::TryFromIntError) -> Self {
+impl From<core::num::TryFromIntError> for ParseError {
+    fn from(_error: core::num::TryFromIntError) -> Self {
         ParseError::BadValue
     }
 }
@@ -87,10 +91,14 @@ impl fmt::Display for ParseError {
             ParseError::MissingValue => write!(f, "an expected data value was missing"),
             ParseError::CompressionError => write!(f, "compression error"),
             ParseError::NotImplemented => write!(f, "feature not implemented"),
+            ParseError::UnsupportedCharstringOperator(op) => {
+                write!(f, "unsupported CharString operator {:#06x}", op)
+            }
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ParseError {}
 
 impl fmt::Display for IndicError {
@@ -105,6 +113,7 @@ impl fmt::Display for IndicError {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for IndicError {}
 
 /// Errors that originate when writing binary data
@@ -114,8 +123,8 @@ pub enum WriteError {
     NotImplemented,
 }
 
-impl From<std::num::TryFromIntError> for WriteError {
-    fn from(_error: std::num::TryFromIntError) -> Self {
+impl From<core::num::TryFromIntError> for WriteError {
+    fn from(_error: core::num::TryFromIntError) -> Self {
         WriteError::BadValue
     }
 }
@@ -129,6 +138,7 @@ impl fmt::Display for WriteError {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for WriteError {}
 
 /// Enum that can hold read (`ParseError`) and write errors
@@ -159,4 +169,5 @@ impl fmt::Display for ReadWriteError {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ReadWriteError {}
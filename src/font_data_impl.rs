@@ -4,6 +4,7 @@ use std::rc::Rc;
 
 use rustc_hash::FxHashMap;
 
+use crate::big5::unicode_to_big5;
 use crate::binary::read::ReadScope;
 use crate::bitmap::cbdt::{self, CBDTTable, CBLCTable};
 use crate::bitmap::sbix::Sbix as SbixTable;
@@ -11,20 +12,13 @@ use crate::bitmap::{BitDepth, BitmapGlyph};
 use crate::error::ParseError;
 use crate::glyph_info::GlyphNames;
 use crate::layout::{new_layout_cache, GDEFTable, LayoutCache, LayoutTable, GPOS, GSUB};
-use crate::tables::cmap::{Cmap, CmapSubtable, EncodingId, EncodingRecord, PlatformId};
+use crate::macroman::char_to_macroman;
+use crate::tables::cmap::{find_good_cmap_subtable, Cmap, CmapSubtable, Encoding, EncodingRecord};
 use crate::tables::os2::Os2;
 use crate::tables::svg::SvgTable;
 use crate::tables::{FontTableProvider, HeadTable, HheaTable, MaxpTable};
 use crate::{glyph_info, tag};
 
-#[derive(Copy, Clone)]
-pub enum Encoding {
-    Unicode = 1,
-    Symbol = 2,
-    AppleRoman = 3,
-    Big5 = 4,
-}
-
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum OutlineFormat {
     Glyf,
@@ -464,68 +458,76 @@ fn charmap_info(cmap_buf: &[u8]) -> Result<Option<(Encoding, u32)>, ParseError>
         .map(|(encoding, encoding_record)| (encoding, encoding_record.offset)))
 }
 
-pub fn read_cmap_subtable<'a>(
-    cmap: &Cmap<'a>,
-) -> Result<Option<(Encoding, CmapSubtable<'a>)>, ParseError> {
-    if let Some((encoding, encoding_record)) = find_good_cmap_subtable(&cmap) {
-        let subtable = cmap
-            .scope
-            .offset(usize::try_from(encoding_record.offset)?)
-            .read::<CmapSubtable<'_>>()?;
-        Ok(Some((encoding, subtable)))
-    } else {
-        Ok(None)
-    }
+/// The preferred `cmap` subtable of a font, selected once for mapping many characters.
+pub struct CmapCache<'a> {
+    encoding: Encoding,
+    encoding_record: EncodingRecord,
+    subtable: CmapSubtable<'a>,
 }
 
-pub fn find_good_cmap_subtable(cmap: &Cmap<'_>) -> Option<(Encoding, EncodingRecord)> {
-    // MS UNICODE, UCS-4 (32 bit)
-    if let Some(encoding_record) =
-        cmap.find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_UNICODE_UCS4)
-    {
-        return Some((Encoding::Unicode, encoding_record));
-    }
-
-    // MS UNICODE, UCS-2 (16 bit)
-    if let Some(encoding_record) =
-        cmap.find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_UNICODE_BMP_UCS2)
-    {
-        return Some((Encoding::Unicode, encoding_record));
+impl<'a> CmapCache<'a> {
+    /// Select and read the preferred subtable of `cmap`.
+    ///
+    /// Returns `None` if `cmap` does not contain a supported subtable.
+    pub fn new(cmap: &Cmap<'a>) -> Result<Option<Self>, ParseError> {
+        match find_good_cmap_subtable(cmap) {
+            Some((encoding, encoding_record)) => {
+                let subtable = cmap
+                    .scope
+                    .offset(usize::try_from(encoding_record.offset)?)
+                    .read::<CmapSubtable<'_>>()?;
+                Ok(Some(CmapCache {
+                    encoding,
+                    encoding_record,
+                    subtable,
+                }))
+            }
+            None => Ok(None),
+        }
     }
 
-    // Apple UNICODE, UCS-4 (32 bit)
-    if let Some(encoding_record) =
-        cmap.find_subtable(PlatformId::UNICODE, EncodingId::MACINTOSH_UNICODE_UCS4)
-    {
-        return Some((Encoding::Unicode, encoding_record));
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
     }
 
-    // Any UNICODE table
-    if let Some(encoding_record) = cmap.find_subtable_for_platform(PlatformId::UNICODE) {
-        return Some((Encoding::Unicode, encoding_record));
+    pub fn platform_id(&self) -> u16 {
+        self.encoding_record.platform_id
     }
 
-    // MS Symbol
-    if let Some(encoding_record) =
-        cmap.find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_SYMBOL)
-    {
-        return Some((Encoding::Symbol, encoding_record));
+    pub fn encoding_id(&self) -> u16 {
+        self.encoding_record.encoding_id
     }
 
-    // Apple Roman
-    if let Some(encoding_record) =
-        cmap.find_subtable(PlatformId::MACINTOSH, EncodingId::MACINTOSH_APPLE_ROMAN)
-    {
-        return Some((Encoding::AppleRoman, encoding_record));
+    pub fn subtable(&self) -> &CmapSubtable<'a> {
+        &self.subtable
     }
 
-    // Big5
-    if let Some(encoding_record) = cmap.find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_BIG5)
-    {
-        return Some((Encoding::Big5, encoding_record));
+    /// Map `ch` to a glyph index, converting it to the encoding of the subtable.
+    pub fn map_glyph(&self, ch: char) -> Result<Option<u16>, ParseError> {
+        let char_code = match self.encoding {
+            Encoding::Unicode => u32::from(ch),
+            Encoding::Symbol => {
+                // Symbol fonts conventionally map their characters into the Private Use Area at
+                // U+F000 to U+F0FF
+                let char_code = u32::from(ch);
+                if char_code <= 0xFF {
+                    if let Some(glyph_index) = self.subtable.map_glyph(0xF000 + char_code)? {
+                        return Ok(Some(glyph_index));
+                    }
+                }
+                char_code
+            }
+            Encoding::AppleRoman => match char_to_macroman(ch) {
+                Some(code) => u32::from(code),
+                None => return Ok(None),
+            },
+            Encoding::Big5 => match unicode_to_big5(ch) {
+                Some(code) => u32::from(code),
+                None => return Ok(None),
+            },
+        };
+        self.subtable.map_glyph(char_code)
     }
-
-    None
 }
 
 fn unique_glyph_names<'a>(
@@ -563,6 +565,7 @@ fn unique_glyph_names<'a>(
 mod tests {
     use super::*;
     use crate::bitmap::{Bitmap, EncapsulatedBitmap};
+    use crate::tables::cmap::{read_cmap_subtable, PlatformId};
     use crate::tables::OpenTypeFile;
     use crate::tests::read_fixture;
 
@@ -621,6 +624,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cmap_cache() {
+        let font_buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&font_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cmap_data = provider.read_table_data(tag::CMAP).unwrap();
+        let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>().unwrap();
+        let cmap_cache = CmapCache::new(&cmap).unwrap().unwrap();
+        let (_, cmap_subtable) = read_cmap_subtable(&cmap).unwrap().unwrap();
+
+        match cmap_cache.subtable() {
+            CmapSubtable::Format12 { .. } => {}
+            _ => panic!("expected the format 12 subtable"),
+        }
+        for ch in "Hello \u{628}\u{627}".chars() {
+            let glyph_index = cmap_cache.map_glyph(ch).unwrap();
+            assert!(glyph_index.is_some());
+            assert_eq!(glyph_index, cmap_subtable.map_glyph(u32::from(ch)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_prefer_format12() {
+        #[rustfmt::skip]
+        let cmap_data = [
+            0, 0, // version
+            0, 2, // numTables
+            0, 0, 0, 4, 0, 0, 0, 20, // Unicode, UCS-4, offset 20
+            0, 3, 0, 1, 0, 0, 0, 48, // Windows, Unicode BMP, offset 48
+            // Format 12 subtable
+            0, 12, 0, 0, // format, reserved
+            0, 0, 0, 28, // length
+            0, 0, 0, 0, // language
+            0, 0, 0, 1, // numGroups
+            0, 1, 0xF6, 0x00, 0, 1, 0xF6, 0x00, 0, 0, 0, 7, // U+1F600 -> glyph 7
+            // Format 4 subtable
+            0, 4, 0, 24, 0, 0, // format, length, language
+            0, 2, 0, 2, 0, 0, 0, 0, // segCountX2, searchRange, entrySelector, rangeShift
+            0xFF, 0xFF, // endCode
+            0, 0, // reservedPad
+            0xFF, 0xFF, // startCode
+            0, 1, // idDelta
+            0, 0, // idRangeOffset
+        ];
+        let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>().unwrap();
+        let cmap_cache = CmapCache::new(&cmap).unwrap().unwrap();
+
+        assert_eq!(cmap_cache.platform_id(), PlatformId::UNICODE.0);
+        assert_eq!(cmap_cache.map_glyph('\u{1F600}').unwrap(), Some(7));
+    }
+
     #[test]
     fn test_unique_glyph_names() {
         let names = vec!["A"; 3].into_iter().map(Cow::from);
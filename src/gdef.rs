@@ -27,3 +27,20 @@ pub fn mark_attach_class(opt_gdef_table: Option<&GDEFTable>, glyph: u16) -> u16
         None => 0,
     }
 }
+
+pub fn is_in_mark_glyph_set(
+    opt_gdef_table: Option<&GDEFTable>,
+    mark_glyph_set: u16,
+    glyph: u16,
+) -> bool {
+    match opt_gdef_table {
+        Some(gdef_table) => match gdef_table.opt_mark_glyph_sets {
+            Some(ref mark_glyph_sets) => match mark_glyph_sets.get(usize::from(mark_glyph_set)) {
+                Some(coverage) => coverage.glyph_coverage_value(glyph).is_some(),
+                None => false,
+            },
+            None => false,
+        },
+        None => false,
+    }
+}
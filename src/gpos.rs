@@ -6,6 +6,7 @@
 //!
 //! — <https://docs.microsoft.com/en-us/typography/opentype/spec/gpos>
 
+use crate::cff::CFF;
 use crate::context::{ContextLookupHelper, Glyph, MatchType};
 use crate::error::ParseError;
 use crate::gdef::gdef_is_mark;
@@ -17,7 +18,10 @@ use crate::layout::{
 };
 use crate::scripts;
 use crate::scripts::ScriptType;
+use crate::tables::glyf::{BoundingBox, GlyfTable};
+use crate::tables::kern::KernTable;
 use crate::tag;
+use crate::unicode::canonical_combining_class;
 
 type PosContext<'a> = ContextLookupHelper<'a, GPOS>;
 
@@ -30,7 +34,7 @@ pub fn gpos_apply_lookup(
 ) -> Result<(), ParseError> {
     if let Some(ref lookup_list) = gpos_table.opt_lookup_list {
         let lookup = lookup_list.lookup_cache_gpos(gpos_cache, lookup_index)?;
-        let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+        let match_type = MatchType::from_lookup(&lookup);
         match lookup.lookup_subtables {
             PosLookup::SinglePos(ref subtables) => {
                 forall_glyphs_match(match_type, opt_gdef_table, infos, |i, infos| {
@@ -222,6 +226,8 @@ fn gpos_lookup_chaincontextpos<'a>(
 pub enum Placement {
     None,
     Distance(i32, i32),
+    /// Cursive attachment: the entry anchor of the next glyph and the exit anchor of this glyph,
+    /// which are positioned at the same point.
     Anchor(Anchor, Anchor),
 }
 
@@ -290,6 +296,48 @@ impl Info {
         }
         Ok(infos)
     }
+
+    /// Describe the glyph and its positioning as `glyph_id@advance+x_offset,y_offset`.
+    ///
+    /// `advance` is the kerning adjustment to the advance of the glyph. The offset of a glyph
+    /// attached by anchors is the difference between the anchor it attaches to and its own anchor.
+    /// This is intended for comparing shaped output in tests and bug reports.
+    pub fn to_debug_string(&self) -> String {
+        let (x_offset, y_offset) = match (&self.mark_placement, &self.placement) {
+            (MarkPlacement::MarkAnchor(_, base_anchor, mark_anchor), _)
+            | (MarkPlacement::None, Placement::Anchor(base_anchor, mark_anchor)) => (
+                i32::from(base_anchor.x) - i32::from(mark_anchor.x),
+                i32::from(base_anchor.y) - i32::from(mark_anchor.y),
+            ),
+            (MarkPlacement::None, Placement::Distance(x, y)) => (*x, *y),
+            (MarkPlacement::MarkOverprint(_), _) | (MarkPlacement::None, Placement::None) => (0, 0),
+        };
+        format!(
+            "{}@{}+{},{}",
+            self.glyph.glyph_index, self.kerning, x_offset, y_offset
+        )
+    }
+}
+
+/// Apply kerning from the legacy `kern` table to `infos`.
+///
+/// This is a fallback for fonts that do not have a `GPOS` table. Marks are skipped when pairing
+/// glyphs.
+pub fn kern_apply(kern_table: &KernTable<'_>, infos: &mut [Info]) {
+    let mut opt_prev: Option<usize> = None;
+    for i in 0..infos.len() {
+        if infos[i].is_mark {
+            continue;
+        }
+        if let Some(prev) = opt_prev {
+            let left = infos[prev].glyph.glyph_index;
+            let right = infos[i].glyph.glyph_index;
+            if let Some(kerning) = kern_table.kerning(left, right) {
+                infos[prev].kerning += kerning;
+            }
+        }
+        opt_prev = Some(i);
+    }
 }
 
 impl Adjust {
@@ -583,7 +631,7 @@ fn apply_pos<'a>(
     index: usize,
 ) -> Result<(), ParseError> {
     let lookup = lookup_list.lookup_cache_gpos(gpos_cache, lookup_index)?;
-    let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+    let match_type = MatchType::from_lookup(&lookup);
     let i1;
     match match_type.find_nth(opt_gdef_table, infos, index, pos_index) {
         Some(index1) => i1 = index1,
@@ -636,7 +684,35 @@ fn apply_pos<'a>(
     }
 }
 
+/// Apply the `GPOS` features for `script_tag` to `infos`.
+///
+/// If `fallback_mark_positioning` is supplied, marks that are not positioned by the font are then
+/// placed above or below their base glyph using the glyph bounds it provides. See
+/// `apply_fallback_mark_positioning`.
 pub fn gpos_apply(
+    gpos_cache: &LayoutCache<GPOS>,
+    opt_gdef_table: Option<&GDEFTable>,
+    kerning: bool,
+    fallback_mark_positioning: Option<&dyn GlyphBounds>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    infos: &mut [Info],
+) -> Result<(), ParseError> {
+    gpos_apply_features(
+        gpos_cache,
+        opt_gdef_table,
+        kerning,
+        script_tag,
+        opt_lang_tag,
+        infos,
+    )?;
+    if let Some(glyph_bounds) = fallback_mark_positioning {
+        apply_fallback_mark_positioning(glyph_bounds, infos)?;
+    }
+    Ok(())
+}
+
+fn gpos_apply_features(
     gpos_cache: &LayoutCache<GPOS>,
     opt_gdef_table: Option<&GDEFTable>,
     kerning: bool,
@@ -662,7 +738,15 @@ pub fn gpos_apply(
         Some(script) => match script.find_langsys_or_default(opt_lang_tag)? {
             None => Ok(()),
             Some(langsys) => match ScriptType::from(script_tag) {
-                ScriptType::Arabic | ScriptType::Syriac => gpos_apply0(
+                ScriptType::Arabic => scripts::arabic::gpos_apply_arabic(
+                    gpos_cache,
+                    gpos_table,
+                    opt_gdef_table,
+                    script_tag,
+                    opt_lang_tag,
+                    infos,
+                ),
+                ScriptType::Mongolian | ScriptType::Syriac => gpos_apply0(
                     &gpos_cache,
                     &gpos_table,
                     opt_gdef_table,
@@ -670,7 +754,7 @@ pub fn gpos_apply(
                     &[tag::CURS, tag::KERN, tag::MARK, tag::MKMK],
                     infos,
                 ),
-                ScriptType::Default => {
+                ScriptType::Default | ScriptType::Hebrew | ScriptType::Thai => {
                     if kerning {
                         gpos_apply0(
                             &gpos_cache,
@@ -692,6 +776,21 @@ pub fn gpos_apply(
                     }
                 }
                 ScriptType::Indic => Ok(()),
+                ScriptType::Khmer | ScriptType::Myanmar | ScriptType::Tibetan => gpos_apply0(
+                    gpos_cache,
+                    gpos_table,
+                    opt_gdef_table,
+                    langsys,
+                    &[
+                        tag::ABVM,
+                        tag::BLWM,
+                        tag::DIST,
+                        tag::KERN,
+                        tag::MARK,
+                        tag::MKMK,
+                    ],
+                    infos,
+                ),
             },
         },
     }
@@ -720,3 +819,328 @@ pub fn gpos_apply0(
     }
     Ok(())
 }
+
+/// A source of glyph bounding boxes for fallback mark positioning.
+pub trait GlyphBounds {
+    /// The bounding box of the glyph `glyph_index`, or `None` if the glyph has no outline.
+    fn glyph_bounds(&self, glyph_index: u16) -> Result<Option<BoundingBox>, ParseError>;
+}
+
+impl<'a> GlyphBounds for GlyfTable<'a> {
+    fn glyph_bounds(&self, glyph_index: u16) -> Result<Option<BoundingBox>, ParseError> {
+        self.records
+            .get(usize::from(glyph_index))
+            .ok_or(ParseError::BadIndex)?
+            .bounding_box()
+    }
+}
+
+impl<'a> GlyphBounds for CFF<'a> {
+    fn glyph_bounds(&self, glyph_index: u16) -> Result<Option<BoundingBox>, ParseError> {
+        if self.fonts.is_empty() {
+            return Err(ParseError::MissingValue);
+        }
+        self.glyph_bounding_box(0, glyph_index)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum MarkPosition {
+    Above,
+    Below,
+}
+
+/// Determine where a combining mark is drawn relative to its base from its combining class.
+fn mark_position(ch: char) -> Option<MarkPosition> {
+    match canonical_combining_class(ch) {
+        // Hebrew holam, rafe, shin dot, sin dot, and varika; Arabic fathatan, dammatan, fatha,
+        // damma, shadda, sukun, and superscript alef; Syriac superscript alaph; Thai, Lao, and
+        // Tibetan above marks; and the positional above classes
+        19 | 23..=28 | 30 | 31 | 33..=36 | 107 | 122 | 130 | 214 | 216 | 228 | 230 | 232 | 234 => {
+            Some(MarkPosition::Above)
+        }
+        // Nukta; Hebrew points; Arabic kasratan and kasra; Thai, Lao, and Tibetan below marks;
+        // and the positional below classes
+        7
+        | 10..=18
+        | 20
+        | 22
+        | 29
+        | 32
+        | 103
+        | 118
+        | 129
+        | 132
+        | 202
+        | 218
+        | 220
+        | 222
+        | 233
+        | 240 => Some(MarkPosition::Below),
+        _ => None,
+    }
+}
+
+/// Position marks that have not been positioned by `GPOS` relative to their base glyph.
+///
+/// Each mark whose combining class places it above or below its base is centred horizontally on
+/// the preceding base glyph and stacked on top of, or underneath, the base and any marks already
+/// placed there. This can be used for fonts that lack mark attachment data, including fonts
+/// without a `GPOS` table.
+pub fn apply_fallback_mark_positioning(
+    glyph_bounds: &dyn GlyphBounds,
+    infos: &mut [Info],
+) -> Result<(), ParseError> {
+    // The index and bounds of the current base, and the current top and bottom of the stack of
+    // marks on it
+    let mut opt_base: Option<(usize, BoundingBox)> = None;
+    let mut top = 0;
+    let mut bottom = 0;
+
+    for (i, info) in infos.iter_mut().enumerate() {
+        let opt_position = info
+            .glyph
+            .unicodes
+            .first()
+            .and_then(|&ch| mark_position(ch));
+        let position = match opt_position {
+            Some(position) => position,
+            None => {
+                if !info.is_mark {
+                    opt_base = glyph_bounds
+                        .glyph_bounds(info.glyph.glyph_index)?
+                        .map(|bounds| (i, bounds));
+                    if let Some((_, bounds)) = &opt_base {
+                        top = bounds.y_max;
+                        bottom = bounds.y_min;
+                    }
+                }
+                continue;
+            }
+        };
+
+        let (base_index, base_bounds) = match &opt_base {
+            Some(base) => base,
+            None => continue,
+        };
+        match (&info.placement, &info.mark_placement) {
+            (Placement::None, MarkPlacement::None) => {}
+            _ => continue,
+        }
+        let mark_bounds = match glyph_bounds.glyph_bounds(info.glyph.glyph_index)? {
+            Some(mark_bounds) => mark_bounds,
+            None => continue,
+        };
+
+        let base_x = centre(base_bounds.x_min, base_bounds.x_max);
+        let mark_x = centre(mark_bounds.x_min, mark_bounds.x_max);
+        let height = mark_bounds.y_max.saturating_sub(mark_bounds.y_min);
+        let (base_anchor, mark_anchor) = match position {
+            MarkPosition::Above => {
+                let anchors = (
+                    Anchor { x: base_x, y: top },
+                    Anchor {
+                        x: mark_x,
+                        y: mark_bounds.y_min,
+                    },
+                );
+                top = top.saturating_add(height);
+                anchors
+            }
+            MarkPosition::Below => {
+                let anchors = (
+                    Anchor {
+                        x: base_x,
+                        y: bottom,
+                    },
+                    Anchor {
+                        x: mark_x,
+                        y: mark_bounds.y_max,
+                    },
+                );
+                bottom = bottom.saturating_sub(height);
+                anchors
+            }
+        };
+        info.mark_placement = MarkPlacement::MarkAnchor(*base_index, base_anchor, mark_anchor);
+    }
+
+    Ok(())
+}
+
+fn centre(min: i16, max: i16) -> i16 {
+    // The average of two i16 values always fits in an i16
+    ((i32::from(min) + i32::from(max)) / 2) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::gsub::GlyphOrigin;
+    use crate::layout::new_layout_cache;
+    use crate::shape::{shape, Features, ShapeDirection};
+    use crate::tables::{FontTableProvider, OpenTypeFile};
+    use crate::tag;
+    use crate::tests::read_fixture;
+    use tinyvec::tiny_vec;
+
+    struct Bounds(Vec<BoundingBox>);
+
+    impl GlyphBounds for Bounds {
+        fn glyph_bounds(&self, glyph_index: u16) -> Result<Option<BoundingBox>, ParseError> {
+            Ok(self.0.get(usize::from(glyph_index)).cloned())
+        }
+    }
+
+    #[test]
+    fn debug_string() {
+        let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        let infos = shape(
+            &provider,
+            tag::ARAB,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "\u{0628}\u{064E}\u{0627}\u{0628}",
+        )
+        .unwrap();
+        let debug = infos
+            .iter()
+            .map(Info::to_debug_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+        // beh with fatha attached, alef, beh
+        assert_eq!(debug, "4556@0+0,0 427@0+-256,0 2020@0+0,0 389@0+0,0");
+    }
+
+    #[test]
+    fn class_based_pair_kerning() {
+        // The kern feature of this font only uses class based (format 2) PairPos subtables
+        let buffer = read_fixture("tests/fonts/noto/NotoSansTelugu-Regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        let infos = shape(
+            &provider,
+            tag::TELU,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "\u{0C39}\u{0C2E}",
+        )
+        .unwrap();
+        let glyphs = infos
+            .iter()
+            .map(|info| (info.glyph.glyph_index, info.kerning))
+            .collect::<Vec<_>>();
+        assert_eq!(glyphs, vec![(55, -63), (45, 0)]);
+    }
+
+    #[test]
+    fn cursive_attachment() {
+        let buffer = read_fixture("tests/fonts/arabic/NafeesNastaleeq.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        let infos = shape(
+            &provider,
+            tag::ARAB,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "\u{0633}\u{0644}\u{0645}",
+        )
+        .unwrap();
+        let glyphs = infos
+            .iter()
+            .map(|info| info.glyph.glyph_index)
+            .collect::<Vec<_>>();
+        // seen.init lam.medi meem.fina
+        assert_eq!(glyphs, [161, 732, 95]);
+
+        // The exit anchor of the seen is attached to the entry anchor of the lam
+        let (entry, exit) = match infos[0].placement {
+            Placement::Anchor(entry, exit) => (entry, exit),
+            _ => panic!("seen was not attached to lam"),
+        };
+        assert_eq!((entry.x, entry.y), (153, 972));
+        assert_eq!((exit.x, exit.y), (0, 0));
+
+        let gpos_data = provider.read_table_data(tag::GPOS).unwrap();
+        let gpos_table = ReadScope::new(&gpos_data)
+            .read::<LayoutTable<GPOS>>()
+            .unwrap();
+        let gpos_cache = new_layout_cache(gpos_table);
+        let lookup_list = gpos_cache.layout_table.opt_lookup_list.as_ref().unwrap();
+        // Lookup 1 of the font connects the seen and lam
+        let lookup = lookup_list.lookup_cache_gpos(&gpos_cache, 1).unwrap();
+        let subtables = match lookup.lookup_subtables {
+            PosLookup::CursivePos(ref subtables) => subtables,
+            _ => panic!("expected a cursive attachment lookup"),
+        };
+        let (exit, entry) = gpos_lookup_cursivepos(subtables, 161, 732)
+            .unwrap()
+            .unwrap();
+        assert_eq!((entry.x, entry.y), (153, 972));
+        assert_eq!((exit.x, exit.y), (0, 0));
+    }
+
+    fn info(ch: char, glyph_index: u16) -> Info {
+        Info {
+            glyph: RawGlyph {
+                unicodes: tiny_vec![[char; 1] => ch],
+                glyph_index,
+                liga_component_pos: 0,
+                cluster: 0,
+                glyph_origin: GlyphOrigin::Char(ch),
+                small_caps: false,
+                multi_subst_dup: false,
+                is_vert_alt: false,
+                fake_bold: false,
+                fake_italic: false,
+                variation: None,
+                extra_data: (),
+            },
+            kerning: 0,
+            placement: Placement::None,
+            mark_placement: MarkPlacement::None,
+            is_mark: false,
+        }
+    }
+
+    #[test]
+    fn fallback_mark_above_base() {
+        let bounds = Bounds(vec![
+            // a
+            BoundingBox {
+                x_min: 50,
+                x_max: 450,
+                y_min: 0,
+                y_max: 500,
+            },
+            // combining acute accent, drawn over the origin
+            BoundingBox {
+                x_min: -150,
+                x_max: -50,
+                y_min: 0,
+                y_max: 100,
+            },
+        ]);
+        let mut infos = vec![info('a', 0), info('\u{0301}', 1)];
+
+        apply_fallback_mark_positioning(&bounds, &mut infos).unwrap();
+
+        match infos[1].mark_placement {
+            MarkPlacement::MarkAnchor(base, base_anchor, mark_anchor) => {
+                assert_eq!(base, 0);
+                assert_eq!((base_anchor.x, base_anchor.y), (250, 500));
+                assert_eq!((mark_anchor.x, mark_anchor.y), (-100, 0));
+            }
+            _ => panic!("mark was not positioned"),
+        }
+    }
+}
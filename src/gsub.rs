@@ -27,13 +27,31 @@ use crate::scripts::ScriptType;
 use crate::tag;
 use crate::unicode::VariationSelector;
 
-const SUBST_RECURSION_LIMIT: usize = 2;
-
 pub struct FeatureInfo {
     pub feature_tag: u32,
     pub alternate: Option<usize>,
 }
 
+/// The features applied by `gsub_apply_default`
+///
+/// A `GsubFeatureMask` converts into options without any extra features.
+#[derive(Clone, Copy)]
+pub struct GsubOptions<'a> {
+    /// The optional features applied by the default script shaper
+    pub feature_mask: GsubFeatureMask,
+    /// Features, such as stylistic sets, applied after the script specific features
+    pub extra_features: &'a [FeatureInfo],
+}
+
+impl<'a> From<GsubFeatureMask> for GsubOptions<'a> {
+    fn from(feature_mask: GsubFeatureMask) -> Self {
+        GsubOptions {
+            feature_mask,
+            extra_features: &[],
+        }
+    }
+}
+
 type SubstContext<'a> = ContextLookupHelper<'a, GSUB>;
 
 impl Ligature {
@@ -73,6 +91,7 @@ impl Ligature {
                     glyphs[i].unicodes.append(&mut unicodes);
                     glyphs[i].extra_data =
                         GlyphData::merge(glyphs[i].extra_data.clone(), extra_data);
+                    glyphs[i].cluster = glyphs[i].cluster.min(glyphs[index].cluster);
                     glyphs.remove(index);
                 } else {
                     glyphs[index].liga_component_pos = matched as u16;
@@ -100,6 +119,10 @@ pub struct RawGlyph<T> {
     pub unicodes: TinyVec<[char; 1]>,
     pub glyph_index: u16,
     pub liga_component_pos: u16,
+    /// The index of the input that the glyph was mapped from, such as the byte offset of its
+    /// character. Ligatures take the smallest index of their components, and the glyphs of a
+    /// syllable reordered by a script shaper take the smallest index in the syllable.
+    pub cluster: usize,
     pub glyph_origin: GlyphOrigin,
     pub small_caps: bool,
     pub multi_subst_dup: bool,
@@ -110,6 +133,16 @@ pub struct RawGlyph<T> {
     pub extra_data: T,
 }
 
+/// Give every glyph in `glyphs` the smallest cluster among them, so that glyphs moved by
+/// reordering stay in the cluster of the syllable they belong to.
+pub(crate) fn merge_clusters<T>(glyphs: &mut [RawGlyph<T>]) {
+    if let Some(cluster) = glyphs.iter().map(|glyph| glyph.cluster).min() {
+        for glyph in glyphs.iter_mut() {
+            glyph.cluster = cluster;
+        }
+    }
+}
+
 /// `merge` is called during ligature substitution (i.e. merging of glyphs),
 /// and determines how the `RawGlyph.extra_data` field should be merged
 pub trait GlyphData: Clone {
@@ -155,13 +188,92 @@ pub fn gsub_feature_would_apply<T: GlyphData>(
     Ok(false)
 }
 
+/// Returns the alternate glyphs of the glyph at index `i` from the AlternateSubst lookups of
+/// the feature `feature_tag`.
+///
+/// An empty `Vec` is returned if the feature has no alternates for the glyph.
+pub fn gsub_query_alternates<T: GlyphData>(
+    gsub_cache: &LayoutCache<GSUB>,
+    opt_gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    feature_tag: u32,
+    glyphs: &[RawGlyph<T>],
+    i: usize,
+) -> Result<Vec<u16>, ParseError> {
+    let gsub_table = &gsub_cache.layout_table;
+    let langsys = match gsub_table.find_script_or_default(script_tag)? {
+        Some(script) => match script.find_langsys_or_default(opt_lang_tag)? {
+            Some(langsys) => langsys,
+            None => return Ok(Vec::new()),
+        },
+        None => return Ok(Vec::new()),
+    };
+    let feature_table = match gsub_table.find_langsys_feature(langsys, feature_tag)? {
+        Some(feature_table) => feature_table,
+        None => return Ok(Vec::new()),
+    };
+
+    if let Some(ref lookup_list) = gsub_table.opt_lookup_list {
+        for lookup_index in &feature_table.lookup_indices {
+            let lookup_index = usize::from(*lookup_index);
+            let lookup = lookup_list.lookup_cache_gsub(gsub_cache, lookup_index)?;
+            let match_type = MatchType::from_lookup(&lookup);
+            if let SubstLookup::AlternateSubst(ref subtables) = lookup.lookup_subtables {
+                if i < glyphs.len() && match_type.match_glyph(opt_gdef_table, &glyphs[i]) {
+                    if let Some(alternate_set) = alternatesubst_would_apply(subtables, i, glyphs)? {
+                        return Ok(alternate_set.alternate_glyphs.clone());
+                    }
+                }
+            }
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Returns true if the feature `feature_tag` would substitute any glyph of `glyphs`.
+///
+/// This can be used to skip applying a feature that would not change the glyphs.
+pub fn gsub_would_substitute<T: GlyphData>(
+    gsub_cache: &LayoutCache<GSUB>,
+    opt_gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    feature_tag: u32,
+    glyphs: &[RawGlyph<T>],
+) -> Result<bool, ParseError> {
+    let gsub_table = &gsub_cache.layout_table;
+    let langsys = match gsub_table.find_script_or_default(script_tag)? {
+        Some(script) => match script.find_langsys_or_default(opt_lang_tag)? {
+            Some(langsys) => langsys,
+            None => return Ok(false),
+        },
+        None => return Ok(false),
+    };
+
+    for i in 0..glyphs.len() {
+        if gsub_feature_would_apply(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            langsys,
+            feature_tag,
+            glyphs,
+            i,
+        )? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 pub fn gsub_lookup_would_apply<T: GlyphData>(
     opt_gdef_table: Option<&GDEFTable>,
     lookup: &LookupCacheItem<SubstLookup>,
     glyphs: &[RawGlyph<T>],
     i: usize,
 ) -> Result<bool, ParseError> {
-    let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+    let match_type = MatchType::from_lookup(lookup);
     if i < glyphs.len() && match_type.match_glyph(opt_gdef_table, &glyphs[i]) {
         return match lookup.lookup_subtables {
             SubstLookup::SingleSubst(ref subtables) => {
@@ -238,7 +350,7 @@ pub fn gsub_apply_lookup<T: GlyphData>(
 ) -> Result<usize, ParseError> {
     if let Some(ref lookup_list) = gsub_table.opt_lookup_list {
         let lookup = lookup_list.lookup_cache_gsub(gsub_cache, lookup_index)?;
-        let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+        let match_type = MatchType::from_lookup(&lookup);
         match lookup.lookup_subtables {
             SubstLookup::SingleSubst(ref subtables) => {
                 for i in start..(start + length) {
@@ -293,7 +405,7 @@ pub fn gsub_apply_lookup<T: GlyphData>(
                 while i < start + length {
                     if match_type.match_glyph(opt_gdef_table, &glyphs[i]) && pred(&glyphs[i]) {
                         match contextsubst(
-                            SUBST_RECURSION_LIMIT,
+                            gsub_cache.subst_recursion_limit(),
                             gsub_cache,
                             lookup_list,
                             opt_gdef_table,
@@ -319,7 +431,7 @@ pub fn gsub_apply_lookup<T: GlyphData>(
                 while i < start + length {
                     if match_type.match_glyph(opt_gdef_table, &glyphs[i]) && pred(&glyphs[i]) {
                         match chaincontextsubst(
-                            SUBST_RECURSION_LIMIT,
+                            gsub_cache.subst_recursion_limit(),
                             gsub_cache,
                             lookup_list,
                             opt_gdef_table,
@@ -413,6 +525,7 @@ fn multiplesubst<T: GlyphData>(
                         unicodes: glyphs[i].unicodes.clone(),
                         glyph_index: output_glyph_index,
                         liga_component_pos: 0, //glyphs[i].liga_component_pos,
+                        cluster: glyphs[i].cluster,
                         glyph_origin: GlyphOrigin::Direct,
                         small_caps: glyphs[i].small_caps,
                         multi_subst_dup: true,
@@ -456,7 +569,6 @@ fn alternatesubst<T: GlyphData>(
     glyphs: &mut [RawGlyph<T>],
 ) -> Result<(), ParseError> {
     if let Some(alternateset) = alternatesubst_would_apply(subtables, i, glyphs)? {
-        // TODO allow users to specify which alternate glyph they want
         if alternate < alternateset.alternate_glyphs.len() {
             glyphs[i].glyph_index = alternateset.alternate_glyphs[alternate];
             glyphs[i].glyph_origin = GlyphOrigin::Direct;
@@ -694,7 +806,7 @@ fn apply_subst<'a, T: GlyphData>(
     index: usize,
 ) -> Result<Option<isize>, ParseError> {
     let lookup = lookup_list.lookup_cache_gsub(gsub_cache, lookup_index)?;
-    let match_type = MatchType::from_lookup_flag(lookup.lookup_flag);
+    let match_type = MatchType::from_lookup(&lookup);
     let i = match parent_match_type.find_nth(opt_gdef_table, glyphs, index, subst_index) {
         Some(index1) => index1,
         None => return Ok(None), // FIXME error?
@@ -830,6 +942,45 @@ fn build_lookups_default(
     Ok(lookups.into_iter().collect())
 }
 
+/// Apply the lookups of the required feature of the `LangSys` for `script_tag` and
+/// `opt_lang_tag`, which always applies.
+///
+/// This is done once, before the script specific and optional features, for all scripts.
+fn gsub_apply_required_feature<T: GlyphData>(
+    gsub_cache: &LayoutCache<GSUB>,
+    opt_gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ShapingError> {
+    let gsub_table = &gsub_cache.layout_table;
+    if let Some(script) = gsub_table.find_script_or_default(script_tag)? {
+        if let Some(langsys) = script.find_langsys_or_default(opt_lang_tag)? {
+            if let Some(feature_index) = langsys.required_feature_index() {
+                let feature_record = gsub_table.feature_by_index(feature_index)?;
+                let mut lookup_indices = feature_record.feature_table().lookup_indices.clone();
+                lookup_indices.sort();
+                lookup_indices.dedup();
+                for lookup_index in lookup_indices {
+                    gsub_apply_lookup(
+                        gsub_cache,
+                        gsub_table,
+                        opt_gdef_table,
+                        usize::from(lookup_index),
+                        feature_record.feature_tag,
+                        None,
+                        glyphs,
+                        0,
+                        glyphs.len(),
+                        |_| true,
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 fn make_supported_features_mask(
     gsub_table: &LayoutTable<GSUB>,
     langsys: &LangSys,
@@ -877,7 +1028,7 @@ fn get_supported_features(
     Ok(feature_mask)
 }
 
-fn find_alternate(features_list: &[FeatureInfo], feature_tag: u32) -> Option<usize> {
+pub(crate) fn find_alternate(features_list: &[FeatureInfo], feature_tag: u32) -> Option<usize> {
     for feature_info in features_list {
         if feature_info.feature_tag == feature_tag {
             return feature_info.alternate;
@@ -894,6 +1045,105 @@ pub fn gsub_apply_custom<T: GlyphData + Debug>(
     features_list: &[FeatureInfo],
     num_glyphs: u16,
     glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ShapingError> {
+    gsub_apply_required_feature(gsub_cache, opt_gdef_table, script_tag, opt_lang_tag, glyphs)?;
+    gsub_apply_features(
+        gsub_cache,
+        opt_gdef_table,
+        script_tag,
+        opt_lang_tag,
+        features_list,
+        glyphs,
+    )?;
+    replace_missing_glyphs(glyphs, num_glyphs);
+    Ok(())
+}
+
+/// Apply the supplied features, selecting a specific glyph from their alternate substitutions.
+///
+/// `alternates` is a list of `(feature_tag, alternate_index)` pairs. Glyphs substituted by an
+/// AlternateSubst lookup of the feature are replaced by the alternate at `alternate_index`.
+/// Glyphs with fewer alternates than `alternate_index` are left unchanged.
+pub fn gsub_apply_alternates<T: GlyphData + Debug>(
+    gsub_cache: &LayoutCache<GSUB>,
+    opt_gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    alternates: &[(u32, usize)],
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ShapingError> {
+    let features_list = alternates
+        .iter()
+        .map(|&(feature_tag, alternate)| FeatureInfo {
+            feature_tag,
+            alternate: Some(alternate),
+        })
+        .collect::<Vec<_>>();
+    gsub_apply_features(
+        gsub_cache,
+        opt_gdef_table,
+        script_tag,
+        opt_lang_tag,
+        &features_list,
+        glyphs,
+    )
+}
+
+/// Apply the required variation alternates feature, `rvrn`, for the variation instance at the
+/// normalized axis coordinates `coords`.
+///
+/// The lookups of the feature are those of the `GSUB` feature variations whose conditions match
+/// `coords`, or those of the `rvrn` feature itself if none match. This should be applied before
+/// any other features.
+pub fn gsub_apply_rvrn<T: GlyphData>(
+    gsub_cache: &LayoutCache<GSUB>,
+    opt_gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    coords: &[f32],
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ParseError> {
+    let gsub_table = &gsub_cache.layout_table;
+    let langsys = match gsub_table.find_script_or_default(script_tag)? {
+        Some(script) => match script.find_langsys_or_default(opt_lang_tag)? {
+            Some(langsys) => langsys,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+    let feature_table =
+        match gsub_table.find_langsys_feature_variation(langsys, tag::RVRN, coords)? {
+            Some(feature_table) => feature_table,
+            None => return Ok(()),
+        };
+
+    let mut lookup_indices = feature_table.lookup_indices.clone();
+    lookup_indices.sort();
+    for lookup_index in lookup_indices {
+        let length = glyphs.len();
+        gsub_apply_lookup(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            usize::from(lookup_index),
+            tag::RVRN,
+            None,
+            glyphs,
+            0,
+            length,
+            |_| true,
+        )?;
+    }
+    Ok(())
+}
+
+fn gsub_apply_features<T: GlyphData + Debug>(
+    gsub_cache: &LayoutCache<GSUB>,
+    opt_gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    features_list: &[FeatureInfo],
+    glyphs: &mut Vec<RawGlyph<T>>,
 ) -> Result<(), ShapingError> {
     let gsub_table = &gsub_cache.layout_table;
     if let Some(script) = gsub_table.find_script_or_default(script_tag)? {
@@ -933,7 +1183,6 @@ pub fn gsub_apply_custom<T: GlyphData + Debug>(
             }
         }
     }
-    replace_missing_glyphs(glyphs, num_glyphs);
     Ok(())
 }
 
@@ -1048,6 +1297,72 @@ impl Default for GsubFeatureMask {
     }
 }
 
+/// Named selection of the optional `GSUB` features, which converts into a `GsubFeatureMask`.
+///
+/// The settings follow the CSS `font-variant` properties. The `ccmp`, `locl`, and `rlig` features
+/// are always enabled. The default settings enable common and contextual ligatures, matching
+/// `GsubFeatureMask::default()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FeatureSettings {
+    /// Common ligatures, `liga` and `clig`.
+    pub common_ligatures: bool,
+    /// Discretionary ligatures, `dlig`.
+    pub discretionary_ligatures: bool,
+    /// Historical ligatures, `hlig`.
+    pub historical_ligatures: bool,
+    /// Contextual alternates, `calt`.
+    pub contextual_ligatures: bool,
+    /// Small capitals, `smcp`.
+    pub small_caps: bool,
+    /// Old-style figures, `onum`.
+    pub oldstyle_figures: bool,
+    /// Lining figures, `lnum`.
+    pub lining_figures: bool,
+    /// Tabular figures, `tnum`.
+    pub tabular_figures: bool,
+    /// Proportional figures, `pnum`.
+    pub proportional_figures: bool,
+    /// Slashed zero, `zero`.
+    pub slashed_zero: bool,
+}
+
+impl Default for FeatureSettings {
+    fn default() -> Self {
+        FeatureSettings {
+            common_ligatures: true,
+            discretionary_ligatures: false,
+            historical_ligatures: false,
+            contextual_ligatures: true,
+            small_caps: false,
+            oldstyle_figures: false,
+            lining_figures: false,
+            tabular_figures: false,
+            proportional_figures: false,
+            slashed_zero: false,
+        }
+    }
+}
+
+impl From<FeatureSettings> for GsubFeatureMask {
+    fn from(settings: FeatureSettings) -> Self {
+        let mut mask = GsubFeatureMask::CCMP | GsubFeatureMask::LOCL | GsubFeatureMask::RLIG;
+        mask.set(
+            GsubFeatureMask::LIGA | GsubFeatureMask::CLIG,
+            settings.common_ligatures,
+        );
+        mask.set(GsubFeatureMask::DLIG, settings.discretionary_ligatures);
+        mask.set(GsubFeatureMask::HLIG, settings.historical_ligatures);
+        mask.set(GsubFeatureMask::CALT, settings.contextual_ligatures);
+        mask.set(GsubFeatureMask::SMCP, settings.small_caps);
+        mask.set(GsubFeatureMask::ONUM, settings.oldstyle_figures);
+        mask.set(GsubFeatureMask::LNUM, settings.lining_figures);
+        mask.set(GsubFeatureMask::TNUM, settings.tabular_figures);
+        mask.set(GsubFeatureMask::PNUM, settings.proportional_figures);
+        mask.set(GsubFeatureMask::ZERO, settings.slashed_zero);
+        mask
+    }
+}
+
 pub fn features_supported(
     gsub_cache: &LayoutCache<GSUB>,
     script_tag: u32,
@@ -1058,6 +1373,34 @@ pub fn features_supported(
     Ok(supported_features.contains(feature_mask))
 }
 
+/// List the tags of the features in the `LangSys` for `script_tag` and `opt_lang_tag`.
+///
+/// The `LangSys` is selected in the same way as for shaping, falling back to the default script
+/// and language system. The required feature, if any, is listed first.
+pub fn list_features(
+    gsub_cache: &LayoutCache<GSUB>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+) -> Result<Vec<u32>, ParseError> {
+    let gsub_table = &gsub_cache.layout_table;
+    let mut feature_tags = Vec::new();
+    if let Some(script) = gsub_table.find_script_or_default(script_tag)? {
+        if let Some(langsys) = script.find_langsys_or_default(opt_lang_tag)? {
+            let feature_indices = langsys
+                .required_feature_index()
+                .into_iter()
+                .chain(langsys.feature_indices_iter().copied());
+            for feature_index in feature_indices {
+                let feature_tag = gsub_table.feature_by_index(feature_index)?.feature_tag;
+                if !feature_tags.contains(&feature_tag) {
+                    feature_tags.push(feature_tag);
+                }
+            }
+        }
+    }
+    Ok(feature_tags)
+}
+
 pub fn get_lookups_cache_index(
     gsub_cache: &LayoutCache<GSUB>,
     script_tag: u32,
@@ -1089,19 +1432,43 @@ pub fn get_lookups_cache_index(
     Ok(index)
 }
 
+/// Apply the default features for `script_tag`, followed by any extra features in `options`.
+///
+/// The `feature_mask` of `options` selects the optional features applied by the default script
+/// shaper, the Arabic, Hebrew, Indic, Khmer, Mongolian, Myanmar, Syriac, Thai, and Tibetan
+/// shapers apply their own set of features, although the Mongolian shaper uses `VRT2_OR_VERT` to
+/// select vertical forms. The `extra_features`, such as stylistic sets, are applied after the
+/// script specific features for all scripts. A `GsubFeatureMask` can be passed as `options` when
+/// there are no extra features. See `gsub_apply_default_generic` for glyphs with other
+/// `extra_data`.
 pub fn gsub_apply_default<'data>(
     make_dotted_circle: &impl Fn() -> Vec<RawGlyph<()>>,
     gsub_cache: &LayoutCache<GSUB>,
     opt_gdef_table: Option<&GDEFTable>,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
-    mut feature_mask: GsubFeatureMask,
+    options: impl Into<GsubOptions<'data>>,
     num_glyphs: u16,
     glyphs: &mut Vec<RawGlyph<()>>,
 ) -> Result<(), ShapingError> {
+    let GsubOptions {
+        feature_mask,
+        extra_features,
+    } = options.into();
     let gsub_table = &gsub_cache.layout_table;
-    match ScriptType::from(script_tag) {
+    let script_type = ScriptType::from(script_tag);
+    gsub_apply_required_feature(gsub_cache, opt_gdef_table, script_tag, opt_lang_tag, glyphs)?;
+    match script_type {
         ScriptType::Arabic => scripts::arabic::gsub_apply_arabic(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            extra_features,
+            glyphs,
+        )?,
+        ScriptType::Hebrew => scripts::hebrew::gsub_apply_hebrew(
             gsub_cache,
             gsub_table,
             opt_gdef_table,
@@ -1118,6 +1485,31 @@ pub fn gsub_apply_default<'data>(
             opt_lang_tag,
             glyphs,
         )?,
+        ScriptType::Khmer => scripts::khmer::gsub_apply_khmer(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            glyphs,
+        )?,
+        ScriptType::Mongolian => scripts::mongolian::gsub_apply_mongolian(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            feature_mask.contains(GsubFeatureMask::VRT2_OR_VERT),
+            glyphs,
+        )?,
+        ScriptType::Myanmar => scripts::myanmar::gsub_apply_myanmar(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            glyphs,
+        )?,
         ScriptType::Syriac => scripts::syriac::gsub_apply_syriac(
             gsub_cache,
             gsub_table,
@@ -1126,31 +1518,42 @@ pub fn gsub_apply_default<'data>(
             opt_lang_tag,
             glyphs,
         )?,
-        ScriptType::Default => {
-            feature_mask &= get_supported_features(gsub_cache, script_tag, opt_lang_tag)?;
-            if feature_mask.contains(GsubFeatureMask::FRAC) {
-                let index_frac =
-                    get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
-                feature_mask.remove(GsubFeatureMask::FRAC);
-                let index =
-                    get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
-                let lookups = &gsub_cache.cached_lookups.borrow()[index];
-                let lookups_frac = &gsub_cache.cached_lookups.borrow()[index_frac];
-                gsub_apply_lookups_frac(
-                    gsub_cache,
-                    gsub_table,
-                    opt_gdef_table,
-                    lookups,
-                    lookups_frac,
-                    glyphs,
-                )?;
-            } else {
-                let index =
-                    get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
-                let lookups = &gsub_cache.cached_lookups.borrow()[index];
-                gsub_apply_lookups(gsub_cache, gsub_table, opt_gdef_table, lookups, glyphs)?;
-            }
-        }
+        ScriptType::Thai => scripts::thai::gsub_apply_thai(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            glyphs,
+        )?,
+        ScriptType::Tibetan => scripts::tibetan::gsub_apply_tibetan(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            glyphs,
+        )?,
+        ScriptType::Default => gsub_apply_default_features(
+            gsub_cache,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            feature_mask,
+            glyphs,
+        )?,
+    }
+
+    // The Arabic shaper applies the extra features itself
+    if !extra_features.is_empty() && script_type != ScriptType::Arabic {
+        gsub_apply_features(
+            gsub_cache,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            extra_features,
+            glyphs,
+        )?;
     }
 
     strip_joiners(glyphs);
@@ -1158,12 +1561,105 @@ pub fn gsub_apply_default<'data>(
     Ok(())
 }
 
-fn gsub_apply_lookups(
+/// Apply the default script shaping features for `script_tag` to glyphs carrying any
+/// `GlyphData`, followed by any extra features in `options`.
+///
+/// This is the pipeline used by `gsub_apply_default` for scripts that don't need a dedicated
+/// shaper. The `extra_data` of each glyph is carried through substitution and combined with
+/// `GlyphData::merge` when glyphs form a ligature. The script specific shapers only support
+/// glyphs without `extra_data`, so `ParseError::NotImplemented` is returned for scripts that
+/// need one, such as Arabic and the Indic scripts. Use `gsub_apply_default` for those scripts.
+pub fn gsub_apply_default_generic<'data, T: GlyphData + Debug>(
+    gsub_cache: &LayoutCache<GSUB>,
+    opt_gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    options: impl Into<GsubOptions<'data>>,
+    num_glyphs: u16,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ShapingError> {
+    let GsubOptions {
+        feature_mask,
+        extra_features,
+    } = options.into();
+    match ScriptType::from(script_tag) {
+        ScriptType::Default => {}
+        ScriptType::Arabic
+        | ScriptType::Hebrew
+        | ScriptType::Indic
+        | ScriptType::Khmer
+        | ScriptType::Mongolian
+        | ScriptType::Myanmar
+        | ScriptType::Syriac
+        | ScriptType::Thai
+        | ScriptType::Tibetan => return Err(ShapingError::from(ParseError::NotImplemented)),
+    }
+    gsub_apply_required_feature(gsub_cache, opt_gdef_table, script_tag, opt_lang_tag, glyphs)?;
+    gsub_apply_default_features(
+        gsub_cache,
+        opt_gdef_table,
+        script_tag,
+        opt_lang_tag,
+        feature_mask,
+        glyphs,
+    )?;
+
+    if !extra_features.is_empty() {
+        gsub_apply_features(
+            gsub_cache,
+            opt_gdef_table,
+            script_tag,
+            opt_lang_tag,
+            extra_features,
+            glyphs,
+        )?;
+    }
+
+    strip_joiners(glyphs);
+    replace_missing_glyphs(glyphs, num_glyphs);
+    Ok(())
+}
+
+/// Apply the features selected by `feature_mask` that the default script shaper supports.
+fn gsub_apply_default_features<T: GlyphData + Debug>(
+    gsub_cache: &LayoutCache<GSUB>,
+    opt_gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    mut feature_mask: GsubFeatureMask,
+    glyphs: &mut Vec<RawGlyph<T>>,
+) -> Result<(), ShapingError> {
+    let gsub_table = &gsub_cache.layout_table;
+    feature_mask &= get_supported_features(gsub_cache, script_tag, opt_lang_tag)?;
+    if feature_mask.contains(GsubFeatureMask::FRAC) {
+        let index_frac =
+            get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
+        feature_mask.remove(GsubFeatureMask::FRAC);
+        let index = get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
+        let lookups = &gsub_cache.cached_lookups.borrow()[index];
+        let lookups_frac = &gsub_cache.cached_lookups.borrow()[index_frac];
+        gsub_apply_lookups_frac(
+            gsub_cache,
+            gsub_table,
+            opt_gdef_table,
+            lookups,
+            lookups_frac,
+            glyphs,
+        )?;
+    } else {
+        let index = get_lookups_cache_index(gsub_cache, script_tag, opt_lang_tag, feature_mask)?;
+        let lookups = &gsub_cache.cached_lookups.borrow()[index];
+        gsub_apply_lookups(gsub_cache, gsub_table, opt_gdef_table, lookups, glyphs)?;
+    }
+    Ok(())
+}
+
+fn gsub_apply_lookups<T: GlyphData>(
     gsub_cache: &LayoutCache<GSUB>,
     gsub_table: &LayoutTable<GSUB>,
     opt_gdef_table: Option<&GDEFTable>,
     lookups: &[(usize, u32)],
-    glyphs: &mut Vec<RawGlyph<()>>,
+    glyphs: &mut Vec<RawGlyph<T>>,
 ) -> Result<(), ShapingError> {
     gsub_apply_lookups_impl(
         gsub_cache,
@@ -1177,12 +1673,12 @@ fn gsub_apply_lookups(
     Ok(())
 }
 
-fn gsub_apply_lookups_impl(
+fn gsub_apply_lookups_impl<T: GlyphData>(
     gsub_cache: &LayoutCache<GSUB>,
     gsub_table: &LayoutTable<GSUB>,
     opt_gdef_table: Option<&GDEFTable>,
     lookups: &[(usize, u32)],
-    glyphs: &mut Vec<RawGlyph<()>>,
+    glyphs: &mut Vec<RawGlyph<T>>,
     start: usize,
     mut length: usize,
 ) -> Result<usize, ShapingError> {
@@ -1203,13 +1699,13 @@ fn gsub_apply_lookups_impl(
     Ok(length)
 }
 
-fn gsub_apply_lookups_frac(
+fn gsub_apply_lookups_frac<T: GlyphData>(
     gsub_cache: &LayoutCache<GSUB>,
     gsub_table: &LayoutTable<GSUB>,
     opt_gdef_table: Option<&GDEFTable>,
     lookups: &[(usize, u32)],
     lookups_frac: &[(usize, u32)],
-    glyphs: &mut Vec<RawGlyph<()>>,
+    glyphs: &mut Vec<RawGlyph<T>>,
 ) -> Result<(), ShapingError> {
     let mut i = 0;
     while i < glyphs.len() {
@@ -1250,14 +1746,15 @@ fn gsub_apply_lookups_frac(
     Ok(())
 }
 
-fn find_fraction(glyphs: &[RawGlyph<()>]) -> Option<(usize, usize, usize)> {
-    let slash_pos = glyphs
-        .iter()
-        .position(|g| g.glyph_origin == GlyphOrigin::Char('/'))?;
+fn find_fraction<T>(glyphs: &[RawGlyph<T>]) -> Option<(usize, usize, usize)> {
+    let slash_pos = glyphs.iter().position(|g| match g.glyph_origin {
+        GlyphOrigin::Char(c) => fraction_slash(c),
+        GlyphOrigin::Direct => false,
+    })?;
     let mut start_pos = slash_pos;
     while start_pos > 0 {
         match glyphs[start_pos - 1].glyph_origin {
-            GlyphOrigin::Char(c) if c.is_digit(10) => {
+            GlyphOrigin::Char(c) if fraction_digit(c) => {
                 start_pos -= 1;
             }
             _ => break,
@@ -1266,7 +1763,7 @@ fn find_fraction(glyphs: &[RawGlyph<()>]) -> Option<(usize, usize, usize)> {
     let mut end_pos = slash_pos;
     while end_pos + 1 < glyphs.len() {
         match glyphs[end_pos + 1].glyph_origin {
-            GlyphOrigin::Char(c) if c.is_digit(10) => {
+            GlyphOrigin::Char(c) if fraction_digit(c) => {
                 end_pos += 1;
             }
             _ => break,
@@ -1278,3 +1775,299 @@ fn find_fraction(glyphs: &[RawGlyph<()>]) -> Option<(usize, usize, usize)> {
         None
     }
 }
+
+/// Solidus and fraction slash
+fn fraction_slash(c: char) -> bool {
+    c == '/' || c == '\u{2044}'
+}
+
+/// ASCII, superscript, and subscript digits
+fn fraction_digit(c: char) -> bool {
+    match c {
+        '0'..='9'
+        | '\u{00B2}'
+        | '\u{00B3}'
+        | '\u{00B9}'
+        | '\u{2070}'
+        | '\u{2074}'..='\u{2079}'
+        | '\u{2080}'..='\u{2089}' => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::layout::new_layout_cache;
+    use crate::shape::make_glyph;
+    use tinyvec::tiny_vec;
+
+    fn make_glyphs(text: &str) -> Vec<RawGlyph<()>> {
+        text.chars()
+            .map(|ch| RawGlyph {
+                unicodes: tiny_vec![[char; 1] => ch],
+                glyph_index: 0,
+                liga_component_pos: 0,
+                cluster: 0,
+                glyph_origin: GlyphOrigin::Char(ch),
+                small_caps: false,
+                multi_subst_dup: false,
+                is_vert_alt: false,
+                fake_bold: false,
+                fake_italic: false,
+                variation: None,
+                extra_data: (),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_feature_settings() {
+        let required = GsubFeatureMask::CCMP | GsubFeatureMask::LOCL | GsubFeatureMask::RLIG;
+        let none = FeatureSettings {
+            common_ligatures: false,
+            contextual_ligatures: false,
+            ..FeatureSettings::default()
+        };
+        assert_eq!(
+            GsubFeatureMask::from(FeatureSettings::default()),
+            GsubFeatureMask::default()
+        );
+        assert_eq!(GsubFeatureMask::from(none), required);
+
+        type Case = (fn(&mut FeatureSettings), GsubFeatureMask);
+        let cases: &[Case] = &[
+            (
+                |s| s.common_ligatures = true,
+                GsubFeatureMask::LIGA | GsubFeatureMask::CLIG,
+            ),
+            (|s| s.discretionary_ligatures = true, GsubFeatureMask::DLIG),
+            (|s| s.historical_ligatures = true, GsubFeatureMask::HLIG),
+            (|s| s.contextual_ligatures = true, GsubFeatureMask::CALT),
+            (|s| s.small_caps = true, GsubFeatureMask::SMCP),
+            (|s| s.oldstyle_figures = true, GsubFeatureMask::ONUM),
+            (|s| s.lining_figures = true, GsubFeatureMask::LNUM),
+            (|s| s.tabular_figures = true, GsubFeatureMask::TNUM),
+            (|s| s.proportional_figures = true, GsubFeatureMask::PNUM),
+            (|s| s.slashed_zero = true, GsubFeatureMask::ZERO),
+        ];
+        for (enable, expected) in cases {
+            let mut settings = none;
+            enable(&mut settings);
+            assert_eq!(GsubFeatureMask::from(settings), required | *expected);
+        }
+    }
+
+    #[test]
+    fn test_find_fraction() {
+        assert_eq!(find_fraction(&make_glyphs("a 12/34 b")), Some((2, 4, 6)));
+        assert_eq!(find_fraction(&make_glyphs("1\u{2044}2")), Some((0, 1, 2)));
+        // Superscript one, fraction slash, subscript two
+        assert_eq!(
+            find_fraction(&make_glyphs("\u{00B9}\u{2044}\u{2082}")),
+            Some((0, 1, 2))
+        );
+        assert_eq!(find_fraction(&make_glyphs("a/b")), None);
+        assert_eq!(find_fraction(&make_glyphs("12")), None);
+    }
+
+    // A GSUB table with a lookup list of four contextual lookups, each of which applies the next,
+    // ending in a single substitution of glyph 1 with glyph 2
+    #[rustfmt::skip]
+    const NESTED_CONTEXT_GSUB: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x00, // script list offset
+        0x00, 0x00, // feature list offset
+        0x00, 0x0A, // lookup list offset
+        // LookupList
+        0x00, 0x05, // lookup count
+        0x00, 0x0C, 0x00, 0x26, 0x00, 0x40, 0x00, 0x5A, 0x00, 0x74, // lookup offsets
+        // Lookups 0 to 3, contextual substitution format 3
+        0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // type, flag, count, subtable offset
+        0x00, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x0C, // format, glyphs, records, coverage
+        0x00, 0x00, 0x00, 0x01, // sequence index, lookup index
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01, // coverage of glyph 1
+        0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08,
+        0x00, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x0C,
+        0x00, 0x00, 0x00, 0x02,
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08,
+        0x00, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x0C,
+        0x00, 0x00, 0x00, 0x03,
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08,
+        0x00, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x0C,
+        0x00, 0x00, 0x00, 0x04,
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01,
+        // Lookup 4, single substitution format 1
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // type, flag, count, subtable offset
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x01, // format, coverage, delta glyph id
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01, // coverage of glyph 1
+    ];
+
+    fn apply_nested_context(recursion_limit: usize) -> Result<u16, ParseError> {
+        let gsub_table = ReadScope::new(NESTED_CONTEXT_GSUB)
+            .read::<LayoutTable<GSUB>>()
+            .unwrap();
+        let gsub_cache = new_layout_cache(gsub_table);
+        gsub_cache.set_subst_recursion_limit(recursion_limit);
+        let mut glyphs = make_glyphs("a");
+        glyphs[0].glyph_index = 1;
+        gsub_apply_lookup(
+            &gsub_cache,
+            &gsub_cache.layout_table,
+            None,
+            0,
+            tag::CALT,
+            None,
+            &mut glyphs,
+            0,
+            1,
+            |_| true,
+        )?;
+        Ok(glyphs[0].glyph_index)
+    }
+
+    // A GSUB table where the default LangSys has rclt as its required feature, which adds one to
+    // glyphs 1 and 2, so applying it twice changes glyph 1 to 3, and ss01, which substitutes
+    // glyph 5 with glyph 6
+    #[rustfmt::skip]
+    const REQUIRED_FEATURE_GSUB: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x0A, // script list offset
+        0x00, 0x1E, // feature list offset
+        0x00, 0x38, // lookup list offset
+        // ScriptList
+        0x00, 0x01, b'D', b'F', b'L', b'T', 0x00, 0x08,
+        // Script
+        0x00, 0x04, 0x00, 0x00, // default langsys offset, langsys count
+        // LangSys
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, // required feature 0, feature 1
+        // FeatureList
+        0x00, 0x02, b'r', b'c', b'l', b't', 0x00, 0x0E, b's', b's', b'0', b'1', 0x00, 0x14,
+        // Features with lookup 0 and lookup 1
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01,
+        // LookupList
+        0x00, 0x02, 0x00, 0x06, 0x00, 0x1C,
+        // Lookup 0, single substitution format 1
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // type, flag, count, subtable offset
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x01, // format, coverage, delta glyph id
+        0x00, 0x01, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02, // coverage of glyphs 1 and 2
+        // Lookup 1, single substitution format 1
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08,
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x01,
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x05, // coverage of glyph 5
+    ];
+
+    #[test]
+    fn test_required_feature_applied_once() {
+        let gsub_table = ReadScope::new(REQUIRED_FEATURE_GSUB)
+            .read::<LayoutTable<GSUB>>()
+            .unwrap();
+        let gsub_cache = new_layout_cache(gsub_table);
+        let ss01 = [FeatureInfo {
+            feature_tag: tag::from_string("ss01").unwrap(),
+            alternate: None,
+        }];
+        let apply = |script_tag, text: &str, glyph_indices: &[u16], extra_features| {
+            let mut glyphs = text
+                .chars()
+                .zip(glyph_indices)
+                .map(|(ch, &glyph_index)| make_glyph(ch, glyph_index, 0))
+                .collect();
+            let options = GsubOptions {
+                feature_mask: GsubFeatureMask::default(),
+                extra_features,
+            };
+            gsub_apply_default(
+                &|| Vec::new(),
+                &gsub_cache,
+                None,
+                script_tag,
+                None,
+                options,
+                10,
+                &mut glyphs,
+            )
+            .unwrap();
+            glyphs.iter().map(|g| g.glyph_index).collect::<Vec<_>>()
+        };
+
+        assert_eq!(apply(tag::LATN, "ab", &[1, 5], &[]), vec![2, 5]);
+        assert_eq!(apply(tag::LATN, "ab", &[1, 5], &ss01), vec![2, 6]);
+        assert_eq!(apply(tag::HEBR, "\u{05D0}", &[1], &[]), vec![2]);
+        assert_eq!(apply(tag::ARAB, "\u{0627}", &[1], &[]), vec![2]);
+        assert_eq!(apply(tag::ARAB, "\u{0627}", &[1], &ss01), vec![2]);
+    }
+
+    // A version 1.1 GSUB table with an rvrn feature that has no lookups, except when the
+    // coordinate of the first axis is between 0.5 and 1.0, when it substitutes glyph 1 with 2
+    #[rustfmt::skip]
+    const FEATURE_VARIATIONS_GSUB: &[u8] = &[
+        0x00, 0x01, 0x00, 0x01, // version
+        0x00, 0x0E, // script list offset
+        0x00, 0x22, // feature list offset
+        0x00, 0x2E, // lookup list offset
+        0x00, 0x00, 0x00, 0x46, // feature variations offset
+        // ScriptList
+        0x00, 0x01, b'D', b'F', b'L', b'T', 0x00, 0x08,
+        // Script
+        0x00, 0x04, 0x00, 0x00, // default langsys offset, langsys count
+        // LangSys
+        0x00, 0x00, 0xFF, 0xFF, 0x00, 0x01, 0x00, 0x00, // one feature, index 0
+        // FeatureList
+        0x00, 0x01, b'r', b'v', b'r', b'n', 0x00, 0x08,
+        // Feature with no lookups
+        0x00, 0x00, 0x00, 0x00,
+        // LookupList
+        0x00, 0x01, 0x00, 0x04,
+        // Lookup 0, single substitution format 1
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // type, flag, count, subtable offset
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x01, // format, coverage, delta glyph id
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01, // coverage of glyph 1
+        // FeatureVariations
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x00, 0x00, 0x01, // record count
+        0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x1E, // condition set, substitution offsets
+        // ConditionSet
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x06, // condition count, condition offset
+        // Condition format 1, axis 0 between 0.5 and 1.0
+        0x00, 0x01, 0x00, 0x00, 0x20, 0x00, 0x40, 0x00,
+        // FeatureTableSubstitution
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, // feature index 0, feature offset
+        // Feature with lookup 0
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_apply_rvrn() {
+        let gsub_table = ReadScope::new(FEATURE_VARIATIONS_GSUB)
+            .read::<LayoutTable<GSUB>>()
+            .unwrap();
+        let gsub_cache = new_layout_cache(gsub_table);
+        let apply_rvrn = |coords: &[f32]| {
+            let mut glyphs = make_glyphs("a");
+            glyphs[0].glyph_index = 1;
+            gsub_apply_rvrn(&gsub_cache, None, tag::LATN, None, coords, &mut glyphs).unwrap();
+            glyphs[0].glyph_index
+        };
+
+        assert_eq!(apply_rvrn(&[]), 1);
+        assert_eq!(apply_rvrn(&[0.25]), 1);
+        assert_eq!(apply_rvrn(&[0.75]), 2);
+        assert_eq!(apply_rvrn(&[1.0, -1.0]), 2);
+    }
+
+    #[test]
+    fn test_subst_recursion_limit() {
+        assert_eq!(apply_nested_context(2), Err(ParseError::LimitExceeded));
+        assert_eq!(apply_nested_context(3), Ok(2));
+        assert_eq!(
+            apply_nested_context(crate::layout::DEFAULT_SUBST_RECURSION_LIMIT),
+            Ok(2)
+        );
+    }
+}
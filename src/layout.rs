@@ -7,12 +7,14 @@ use crate::binary::read::{
     CheckIndex, ReadArray, ReadBinary, ReadBinaryDep, ReadCache, ReadCtxt, ReadFixedSizeDep,
     ReadFrom, ReadScope, ReadScopeOwned,
 };
-use crate::binary::U16Be;
+use crate::binary::{U16Be, U32Be};
 use crate::size;
+use crate::tables::F2Dot14;
 use crate::tag;
 use log::warn;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
 use std::marker::PhantomData;
 use std::rc::Rc;
 use std::u16;
@@ -23,9 +25,10 @@ pub enum GPOS {}
 pub struct GDEFTable {
     pub opt_glyph_classdef: Option<ClassDef>,
     // pub opt_attach_list: Option<ReadScope<'a>>,
-    // pub opt_lig_caret_list: Option<ReadScope<'a>>,
+    pub opt_lig_caret_list: Option<LigCaretList>,
     pub opt_mark_attach_classdef: Option<ClassDef>,
-    // TODO read additional GDEF 1.2 and 1.3 fields
+    pub opt_mark_glyph_sets: Option<Vec<Coverage>>,
+    // TODO read additional GDEF 1.3 fields
 }
 
 // GSUB and GPOS tables have the same top-level structure
@@ -33,6 +36,8 @@ pub struct LayoutTable<T> {
     pub opt_script_list: Option<ScriptList>,
     pub opt_feature_list: Option<FeatureList>,
     pub opt_lookup_list: Option<LookupList<T>>,
+    /// Present in version 1.1 tables.
+    pub opt_feature_variations: Option<FeatureVariations>,
 }
 
 pub struct ScriptList {
@@ -55,8 +60,8 @@ pub struct LangSysRecord {
 }
 
 pub struct LangSys {
-    _lookup_order: usize,           // reserved field, should be zero
-    _required_feature_index: usize, // ignored for now, 0xFFFF
+    _lookup_order: usize, // reserved field, should be zero
+    required_feature_index: u16,
     feature_indices: Vec<u16>,
 }
 
@@ -74,6 +79,193 @@ pub struct FeatureTable {
     pub lookup_indices: Vec<u16>,
 }
 
+impl SubstLookup {
+    /// Add the glyphs that the subtables substitute for the glyphs in `glyphs` to `glyphs`.
+    ///
+    /// Contextual subtables do not substitute glyphs themselves; the lookups they apply are
+    /// returned by `nested_lookup_indices`.
+    pub fn closure_glyphs(&self, glyphs: &mut BTreeSet<u16>) {
+        let mut new_glyphs = Vec::new();
+        match self {
+            SubstLookup::SingleSubst(subtables) => {
+                for subtable in subtables {
+                    match subtable {
+                        SingleSubst::Format1 {
+                            coverage,
+                            delta_glyph_index,
+                        } => {
+                            for (glyph, _) in coverage.glyph_coverage_values() {
+                                if glyphs.contains(&glyph) {
+                                    new_glyphs.push(glyph.wrapping_add(*delta_glyph_index as u16));
+                                }
+                            }
+                        }
+                        SingleSubst::Format2 {
+                            coverage,
+                            substitute_glyph_array,
+                        } => {
+                            for (glyph, index) in coverage.glyph_coverage_values() {
+                                if glyphs.contains(&glyph) {
+                                    new_glyphs.extend(
+                                        substitute_glyph_array.get(usize::from(index)).copied(),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            SubstLookup::MultipleSubst(subtables) => {
+                for subtable in subtables {
+                    for (glyph, index) in subtable.coverage.glyph_coverage_values() {
+                        if let Some(sequence) = subtable.sequences.get(usize::from(index)) {
+                            if glyphs.contains(&glyph) {
+                                new_glyphs.extend_from_slice(&sequence.substitute_glyphs);
+                            }
+                        }
+                    }
+                }
+            }
+            SubstLookup::AlternateSubst(subtables) => {
+                for subtable in subtables {
+                    for (glyph, index) in subtable.coverage.glyph_coverage_values() {
+                        if let Some(alternateset) = subtable.alternatesets.get(usize::from(index)) {
+                            if glyphs.contains(&glyph) {
+                                new_glyphs.extend_from_slice(&alternateset.alternate_glyphs);
+                            }
+                        }
+                    }
+                }
+            }
+            SubstLookup::LigatureSubst(subtables) => {
+                for subtable in subtables {
+                    for (glyph, index) in subtable.coverage.glyph_coverage_values() {
+                        if let Some(ligatureset) = subtable.ligaturesets.get(usize::from(index)) {
+                            if !glyphs.contains(&glyph) {
+                                continue;
+                            }
+                            for ligature in &ligatureset.ligatures {
+                                if ligature
+                                    .component_glyphs
+                                    .iter()
+                                    .all(|component| glyphs.contains(component))
+                                {
+                                    new_glyphs.push(ligature.ligature_glyph);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            SubstLookup::ReverseChainSingleSubst(subtables) => {
+                for subtable in subtables {
+                    let ReverseChainSingleSubst::Format1 {
+                        coverage,
+                        substitute_glyphs,
+                        ..
+                    } = subtable;
+                    for (glyph, index) in coverage.glyph_coverage_values() {
+                        if glyphs.contains(&glyph) {
+                            new_glyphs.extend(substitute_glyphs.get(usize::from(index)).copied());
+                        }
+                    }
+                }
+            }
+            SubstLookup::ContextSubst(_) | SubstLookup::ChainContextSubst(_) => {}
+        }
+        glyphs.extend(new_glyphs);
+    }
+
+    /// The indices of the lookups applied by the contextual subtables of the lookup.
+    pub fn nested_lookup_indices(&self) -> Vec<u16> {
+        let mut lookup_records: Vec<&(u16, u16)> = Vec::new();
+        match self {
+            SubstLookup::ContextSubst(subtables) => {
+                for subtable in subtables {
+                    match subtable {
+                        ContextLookup::Format1 { subrulesets, .. } => {
+                            for subruleset in subrulesets.iter().flatten() {
+                                for subrule in &subruleset.subrules {
+                                    lookup_records.extend(&subrule.lookup_records);
+                                }
+                            }
+                        }
+                        ContextLookup::Format2 { subclasssets, .. } => {
+                            for subclassset in subclasssets.iter().flatten() {
+                                for subclassrule in &subclassset.subclassrules {
+                                    lookup_records.extend(&subclassrule.lookup_records);
+                                }
+                            }
+                        }
+                        ContextLookup::Format3 {
+                            lookup_records: records,
+                            ..
+                        } => lookup_records.extend(records),
+                    }
+                }
+            }
+            SubstLookup::ChainContextSubst(subtables) => {
+                for subtable in subtables {
+                    match subtable {
+                        ChainContextLookup::Format1 {
+                            chainsubrulesets, ..
+                        } => {
+                            for chainsubruleset in chainsubrulesets.iter().flatten() {
+                                for chainsubrule in &chainsubruleset.chainsubrules {
+                                    lookup_records.extend(&chainsubrule.lookup_records);
+                                }
+                            }
+                        }
+                        ChainContextLookup::Format2 {
+                            chainsubclasssets, ..
+                        } => {
+                            for chainsubclassset in chainsubclasssets.iter().flatten() {
+                                for chainsubclassrule in &chainsubclassset.chainsubclassrules {
+                                    lookup_records.extend(&chainsubclassrule.lookup_records);
+                                }
+                            }
+                        }
+                        ChainContextLookup::Format3 {
+                            lookup_records: records,
+                            ..
+                        } => lookup_records.extend(records),
+                    }
+                }
+            }
+            _ => {}
+        }
+        lookup_records
+            .into_iter()
+            .map(|&(_sequence_index, lookup_index)| lookup_index)
+            .collect()
+    }
+}
+
+/// Substitutions of feature tables that apply to particular instances of a variable font.
+///
+/// <https://docs.microsoft.com/en-us/typography/opentype/spec/chapter2#featurevariations-table>
+pub struct FeatureVariations {
+    feature_variation_records: Vec<FeatureVariationRecord>,
+}
+
+pub struct FeatureVariationRecord {
+    /// The conditions that must all be met for the substitutions to apply.
+    pub conditions: Vec<Condition>,
+    /// Pairs of feature index and the feature table that replaces it.
+    pub substitutions: Vec<(u16, FeatureTable)>,
+}
+
+pub enum Condition {
+    /// Format 1: the normalized coordinate of the axis at `axis_index` is within the range.
+    AxisRange {
+        axis_index: u16,
+        filter_range_min: F2Dot14,
+        filter_range_max: F2Dot14,
+    },
+    /// A condition with an unsupported format, which is never met.
+    Unsupported,
+}
+
 pub struct LookupList<T> {
     scope_owned: ReadScopeOwned,
     lookup_offsets: Vec<u16>,
@@ -85,6 +277,7 @@ pub struct Lookup<'a, T: LayoutTableType> {
     lookup_type: LookupType<T>,
     pub lookup_flag: u16,
     subtable_offsets: ReadArray<'a, U16Be>,
+    pub mark_filtering_set: Option<u16>,
     phantom: PhantomData<T>,
 }
 
@@ -172,10 +365,10 @@ impl<'a> ReadBinary<'a> for GDEFTable {
 
         let major_version = ctxt.read_u16be()?;
         ctxt.check(major_version == 1)?;
-        let _minor_version = ctxt.read_u16be()?;
+        let minor_version = ctxt.read_u16be()?;
         let glyph_classdef_offset = usize::from(ctxt.read_u16be()?);
         let _attach_list_offset = usize::from(ctxt.read_u16be()?);
-        let _lig_caret_list_offset = usize::from(ctxt.read_u16be()?);
+        let lig_caret_list_offset = usize::from(ctxt.read_u16be()?);
         // MarkAttachClassDef was added to GDEF in OpenType 1.2 but they did not change the GDEF
         // version. This means that it's not possible to know from the version alone whether the
         // field should be read. Some implementations use GSUB/GPOS to determine if it should be
@@ -185,6 +378,12 @@ impl<'a> ReadBinary<'a> for GDEFTable {
         //
         // See: https://github.com/yeslogic/prince/issues/297 for more detail.
         let mark_attach_classdef_offset = usize::from(ctxt.read_u16be()?);
+        // MarkGlyphSetsDef was added in GDEF 1.2
+        let mark_glyph_sets_def_offset = if minor_version >= 2 {
+            usize::from(ctxt.read_u16be()?)
+        } else {
+            0
+        };
 
         let gdef_header_size = 6 * size::U16;
 
@@ -193,7 +392,11 @@ impl<'a> ReadBinary<'a> for GDEFTable {
         } else if glyph_classdef_offset < gdef_header_size {
             None
         } else {
-            Some(table.offset(glyph_classdef_offset).read::<ClassDef>()?)
+            Some(
+                table
+                    .offset_checked(glyph_classdef_offset)?
+                    .read::<ClassDef>()?,
+            )
         };
 
         /*
@@ -206,17 +409,17 @@ impl<'a> ReadBinary<'a> for GDEFTable {
                 } else {
                     Some(table.offset(attach_list_offset))
                 };
-
-                let opt_lig_caret_list = if lig_caret_list_offset >= table.data().len() {
-                    return Err(ParseError::BadOffset);
-                } else if lig_caret_list_offset == 0 {
-                    None
-                } else if lig_caret_list_offset < gdef_header_size {
-                    return Err(ParseError::BadOffset);
-                } else {
-                    Some(table.offset(lig_caret_list_offset))
-                };
         */
+        let opt_lig_caret_list = if lig_caret_list_offset < gdef_header_size {
+            None
+        } else {
+            Some(
+                table
+                    .offset_checked(lig_caret_list_offset)?
+                    .read::<LigCaretList>()?,
+            )
+        };
+
         let opt_mark_attach_classdef = if mark_attach_classdef_offset == 0 {
             None
         } else if mark_attach_classdef_offset < gdef_header_size {
@@ -224,20 +427,134 @@ impl<'a> ReadBinary<'a> for GDEFTable {
         } else {
             Some(
                 table
-                    .offset(mark_attach_classdef_offset)
+                    .offset_checked(mark_attach_classdef_offset)?
                     .read::<ClassDef>()?,
             )
         };
 
+        let opt_mark_glyph_sets = if mark_glyph_sets_def_offset < gdef_header_size {
+            None
+        } else {
+            Some(read_mark_glyph_sets(
+                &table.offset_checked(mark_glyph_sets_def_offset)?,
+            )?)
+        };
+
         Ok(GDEFTable {
             opt_glyph_classdef,
             // opt_attach_list,
-            // opt_lig_caret_list,
+            opt_lig_caret_list,
             opt_mark_attach_classdef,
+            opt_mark_glyph_sets,
         })
     }
 }
 
+impl GDEFTable {
+    /// Returns the caret values of the ligature glyph `glyph_id`.
+    ///
+    /// There is one caret value between each pair of components of the ligature. Returns `None` if
+    /// the font has no caret values for the glyph.
+    pub fn ligature_caret_values(&self, glyph_id: u16) -> Option<&[CaretValue]> {
+        let lig_caret_list = self.opt_lig_caret_list.as_ref()?;
+        let index = lig_caret_list.coverage.glyph_coverage_value(glyph_id)?;
+        lig_caret_list
+            .lig_glyphs
+            .get(usize::from(index))
+            .map(Vec::as_slice)
+    }
+
+    /// Returns the caret coordinates of the ligature glyph `glyph_id` in design units.
+    ///
+    /// Returns `None` if the font has no caret values for the glyph, or if any of them are
+    /// contour points, which need the glyph outline to be resolved. Use `ligature_caret_values`
+    /// to get these.
+    pub fn ligature_carets(&self, glyph_id: u16) -> Option<Vec<i16>> {
+        self.ligature_caret_values(glyph_id)?
+            .iter()
+            .map(|caret_value| match *caret_value {
+                CaretValue::Coordinate(coordinate) => Some(coordinate),
+                CaretValue::ContourPoint(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// The caret positions of ligature glyphs from the `LigCaretList` of the `GDEF` table.
+pub struct LigCaretList {
+    coverage: Coverage,
+    lig_glyphs: Vec<Vec<CaretValue>>,
+}
+
+/// A caret position within a ligature glyph.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CaretValue {
+    /// An X or Y coordinate in design units, depending on the text direction.
+    ///
+    /// The device or variation table of format 3 caret values is ignored.
+    Coordinate(i16),
+    /// The index of a contour point of the ligature glyph.
+    ContourPoint(u16),
+}
+
+impl<'a> ReadBinary<'a> for LigCaretList {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let table = ctxt.scope();
+        let coverage_offset = usize::from(ctxt.read_u16be()?);
+        let coverage = table.offset(coverage_offset).read::<Coverage>()?;
+        let lig_glyph_count = usize::from(ctxt.read_u16be()?);
+        let lig_glyph_offsets = ctxt.read_array::<U16Be>(lig_glyph_count)?;
+        let lig_glyphs = lig_glyph_offsets
+            .iter()
+            .map(|lig_glyph_offset| read_lig_glyph(&table.offset(usize::from(lig_glyph_offset))))
+            .collect::<Result<_, _>>()?;
+        Ok(LigCaretList {
+            coverage,
+            lig_glyphs,
+        })
+    }
+}
+
+fn read_lig_glyph(scope: &ReadScope<'_>) -> Result<Vec<CaretValue>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let caret_count = usize::from(ctxt.read_u16be()?);
+    let caret_value_offsets = ctxt.read_array::<U16Be>(caret_count)?;
+    caret_value_offsets
+        .iter()
+        .map(|caret_value_offset| {
+            scope
+                .offset(usize::from(caret_value_offset))
+                .read::<CaretValue>()
+        })
+        .collect()
+}
+
+impl<'a> ReadBinary<'a> for CaretValue {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        match ctxt.read_u16be()? {
+            1 | 3 => Ok(CaretValue::Coordinate(ctxt.read_i16be()?)),
+            2 => Ok(CaretValue::ContourPoint(ctxt.read_u16be()?)),
+            _ => Err(ParseError::BadVersion),
+        }
+    }
+}
+
+fn read_mark_glyph_sets(scope: &ReadScope<'_>) -> Result<Vec<Coverage>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let format = ctxt.read_u16be()?;
+    ctxt.check(format == 1)?;
+    let mark_glyph_set_count = usize::from(ctxt.read_u16be()?);
+    let coverage_offsets = ctxt.read_array::<U32Be>(mark_glyph_set_count)?;
+    coverage_offsets
+        .iter()
+        .map(|coverage_offset| scope.offset(coverage_offset as usize).read::<Coverage>())
+        .collect()
+}
+
 impl<'a, T> ReadBinary<'a> for LayoutTable<T> {
     type HostType = Self;
 
@@ -249,38 +566,57 @@ impl<'a, T> ReadBinary<'a> for LayoutTable<T> {
         let feature_list_offset = usize::from(ctxt.read_u16be()?);
         let lookup_list_offset = usize::from(ctxt.read_u16be()?);
 
-        if version != 0x10000 {
-            return Err(ParseError::BadVersion);
-        }
+        let feature_variations_offset = match version {
+            0x10000 => 0,
+            0x10001 => usize::try_from(ctxt.read_u32be()?)?,
+            _ => return Err(ParseError::BadVersion),
+        };
 
-        let opt_script_list = if script_list_offset >= table.data().len() {
-            return Err(ParseError::BadOffset);
-        } else if script_list_offset == 0 {
+        let opt_script_list = if script_list_offset == 0 {
             None
         } else {
-            Some(table.offset(script_list_offset).read::<ScriptList>()?)
+            Some(
+                table
+                    .offset_checked(script_list_offset)?
+                    .read::<ScriptList>()?,
+            )
+        };
+
+        let opt_feature_list = if feature_list_offset == 0 {
+            None
+        } else {
+            Some(
+                table
+                    .offset_checked(feature_list_offset)?
+                    .read::<FeatureList>()?,
+            )
         };
 
-        let opt_feature_list = if feature_list_offset >= table.data().len() {
-            return Err(ParseError::BadOffset);
-        } else if feature_list_offset == 0 {
+        let opt_lookup_list = if lookup_list_offset == 0 {
             None
         } else {
-            Some(table.offset(feature_list_offset).read::<FeatureList>()?)
+            Some(
+                table
+                    .offset_checked(lookup_list_offset)?
+                    .read::<LookupList<T>>()?,
+            )
         };
 
-        let opt_lookup_list = if lookup_list_offset >= table.data().len() {
-            return Err(ParseError::BadOffset);
-        } else if lookup_list_offset == 0 {
+        let opt_feature_variations = if feature_variations_offset == 0 {
             None
         } else {
-            Some(table.offset(lookup_list_offset).read::<LookupList<T>>()?)
+            Some(
+                table
+                    .offset_checked(feature_variations_offset)?
+                    .read::<FeatureVariations>()?,
+            )
         };
 
         Ok(LayoutTable {
             opt_script_list,
             opt_feature_list,
             opt_lookup_list,
+            opt_feature_variations,
         })
     }
 }
@@ -361,6 +697,16 @@ impl FeatureList {
         self.feature_records.check_index(index)?;
         Ok(&self.feature_records[index])
     }
+
+    pub fn feature_records(&self) -> &[FeatureRecord] {
+        &self.feature_records
+    }
+}
+
+impl FeatureRecord {
+    pub fn feature_table(&self) -> &FeatureTable {
+        &self.feature_table
+    }
 }
 
 impl<'a> ReadBinaryDep<'a> for FeatureRecord {
@@ -400,6 +746,137 @@ impl<'a> ReadBinary<'a> for FeatureTable {
     }
 }
 
+impl<'a> ReadBinary<'a> for FeatureVariations {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        let _minor_version = ctxt.read_u16be()?;
+        ctxt.check_version(major_version == 1)?;
+        let record_count = usize::try_from(ctxt.read_u32be()?)?;
+        let feature_variation_records = ctxt
+            .read_array::<(U32Be, U32Be)>(record_count)?
+            .iter()
+            .map(|(condition_set_offset, substitution_offset)| {
+                let conditions = if condition_set_offset == 0 {
+                    Vec::new()
+                } else {
+                    read_condition_set(&scope.offset(usize::try_from(condition_set_offset)?))?
+                };
+                let substitutions = if substitution_offset == 0 {
+                    Vec::new()
+                } else {
+                    read_feature_table_substitution(
+                        &scope.offset(usize::try_from(substitution_offset)?),
+                    )?
+                };
+                Ok(FeatureVariationRecord {
+                    conditions,
+                    substitutions,
+                })
+            })
+            .collect::<Result<_, ParseError>>()?;
+        Ok(FeatureVariations {
+            feature_variation_records,
+        })
+    }
+}
+
+fn read_condition_set(scope: &ReadScope<'_>) -> Result<Vec<Condition>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let condition_count = usize::from(ctxt.read_u16be()?);
+    ctxt.read_array::<U32Be>(condition_count)?
+        .iter()
+        .map(|condition_offset| {
+            scope
+                .offset(usize::try_from(condition_offset)?)
+                .read::<Condition>()
+        })
+        .collect()
+}
+
+fn read_feature_table_substitution(
+    scope: &ReadScope<'_>,
+) -> Result<Vec<(u16, FeatureTable)>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let major_version = ctxt.read_u16be()?;
+    let _minor_version = ctxt.read_u16be()?;
+    ctxt.check_version(major_version == 1)?;
+    let substitution_count = usize::from(ctxt.read_u16be()?);
+    ctxt.read_array::<(U16Be, U32Be)>(substitution_count)?
+        .iter()
+        .map(|(feature_index, feature_table_offset)| {
+            let feature_table = scope
+                .offset(usize::try_from(feature_table_offset)?)
+                .read::<FeatureTable>()?;
+            Ok((feature_index, feature_table))
+        })
+        .collect()
+}
+
+impl<'a> ReadBinary<'a> for Condition {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        match ctxt.read_u16be()? {
+            1 => Ok(Condition::AxisRange {
+                axis_index: ctxt.read_u16be()?,
+                filter_range_min: ctxt.read::<F2Dot14>()?,
+                filter_range_max: ctxt.read::<F2Dot14>()?,
+            }),
+            _ => Ok(Condition::Unsupported),
+        }
+    }
+}
+
+impl Condition {
+    /// Returns true if the condition is met by the normalized axis coordinates `coords`.
+    ///
+    /// Axes without a coordinate are at their default, 0.
+    pub fn matches(&self, coords: &[f32]) -> bool {
+        match *self {
+            Condition::AxisRange {
+                axis_index,
+                filter_range_min,
+                filter_range_max,
+            } => {
+                let coord = coords.get(usize::from(axis_index)).copied().unwrap_or(0.);
+                f32::from(filter_range_min) <= coord && coord <= f32::from(filter_range_max)
+            }
+            Condition::Unsupported => false,
+        }
+    }
+}
+
+impl FeatureVariations {
+    pub fn records(&self) -> &[FeatureVariationRecord] {
+        &self.feature_variation_records
+    }
+
+    /// Find the feature table that replaces the feature at `feature_index` for the variation
+    /// instance at the normalized axis coordinates `coords`.
+    ///
+    /// Only the first record whose conditions are all met is used.
+    pub fn find_substitute(&self, feature_index: u16, coords: &[f32]) -> Option<&FeatureTable> {
+        self.feature_variation_records
+            .iter()
+            .find(|record| {
+                record
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.matches(coords))
+            })
+            .and_then(|record| {
+                record
+                    .substitutions
+                    .iter()
+                    .find(|(index, _)| *index == feature_index)
+                    .map(|(_, feature_table)| feature_table)
+            })
+    }
+}
+
 impl<'a, T> ReadBinary<'a> for LookupList<T> {
     type HostType = Self;
 
@@ -420,12 +897,12 @@ impl<'a> ReadBinary<'a> for LangSys {
 
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
         let _lookup_order = usize::from(ctxt.read_u16be()?);
-        let _required_feature_index = usize::from(ctxt.read_u16be()?);
+        let required_feature_index = ctxt.read_u16be()?;
         let feature_index_count = usize::from(ctxt.read_u16be()?);
         let feature_indices = ctxt.read_array::<U16Be>(feature_index_count)?.to_vec();
         Ok(LangSys {
             _lookup_order,
-            _required_feature_index,
+            required_feature_index,
             feature_indices,
         })
     }
@@ -435,6 +912,14 @@ impl LangSys {
     pub fn feature_indices_iter<'b>(&self) -> impl Iterator<Item = &u16> {
         self.feature_indices.iter()
     }
+
+    /// The index of the feature that must always be applied for this language system, if any.
+    pub fn required_feature_index(&self) -> Option<u16> {
+        match self.required_feature_index {
+            0xFFFF => None,
+            feature_index => Some(feature_index),
+        }
+    }
 }
 
 impl<T> LayoutTable<T> {
@@ -447,6 +932,11 @@ impl<T> LayoutTable<T> {
         Ok(None)
     }
 
+    /// The feature variations of the table, present in version 1.1 tables.
+    pub fn feature_variations(&self) -> Option<&FeatureVariations> {
+        self.opt_feature_variations.as_ref()
+    }
+
     pub fn find_script_or_default(
         &self,
         script_tag: u32,
@@ -461,6 +951,32 @@ impl<T> LayoutTable<T> {
         Ok(None)
     }
 
+    /// Find the feature `feature_tag` of `langsys`, substituted by the feature variations of the
+    /// table for the variation instance at the normalized axis coordinates `coords`.
+    pub fn find_langsys_feature_variation(
+        &self,
+        langsys: &LangSys,
+        feature_tag: u32,
+        coords: &[f32],
+    ) -> Result<Option<&FeatureTable>, ParseError> {
+        if let Some(ref feature_list) = self.opt_feature_list {
+            for feature_index in &langsys.feature_indices {
+                let feature_record =
+                    feature_list.nth_feature_record(usize::from(*feature_index))?;
+                if feature_record.feature_tag == feature_tag {
+                    let opt_substitute = self
+                        .opt_feature_variations
+                        .as_ref()
+                        .and_then(|variations| variations.find_substitute(*feature_index, coords));
+                    return Ok(Some(
+                        opt_substitute.unwrap_or(&feature_record.feature_table),
+                    ));
+                }
+            }
+        }
+        Ok(None)
+    }
+
     pub fn find_langsys_feature(
         &self,
         langsys: &LangSys,
@@ -613,6 +1129,7 @@ impl LookupList<GSUB> {
         };
         Ok(LookupCacheItem {
             lookup_flag,
+            mark_filtering_set: lookup.mark_filtering_set,
             lookup_subtables,
         })
     }
@@ -671,6 +1188,7 @@ impl LookupList<GPOS> {
         };
         Ok(LookupCacheItem {
             lookup_flag,
+            mark_filtering_set: lookup.mark_filtering_set,
             lookup_subtables,
         })
     }
@@ -756,11 +1274,17 @@ impl<'a, T: LayoutTableType> ReadBinary<'a> for Lookup<'a, T> {
         let lookup_flag = ctxt.read_u16be()?;
         let subtable_count = usize::from(ctxt.read_u16be()?);
         let subtable_offsets = ctxt.read_array::<U16Be>(subtable_count)?;
+        let mark_filtering_set = if LookupFlag(lookup_flag).get_use_mark_filtering_set() {
+            Some(ctxt.read_u16be()?)
+        } else {
+            None
+        };
         Ok(Lookup {
             scope,
             lookup_type,
             lookup_flag,
             subtable_offsets,
+            mark_filtering_set,
             phantom: PhantomData,
         })
     }
@@ -824,10 +1348,8 @@ impl<'a, 'b, T: LayoutTableType> Iterator for ExtensionLookupSubtableIter<'a, 'b
                     if ext_subtable.extension_lookup_type != self.lookup_type {
                         return Some(Err(ParseError::BadVersion));
                     }
-                    let subtable = ext_subtable
-                        .scope
-                        .offset(ext_subtable.extension_offset as usize);
-                    Some(Ok(subtable))
+                    let scope = &ext_subtable.scope;
+                    Some(scope.offset_checked(ext_subtable.extension_offset as usize))
                 }
                 Err(err) => Some(Err(err)),
             }
@@ -976,6 +1498,45 @@ impl SingleSubst {
             },
         }
     }
+
+    /// Remap this subtable to the glyph ids of a subset font.
+    ///
+    /// Substitutions involving glyphs that are not in `old_to_new_glyph_id` are dropped. Returns
+    /// `None` if no substitutions remain.
+    pub fn subset(
+        &self,
+        old_to_new_glyph_id: &HashMap<u16, u16>,
+    ) -> Result<Option<SingleSubst>, ParseError> {
+        let coverage = match *self {
+            SingleSubst::Format1 { ref coverage, .. }
+            | SingleSubst::Format2 { ref coverage, .. } => coverage,
+        };
+
+        let mut substitutions = Vec::new();
+        for (glyph, _coverage_index) in coverage.glyph_coverage_values() {
+            let substitute = match self.apply_glyph(glyph)? {
+                Some(substitute) => substitute,
+                None => continue,
+            };
+            if let (Some(&new_glyph), Some(&new_substitute)) = (
+                old_to_new_glyph_id.get(&glyph),
+                old_to_new_glyph_id.get(&substitute),
+            ) {
+                substitutions.push((new_glyph, new_substitute));
+            }
+        }
+        if substitutions.is_empty() {
+            return Ok(None);
+        }
+
+        // The new glyph ids may be in a different order to the old ones
+        substitutions.sort_by_key(|&(glyph, _)| glyph);
+        let (glyph_array, substitute_glyph_array) = substitutions.into_iter().unzip();
+        Ok(Some(SingleSubst::Format2 {
+            coverage: Rc::new(Coverage::Format1 { glyph_array }),
+            substitute_glyph_array,
+        }))
+    }
 }
 
 pub struct MultipleSubst {
@@ -1148,6 +1709,59 @@ impl<'a> LigatureSubst {
             None => Ok(None),
         }
     }
+
+    /// Remap this subtable to the glyph ids of a subset font.
+    ///
+    /// Ligatures involving glyphs that are not in `old_to_new_glyph_id` are dropped. Returns
+    /// `None` if no ligatures remain.
+    pub fn subset(
+        &self,
+        old_to_new_glyph_id: &HashMap<u16, u16>,
+    ) -> Result<Option<LigatureSubst>, ParseError> {
+        let mut ligaturesets = Vec::new();
+        for (glyph, coverage_index) in self.coverage.glyph_coverage_values() {
+            let new_glyph = match old_to_new_glyph_id.get(&glyph) {
+                Some(&new_glyph) => new_glyph,
+                None => continue,
+            };
+            let coverage_index = usize::from(coverage_index);
+            self.ligaturesets.check_index(coverage_index)?;
+            let ligatures = self.ligaturesets[coverage_index]
+                .ligatures
+                .iter()
+                .filter_map(|ligature| ligature.subset(old_to_new_glyph_id))
+                .collect::<Vec<_>>();
+            if !ligatures.is_empty() {
+                ligaturesets.push((new_glyph, LigatureSet { ligatures }));
+            }
+        }
+        if ligaturesets.is_empty() {
+            return Ok(None);
+        }
+
+        // The new glyph ids may be in a different order to the old ones
+        ligaturesets.sort_by_key(|&(glyph, _)| glyph);
+        let (glyph_array, ligaturesets) = ligaturesets.into_iter().unzip();
+        Ok(Some(LigatureSubst {
+            coverage: Rc::new(Coverage::Format1 { glyph_array }),
+            ligaturesets,
+        }))
+    }
+}
+
+impl Ligature {
+    fn subset(&self, old_to_new_glyph_id: &HashMap<u16, u16>) -> Option<Ligature> {
+        let ligature_glyph = *old_to_new_glyph_id.get(&self.ligature_glyph)?;
+        let component_glyphs = self
+            .component_glyphs
+            .iter()
+            .map(|glyph| old_to_new_glyph_id.get(glyph).cloned())
+            .collect::<Option<Vec<_>>>()?;
+        Some(Ligature {
+            ligature_glyph,
+            component_glyphs,
+        })
+    }
 }
 
 impl<'a> ReadBinary<'a> for LigatureSet {
@@ -2824,6 +3438,31 @@ impl Coverage {
         }
     }
 
+    /// The glyphs covered by this table paired with their coverage index.
+    pub fn glyph_coverage_values(&self) -> Vec<(u16, u16)> {
+        match self {
+            Coverage::Format1 { glyph_array } => glyph_array
+                .iter()
+                .enumerate()
+                .map(|(index, &glyph)| (glyph, index as u16))
+                .collect(),
+            Coverage::Format2 {
+                coverage_range_array,
+            } => coverage_range_array
+                .iter()
+                .flat_map(|coverage_range| {
+                    (coverage_range.start_glyph..=coverage_range.end_glyph).map(move |glyph| {
+                        (
+                            glyph,
+                            coverage_range.start_coverage_index
+                                + (glyph - coverage_range.start_glyph),
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+
     /// Convenience method to count the total number of glyphs covered
     pub fn glyph_count(&self) -> usize {
         match self {
@@ -2933,10 +3572,14 @@ impl ClassDef {
 
 pub type LayoutCache<T> = Rc<LayoutCacheData<T>>;
 
+/// The default maximum depth of nested contextual substitutions.
+pub const DEFAULT_SUBST_RECURSION_LIMIT: usize = 8;
+
 pub type LookupCache<T> = Vec<Option<Rc<LookupCacheItem<T>>>>;
 
 pub struct LookupCacheItem<T> {
     pub lookup_flag: LookupFlag,
+    pub mark_filtering_set: Option<u16>,
     pub lookup_subtables: T,
 }
 
@@ -2954,6 +3597,26 @@ pub struct LayoutCacheData<T: LayoutTableType> {
     pub lookups_index: RefCell<HashMap<(u32, u32, u32), usize>>,
 
     pub cached_lookups: RefCell<Vec<Vec<(usize, u32)>>>,
+
+    subst_recursion_limit: Cell<usize>,
+}
+
+impl<T: LayoutTableType> LayoutCacheData<T> {
+    /// The maximum depth of nested contextual substitutions when applying `GSUB` lookups.
+    ///
+    /// Exceeding the limit results in `ParseError::LimitExceeded`.
+    pub fn subst_recursion_limit(&self) -> usize {
+        self.subst_recursion_limit.get()
+    }
+
+    /// Set the maximum depth of nested contextual substitutions.
+    ///
+    /// The default of `DEFAULT_SUBST_RECURSION_LIMIT` is sufficient for most fonts. Like the
+    /// cached lookups, the limit belongs to the font rather than to a single call, so it applies
+    /// to all shaping done with this cache, including by the script specific shapers.
+    pub fn set_subst_recursion_limit(&self, limit: usize) {
+        self.subst_recursion_limit.set(limit)
+    }
 }
 
 pub fn new_layout_cache<T: LayoutTableType>(layout_table: LayoutTable<T>) -> LayoutCache<T> {
@@ -2963,6 +3626,7 @@ pub fn new_layout_cache<T: LayoutTableType>(layout_table: LayoutTable<T>) -> Lay
     let supported_features = RefCell::new(HashMap::new());
     let lookups_index = RefCell::new(HashMap::new());
     let cached_lookups = RefCell::new(vec![Vec::new()]);
+    let subst_recursion_limit = Cell::new(DEFAULT_SUBST_RECURSION_LIMIT);
     Rc::new(LayoutCacheData {
         layout_table,
         coverages,
@@ -2971,12 +3635,16 @@ pub fn new_layout_cache<T: LayoutTableType>(layout_table: LayoutTable<T>) -> Lay
         supported_features,
         lookups_index,
         cached_lookups,
+        subst_recursion_limit,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tables::{FontTableProvider, OpenTypeFile};
+    use crate::tag;
+    use crate::tests::read_fixture;
 
     fn make_gdef_header(glyph_classdef_offset: u16) -> Vec<u8> {
         let mut data = vec![
@@ -3015,8 +3683,252 @@ mod tests {
         let data = make_gdef_header(1000);
         match ReadScope::new(&data).read::<GDEFTable>() {
             Ok(_) => panic!("expected error got success"),
-            Err(ParseError::BadEof) => {}
-            Err(err) => panic!("expeceted ParseError::BadEof got {:?}", err),
+            Err(ParseError::BadOffset) => {}
+            Err(err) => panic!("expeceted ParseError::BadOffset got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_read_layout_table_bad_lookup_list_offset() {
+        #[rustfmt::skip]
+        let data = [
+            0x00, 0x01, // major version
+            0x00, 0x00, // minor version
+            0x00, 0x00, // script list offset
+            0x00, 0x00, // feature list offset
+            0x03, 0xE8, // lookup list offset
+        ];
+        match ReadScope::new(&data).read::<LayoutTable<GSUB>>() {
+            Err(ParseError::BadOffset) => {}
+            Err(err) => panic!("expected ParseError::BadOffset got {:?}", err),
+            Ok(_) => panic!("expected error got success"),
+        }
+    }
+
+    #[test]
+    fn test_read_extension_bad_offset() {
+        #[rustfmt::skip]
+        let data = [
+            0x00, 0x01, // major version
+            0x00, 0x00, // minor version
+            0x00, 0x00, // script list offset
+            0x00, 0x00, // feature list offset
+            0x00, 0x0A, // lookup list offset
+            // LookupList
+            0x00, 0x01, // lookup count
+            0x00, 0x04, // lookup offsets
+            // Lookup
+            0x00, 0x07, // lookup type
+            0x00, 0x00, // lookup flag
+            0x00, 0x01, // subtable count
+            0x00, 0x08, // subtable offsets
+            // ExtensionSubstFormat1
+            0x00, 0x01, // format
+            0x00, 0x01, // extension lookup type
+            0x00, 0x00, 0x03, 0xE8, // extension offset
+        ];
+        let gsub = ReadScope::new(&data).read::<LayoutTable<GSUB>>().unwrap();
+        let cache = new_layout_cache(gsub);
+        let lookup_list = cache.layout_table.opt_lookup_list.as_ref().unwrap();
+        match lookup_list.lookup_cache_gsub(&cache, 0) {
+            Err(ParseError::BadOffset) => {}
+            Err(err) => panic!("expected ParseError::BadOffset got {:?}", err),
+            Ok(_) => panic!("expected error got success"),
         }
     }
+
+    #[rustfmt::skip]
+    const LIG_CARET_LIST: &[u8] = &[
+        0x00, 0x08, // coverage offset
+        0x00, 0x02, // lig glyph count
+        0x00, 0x10, // lig glyph offsets
+        0x00, 0x1E,
+        // Coverage
+        0x00, 0x01, // format
+        0x00, 0x02, // glyph count
+        0x00, 0x05, // glyph array
+        0x00, 0x09,
+        // LigGlyph for glyph 5
+        0x00, 0x02, // caret count
+        0x00, 0x06, // caret value offsets
+        0x00, 0x0A,
+        0x00, 0x01, 0x01, 0xF4, // format 1, coordinate 500
+        0x00, 0x03, 0x03, 0xE8, // format 3, coordinate 1000
+        // LigGlyph for glyph 9
+        0x00, 0x01, // caret count
+        0x00, 0x04, // caret value offsets
+        0x00, 0x02, 0x00, 0x07, // format 2, contour point 7
+    ];
+
+    #[test]
+    fn test_read_lig_caret_list() {
+        let lig_caret_list = ReadScope::new(LIG_CARET_LIST)
+            .read::<LigCaretList>()
+            .unwrap();
+        let gdef = GDEFTable {
+            opt_glyph_classdef: None,
+            opt_lig_caret_list: Some(lig_caret_list),
+            opt_mark_attach_classdef: None,
+            opt_mark_glyph_sets: None,
+        };
+
+        assert_eq!(gdef.ligature_carets(5), Some(vec![500, 1000]));
+        assert_eq!(
+            gdef.ligature_caret_values(9),
+            Some([CaretValue::ContourPoint(7)].as_ref())
+        );
+        assert_eq!(gdef.ligature_carets(9), None);
+        assert_eq!(gdef.ligature_carets(6), None);
+    }
+
+    #[test]
+    fn test_ligature_carets() {
+        let buffer = read_fixture("tests/fonts/noto/NotoNaskhArabic-Regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let gdef_data = provider.read_table_data(tag::GDEF).unwrap();
+        let gdef = ReadScope::new(&gdef_data).read::<GDEFTable>().unwrap();
+
+        // A three part ligature
+        assert_eq!(gdef.ligature_carets(988), Some(vec![853, 1707, 2560]));
+        assert_eq!(gdef.ligature_carets(507), Some(vec![530]));
+        assert_eq!(gdef.ligature_carets(0), None);
+    }
+
+    #[rustfmt::skip]
+    const FEATURE_VARIATIONS: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x00, 0x00, 0x02, // record count
+        0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00, 0x32, // condition set, substitution offsets
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // no conditions or substitutions
+        // ConditionSet
+        0x00, 0x02, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x12,
+        // Condition format 1, axis 0 between 0.5 and 1.0
+        0x00, 0x01, 0x00, 0x00, 0x20, 0x00, 0x40, 0x00,
+        // Condition format 1, axis 1 between -1.0 and 0.0
+        0x00, 0x01, 0x00, 0x01, 0xC0, 0x00, 0x00, 0x00,
+        // FeatureTableSubstitution
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, // feature index 0, feature offset
+        // Feature with lookup 1
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn test_read_feature_variations() {
+        let feature_variations = ReadScope::new(FEATURE_VARIATIONS)
+            .read::<FeatureVariations>()
+            .unwrap();
+        let records = feature_variations.records();
+        assert_eq!(records.len(), 2);
+
+        let ranges = records[0]
+            .conditions
+            .iter()
+            .map(|condition| match *condition {
+                Condition::AxisRange {
+                    axis_index,
+                    filter_range_min,
+                    filter_range_max,
+                } => (
+                    axis_index,
+                    f32::from(filter_range_min),
+                    f32::from(filter_range_max),
+                ),
+                Condition::Unsupported => panic!("unexpected condition format"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, vec![(0, 0.5, 1.0), (1, -1.0, 0.0)]);
+        assert_eq!(records[0].substitutions.len(), 1);
+        assert_eq!(records[0].substitutions[0].0, 0);
+        assert_eq!(records[0].substitutions[0].1.lookup_indices, vec![1]);
+        assert!(records[1].conditions.is_empty());
+        assert!(records[1].substitutions.is_empty());
+
+        let lookup_indices = |coords: &[f32]| {
+            feature_variations
+                .find_substitute(0, coords)
+                .map(|feature_table| feature_table.lookup_indices.clone())
+        };
+        assert_eq!(lookup_indices(&[0.75, -0.5]), Some(vec![1]));
+        // The second record has no conditions so it matches, but it has no substitutions
+        assert_eq!(lookup_indices(&[0.75, 0.5]), None);
+        assert_eq!(lookup_indices(&[]), None);
+    }
+
+    #[test]
+    fn test_single_subst_subset() {
+        // 10 -> 20, 11 -> 21, 12 -> 22
+        let single_subst = SingleSubst::Format1 {
+            coverage: Rc::new(Coverage::Format2 {
+                coverage_range_array: vec![CoverageRangeRecord {
+                    start_glyph: 10,
+                    end_glyph: 12,
+                    start_coverage_index: 0,
+                }],
+            }),
+            delta_glyph_index: 10,
+        };
+        // Glyph 11 is not retained and glyph 12 is reordered before glyph 10
+        let old_to_new_glyph_id = [(0, 0), (12, 1), (10, 2), (20, 3), (22, 4), (21, 5)]
+            .iter()
+            .cloned()
+            .collect::<HashMap<_, _>>();
+
+        let subset = single_subst.subset(&old_to_new_glyph_id).unwrap().unwrap();
+        assert_eq!(subset.apply_glyph(1).unwrap(), Some(4));
+        assert_eq!(subset.apply_glyph(2).unwrap(), Some(3));
+        assert_eq!(subset.apply_glyph(5).unwrap(), None);
+    }
+
+    #[test]
+    fn test_single_subst_subset_empty() {
+        let single_subst = SingleSubst::Format2 {
+            coverage: Rc::new(Coverage::Format1 {
+                glyph_array: vec![10],
+            }),
+            substitute_glyph_array: vec![20],
+        };
+        // The substitute glyph is not retained
+        let old_to_new_glyph_id = [(0, 0), (10, 1)].iter().cloned().collect();
+
+        assert!(single_subst.subset(&old_to_new_glyph_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_ligature_subst_subset() {
+        // f f -> 30, f i -> 31, i j -> 32 where f = 10, i = 11, j = 12
+        let ligature = |ligature_glyph, component_glyphs| Ligature {
+            ligature_glyph,
+            component_glyphs,
+        };
+        let ligature_subst = LigatureSubst {
+            coverage: Rc::new(Coverage::Format1 {
+                glyph_array: vec![10, 11],
+            }),
+            ligaturesets: vec![
+                LigatureSet {
+                    ligatures: vec![ligature(30, vec![10]), ligature(31, vec![11])],
+                },
+                LigatureSet {
+                    ligatures: vec![ligature(32, vec![12])],
+                },
+            ],
+        };
+        // Glyphs 12 and 30 are not retained and the order of 10 and 11 is swapped
+        let old_to_new_glyph_id = [(0, 0), (11, 1), (10, 2), (31, 3)]
+            .iter()
+            .cloned()
+            .collect::<HashMap<_, _>>();
+
+        let subset = ligature_subst
+            .subset(&old_to_new_glyph_id)
+            .unwrap()
+            .unwrap();
+        assert!(subset.apply_glyph(1).unwrap().is_none());
+        let ligatureset = subset.apply_glyph(2).unwrap().unwrap();
+        assert_eq!(ligatureset.ligatures.len(), 1);
+        assert_eq!(ligatureset.ligatures[0].ligature_glyph, 3);
+        assert_eq!(ligatureset.ligatures[0].component_glyphs, vec![1]);
+    }
 }
@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(rust_2018_idioms)]
 #![doc(html_logo_url = "https://github.com/yeslogic/allsorts/raw/master/allsorts.svg?sanitize=1")]
 
@@ -80,43 +81,80 @@
 //!
 //! Build with `cargo build` and run the tests with `cargo test`.
 //!
+//! ## `no_std` Support
+//!
+//! The table parsing core of Allsorts, which includes `ReadScope`, the binary reading and writing
+//! traits, and the `cmap`, `glyf`, `loca`, `hmtx`, and other `tables`, can be used without the
+//! standard library by disabling the default `std` feature. It still requires an allocator.
+//! Shaping, subsetting, WOFF and WOFF2, CFF, and bitmap font support all require `std`.
+//!
 //! ## License
 //!
 //! Allsorts is distributed under the terms of the Apache License (Version 2.0).
 //!
 //! See [LICENSE](https://github.com/yeslogic/allsorts/blob/master/LICENSE) for details.
 
+#[cfg(feature = "std")]
 pub mod big5;
 pub mod binary;
+#[cfg(feature = "std")]
 pub mod bitmap;
+#[cfg(feature = "std")]
 pub mod cff;
+#[cfg(feature = "std")]
+pub mod cff2;
 pub mod checksum;
+#[cfg(feature = "std")]
 pub mod context;
 pub mod error;
+#[cfg(feature = "std")]
 pub mod font_data_impl;
+#[cfg(feature = "std")]
 pub mod fontfile;
+#[cfg(feature = "std")]
 pub mod gdef;
+#[cfg(feature = "std")]
 pub mod get_name;
+#[cfg(feature = "std")]
 pub mod glyph_info;
+#[cfg(feature = "std")]
 pub mod gpos;
+#[cfg(feature = "std")]
 pub mod gsub;
+#[cfg(feature = "std")]
 pub mod layout;
+#[cfg(feature = "std")]
 pub mod macroman;
+#[cfg(feature = "std")]
 pub mod post;
+#[cfg(feature = "std")]
 pub mod scripts;
+#[cfg(feature = "std")]
+pub mod shape;
 pub mod size;
+#[cfg(feature = "std")]
 pub mod subset;
 pub mod tables;
 pub mod tag;
 #[cfg(test)]
 pub mod tests;
+#[cfg(feature = "std")]
 pub mod unicode;
+#[cfg(feature = "std")]
 pub mod woff;
+#[cfg(feature = "std")]
 pub mod woff2;
 
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std as alloc;
+
 // Note from rental docs:
 // NOTE for Rust 2018: Relying on implicit crate imports may cause compile errors in code generated
 // by this macro. To avoid this, import the crate manually like so
+#[cfg(feature = "std")]
 #[macro_use]
 extern crate rental;
 
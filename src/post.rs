@@ -1,9 +1,10 @@
 //! `post` table parsing and writing.
 
-use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt};
+use crate::binary::read::{ReadArrayCow, ReadBinary, ReadCtxt};
 use crate::binary::write::{WriteBinary, WriteContext};
 use crate::binary::{I16Be, I32Be, U16Be, U32Be, U8};
 use crate::error::{ParseError, WriteError};
+use std::convert::TryFrom;
 use std::str;
 
 pub struct PostTable<'a> {
@@ -11,6 +12,7 @@ pub struct PostTable<'a> {
     pub opt_sub_table: Option<SubTable<'a>>,
 }
 
+#[derive(Clone)]
 pub struct Header {
     pub version: i32,
     pub italic_angle: i32,
@@ -25,7 +27,7 @@ pub struct Header {
 
 pub struct SubTable<'a> {
     pub num_glyphs: u16,
-    pub glyph_name_index: ReadArray<'a, U16Be>,
+    pub glyph_name_index: ReadArrayCow<'a, U16Be>,
     pub names: Vec<PascalString<'a>>,
 }
 
@@ -104,7 +106,7 @@ impl<'a> ReadBinary<'a> for PostTable<'a> {
 
                 Some(SubTable {
                     num_glyphs,
-                    glyph_name_index,
+                    glyph_name_index: ReadArrayCow::Borrowed(glyph_name_index),
                     names,
                 })
             }
@@ -138,7 +140,7 @@ impl<'a> WriteBinary<&Self> for SubTable<'a> {
 
     fn write<C: WriteContext>(ctxt: &mut C, table: &SubTable<'a>) -> Result<(), WriteError> {
         U16Be::write(ctxt, table.num_glyphs)?;
-        <&ReadArray<'_, _>>::write(ctxt, &table.glyph_name_index)?;
+        ReadArrayCow::write(ctxt, &table.glyph_name_index)?;
         for name in &table.names {
             PascalString::write(ctxt, name)?;
         }
@@ -163,6 +165,50 @@ impl<'a> WriteBinary<&Self> for PascalString<'a> {
 }
 
 impl<'a> PostTable<'a> {
+    /// Build a version 2.0 table containing the names of the glyphs in `new_to_old_glyph_id`.
+    ///
+    /// Each glyph in the new table takes the name of the old glyph it was created from. Names in
+    /// the standard Macintosh glyph set are referred to by index, other names are stored in the
+    /// new table. Glyphs without a name are given the name `.notdef`.
+    pub fn subset(&self, new_to_old_glyph_id: &[u16]) -> Result<PostTable<'a>, ParseError> {
+        let num_glyphs = u16::try_from(new_to_old_glyph_id.len())?;
+        let mut glyph_name_index = Vec::with_capacity(new_to_old_glyph_id.len());
+        let mut names = Vec::new();
+
+        for &old_glyph_id in new_to_old_glyph_id {
+            let index = match self.glyph_name(old_glyph_id)? {
+                Some(name) => match FORMAT_1_NAMES.iter().position(|&mac_name| mac_name == name) {
+                    Some(index) => index,
+                    None => {
+                        names.push(PascalString {
+                            bytes: name.as_bytes(),
+                        });
+                        FORMAT_1_NAMES.len() + names.len() - 1
+                    }
+                },
+                None => 0, // .notdef
+            };
+            glyph_name_index.push(u16::try_from(index)?);
+        }
+
+        let mut header = self.header.clone();
+        header.version = 0x00020000; // version 2.0
+
+        Ok(PostTable {
+            header,
+            opt_sub_table: Some(SubTable {
+                num_glyphs,
+                glyph_name_index: ReadArrayCow::Owned(glyph_name_index),
+                names,
+            }),
+        })
+    }
+
+    /// Look up the name of `glyph_index`.
+    ///
+    /// Version 1.0 tables use the standard Macintosh glyph names. Version 2.0 tables map each
+    /// glyph to either a standard name or one of the names stored in the table. Other versions
+    /// don't contain glyph names and return `None`.
     pub fn glyph_name(&self, glyph_index: u16) -> Result<Option<&'a str>, ParseError> {
         if let Some(sub_table) = &self.opt_sub_table {
             if glyph_index >= sub_table.num_glyphs {
@@ -185,7 +231,8 @@ impl<'a> PostTable<'a> {
                         Ok(Some(FORMAT_1_NAMES[usize::from(name_index)]))
                     } else {
                         let index = usize::from(name_index) - FORMAT_1_NAMES.len();
-                        let pascal_string = &sub_table.names[index];
+                        let pascal_string =
+                            sub_table.names.get(index).ok_or(ParseError::BadIndex)?;
 
                         match str::from_utf8(pascal_string.bytes) {
                             Ok(name) => Ok(Some(name)),
@@ -461,3 +508,41 @@ static FORMAT_1_NAMES: &'static [&'static str; 258] = &[
     "ccaron",
     "dcroat",
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::tables::{FontTableProvider, OpenTypeFile};
+    use crate::tag;
+    use crate::tests::read_fixture;
+
+    #[test]
+    fn glyph_name_version_2() {
+        let buffer = read_fixture("tests/fonts/noto/NotoSansTamil-Regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let post_data = provider.read_table_data(tag::POST).unwrap();
+        let post = ReadScope::new(&post_data).read::<PostTable<'_>>().unwrap();
+
+        assert_eq!(post.header.version, 0x00020000);
+        assert_eq!(post.glyph_name(0), Ok(Some(".notdef")));
+        assert_eq!(post.glyph_name(1), Ok(Some("NULL")));
+        assert_eq!(post.glyph_name(3), Ok(Some("space")));
+        assert_eq!(post.glyph_name(4), Ok(Some("anusvaratamil")));
+        assert_eq!(post.glyph_name(240), Ok(None));
+    }
+
+    #[test]
+    fn glyph_name_bad_index() {
+        let mut data = vec![0; 32];
+        data[1] = 2; // version 2.0
+        data.extend_from_slice(&[
+            0, 1, // numGlyphs
+            1, 3, // glyphNameIndex: 259
+            1, b'a', // names[0]
+        ]);
+        let post = ReadScope::new(&data).read::<PostTable<'_>>().unwrap();
+        assert_eq!(post.glyph_name(0), Err(ParseError::BadIndex));
+    }
+}
@@ -4,34 +4,76 @@
 //! <https://github.com/n8willis/opentype-shaping-documents/blob/master/opentype-shaping-arabic-general.md>
 
 use crate::error::{ParseError, ShapingError};
-use crate::gsub::{self, build_lookups, GlyphData, GlyphOrigin, RawGlyph};
-use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::gpos::{self, Info};
+use crate::gsub::{
+    self, build_lookups, find_alternate, FeatureInfo, GlyphData, GlyphOrigin, RawGlyph,
+};
+use crate::layout::{ClassDef, GDEFTable, LangSys, LayoutCache, LayoutTable, GPOS, GSUB};
 use crate::tag;
+use crate::unicode::canonical_combining_class;
 
 use std::convert::From;
+use std::u16;
 use unicode_joining_type::{get_joining_type, JoiningType};
 
 #[derive(Clone)]
 struct ArabicData {
     joining_type: JoiningType,
     feature_tag: u32,
+    canonical_combining_class: u8,
 }
 
 impl GlyphData for ArabicData {
-    fn merge(data1: ArabicData, _data2: ArabicData) -> ArabicData {
-        // TODO hold off for future Unicode normalisation changes
-        data1
+    /// Keep the data of the glyph with the lower canonical combining class, as it is the one
+    /// closest to the base. On ties the data of the first glyph is kept.
+    fn merge(data1: ArabicData, data2: ArabicData) -> ArabicData {
+        if data2.canonical_combining_class < data1.canonical_combining_class {
+            data2
+        } else {
+            data1
+        }
     }
 }
 
 // Arabic glyphs are represented as `RawGlyph` structs with `ArabicData` for its `extra_data`.
 type ArabicGlyph = RawGlyph<ArabicData>;
 
+/// Marks that modify the shape of their base, so are positioned closest to it.
+///
+/// <https://www.unicode.org/reports/tr53/#MCM>
+const MODIFIER_COMBINING_MARKS: [char; 14] = [
+    '\u{0654}', // ARABIC HAMZA ABOVE
+    '\u{0655}', // ARABIC HAMZA BELOW
+    '\u{0658}', // ARABIC MARK NOON GHUNNA
+    '\u{06DC}', // ARABIC SMALL HIGH SEEN
+    '\u{06E3}', // ARABIC SMALL LOW SEEN
+    '\u{06E7}', // ARABIC SMALL HIGH YEH
+    '\u{06E8}', // ARABIC SMALL HIGH NOON
+    '\u{08CA}', // ARABIC SMALL HIGH FARSI YEH
+    '\u{08CB}', // ARABIC SMALL HIGH YEH BARREE WITH TWO DOTS BELOW
+    '\u{08CD}', // ARABIC SMALL HIGH ZAH
+    '\u{08CE}', // ARABIC LARGE ROUND DOT ABOVE
+    '\u{08CF}', // ARABIC LARGE ROUND DOT BELOW
+    '\u{08D3}', // ARABIC SMALL LOW WAW
+    '\u{08F3}', // ARABIC SMALL HIGH WAW
+];
+
 impl ArabicGlyph {
     fn is_transparent(&self) -> bool {
         self.extra_data.joining_type == JoiningType::Transparent || self.multi_subst_dup
     }
 
+    fn is_mark(&self) -> bool {
+        self.extra_data.joining_type == JoiningType::Transparent
+    }
+
+    fn is_modifier_combining_mark(&self) -> bool {
+        match self.glyph_origin {
+            GlyphOrigin::Char(c) => MODIFIER_COMBINING_MARKS.contains(&c),
+            GlyphOrigin::Direct => false,
+        }
+    }
+
     fn is_left_joining(&self) -> bool {
         self.extra_data.joining_type == JoiningType::LeftJoining
             || self.extra_data.joining_type == JoiningType::DualJoining
@@ -44,6 +86,13 @@ impl ArabicGlyph {
             || self.extra_data.joining_type == JoiningType::JoinCausing
     }
 
+    fn is_joiner(&self) -> bool {
+        match self.glyph_origin {
+            GlyphOrigin::Char('\u{200C}') | GlyphOrigin::Char('\u{200D}') => true,
+            _ => false,
+        }
+    }
+
     fn feature_tag(&self) -> u32 {
         self.extra_data.feature_tag
     }
@@ -58,15 +107,26 @@ impl From<&RawGlyph<()>> for ArabicGlyph {
         // Since there's no `Char` to work out the `ArabicGlyph`s joining type when the glyph's
         // `glyph_origin` is `GlyphOrigin::Direct`, we fallback to `JoiningType::NonJoining` as
         // the safest approach
+        //
+        // ZWJ and ZWNJ are matched explicitly so that they always join and break respectively
         let joining_type = match raw_glyph.glyph_origin {
+            GlyphOrigin::Char('\u{200D}') => JoiningType::JoinCausing,
+            GlyphOrigin::Char('\u{200C}') => JoiningType::NonJoining,
             GlyphOrigin::Char(c) => get_joining_type(c),
             GlyphOrigin::Direct => JoiningType::NonJoining,
         };
 
+        // As above, we'll fallback onto treating the glyph as a base
+        let canonical_combining_class = match raw_glyph.glyph_origin {
+            GlyphOrigin::Char(c) => canonical_combining_class(c),
+            GlyphOrigin::Direct => 0,
+        };
+
         ArabicGlyph {
             unicodes: raw_glyph.unicodes.clone(),
             glyph_index: raw_glyph.glyph_index,
             liga_component_pos: raw_glyph.liga_component_pos,
+            cluster: raw_glyph.cluster,
             glyph_origin: raw_glyph.glyph_origin,
             small_caps: raw_glyph.small_caps,
             multi_subst_dup: raw_glyph.multi_subst_dup,
@@ -79,6 +139,7 @@ impl From<&RawGlyph<()>> for ArabicGlyph {
                 // For convenience, we losely follow the spec (`2. Computing letter joining
                 // states`) here by initialising all `ArabicGlyph`s to `tag::ISOL`
                 feature_tag: tag::ISOL,
+                canonical_combining_class,
             },
         }
     }
@@ -90,6 +151,7 @@ impl From<&ArabicGlyph> for RawGlyph<()> {
             unicodes: arabic_glyph.unicodes.clone(),
             glyph_index: arabic_glyph.glyph_index,
             liga_component_pos: arabic_glyph.liga_component_pos,
+            cluster: arabic_glyph.cluster,
             glyph_origin: arabic_glyph.glyph_origin,
             small_caps: arabic_glyph.small_caps,
             multi_subst_dup: arabic_glyph.multi_subst_dup,
@@ -102,12 +164,17 @@ impl From<&ArabicGlyph> for RawGlyph<()> {
     }
 }
 
+/// Apply the Arabic shaping features to `raw_glyphs`, followed by `extra_features`.
+///
+/// The `extra_features`, such as stylistic sets, are applied to all glyphs after the standard
+/// Arabic features, in the order of their lookups.
 pub fn gsub_apply_arabic(
     gsub_cache: &LayoutCache<GSUB>,
     gsub_table: &LayoutTable<GSUB>,
     gdef_table: Option<&GDEFTable>,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
+    extra_features: &[FeatureInfo],
     raw_glyphs: &mut Vec<RawGlyph<()>>,
 ) -> Result<(), ShapingError> {
     let langsys = match gsub_table.find_script(script_tag)? {
@@ -159,6 +226,10 @@ pub fn gsub_apply_arabic(
         }
     }
 
+    // Now that they have contributed to the joining states of their neighbours, ZWJ and ZWNJ can
+    // be removed. The remaining glyphs keep the feature tags computed above
+    arabic_glyphs.retain(|g| !g.is_joiner());
+
     // 3. Applying the stch feature
     //
     // TODO hold off for future generalised solution (including the Syriac Abbreviation Mark)
@@ -222,15 +293,128 @@ pub fn gsub_apply_arabic(
         |_, _| true,
     )?;
 
-    // 6. Mark reordering
-    //
-    // This is currently not implemented as results would then differ from other Arabic shaperers
+    // 6. Applying the user-specified features from GSUB
+
+    let extra_feature_tags = extra_features
+        .iter()
+        .map(|feature_info| feature_info.feature_tag)
+        .collect::<Vec<_>>();
+    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, &extra_feature_tags)? {
+        gsub::gsub_apply_lookup(
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            lookup_index,
+            feature_tag,
+            find_alternate(extra_features, feature_tag),
+            arabic_glyphs,
+            0,
+            arabic_glyphs.len(),
+            |_| true,
+        )?;
+    }
+
+    // 7. Mark reordering
+
+    reorder_marks(arabic_glyphs);
 
     *raw_glyphs = arabic_glyphs.iter().map(RawGlyph::from).collect();
 
     Ok(())
 }
 
+/// Apply the Arabic positioning features to `infos`.
+///
+/// Cursive attachment joins each letter to the next, then marks are attached to the preceding
+/// base glyph or ligature component, and to other marks. The index of the glyph that each mark
+/// is attached to is recorded in its `MarkPlacement`.
+///
+/// If the font does not classify its glyphs in `GDEF`, the glyphs of transparent characters,
+/// such as the harakat, are treated as marks so that cursive attachment skips over them and
+/// mark attachment finds their base.
+pub fn gpos_apply_arabic(
+    gpos_cache: &LayoutCache<GPOS>,
+    gpos_table: &LayoutTable<GPOS>,
+    gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    infos: &mut [Info],
+) -> Result<(), ParseError> {
+    let langsys = match gpos_table.find_script_or_default(script_tag)? {
+        Some(s) => match s.find_langsys_or_default(opt_lang_tag)? {
+            Some(v) => v,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+
+    let synthetic_gdef_table;
+    let gdef_table = match gdef_table {
+        Some(gdef_table) if gdef_table.opt_glyph_classdef.is_some() => Some(gdef_table),
+        _ => {
+            synthetic_gdef_table = synthesize_gdef_table(infos);
+            Some(&synthetic_gdef_table)
+        }
+    };
+
+    gpos::gpos_apply0(
+        gpos_cache,
+        gpos_table,
+        gdef_table,
+        langsys,
+        &[tag::CURS, tag::KERN, tag::MARK, tag::MKMK],
+        infos,
+    )
+}
+
+/// Build a `GDEFTable` that classifies the glyphs in `infos` from their characters, marking the
+/// glyphs of transparent characters as marks and all others as base glyphs.
+fn synthesize_gdef_table(infos: &mut [Info]) -> GDEFTable {
+    let (min_glyph, max_glyph) = infos.iter().fold((u16::MAX, 0), |(min, max), info| {
+        let glyph_index = info.glyph.glyph_index;
+        (min.min(glyph_index), max.max(glyph_index))
+    });
+    let mut class_value_array = vec![0; usize::from(max_glyph.saturating_sub(min_glyph)) + 1];
+    for info in infos.iter_mut() {
+        let class_value = &mut class_value_array[usize::from(info.glyph.glyph_index - min_glyph)];
+        if ArabicGlyph::from(&info.glyph).is_mark() {
+            info.is_mark = true;
+            *class_value = 3;
+        } else if *class_value == 0 {
+            *class_value = 1;
+        }
+    }
+
+    GDEFTable {
+        opt_glyph_classdef: Some(ClassDef::Format1 {
+            start_glyph: min_glyph,
+            class_value_array,
+        }),
+        opt_lig_caret_list: None,
+        opt_mark_attach_classdef: None,
+        opt_mark_glyph_sets: None,
+    }
+}
+
+/// Move the modifier combining marks in each run of marks to the start of the run, so they
+/// immediately follow their base. The order of the marks is otherwise kept.
+fn reorder_marks(arabic_glyphs: &mut [ArabicGlyph]) {
+    let mut start = 0;
+    while start < arabic_glyphs.len() {
+        if !arabic_glyphs[start].is_mark() {
+            start += 1;
+            continue;
+        }
+        let end = arabic_glyphs[start..]
+            .iter()
+            .position(|g| !g.is_mark())
+            .map_or(arabic_glyphs.len(), |len| start + len);
+        // The sort is stable, so marks of each kind keep their order
+        arabic_glyphs[start..end].sort_by_key(|g| !g.is_modifier_combining_mark());
+        start = end;
+    }
+}
+
 fn apply_lookup(
     feature_tags: &[u32],
     gsub_cache: &LayoutCache<GSUB>,
@@ -257,3 +441,138 @@ fn apply_lookup(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::layout::new_layout_cache;
+    use crate::shape::{make_glyph, shape, Features, ShapeDirection};
+    use crate::tables::{FontTableProvider, OpenTypeFile};
+    use crate::tests::read_fixture;
+
+    fn arabic_data(ch: char, feature_tag: u32) -> ArabicData {
+        ArabicData {
+            joining_type: get_joining_type(ch),
+            feature_tag,
+            canonical_combining_class: canonical_combining_class(ch),
+        }
+    }
+
+    fn arabic_glyph(ch: char, glyph_index: u16) -> ArabicGlyph {
+        ArabicGlyph::from(&make_glyph(ch, glyph_index, 0))
+    }
+
+    fn glyph_indices(arabic_glyphs: &[ArabicGlyph]) -> Vec<u16> {
+        arabic_glyphs.iter().map(|g| g.glyph_index).collect()
+    }
+
+    #[test]
+    fn test_reorder_marks_keeps_harakat() {
+        // beh, shadda, fatha
+        let mut glyphs = vec![
+            arabic_glyph('\u{0628}', 1),
+            arabic_glyph('\u{0651}', 2),
+            arabic_glyph('\u{064E}', 3),
+        ];
+        reorder_marks(&mut glyphs);
+        assert_eq!(glyph_indices(&glyphs), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_reorder_marks_modifier_first() {
+        // alef, fatha, hamza above, beh, kasra, hamza below, shadda
+        let mut glyphs = vec![
+            arabic_glyph('\u{0627}', 1),
+            arabic_glyph('\u{064E}', 2),
+            arabic_glyph('\u{0654}', 3),
+            arabic_glyph('\u{0628}', 4),
+            arabic_glyph('\u{0650}', 5),
+            arabic_glyph('\u{0655}', 6),
+            arabic_glyph('\u{0651}', 7),
+        ];
+        reorder_marks(&mut glyphs);
+        assert_eq!(glyph_indices(&glyphs), [1, 3, 2, 4, 6, 5, 7]);
+    }
+
+    #[test]
+    fn test_merge_stacked_marks() {
+        // FATHA (30) has a lower canonical combining class than SHADDA (33), so its data is kept
+        // regardless of order
+        let shadda = arabic_data('\u{0651}', tag::MEDI);
+        let fatha = arabic_data('\u{064E}', tag::FINA);
+        assert_eq!(
+            ArabicData::merge(shadda.clone(), fatha.clone()).feature_tag,
+            tag::FINA
+        );
+        assert_eq!(ArabicData::merge(fatha, shadda).feature_tag, tag::FINA);
+    }
+
+    #[test]
+    fn test_merge_tie() {
+        // MADDAH ABOVE and HAMZA ABOVE are both 230
+        let maddah = arabic_data('\u{0653}', tag::INIT);
+        let hamza = arabic_data('\u{0654}', tag::FINA);
+        assert_eq!(ArabicData::merge(maddah, hamza).feature_tag, tag::INIT);
+    }
+
+    #[test]
+    fn test_merge_base_and_mark() {
+        let lam = arabic_data('\u{0644}', tag::INIT);
+        let kasra = arabic_data('\u{0650}', tag::ISOL);
+        assert_eq!(ArabicData::merge(kasra, lam).feature_tag, tag::INIT);
+    }
+
+    #[test]
+    fn test_gpos_apply_arabic_without_glyph_classes() {
+        let buffer = read_fixture("tests/fonts/arabic/NafeesNastaleeq.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let gpos_data = provider.read_table_data(tag::GPOS).unwrap();
+        let gpos_table = ReadScope::new(&gpos_data)
+            .read::<LayoutTable<GPOS>>()
+            .unwrap();
+        let gpos_cache = new_layout_cache(gpos_table);
+
+        // ala: ain, fatha, lam, fatha, alef maksura
+        let infos = shape(
+            &provider,
+            tag::ARAB,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "\u{0639}\u{064E}\u{0644}\u{064E}\u{0649}",
+        )
+        .unwrap();
+        let debug = |infos: &[Info]| {
+            infos
+                .iter()
+                .map(Info::to_debug_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        // The ain is cursively attached to the lam across the fatha
+        assert_eq!(
+            debug(&infos),
+            "239@0+532,204 848@0+0,0 719@0+0,0 848@0+0,0 0@0+0,0"
+        );
+
+        // Without GDEF glyph classes the fathas are identified as marks from their characters,
+        // so the positioning is unchanged
+        let glyphs = infos.into_iter().map(|info| info.glyph).collect();
+        let mut infos = Info::init_from_glyphs(None, glyphs).unwrap();
+        gpos_apply_arabic(
+            &gpos_cache,
+            &gpos_cache.layout_table,
+            None,
+            tag::ARAB,
+            None,
+            &mut infos,
+        )
+        .unwrap();
+        assert_eq!(
+            debug(&infos),
+            "239@0+532,204 848@0+0,0 719@0+0,0 848@0+0,0 0@0+0,0"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Implementation of font shaping for Hebrew scripts
+
+//!
+//! Code herein follows the specification at:
+//! <https://github.com/n8willis/opentype-shaping-documents/blob/master/opentype-shaping-hebrew.md>
+
+use crate::error::{ParseError, ShapingError};
+use crate::gsub::{self, build_lookups, RawGlyph};
+use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::tag;
+
+pub fn gsub_apply_hebrew(
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ShapingError> {
+    let langsys = match gsub_table.find_script(script_tag)? {
+        Some(s) => match s.find_langsys_or_default(opt_lang_tag)? {
+            Some(v) => v,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+
+    // 1. Compound character composition and decomposition
+
+    apply_lookup(
+        &[tag::CCMP],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    // 2. Applying the language-form substitution features from GSUB
+
+    apply_lookup(
+        &[tag::LOCL],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    // 3. Applying the typographic-form substitution features from GSUB
+    //
+    // `DLIG` is applied as Hebrew fonts use it to form presentation forms, such as
+    // U+FB2A HEBREW LETTER SHIN WITH SHIN DOT, from a base and its marks
+
+    apply_lookup(
+        &[tag::RLIG, tag::DLIG, tag::LIGA, tag::CALT],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    // Marks that were not composed are left in logical order after their base so that GPOS mark
+    // attachment can position them
+
+    Ok(())
+}
+
+fn apply_lookup(
+    feature_tags: &[u32],
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    langsys: &LangSys,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ParseError> {
+    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, feature_tags)? {
+        gsub::gsub_apply_lookup(
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            lookup_index,
+            feature_tag,
+            None,
+            raw_glyphs,
+            0,
+            raw_glyphs.len(),
+            |_| true,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::layout::new_layout_cache;
+    use crate::shape::make_glyph;
+
+    // A GSUB table for the `hebr` script where ccmp ligates shin (glyph 1) and shin dot (glyph 2)
+    // into glyph 10, and liga substitutes lamed (glyph 4) with glyph 11
+    #[rustfmt::skip]
+    const HEBREW_GSUB: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x0A, // script list offset
+        0x00, 0x20, // feature list offset
+        0x00, 0x3A, // lookup list offset
+        // ScriptList
+        0x00, 0x01, b'h', b'e', b'b', b'r', 0x00, 0x08,
+        // Script
+        0x00, 0x04, 0x00, 0x00, // default langsys offset, langsys count
+        // LangSys
+        0x00, 0x00, 0xFF, 0xFF, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, // features 0 and 1
+        // FeatureList
+        0x00, 0x02,
+        b'c', b'c', b'm', b'p', 0x00, 0x0E,
+        b'l', b'i', b'g', b'a', 0x00, 0x14,
+        // Features
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, // ccmp: lookup 0
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, // liga: lookup 1
+        // LookupList
+        0x00, 0x02, 0x00, 0x06, 0x00, 0x26,
+        // Lookup 0, ligature substitution format 1
+        0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // type, flag, count, subtable offset
+        0x00, 0x01, 0x00, 0x12, 0x00, 0x01, 0x00, 0x08, // format, coverage, set count, set offset
+        0x00, 0x01, 0x00, 0x04, // LigatureSet: count, ligature offset
+        0x00, 0x0A, 0x00, 0x02, 0x00, 0x02, // Ligature: glyph 10 from glyphs 1 and 2
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x01, // coverage of glyph 1
+        // Lookup 1, single substitution format 1
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08,
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x07, // format, coverage, delta glyph id
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x04, // coverage of glyph 4
+    ];
+
+    #[test]
+    fn ccmp_and_liga() {
+        let gsub_table = ReadScope::new(HEBREW_GSUB)
+            .read::<LayoutTable<GSUB>>()
+            .unwrap();
+        let gsub_cache = new_layout_cache(gsub_table);
+        // שָׁל: shin, shin dot, qamats, lamed
+        let mut glyphs = vec![
+            make_glyph('\u{05E9}', 1, 0),
+            make_glyph('\u{05C1}', 2, 1),
+            make_glyph('\u{05B8}', 3, 2),
+            make_glyph('\u{05DC}', 4, 3),
+        ];
+        gsub_apply_hebrew(
+            &gsub_cache,
+            &gsub_cache.layout_table,
+            None,
+            tag::HEBR,
+            None,
+            &mut glyphs,
+        )
+        .unwrap();
+
+        // The qamats is kept after the composed shin
+        let glyph_indices = glyphs.iter().map(|g| g.glyph_index).collect::<Vec<_>>();
+        assert_eq!(glyph_indices, vec![10, 3, 11]);
+    }
+}
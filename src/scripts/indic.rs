@@ -2,7 +2,7 @@
 
 use crate::error::{IndicError, ParseError, ShapingError};
 use crate::gpos::{self, Info};
-use crate::gsub::{self, GlyphData, GlyphOrigin, RawGlyph};
+use crate::gsub::{self, merge_clusters, GlyphData, GlyphOrigin, RawGlyph};
 use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GPOS, GSUB};
 use crate::tag;
 
@@ -842,6 +842,35 @@ fn match_syllable(cs: &[char]) -> Option<(usize, Syllable)> {
     }
 }
 
+/// Find the syllables of a sequence of Indic characters.
+///
+/// Returns the index in `chars` of the first character of each syllable, which can be used for
+/// line breaking and cursor placement. Runs of characters that don't form a valid syllable are
+/// grouped together, as they are when shaping. `chars` should already have been processed by
+/// `preprocess_indic`.
+pub fn syllable_boundaries(chars: &[char]) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    let mut start = 0;
+    let mut in_invalid_run = false;
+    while start < chars.len() {
+        match match_syllable(&chars[start..]) {
+            Some((len, _syllable_type)) => {
+                boundaries.push(start);
+                in_invalid_run = false;
+                start += len;
+            }
+            None => {
+                if !in_invalid_run {
+                    boundaries.push(start);
+                    in_invalid_run = true;
+                }
+                start += 1;
+            }
+        }
+    }
+    boundaries
+}
+
 /////////////////////////////////////////////////////////////////////////////
 // Preprocessing
 /////////////////////////////////////////////////////////////////////////////
@@ -1379,6 +1408,7 @@ fn shape_syllable(
         | Some(Syllable::Standalone)
         | Some(Syllable::Broken) => {
             initial_reorder_consonant_syllable(&shaping_data, syllable)?;
+            merge_clusters(syllable);
             apply_basic_features(&shaping_data, syllable)?;
             final_reorder_consonant_syllable(&shaping_data, syllable);
             apply_presentation_features(&shaping_data, is_first_syllable, syllable)?;
@@ -1395,9 +1425,13 @@ fn insert_dotted_circle(
     script: Script,
     glyphs: &mut Vec<RawGlyphIndic>,
 ) -> Result<(), IndicError> {
-    let dotted_circle = make_dotted_circle()
+    let mut dotted_circle = make_dotted_circle()
         .pop()
         .ok_or(IndicError::MissingDottedCircle)?;
+    // The dotted circle belongs to the same cluster as the syllable it is inserted into
+    if let Some(glyph) = glyphs.first() {
+        dotted_circle.cluster = glyph.cluster;
+    }
 
     let mut pos = 0;
     if let (Script::Malayalam, Some(glyph)) = (script, glyphs.first()) {
@@ -2436,6 +2470,7 @@ fn to_raw_glyph_indic(glyph: &RawGlyph<()>) -> RawGlyphIndic {
         unicodes: glyph.unicodes.clone(),
         glyph_index: glyph.glyph_index,
         liga_component_pos: glyph.liga_component_pos,
+        cluster: glyph.cluster,
         glyph_origin: glyph.glyph_origin,
         small_caps: glyph.small_caps,
         multi_subst_dup: glyph.multi_subst_dup,
@@ -2455,6 +2490,7 @@ fn from_raw_glyph_indic(glyph: RawGlyphIndic) -> RawGlyph<()> {
         unicodes: glyph.unicodes,
         glyph_index: glyph.glyph_index,
         liga_component_pos: glyph.liga_component_pos,
+        cluster: glyph.cluster,
         glyph_origin: glyph.glyph_origin,
         small_caps: glyph.small_caps,
         multi_subst_dup: glyph.multi_subst_dup,
@@ -3925,6 +3961,18 @@ fn indic_character(ch: char) -> (Option<ShapingClass>, Option<MarkPlacementSubcl
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_syllable_boundaries() {
+        // नमस्ते दुनिया: न म स्ते ␠ दु नि या
+        let chars = "\u{0928}\u{092E}\u{0938}\u{094D}\u{0924}\u{0947} \u{0926}\u{0941}\u{0928}\u{093F}\u{092F}\u{093E}"
+            .chars()
+            .collect::<Vec<_>>();
+        assert_eq!(syllable_boundaries(&chars), [0, 1, 2, 6, 7, 9, 11]);
+        // Characters that don't form syllables are grouped together
+        assert_eq!(syllable_boundaries(&['a', ' ', 'b', '\u{0915}']), [0, 3]);
+        assert_eq!(syllable_boundaries(&[]), []);
+    }
+
     mod matra_pos {
         use super::*;
 
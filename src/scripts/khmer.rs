@@ -0,0 +1,457 @@
+//! Implementation of font shaping for Khmer scripts
+
+//!
+//! Code herein follows the specification at:
+//! <https://github.com/n8willis/opentype-shaping-documents/blob/master/opentype-shaping-khmer.md>
+
+use crate::error::{ParseError, ShapingError};
+use crate::gsub::{self, build_lookups, merge_clusters, GlyphData, GlyphOrigin, RawGlyph};
+use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::tag;
+
+use bitflags::bitflags;
+
+bitflags! {
+    struct FeatureMask: u8 {
+        const PREF = 0b0000_0001;
+        const CFAR = 0b0000_0010;
+    }
+}
+
+#[derive(Clone)]
+struct KhmerData {
+    mask: FeatureMask,
+}
+
+impl GlyphData for KhmerData {
+    fn merge(data1: KhmerData, _data2: KhmerData) -> KhmerData {
+        data1
+    }
+}
+
+// Khmer glyphs are represented as `RawGlyph` structs with `KhmerData` for its `extra_data`.
+type KhmerGlyph = RawGlyph<KhmerData>;
+
+impl KhmerGlyph {
+    fn is(&self, pred: impl FnOnce(char) -> bool) -> bool {
+        match self.glyph_origin {
+            GlyphOrigin::Char(c) => pred(c),
+            GlyphOrigin::Direct => false,
+        }
+    }
+
+    fn has_mask(&self, mask: FeatureMask) -> bool {
+        self.extra_data.mask.contains(mask)
+    }
+
+    fn add_mask(&mut self, mask: FeatureMask) {
+        self.extra_data.mask.insert(mask)
+    }
+}
+
+impl From<&RawGlyph<()>> for KhmerGlyph {
+    fn from(raw_glyph: &RawGlyph<()>) -> KhmerGlyph {
+        KhmerGlyph {
+            unicodes: raw_glyph.unicodes.clone(),
+            glyph_index: raw_glyph.glyph_index,
+            liga_component_pos: raw_glyph.liga_component_pos,
+            cluster: raw_glyph.cluster,
+            glyph_origin: raw_glyph.glyph_origin,
+            small_caps: raw_glyph.small_caps,
+            multi_subst_dup: raw_glyph.multi_subst_dup,
+            is_vert_alt: raw_glyph.is_vert_alt,
+            fake_bold: raw_glyph.fake_bold,
+            fake_italic: raw_glyph.fake_italic,
+            variation: raw_glyph.variation,
+            extra_data: KhmerData {
+                mask: FeatureMask::empty(),
+            },
+        }
+    }
+}
+
+impl From<&KhmerGlyph> for RawGlyph<()> {
+    fn from(khmer_glyph: &KhmerGlyph) -> RawGlyph<()> {
+        RawGlyph {
+            unicodes: khmer_glyph.unicodes.clone(),
+            glyph_index: khmer_glyph.glyph_index,
+            liga_component_pos: khmer_glyph.liga_component_pos,
+            cluster: khmer_glyph.cluster,
+            glyph_origin: khmer_glyph.glyph_origin,
+            small_caps: khmer_glyph.small_caps,
+            multi_subst_dup: khmer_glyph.multi_subst_dup,
+            is_vert_alt: khmer_glyph.is_vert_alt,
+            fake_bold: khmer_glyph.fake_bold,
+            fake_italic: khmer_glyph.fake_italic,
+            variation: khmer_glyph.variation,
+            extra_data: (),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// Character classes
+/////////////////////////////////////////////////////////////////////////////
+
+fn consonant(ch: char) -> bool {
+    match ch {
+        '\u{1780}'..='\u{17A2}' => true,
+        _ => false,
+    }
+}
+
+fn independent_vowel(ch: char) -> bool {
+    match ch {
+        '\u{17A3}'..='\u{17B3}' => true,
+        _ => false,
+    }
+}
+
+fn placeholder(ch: char) -> bool {
+    match ch {
+        '\u{00A0}' | '\u{25CC}' => true,
+        _ => false,
+    }
+}
+
+fn coeng(ch: char) -> bool {
+    ch == '\u{17D2}'
+}
+
+fn ro(ch: char) -> bool {
+    ch == '\u{179A}'
+}
+
+fn pre_base_vowel(ch: char) -> bool {
+    match ch {
+        '\u{17C1}'..='\u{17C3}' => true,
+        _ => false,
+    }
+}
+
+/// Dependent vowels, register shifters, signs and joiners that may follow the base of a
+/// syllable.
+fn syllable_tail(ch: char) -> bool {
+    match ch {
+        '\u{17B4}'..='\u{17D1}' | '\u{17D3}' | '\u{17DD}' | '\u{200C}' | '\u{200D}' => true,
+        _ => false,
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// Preprocessing
+/////////////////////////////////////////////////////////////////////////////
+
+/// Preprocess Khmer character sequences. This function should be called
+/// prior to mapping Khmer characters to their corresponding glyphs.
+///
+/// Split vowels are decomposed into the pre-base vowel Sign E followed by the
+/// original vowel, which the font is expected to render as the remaining part.
+pub fn preprocess_khmer(cs: &mut Vec<char>) {
+    let mut i = 0;
+    while i < cs.len() {
+        match cs[i] {
+            '\u{17BE}' | '\u{17BF}' | '\u{17C0}' | '\u{17C4}' | '\u{17C5}' => {
+                cs.insert(i, '\u{17C1}');
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// Shaping
+/////////////////////////////////////////////////////////////////////////////
+
+/// Does the following:
+///   * Splits syllables
+///   * Reorders pre-base vowels and Coeng Ro clusters
+///   * Applies basic features
+///   * Applies presentation features
+///
+/// Syllables without a base are passed through unchanged.
+pub fn gsub_apply_khmer(
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ShapingError> {
+    let langsys = match gsub_table.find_script_or_default(script_tag)? {
+        Some(s) => match s.find_langsys_or_default(opt_lang_tag)? {
+            Some(v) => v,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+
+    let mut syllables = to_khmer_syllables(raw_glyphs);
+
+    for (syllable, is_syllable) in syllables.iter_mut() {
+        if *is_syllable {
+            reorder_syllable(syllable);
+            merge_clusters(syllable);
+        }
+
+        // 1. Applying the basic substitution features from GSUB
+
+        apply_lookup(
+            &[tag::CCMP, tag::LOCL],
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            langsys,
+            syllable,
+            |_, _| true,
+        )?;
+
+        apply_lookup(
+            &[tag::PREF, tag::BLWF, tag::ABVF, tag::PSTF, tag::CFAR],
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            langsys,
+            syllable,
+            |g, feature_tag| match feature_tag {
+                tag::PREF => g.has_mask(FeatureMask::PREF),
+                tag::CFAR => g.has_mask(FeatureMask::CFAR),
+                _ => true,
+            },
+        )?;
+
+        // 2. Applying all remaining substitution features from GSUB
+
+        apply_lookup(
+            &[tag::PRES, tag::ABVS, tag::BLWS, tag::PSTS],
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            langsys,
+            syllable,
+            |_, _| true,
+        )?;
+    }
+
+    let mut khmer_glyphs: Vec<KhmerGlyph> = syllables
+        .into_iter()
+        .flat_map(|(s, _)| s.into_iter())
+        .collect();
+
+    apply_lookup(
+        &[tag::CALT, tag::LIGA],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        &mut khmer_glyphs,
+        |_, _| true,
+    )?;
+
+    *raw_glyphs = khmer_glyphs.iter().map(RawGlyph::from).collect();
+
+    Ok(())
+}
+
+/// Matches a syllable consisting of a base followed by any number of Coeng
+/// clusters and tail characters, returning its length.
+fn match_syllable(cs: &[char]) -> Option<usize> {
+    match cs.first() {
+        Some(&c) if consonant(c) || independent_vowel(c) || placeholder(c) => {}
+        _ => return None,
+    }
+
+    let mut i = 1;
+    while i < cs.len() {
+        if coeng(cs[i])
+            && i + 1 < cs.len()
+            && (consonant(cs[i + 1]) || independent_vowel(cs[i + 1]))
+        {
+            i += 2;
+        } else if syllable_tail(cs[i]) {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    Some(i)
+}
+
+/// Splits the input glyph buffer into syllables. Each syllable is paired with
+/// a flag indicating whether it is a valid syllable, or a run of glyphs that
+/// are not part of one.
+fn to_khmer_syllables(glyphs: &[RawGlyph<()>]) -> Vec<(Vec<KhmerGlyph>, bool)> {
+    let mut syllables: Vec<(Vec<KhmerGlyph>, bool)> = Vec::new();
+
+    // Glyphs that did not originate from a character never start or continue a syllable
+    let cs: Vec<char> = glyphs
+        .iter()
+        .map(|g| match g.glyph_origin {
+            GlyphOrigin::Char(c) => c,
+            GlyphOrigin::Direct => '\u{FFFD}',
+        })
+        .collect();
+
+    let mut start = 0;
+    while start < cs.len() {
+        match match_syllable(&cs[start..]) {
+            Some(len) => {
+                let syllable = glyphs[start..start + len]
+                    .iter()
+                    .map(KhmerGlyph::from)
+                    .collect();
+                syllables.push((syllable, true));
+                start += len;
+            }
+            None => {
+                let glyph = KhmerGlyph::from(&glyphs[start]);
+                match syllables.last_mut() {
+                    Some((other, false)) => other.push(glyph),
+                    _ => syllables.push((vec![glyph], false)),
+                }
+                start += 1;
+            }
+        }
+    }
+
+    syllables
+}
+
+/// Moves a Coeng Ro cluster and then any pre-base vowel to the start of the
+/// syllable, tagging the glyphs that `PREF` and `CFAR` apply to.
+fn reorder_syllable(glyphs: &mut Vec<KhmerGlyph>) {
+    let coeng_ro_index =
+        (1..glyphs.len().saturating_sub(1)).find(|&i| glyphs[i].is(coeng) && glyphs[i + 1].is(ro));
+    if let Some(i) = coeng_ro_index {
+        glyphs[i].add_mask(FeatureMask::PREF);
+        glyphs[i + 1].add_mask(FeatureMask::PREF);
+        for glyph in glyphs[i + 2..].iter_mut() {
+            glyph.add_mask(FeatureMask::CFAR);
+        }
+
+        let ro = glyphs.remove(i + 1);
+        let coeng = glyphs.remove(i);
+        glyphs.insert(0, ro);
+        glyphs.insert(0, coeng);
+    }
+
+    if let Some(i) = glyphs.iter().position(|g| g.is(pre_base_vowel)) {
+        let vowel = glyphs.remove(i);
+        glyphs.insert(0, vowel);
+    }
+}
+
+fn apply_lookup(
+    feature_tags: &[u32],
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    langsys: &LangSys,
+    khmer_glyphs: &mut Vec<KhmerGlyph>,
+    pred: impl Fn(&KhmerGlyph, u32) -> bool + Copy,
+) -> Result<(), ParseError> {
+    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, feature_tags)? {
+        gsub::gsub_apply_lookup(
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            lookup_index,
+            feature_tag,
+            None,
+            khmer_glyphs,
+            0,
+            khmer_glyphs.len(),
+            |g| pred(g, feature_tag),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::shape::make_glyph;
+
+    fn make_glyphs(cs: &[char]) -> Vec<RawGlyph<()>> {
+        cs.iter().map(|&ch| make_glyph(ch, 0, 0)).collect()
+    }
+
+    fn chars(glyphs: &[KhmerGlyph]) -> Vec<char> {
+        glyphs
+            .iter()
+            .flat_map(|g| g.unicodes.iter().cloned())
+            .collect()
+    }
+
+    #[test]
+    fn test_preprocess_split_vowel() {
+        // Ka, Sign Oo
+        let mut cs = vec!['\u{1780}', '\u{17C4}'];
+        preprocess_khmer(&mut cs);
+
+        assert_eq!(vec!['\u{1780}', '\u{17C1}', '\u{17C4}'], cs);
+    }
+
+    #[test]
+    fn test_syllables() {
+        // Ka, Coeng, Ka, Sign Aa, Space, Sa, Sign Ii
+        let glyphs = make_glyphs(&[
+            '\u{1780}', '\u{17D2}', '\u{1780}', '\u{17B6}', ' ', '\u{179F}', '\u{17B8}',
+        ]);
+        let syllables = to_khmer_syllables(&glyphs);
+
+        assert_eq!(
+            syllables
+                .iter()
+                .map(|(s, is_syllable)| (s.len(), *is_syllable))
+                .collect::<Vec<_>>(),
+            vec![(4, true), (1, false), (2, true)]
+        );
+    }
+
+    #[test]
+    fn test_reorder_pre_base_vowel() {
+        // Ka, Sign E
+        let mut glyphs: Vec<KhmerGlyph> = make_glyphs(&['\u{1780}', '\u{17C1}'])
+            .iter()
+            .map(KhmerGlyph::from)
+            .collect();
+        reorder_syllable(&mut glyphs);
+
+        assert_eq!(vec!['\u{17C1}', '\u{1780}'], chars(&glyphs));
+    }
+
+    #[test]
+    fn test_reorder_coeng_ro() {
+        // Ka, Coeng, Ro, Sign E, Sign Aa
+        let mut glyphs: Vec<KhmerGlyph> =
+            make_glyphs(&['\u{1780}', '\u{17D2}', '\u{179A}', '\u{17C1}', '\u{17B6}'])
+                .iter()
+                .map(KhmerGlyph::from)
+                .collect();
+        reorder_syllable(&mut glyphs);
+
+        assert_eq!(
+            vec!['\u{17C1}', '\u{17D2}', '\u{179A}', '\u{1780}', '\u{17B6}'],
+            chars(&glyphs)
+        );
+        assert_eq!(
+            glyphs
+                .iter()
+                .map(|g| g.has_mask(FeatureMask::PREF))
+                .collect::<Vec<_>>(),
+            vec![false, true, true, false, false]
+        );
+        assert_eq!(
+            glyphs
+                .iter()
+                .map(|g| g.has_mask(FeatureMask::CFAR))
+                .collect::<Vec<_>>(),
+            vec![true, false, false, false, true]
+        );
+    }
+}
@@ -1,6 +1,12 @@
 pub mod arabic;
+pub mod hebrew;
 pub mod indic;
+pub mod khmer;
+pub mod mongolian;
+pub mod myanmar;
 pub mod syriac;
+pub mod thai;
+pub mod tibetan;
 
 use crate::tag;
 
@@ -10,8 +16,14 @@ use std::convert::From;
 pub enum ScriptType {
     Arabic,
     Default,
+    Hebrew,
     Indic,
+    Khmer,
+    Mongolian,
+    Myanmar,
     Syriac,
+    Thai,
+    Tibetan,
 }
 
 impl From<u32> for ScriptType {
@@ -21,6 +33,7 @@ impl From<u32> for ScriptType {
             tag::LATN => ScriptType::Default,
             tag::CYRL => ScriptType::Default,
             tag::GREK => ScriptType::Default,
+            tag::HEBR => ScriptType::Hebrew,
             tag::DEVA => ScriptType::Indic,
             tag::BENG => ScriptType::Indic,
             tag::GURU => ScriptType::Indic,
@@ -30,7 +43,14 @@ impl From<u32> for ScriptType {
             tag::TELU => ScriptType::Indic,
             tag::KNDA => ScriptType::Indic,
             tag::MLYM => ScriptType::Indic,
+            tag::KHMR => ScriptType::Khmer,
+            tag::MONG => ScriptType::Mongolian,
+            tag::MYMR => ScriptType::Myanmar,
+            tag::MYM2 => ScriptType::Myanmar,
             tag::SYRC => ScriptType::Syriac,
+            tag::THAI => ScriptType::Thai,
+            tag::LAO => ScriptType::Thai,
+            tag::TIBT => ScriptType::Tibetan,
             _ => ScriptType::Default,
         }
     }
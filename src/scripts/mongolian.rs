@@ -0,0 +1,355 @@
+//! Implementation of font shaping for Mongolian scripts
+//!
+//! Code herein follows the specification at:
+//! <https://github.com/n8willis/opentype-shaping-documents/blob/master/opentype-shaping-mongolian.md>
+
+use crate::error::{ParseError, ShapingError};
+use crate::gsub::{self, build_lookups, GlyphData, GlyphOrigin, RawGlyph};
+use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::tag;
+
+use std::convert::From;
+use unicode_joining_type::{get_joining_type, JoiningType};
+
+#[derive(Clone)]
+struct MongolianData {
+    joining_type: JoiningType,
+    feature_tag: u32,
+}
+
+impl GlyphData for MongolianData {
+    fn merge(data1: MongolianData, _data2: MongolianData) -> MongolianData {
+        data1
+    }
+}
+
+// Mongolian glyphs are represented as `RawGlyph` structs with `MongolianData` for its
+// `extra_data`.
+type MongolianGlyph = RawGlyph<MongolianData>;
+
+impl MongolianGlyph {
+    fn is_free_variation_selector(&self) -> bool {
+        match self.glyph_origin {
+            GlyphOrigin::Char('\u{180B}'..='\u{180D}') | GlyphOrigin::Char('\u{180F}') => true,
+            _ => false,
+        }
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.extra_data.joining_type == JoiningType::Transparent || self.multi_subst_dup
+    }
+
+    fn is_left_joining(&self) -> bool {
+        self.extra_data.joining_type == JoiningType::LeftJoining
+            || self.extra_data.joining_type == JoiningType::DualJoining
+            || self.extra_data.joining_type == JoiningType::JoinCausing
+    }
+
+    fn is_right_joining(&self) -> bool {
+        self.extra_data.joining_type == JoiningType::RightJoining
+            || self.extra_data.joining_type == JoiningType::DualJoining
+            || self.extra_data.joining_type == JoiningType::JoinCausing
+    }
+
+    fn feature_tag(&self) -> u32 {
+        self.extra_data.feature_tag
+    }
+
+    fn set_feature_tag(&mut self, feature_tag: u32) {
+        self.extra_data.feature_tag = feature_tag
+    }
+}
+
+impl From<&RawGlyph<()>> for MongolianGlyph {
+    fn from(raw_glyph: &RawGlyph<()>) -> MongolianGlyph {
+        // Since there's no `Char` to work out the `MongolianGlyph`s joining type when the glyph's
+        // `glyph_origin` is `GlyphOrigin::Direct`, we fallback to `JoiningType::NonJoining` as
+        // the safest approach
+        //
+        // FVS4 is newer than the joining type data, so it is matched explicitly to make it
+        // transparent like the other free variation selectors
+        let joining_type = match raw_glyph.glyph_origin {
+            GlyphOrigin::Char('\u{180F}') => JoiningType::Transparent,
+            GlyphOrigin::Char(c) => get_joining_type(c),
+            GlyphOrigin::Direct => JoiningType::NonJoining,
+        };
+
+        MongolianGlyph {
+            unicodes: raw_glyph.unicodes.clone(),
+            glyph_index: raw_glyph.glyph_index,
+            liga_component_pos: raw_glyph.liga_component_pos,
+            cluster: raw_glyph.cluster,
+            glyph_origin: raw_glyph.glyph_origin,
+            small_caps: raw_glyph.small_caps,
+            multi_subst_dup: raw_glyph.multi_subst_dup,
+            is_vert_alt: raw_glyph.is_vert_alt,
+            fake_bold: raw_glyph.fake_bold,
+            fake_italic: raw_glyph.fake_italic,
+            variation: raw_glyph.variation,
+            extra_data: MongolianData {
+                joining_type,
+                // For convenience, we losely follow the spec (`2. Computing letter joining
+                // states`) here by initialising all `MongolianGlyph`s to `tag::ISOL`
+                feature_tag: tag::ISOL,
+            },
+        }
+    }
+}
+
+impl From<&MongolianGlyph> for RawGlyph<()> {
+    fn from(mongolian_glyph: &MongolianGlyph) -> RawGlyph<()> {
+        RawGlyph {
+            unicodes: mongolian_glyph.unicodes.clone(),
+            glyph_index: mongolian_glyph.glyph_index,
+            liga_component_pos: mongolian_glyph.liga_component_pos,
+            cluster: mongolian_glyph.cluster,
+            glyph_origin: mongolian_glyph.glyph_origin,
+            small_caps: mongolian_glyph.small_caps,
+            multi_subst_dup: mongolian_glyph.multi_subst_dup,
+            is_vert_alt: mongolian_glyph.is_vert_alt,
+            fake_bold: mongolian_glyph.fake_bold,
+            variation: mongolian_glyph.variation,
+            fake_italic: mongolian_glyph.fake_italic,
+            extra_data: (),
+        }
+    }
+}
+
+/// Apply the `GSUB` features for Mongolian to `raw_glyphs`.
+///
+/// When `vertical` is set the `vrt2` feature, or `vert` if the font lacks `vrt2`, is applied
+/// for vertical layout.
+pub fn gsub_apply_mongolian(
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    vertical: bool,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ShapingError> {
+    let langsys = match gsub_table.find_script(script_tag)? {
+        Some(s) => match s.find_langsys_or_default(opt_lang_tag)? {
+            Some(v) => v,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+
+    let mongolian_glyphs = &mut raw_glyphs.iter().map(MongolianGlyph::from).collect();
+
+    // 1. Compound character composition and decomposition
+
+    apply_lookup(
+        &[tag::CCMP],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        mongolian_glyphs,
+        |_, _| true,
+    )?;
+
+    // 2. Computing letter joining states
+
+    compute_joining_states(mongolian_glyphs);
+
+    // 3. Applying the language-form substitution features from GSUB
+
+    apply_lookup(
+        &[tag::LOCL],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        mongolian_glyphs,
+        |_, _| true,
+    )?;
+
+    apply_lookup(
+        &[tag::ISOL, tag::FINA, tag::MEDI, tag::INIT],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        mongolian_glyphs,
+        |g, feature_tag| g.feature_tag() == feature_tag,
+    )?;
+
+    // Fonts select the variant forms requested by free variation selectors with contextual
+    // lookups, so these are applied serially after the positional forms
+
+    apply_lookup(
+        &[tag::RLIG],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        mongolian_glyphs,
+        |_, _| true,
+    )?;
+
+    apply_lookup(
+        &[tag::RCLT, tag::CALT],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        mongolian_glyphs,
+        |_, _| true,
+    )?;
+
+    // 4. Applying the typographic-form substitution features from GSUB
+    //
+    // Note that we skip `GSUB`'s `DLIG` feature as it should be off by default
+
+    apply_lookup(
+        &[tag::LIGA],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        mongolian_glyphs,
+        |_, _| true,
+    )?;
+
+    // 5. Applying the vertical forms
+
+    if vertical {
+        let vertical_tag = if gsub_table
+            .find_langsys_feature(langsys, tag::VRT2)?
+            .is_some()
+        {
+            tag::VRT2
+        } else {
+            tag::VERT
+        };
+        apply_lookup(
+            &[vertical_tag],
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            langsys,
+            mongolian_glyphs,
+            |_, _| true,
+        )?;
+    }
+
+    *raw_glyphs = mongolian_glyphs.iter().map(RawGlyph::from).collect();
+
+    Ok(())
+}
+
+fn compute_joining_states(mongolian_glyphs: &mut [MongolianGlyph]) {
+    let mut previous_i = mongolian_glyphs
+        .iter()
+        .position(|g| !g.is_transparent())
+        .unwrap_or(0);
+
+    for i in (previous_i + 1)..mongolian_glyphs.len() {
+        if mongolian_glyphs[i].is_transparent() {
+            continue;
+        }
+
+        if mongolian_glyphs[previous_i].is_left_joining() && mongolian_glyphs[i].is_right_joining()
+        {
+            mongolian_glyphs[i].set_feature_tag(tag::FINA);
+
+            match mongolian_glyphs[previous_i].feature_tag() {
+                tag::ISOL => mongolian_glyphs[previous_i].set_feature_tag(tag::INIT),
+                tag::FINA => mongolian_glyphs[previous_i].set_feature_tag(tag::MEDI),
+                _ => {}
+            }
+        }
+
+        previous_i = i;
+    }
+
+    // A free variation selector selects a variant of the positional form of the letter before
+    // it, so it takes the same feature as that letter. This lets positional lookups that match
+    // the letter followed by the selector apply.
+    for i in 1..mongolian_glyphs.len() {
+        if mongolian_glyphs[i].is_free_variation_selector() {
+            let feature_tag = mongolian_glyphs[i - 1].feature_tag();
+            mongolian_glyphs[i].set_feature_tag(feature_tag);
+        }
+    }
+}
+
+fn apply_lookup(
+    feature_tags: &[u32],
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    langsys: &LangSys,
+    mongolian_glyphs: &mut Vec<RawGlyph<MongolianData>>,
+    pred: impl Fn(&RawGlyph<MongolianData>, u32) -> bool + Copy,
+) -> Result<(), ParseError> {
+    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, feature_tags)? {
+        gsub::gsub_apply_lookup(
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            lookup_index,
+            feature_tag,
+            None,
+            mongolian_glyphs,
+            0,
+            mongolian_glyphs.len(),
+            |g| pred(g, feature_tag),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::make_glyph;
+
+    fn mongolian_glyphs(text: &str) -> Vec<MongolianGlyph> {
+        text.chars()
+            .map(|ch| MongolianGlyph::from(&make_glyph(ch, 0, 0)))
+            .collect()
+    }
+
+    fn feature_tags(glyphs: &[MongolianGlyph]) -> Vec<u32> {
+        glyphs.iter().map(MongolianGlyph::feature_tag).collect()
+    }
+
+    #[test]
+    fn test_joining_states() {
+        // MONGGOL
+        let mut glyphs = mongolian_glyphs("\u{182E}\u{1823}\u{1829}\u{182D}\u{1823}\u{182F}");
+        compute_joining_states(&mut glyphs);
+        assert_eq!(
+            feature_tags(&glyphs),
+            &[
+                tag::INIT,
+                tag::MEDI,
+                tag::MEDI,
+                tag::MEDI,
+                tag::MEDI,
+                tag::FINA
+            ]
+        );
+
+        // A word broken by a space
+        let mut glyphs = mongolian_glyphs("\u{1820} \u{1821}");
+        compute_joining_states(&mut glyphs);
+        assert_eq!(feature_tags(&glyphs), &[tag::ISOL, tag::ISOL, tag::ISOL]);
+    }
+
+    #[test]
+    fn test_free_variation_selector() {
+        // A, FVS1, E: the selector is transparent and takes the form of the A before it
+        let mut glyphs = mongolian_glyphs("\u{1820}\u{180B}\u{1821}");
+        compute_joining_states(&mut glyphs);
+        assert_eq!(feature_tags(&glyphs), &[tag::INIT, tag::INIT, tag::FINA]);
+
+        let mut glyphs = mongolian_glyphs("\u{1820}\u{1821}\u{180F}");
+        compute_joining_states(&mut glyphs);
+        assert_eq!(feature_tags(&glyphs), &[tag::INIT, tag::FINA, tag::FINA]);
+    }
+}
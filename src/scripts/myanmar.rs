@@ -0,0 +1,487 @@
+//! Implementation of font shaping for Myanmar scripts
+//!
+//! Code herein follows the specification at:
+//! <https://github.com/n8willis/opentype-shaping-documents/blob/master/opentype-shaping-myanmar.md>
+
+use crate::error::{ParseError, ShapingError};
+use crate::gsub::{self, build_lookups, merge_clusters, GlyphData, GlyphOrigin, RawGlyph};
+use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::tag;
+
+use bitflags::bitflags;
+
+bitflags! {
+    struct FeatureMask: u8 {
+        const RPHF = 0b0000_0001;
+        const PREF = 0b0000_0010;
+    }
+}
+
+#[derive(Clone)]
+struct MyanmarData {
+    mask: FeatureMask,
+}
+
+impl GlyphData for MyanmarData {
+    fn merge(data1: MyanmarData, _data2: MyanmarData) -> MyanmarData {
+        data1
+    }
+}
+
+// Myanmar glyphs are represented as `RawGlyph` structs with `MyanmarData` for its `extra_data`.
+type MyanmarGlyph = RawGlyph<MyanmarData>;
+
+impl MyanmarGlyph {
+    fn is(&self, pred: impl FnOnce(char) -> bool) -> bool {
+        match self.glyph_origin {
+            GlyphOrigin::Char(c) => pred(c),
+            GlyphOrigin::Direct => false,
+        }
+    }
+
+    fn has_mask(&self, mask: FeatureMask) -> bool {
+        self.extra_data.mask.contains(mask)
+    }
+
+    fn add_mask(&mut self, mask: FeatureMask) {
+        self.extra_data.mask.insert(mask)
+    }
+}
+
+impl From<&RawGlyph<()>> for MyanmarGlyph {
+    fn from(raw_glyph: &RawGlyph<()>) -> MyanmarGlyph {
+        MyanmarGlyph {
+            unicodes: raw_glyph.unicodes.clone(),
+            glyph_index: raw_glyph.glyph_index,
+            liga_component_pos: raw_glyph.liga_component_pos,
+            cluster: raw_glyph.cluster,
+            glyph_origin: raw_glyph.glyph_origin,
+            small_caps: raw_glyph.small_caps,
+            multi_subst_dup: raw_glyph.multi_subst_dup,
+            is_vert_alt: raw_glyph.is_vert_alt,
+            fake_bold: raw_glyph.fake_bold,
+            fake_italic: raw_glyph.fake_italic,
+            variation: raw_glyph.variation,
+            extra_data: MyanmarData {
+                mask: FeatureMask::empty(),
+            },
+        }
+    }
+}
+
+impl From<&MyanmarGlyph> for RawGlyph<()> {
+    fn from(myanmar_glyph: &MyanmarGlyph) -> RawGlyph<()> {
+        RawGlyph {
+            unicodes: myanmar_glyph.unicodes.clone(),
+            glyph_index: myanmar_glyph.glyph_index,
+            liga_component_pos: myanmar_glyph.liga_component_pos,
+            cluster: myanmar_glyph.cluster,
+            glyph_origin: myanmar_glyph.glyph_origin,
+            small_caps: myanmar_glyph.small_caps,
+            multi_subst_dup: myanmar_glyph.multi_subst_dup,
+            is_vert_alt: myanmar_glyph.is_vert_alt,
+            fake_bold: myanmar_glyph.fake_bold,
+            fake_italic: myanmar_glyph.fake_italic,
+            variation: myanmar_glyph.variation,
+            extra_data: (),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// Character classes
+/////////////////////////////////////////////////////////////////////////////
+
+fn consonant(ch: char) -> bool {
+    match ch {
+        '\u{1000}'..='\u{1020}'
+        | '\u{103F}'
+        | '\u{104E}'
+        | '\u{1050}'..='\u{1051}'
+        | '\u{105A}'..='\u{105D}'
+        | '\u{1061}'
+        | '\u{1065}'..='\u{1066}'
+        | '\u{106E}'..='\u{1070}'
+        | '\u{1075}'..='\u{1081}'
+        | '\u{108E}' => true,
+        _ => false,
+    }
+}
+
+fn independent_vowel(ch: char) -> bool {
+    match ch {
+        '\u{1021}'..='\u{102A}' | '\u{1052}'..='\u{1055}' => true,
+        _ => false,
+    }
+}
+
+fn placeholder(ch: char) -> bool {
+    match ch {
+        '\u{00A0}' | '\u{25CC}' => true,
+        _ => false,
+    }
+}
+
+fn asat(ch: char) -> bool {
+    ch == '\u{103A}'
+}
+
+fn virama(ch: char) -> bool {
+    ch == '\u{1039}'
+}
+
+/// The consonants that form a kinzi when followed by Asat and Virama: Nga, Ra, and Mon Nga.
+fn kinzi_consonant(ch: char) -> bool {
+    match ch {
+        '\u{1004}' | '\u{101B}' | '\u{105A}' => true,
+        _ => false,
+    }
+}
+
+fn medial_ra(ch: char) -> bool {
+    ch == '\u{103C}'
+}
+
+fn pre_base_vowel(ch: char) -> bool {
+    match ch {
+        '\u{1031}' | '\u{1084}' => true,
+        _ => false,
+    }
+}
+
+/// Medials, dependent vowels, tone marks, signs and joiners that may follow the base of a
+/// syllable.
+fn syllable_tail(ch: char) -> bool {
+    match ch {
+        '\u{102B}'..='\u{1038}'
+        | '\u{103A}'..='\u{103E}'
+        | '\u{1056}'..='\u{1059}'
+        | '\u{105E}'..='\u{1060}'
+        | '\u{1062}'..='\u{1064}'
+        | '\u{1067}'..='\u{106D}'
+        | '\u{1071}'..='\u{1074}'
+        | '\u{1082}'..='\u{108D}'
+        | '\u{108F}'
+        | '\u{109A}'..='\u{109D}'
+        | '\u{FE00}'
+        | '\u{200C}'
+        | '\u{200D}' => true,
+        _ => false,
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// Shaping
+/////////////////////////////////////////////////////////////////////////////
+
+/// Does the following:
+///   * Splits syllables
+///   * Reorders kinzi, medial Ra and pre-base vowels
+///   * Applies basic features
+///   * Applies presentation features
+///
+/// The `mym2` script is used in preference to `script_tag` if the font supports it. Syllables
+/// without a base are passed through unchanged.
+pub fn gsub_apply_myanmar(
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ShapingError> {
+    let script_table = match gsub_table.find_script(tag::MYM2)? {
+        Some(script_table) => script_table,
+        None => match gsub_table.find_script_or_default(script_tag)? {
+            Some(script_table) => script_table,
+            None => return Ok(()),
+        },
+    };
+    let langsys = match script_table.find_langsys_or_default(opt_lang_tag)? {
+        Some(langsys) => langsys,
+        None => return Ok(()),
+    };
+
+    let mut syllables = to_myanmar_syllables(raw_glyphs);
+
+    for (syllable, is_syllable) in syllables.iter_mut() {
+        if *is_syllable {
+            reorder_syllable(syllable);
+            merge_clusters(syllable);
+        }
+
+        // 1. Applying the basic substitution features from GSUB
+
+        apply_lookup(
+            &[tag::LOCL, tag::CCMP],
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            langsys,
+            syllable,
+            |_, _| true,
+        )?;
+
+        apply_lookup(
+            &[tag::RPHF, tag::PREF, tag::BLWF, tag::PSTF],
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            langsys,
+            syllable,
+            |g, feature_tag| match feature_tag {
+                tag::RPHF => g.has_mask(FeatureMask::RPHF),
+                tag::PREF => g.has_mask(FeatureMask::PREF),
+                _ => true,
+            },
+        )?;
+
+        // 2. Applying all remaining substitution features from GSUB
+
+        apply_lookup(
+            &[tag::PRES, tag::ABVS, tag::BLWS, tag::PSTS],
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            langsys,
+            syllable,
+            |_, _| true,
+        )?;
+    }
+
+    let mut myanmar_glyphs: Vec<MyanmarGlyph> = syllables
+        .into_iter()
+        .flat_map(|(s, _)| s.into_iter())
+        .collect();
+
+    apply_lookup(
+        &[tag::CALT, tag::LIGA],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        &mut myanmar_glyphs,
+        |_, _| true,
+    )?;
+
+    *raw_glyphs = myanmar_glyphs.iter().map(RawGlyph::from).collect();
+
+    Ok(())
+}
+
+/// Returns the length of the kinzi at the start of `cs`, if there is one.
+///
+/// A kinzi is only formed when it is followed by the consonant it sits above.
+fn match_kinzi(cs: &[char]) -> Option<usize> {
+    if cs.len() > 3 && kinzi_consonant(cs[0]) && asat(cs[1]) && virama(cs[2]) && consonant(cs[3]) {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/// Matches a syllable consisting of an optional kinzi, a base, and any number of stacked
+/// consonants and tail characters, returning its length.
+fn match_syllable(cs: &[char]) -> Option<usize> {
+    let kinzi_len = match_kinzi(cs).unwrap_or(0);
+    match cs.get(kinzi_len) {
+        Some(&c) if consonant(c) || independent_vowel(c) || placeholder(c) => {}
+        _ => return None,
+    }
+
+    let mut i = kinzi_len + 1;
+    while i < cs.len() {
+        if virama(cs[i]) && i + 1 < cs.len() && consonant(cs[i + 1]) {
+            i += 2;
+        } else if syllable_tail(cs[i]) {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    Some(i)
+}
+
+/// Splits the input glyph buffer into syllables. Each syllable is paired with
+/// a flag indicating whether it is a valid syllable, or a run of glyphs that
+/// are not part of one.
+fn to_myanmar_syllables(glyphs: &[RawGlyph<()>]) -> Vec<(Vec<MyanmarGlyph>, bool)> {
+    let mut syllables: Vec<(Vec<MyanmarGlyph>, bool)> = Vec::new();
+
+    // Glyphs that did not originate from a character never start or continue a syllable
+    let cs: Vec<char> = glyphs
+        .iter()
+        .map(|g| match g.glyph_origin {
+            GlyphOrigin::Char(c) => c,
+            GlyphOrigin::Direct => '\u{FFFD}',
+        })
+        .collect();
+
+    let mut start = 0;
+    while start < cs.len() {
+        match match_syllable(&cs[start..]) {
+            Some(len) => {
+                let syllable = glyphs[start..start + len]
+                    .iter()
+                    .map(MyanmarGlyph::from)
+                    .collect();
+                syllables.push((syllable, true));
+                start += len;
+            }
+            None => {
+                let glyph = MyanmarGlyph::from(&glyphs[start]);
+                match syllables.last_mut() {
+                    Some((other, false)) => other.push(glyph),
+                    _ => syllables.push((vec![glyph], false)),
+                }
+                start += 1;
+            }
+        }
+    }
+
+    syllables
+}
+
+/// Reorders a syllable into the order its glyphs are drawn in, tagging the glyphs that `RPHF`
+/// and `PREF` apply to.
+///
+/// A kinzi is moved after the base, and then medial Ra and any pre-base vowel are moved to the
+/// start of the syllable, with the vowel first.
+fn reorder_syllable(glyphs: &mut Vec<MyanmarGlyph>) {
+    let is_kinzi = glyphs.len() > 3
+        && glyphs[0].is(kinzi_consonant)
+        && glyphs[1].is(asat)
+        && glyphs[2].is(virama);
+    if is_kinzi {
+        let kinzi: Vec<MyanmarGlyph> = glyphs
+            .drain(..3)
+            .map(|mut g| {
+                g.add_mask(FeatureMask::RPHF);
+                g
+            })
+            .collect();
+        // The base is now the first glyph
+        glyphs.splice(1..1, kinzi);
+    }
+
+    if let Some(i) = glyphs.iter().position(|g| g.is(medial_ra)) {
+        let mut ra = glyphs.remove(i);
+        ra.add_mask(FeatureMask::PREF);
+        glyphs.insert(0, ra);
+    }
+
+    if let Some(i) = glyphs.iter().position(|g| g.is(pre_base_vowel)) {
+        let vowel = glyphs.remove(i);
+        glyphs.insert(0, vowel);
+    }
+}
+
+fn apply_lookup(
+    feature_tags: &[u32],
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    langsys: &LangSys,
+    myanmar_glyphs: &mut Vec<MyanmarGlyph>,
+    pred: impl Fn(&MyanmarGlyph, u32) -> bool + Copy,
+) -> Result<(), ParseError> {
+    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, feature_tags)? {
+        gsub::gsub_apply_lookup(
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            lookup_index,
+            feature_tag,
+            None,
+            myanmar_glyphs,
+            0,
+            myanmar_glyphs.len(),
+            |g| pred(g, feature_tag),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::shape::make_glyph;
+
+    fn make_glyphs(cs: &[char]) -> Vec<RawGlyph<()>> {
+        cs.iter().map(|&ch| make_glyph(ch, 0, 0)).collect()
+    }
+
+    fn chars(glyphs: &[MyanmarGlyph]) -> Vec<char> {
+        glyphs
+            .iter()
+            .flat_map(|g| g.unicodes.iter().cloned())
+            .collect()
+    }
+
+    #[test]
+    fn test_syllables() {
+        // Ka, Sign Aa, Space, Sa, Nga, Asat, Virama, Ga, Sign Ii
+        let glyphs = make_glyphs(&[
+            '\u{1000}', '\u{102C}', ' ', '\u{101E}', '\u{1004}', '\u{103A}', '\u{1039}',
+            '\u{1002}', '\u{102E}',
+        ]);
+        let syllables = to_myanmar_syllables(&glyphs);
+
+        assert_eq!(
+            syllables
+                .iter()
+                .map(|(s, is_syllable)| (s.len(), *is_syllable))
+                .collect::<Vec<_>>(),
+            vec![(2, true), (1, false), (1, true), (5, true)]
+        );
+    }
+
+    #[test]
+    fn test_reorder_kinzi() {
+        // Nga, Asat, Virama, Ga, Sign Ii
+        let mut glyphs: Vec<MyanmarGlyph> =
+            make_glyphs(&['\u{1004}', '\u{103A}', '\u{1039}', '\u{1002}', '\u{102E}'])
+                .iter()
+                .map(MyanmarGlyph::from)
+                .collect();
+        reorder_syllable(&mut glyphs);
+
+        assert_eq!(
+            vec!['\u{1002}', '\u{1004}', '\u{103A}', '\u{1039}', '\u{102E}'],
+            chars(&glyphs)
+        );
+        assert_eq!(
+            glyphs
+                .iter()
+                .map(|g| g.has_mask(FeatureMask::RPHF))
+                .collect::<Vec<_>>(),
+            vec![false, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_reorder_medial_ra_and_pre_base_vowel() {
+        // Ka, Medial Ra, Sign E, Sign Aa
+        let mut glyphs: Vec<MyanmarGlyph> =
+            make_glyphs(&['\u{1000}', '\u{103C}', '\u{1031}', '\u{102C}'])
+                .iter()
+                .map(MyanmarGlyph::from)
+                .collect();
+        reorder_syllable(&mut glyphs);
+
+        assert_eq!(
+            vec!['\u{1031}', '\u{103C}', '\u{1000}', '\u{102C}'],
+            chars(&glyphs)
+        );
+        assert_eq!(
+            glyphs
+                .iter()
+                .map(|g| g.has_mask(FeatureMask::PREF))
+                .collect::<Vec<_>>(),
+            vec![false, true, false, false]
+        );
+    }
+}
@@ -87,6 +87,7 @@ impl From<&RawGlyph<()>> for SyriacGlyph {
             unicodes: raw_glyph.unicodes.clone(),
             glyph_index: raw_glyph.glyph_index,
             liga_component_pos: raw_glyph.liga_component_pos,
+            cluster: raw_glyph.cluster,
             glyph_origin: raw_glyph.glyph_origin,
             small_caps: raw_glyph.small_caps,
             multi_subst_dup: raw_glyph.multi_subst_dup,
@@ -111,6 +112,7 @@ impl From<&SyriacGlyph> for RawGlyph<()> {
             unicodes: syriac_glyph.unicodes.clone(),
             glyph_index: syriac_glyph.glyph_index,
             liga_component_pos: syriac_glyph.liga_component_pos,
+            cluster: syriac_glyph.cluster,
             glyph_origin: syriac_glyph.glyph_origin,
             small_caps: syriac_glyph.small_caps,
             multi_subst_dup: syriac_glyph.multi_subst_dup,
@@ -0,0 +1,434 @@
+//! Implementation of font shaping for Thai and Lao scripts
+
+//!
+//! Code herein follows the specification at:
+//! <https://github.com/n8willis/opentype-shaping-documents/blob/master/opentype-shaping-thai-lao.md>
+
+use crate::error::{ParseError, ShapingError};
+use crate::gsub::{self, build_lookups, GlyphOrigin, RawGlyph};
+use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::tag;
+
+#[derive(Copy, Clone, PartialEq)]
+enum ConsonantType {
+    /// Consonants without an ascender or descender
+    Normal,
+    /// Consonants with an ascender, which clashes with above-base marks
+    Ascender,
+    /// Consonants with a descender that is removed when a below-base mark is attached
+    RemovableDescender,
+    /// Consonants with a descender that below-base marks are shifted down to avoid
+    Descender,
+    NotConsonant,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum MarkType {
+    AboveVowel,
+    BelowVowel,
+    Tone,
+}
+
+/// The substitution made by the Windows private use area fallback
+#[derive(Copy, Clone, PartialEq)]
+enum Action {
+    None,
+    ShiftDown,
+    ShiftLeft,
+    ShiftDownLeft,
+    RemoveDescender,
+}
+
+fn consonant_type(ch: char) -> ConsonantType {
+    match ch {
+        '\u{0E1B}' | '\u{0E1D}' | '\u{0E1F}' => ConsonantType::Ascender,
+        '\u{0E0D}' | '\u{0E10}' => ConsonantType::RemovableDescender,
+        '\u{0E0E}' | '\u{0E0F}' => ConsonantType::Descender,
+        '\u{0E01}'..='\u{0E2E}' => ConsonantType::Normal,
+        _ => ConsonantType::NotConsonant,
+    }
+}
+
+fn mark_type(ch: char) -> Option<MarkType> {
+    match ch {
+        '\u{0E31}' | '\u{0E34}'..='\u{0E37}' | '\u{0E47}' | '\u{0E4D}' | '\u{0E4E}' => {
+            Some(MarkType::AboveVowel)
+        }
+        '\u{0E38}'..='\u{0E3A}' => Some(MarkType::BelowVowel),
+        '\u{0E48}'..='\u{0E4C}' => Some(MarkType::Tone),
+        _ => None,
+    }
+}
+
+/// Above-base marks of Thai, and the corresponding marks of Lao
+fn is_above_base_mark(ch: char) -> bool {
+    match ch as u32 & !0x80 {
+        0x0E31 | 0x0E34..=0x0E37 | 0x0E3B | 0x0E47..=0x0E4E => true,
+        _ => false,
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// Preprocessing
+/////////////////////////////////////////////////////////////////////////////
+
+/// Preprocess Thai and Lao character sequences. This function should be called
+/// prior to mapping Thai or Lao characters to their corresponding glyphs.
+///
+/// Sara Am is decomposed into Nikhahit followed by Sara Aa, and the Nikhahit is
+/// moved before any above-base marks, such as tone marks, that precede it.
+///
+/// Leading vowels, such as Sara E, are encoded in visual order so they are
+/// not reordered.
+pub fn preprocess_thai(cs: &mut Vec<char>) {
+    let mut i = 0;
+    while i < cs.len() {
+        let (nikhahit, sara_aa) = match cs[i] {
+            '\u{0E33}' => ('\u{0E4D}', '\u{0E32}'),
+            '\u{0EB3}' => ('\u{0ECD}', '\u{0EB2}'),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let mut start = i;
+        while start > 0 && is_above_base_mark(cs[start - 1]) {
+            start -= 1;
+        }
+        cs[i] = sara_aa;
+        cs.insert(start, nikhahit);
+        i += 2;
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// Shaping
+/////////////////////////////////////////////////////////////////////////////
+
+pub fn gsub_apply_thai(
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ShapingError> {
+    let langsys = match gsub_table.find_script(script_tag)? {
+        Some(s) => match s.find_langsys_or_default(opt_lang_tag)? {
+            Some(v) => v,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+
+    // 1. Compound character composition and decomposition, and language forms
+
+    apply_lookup(
+        &[tag::LOCL, tag::CCMP],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    // 2. Applying the typographic-form substitution features from GSUB
+
+    apply_lookup(
+        &[tag::CALT, tag::CLIG, tag::LIGA, tag::RLIG],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    Ok(())
+}
+
+/// Substitute the Windows private use area forms of Thai marks and consonants.
+///
+/// This is a fallback for fonts without `GSUB` features for Thai, which position
+/// marks that clash with the base consonant, or with other marks, by mapping the
+/// shifted forms to code points in U+F700..U+F71A. `map_glyph` maps characters to
+/// glyphs with the `cmap` table of the font, characters without a glyph are not
+/// substituted.
+pub fn pua_fallback_thai(glyphs: &mut [RawGlyph<()>], map_glyph: impl Fn(char) -> Option<u16>) {
+    let mut above_state = AboveState::start(ConsonantType::NotConsonant);
+    let mut below_state = BelowState::start(ConsonantType::NotConsonant);
+    let mut base = 0;
+
+    for i in 0..glyphs.len() {
+        let ch = match glyphs[i].glyph_origin {
+            GlyphOrigin::Char(ch) => ch,
+            GlyphOrigin::Direct => '\u{0}',
+        };
+        let mark_type = match mark_type(ch) {
+            Some(mark_type) => mark_type,
+            None => {
+                above_state = AboveState::start(consonant_type(ch));
+                below_state = BelowState::start(consonant_type(ch));
+                base = i;
+                continue;
+            }
+        };
+
+        let (above_action, next_above_state) = above_state.next(mark_type);
+        let (below_action, next_below_state) = below_state.next(mark_type);
+        above_state = next_above_state;
+        below_state = next_below_state;
+
+        // At most one of the actions is not `Action::None`
+        let (index, action) = match (above_action, below_action) {
+            (Action::None, Action::RemoveDescender) => (base, Action::RemoveDescender),
+            (Action::None, action) => (i, action),
+            (action, _) => (i, action),
+        };
+        let glyph = &mut glyphs[index];
+        if let GlyphOrigin::Char(ch) = glyph.glyph_origin {
+            if let Some(glyph_index) = pua_char(ch, action).and_then(&map_glyph) {
+                glyph.glyph_index = glyph_index;
+            }
+        }
+    }
+}
+
+/// The state of the marks above a Thai cluster
+#[derive(Copy, Clone, PartialEq)]
+enum AboveState {
+    /// Nothing above the base
+    T0,
+    /// An ascender
+    T1,
+    /// An ascender with a mark shifted left of it
+    T2,
+    /// No further above-base marks are shifted
+    T3,
+}
+
+impl AboveState {
+    fn start(consonant_type: ConsonantType) -> Self {
+        match consonant_type {
+            ConsonantType::Ascender => AboveState::T1,
+            ConsonantType::NotConsonant => AboveState::T3,
+            _ => AboveState::T0,
+        }
+    }
+
+    fn next(self, mark_type: MarkType) -> (Action, AboveState) {
+        match (self, mark_type) {
+            (AboveState::T0, MarkType::AboveVowel) => (Action::None, AboveState::T3),
+            (AboveState::T0, MarkType::BelowVowel) => (Action::None, AboveState::T0),
+            (AboveState::T0, MarkType::Tone) => (Action::ShiftDown, AboveState::T3),
+            (AboveState::T1, MarkType::AboveVowel) => (Action::ShiftLeft, AboveState::T2),
+            (AboveState::T1, MarkType::BelowVowel) => (Action::None, AboveState::T1),
+            (AboveState::T1, MarkType::Tone) => (Action::ShiftDownLeft, AboveState::T2),
+            (AboveState::T2, MarkType::AboveVowel) => (Action::None, AboveState::T3),
+            (AboveState::T2, MarkType::BelowVowel) => (Action::None, AboveState::T2),
+            (AboveState::T2, MarkType::Tone) => (Action::ShiftLeft, AboveState::T3),
+            (AboveState::T3, _) => (Action::None, AboveState::T3),
+        }
+    }
+}
+
+/// The state of the base of a Thai cluster, below the baseline
+#[derive(Copy, Clone, PartialEq)]
+enum BelowState {
+    /// No descender
+    B0,
+    /// A removable descender
+    B1,
+    /// A descender
+    B2,
+}
+
+impl BelowState {
+    fn start(consonant_type: ConsonantType) -> Self {
+        match consonant_type {
+            ConsonantType::RemovableDescender => BelowState::B1,
+            ConsonantType::Descender | ConsonantType::NotConsonant => BelowState::B2,
+            _ => BelowState::B0,
+        }
+    }
+
+    fn next(self, mark_type: MarkType) -> (Action, BelowState) {
+        match (self, mark_type) {
+            (BelowState::B0, MarkType::BelowVowel) => (Action::None, BelowState::B2),
+            (BelowState::B1, MarkType::BelowVowel) => (Action::RemoveDescender, BelowState::B2),
+            (BelowState::B2, MarkType::BelowVowel) => (Action::ShiftDown, BelowState::B2),
+            (state, _) => (Action::None, state),
+        }
+    }
+}
+
+/// The Windows private use area code point for `ch` after `action`
+fn pua_char(ch: char, action: Action) -> Option<char> {
+    let pua = match (action, ch) {
+        (Action::ShiftDown, '\u{0E48}') => '\u{F70A}',
+        (Action::ShiftDown, '\u{0E49}') => '\u{F70B}',
+        (Action::ShiftDown, '\u{0E4A}') => '\u{F70C}',
+        (Action::ShiftDown, '\u{0E4B}') => '\u{F70D}',
+        (Action::ShiftDown, '\u{0E4C}') => '\u{F70E}',
+        (Action::ShiftDown, '\u{0E38}') => '\u{F718}',
+        (Action::ShiftDown, '\u{0E39}') => '\u{F719}',
+        (Action::ShiftDown, '\u{0E3A}') => '\u{F71A}',
+        (Action::ShiftDownLeft, '\u{0E48}') => '\u{F705}',
+        (Action::ShiftDownLeft, '\u{0E49}') => '\u{F706}',
+        (Action::ShiftDownLeft, '\u{0E4A}') => '\u{F707}',
+        (Action::ShiftDownLeft, '\u{0E4B}') => '\u{F708}',
+        (Action::ShiftDownLeft, '\u{0E4C}') => '\u{F709}',
+        (Action::ShiftLeft, '\u{0E48}') => '\u{F713}',
+        (Action::ShiftLeft, '\u{0E49}') => '\u{F714}',
+        (Action::ShiftLeft, '\u{0E4A}') => '\u{F715}',
+        (Action::ShiftLeft, '\u{0E4B}') => '\u{F716}',
+        (Action::ShiftLeft, '\u{0E4C}') => '\u{F717}',
+        (Action::ShiftLeft, '\u{0E31}') => '\u{F710}',
+        (Action::ShiftLeft, '\u{0E34}') => '\u{F701}',
+        (Action::ShiftLeft, '\u{0E35}') => '\u{F702}',
+        (Action::ShiftLeft, '\u{0E36}') => '\u{F703}',
+        (Action::ShiftLeft, '\u{0E37}') => '\u{F704}',
+        (Action::ShiftLeft, '\u{0E47}') => '\u{F712}',
+        (Action::ShiftLeft, '\u{0E4D}') => '\u{F711}',
+        (Action::RemoveDescender, '\u{0E0D}') => '\u{F70F}',
+        (Action::RemoveDescender, '\u{0E10}') => '\u{F700}',
+        _ => return None,
+    };
+    Some(pua)
+}
+
+fn apply_lookup(
+    feature_tags: &[u32],
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    langsys: &LangSys,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ParseError> {
+    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, feature_tags)? {
+        gsub::gsub_apply_lookup(
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            lookup_index,
+            feature_tag,
+            None,
+            raw_glyphs,
+            0,
+            raw_glyphs.len(),
+            |_| true,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::gsub::GsubFeatureMask;
+    use crate::layout::new_layout_cache;
+    use crate::shape::make_glyph;
+
+    // A GSUB table for the `thai` script where ccmp substitutes Mai Ek (glyph 3) with glyph 4
+    #[rustfmt::skip]
+    const THAI_GSUB: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x0A, // script list offset
+        0x00, 0x1E, // feature list offset
+        0x00, 0x2C, // lookup list offset
+        // ScriptList
+        0x00, 0x01, b't', b'h', b'a', b'i', 0x00, 0x08,
+        // Script
+        0x00, 0x04, 0x00, 0x00, // default langsys offset, langsys count
+        // LangSys
+        0x00, 0x00, 0xFF, 0xFF, 0x00, 0x01, 0x00, 0x00, // one feature, index 0
+        // FeatureList
+        0x00, 0x01, b'c', b'c', b'm', b'p', 0x00, 0x08,
+        // Feature with lookup 0
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        // LookupList
+        0x00, 0x01, 0x00, 0x04,
+        // Lookup 0, single substitution format 1
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // type, flag, count, subtable offset
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x01, // format, coverage, delta glyph id
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x03, // coverage of glyph 3
+    ];
+
+    fn pua_fallback(cs: &[char]) -> Vec<u16> {
+        // Map code points in the private use area to glyphs 0xF7xx, and others to 0x0Exx
+        let mut glyphs = cs
+            .iter()
+            .map(|&ch| make_glyph(ch, ch as u16 & 0xFF, 0))
+            .collect::<Vec<_>>();
+        pua_fallback_thai(&mut glyphs, |ch| match ch {
+            '\u{F700}'..='\u{F7FF}' => Some(ch as u16),
+            _ => None,
+        });
+        glyphs.iter().map(|g| g.glyph_index).collect()
+    }
+
+    #[test]
+    fn test_preprocess_sara_am() {
+        // Ko Kai, Mai Tho, Sara Am
+        let mut cs = vec!['\u{0E01}', '\u{0E49}', '\u{0E33}'];
+        preprocess_thai(&mut cs);
+        assert_eq!(vec!['\u{0E01}', '\u{0E4D}', '\u{0E49}', '\u{0E32}'], cs);
+
+        // Lao Ko, Mai Ek, Sign Am
+        let mut cs = vec!['\u{0E81}', '\u{0EC8}', '\u{0EB3}'];
+        preprocess_thai(&mut cs);
+        assert_eq!(vec!['\u{0E81}', '\u{0ECD}', '\u{0EC8}', '\u{0EB2}'], cs);
+    }
+
+    #[test]
+    fn test_leading_sara_e() {
+        let gsub_table = ReadScope::new(THAI_GSUB)
+            .read::<LayoutTable<GSUB>>()
+            .unwrap();
+        let gsub_cache = new_layout_cache(gsub_table);
+        // เก่: Sara E, Ko Kai, Mai Ek
+        let mut glyphs = vec![
+            make_glyph('\u{0E40}', 1, 0),
+            make_glyph('\u{0E01}', 2, 0),
+            make_glyph('\u{0E48}', 3, 0),
+        ];
+        gsub::gsub_apply_default(
+            &|| Vec::new(),
+            &gsub_cache,
+            None,
+            tag::THAI,
+            None,
+            GsubFeatureMask::default(),
+            5,
+            &mut glyphs,
+        )
+        .unwrap();
+
+        // Sara E remains before the base consonant, as it is encoded in visual order
+        let glyph_indices = glyphs.iter().map(|g| g.glyph_index).collect::<Vec<_>>();
+        assert_eq!(glyph_indices, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_pua_fallback() {
+        // Ko Kai, Mai Ek: shifted down
+        assert_eq!(pua_fallback(&['\u{0E01}', '\u{0E48}']), vec![0x01, 0xF70A]);
+        // Po Pla, Mai Ek: shifted down and left of the ascender
+        assert_eq!(pua_fallback(&['\u{0E1B}', '\u{0E48}']), vec![0x1B, 0xF705]);
+        // Po Pla, Sara I, Mai Ek: both shifted left of the ascender
+        assert_eq!(
+            pua_fallback(&['\u{0E1B}', '\u{0E34}', '\u{0E48}']),
+            vec![0x1B, 0xF701, 0xF713]
+        );
+        // Ko Kai, Sara I, Mai Ek: unchanged
+        assert_eq!(
+            pua_fallback(&['\u{0E01}', '\u{0E34}', '\u{0E48}']),
+            vec![0x01, 0x34, 0x48]
+        );
+        // Yo Ying, Sara U: descender removed
+        assert_eq!(pua_fallback(&['\u{0E0D}', '\u{0E38}']), vec![0xF70F, 0x38]);
+        // Do Chada, Sara U: shifted down below the descender
+        assert_eq!(pua_fallback(&['\u{0E0E}', '\u{0E38}']), vec![0x0E, 0xF718]);
+    }
+}
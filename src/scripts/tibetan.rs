@@ -0,0 +1,180 @@
+//! Implementation of font shaping for Tibetan scripts
+
+//!
+//! Tibetan is written without reordering. A stack is a base consonant followed by subjoined
+//! consonants (U+0F90..U+0FBC) and vowel signs, which the font forms with the below-base and
+//! above-base features. The features are applied in the order of the Universal Shaping Engine:
+//! <https://docs.microsoft.com/en-us/typography/script-development/use>
+
+use crate::error::{ParseError, ShapingError};
+use crate::gsub::{self, build_lookups, RawGlyph};
+use crate::layout::{GDEFTable, LangSys, LayoutCache, LayoutTable, GSUB};
+use crate::tag;
+
+pub fn gsub_apply_tibetan(
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ShapingError> {
+    let langsys = match gsub_table.find_script(script_tag)? {
+        Some(s) => match s.find_langsys_or_default(opt_lang_tag)? {
+            Some(v) => v,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+
+    // 1. Compound character composition and decomposition, and language forms
+
+    apply_lookup(
+        &[tag::LOCL, tag::CCMP],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    // 2. Subjoined consonant forms
+    //
+    // Subjoined consonants are usually substituted with their below-base form, although some,
+    // such as subjoined YA and RA, may take a post-base or above-base form
+
+    apply_lookup(
+        &[tag::ABVF, tag::BLWF, tag::PSTF],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    // 3. Vowel signs and stacked forms
+    //
+    // Vowel signs are left in logical order after the stack that they apply to. These features
+    // form the above-base and below-base vowel signs, and ligate them with the stack where the
+    // font has a precomposed form
+
+    apply_lookup(
+        &[tag::ABVS, tag::BLWS, tag::PSTS],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    // 4. Typographic forms
+
+    apply_lookup(
+        &[tag::CALT, tag::CLIG, tag::LIGA, tag::RLIG],
+        gsub_cache,
+        gsub_table,
+        gdef_table,
+        langsys,
+        raw_glyphs,
+    )?;
+
+    // Vowel signs are positioned on the stack by the GPOS abvm and blwm features
+
+    Ok(())
+}
+
+fn apply_lookup(
+    feature_tags: &[u32],
+    gsub_cache: &LayoutCache<GSUB>,
+    gsub_table: &LayoutTable<GSUB>,
+    gdef_table: Option<&GDEFTable>,
+    langsys: &LangSys,
+    raw_glyphs: &mut Vec<RawGlyph<()>>,
+) -> Result<(), ParseError> {
+    for (lookup_index, feature_tag) in build_lookups(gsub_table, langsys, feature_tags)? {
+        gsub::gsub_apply_lookup(
+            gsub_cache,
+            gsub_table,
+            gdef_table,
+            lookup_index,
+            feature_tag,
+            None,
+            raw_glyphs,
+            0,
+            raw_glyphs.len(),
+            |_| true,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::gsub::GsubFeatureMask;
+    use crate::layout::new_layout_cache;
+    use crate::shape::make_glyph;
+
+    // A GSUB table for the `tibt` script where blwf substitutes subjoined RA (glyph 2) with its
+    // below-base form (glyph 3), and abvs substitutes vowel sign I (glyph 4) with glyph 5
+    #[rustfmt::skip]
+    const TIBETAN_GSUB: &[u8] = &[
+        0x00, 0x01, 0x00, 0x00, // version
+        0x00, 0x0A, // script list offset
+        0x00, 0x20, // feature list offset
+        0x00, 0x3A, // lookup list offset
+        // ScriptList
+        0x00, 0x01, b't', b'i', b'b', b't', 0x00, 0x08,
+        // Script
+        0x00, 0x04, 0x00, 0x00, // default langsys offset, langsys count
+        // LangSys
+        0x00, 0x00, 0xFF, 0xFF, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, // features 0 and 1
+        // FeatureList
+        0x00, 0x02,
+        b'a', b'b', b'v', b's', 0x00, 0x0E,
+        b'b', b'l', b'w', b'f', 0x00, 0x14,
+        // Features
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x01, // abvs: lookup 1
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, // blwf: lookup 0
+        // LookupList
+        0x00, 0x02, 0x00, 0x06, 0x00, 0x1A,
+        // Lookup 0, single substitution format 1
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, // type, flag, count, subtable offset
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x01, // format, coverage, delta glyph id
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x02, // coverage of glyph 2
+        // Lookup 1, single substitution format 1
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08,
+        0x00, 0x01, 0x00, 0x06, 0x00, 0x01,
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x04, // coverage of glyph 4
+    ];
+
+    #[test]
+    fn subjoined_ra() {
+        let gsub_table = ReadScope::new(TIBETAN_GSUB)
+            .read::<LayoutTable<GSUB>>()
+            .unwrap();
+        let gsub_cache = new_layout_cache(gsub_table);
+        // ཀྲི: KA, subjoined RA, vowel sign I
+        let mut glyphs = vec![
+            make_glyph('\u{0F40}', 1, 0),
+            make_glyph('\u{0FB2}', 2, 0),
+            make_glyph('\u{0F72}', 4, 0),
+        ];
+        gsub::gsub_apply_default(
+            &|| Vec::new(),
+            &gsub_cache,
+            None,
+            tag::TIBT,
+            None,
+            GsubFeatureMask::default(),
+            6,
+            &mut glyphs,
+        )
+        .unwrap();
+
+        let glyph_indices = glyphs.iter().map(|g| g.glyph_index).collect::<Vec<_>>();
+        assert_eq!(glyph_indices, vec![1, 3, 5]);
+    }
+}
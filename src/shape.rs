@@ -0,0 +1,567 @@
+#![deny(missing_docs)]
+
+//! High-level text shaping.
+//!
+//! Maps the characters of some text to glyphs with the `cmap` table, then applies the `GSUB` and
+//! `GPOS` tables of the font to produce positioned glyphs.
+
+use std::convert::TryFrom;
+
+use tinyvec::tiny_vec;
+use unicode_general_category::{get_general_category, GeneralCategory};
+
+use crate::binary::read::ReadScope;
+use crate::error::{ParseError, ShapingError};
+use crate::font_data_impl::CmapCache;
+use crate::gpos::{gpos_apply, kern_apply, Info, MarkPlacement};
+use crate::gsub::{gsub_apply_default, gsub_apply_rvrn, GlyphOrigin, GsubFeatureMask, RawGlyph};
+use crate::layout::{new_layout_cache, GDEFTable, LayoutTable, GPOS, GSUB};
+use crate::scripts::indic::preprocess_indic;
+use crate::scripts::khmer::preprocess_khmer;
+use crate::scripts::thai::{preprocess_thai, pua_fallback_thai};
+use crate::scripts::ScriptType;
+use crate::tables::cmap::Cmap;
+use crate::tables::kern::KernTable;
+use crate::tables::{FontTableProvider, MaxpTable};
+use crate::tag;
+use crate::unicode::{canonical_combining_class, VariationSelector};
+
+/// Options that control which features are applied when shaping.
+#[derive(Debug, Copy, Clone)]
+pub struct Features {
+    /// The optional `GSUB` features to apply.
+    pub gsub_features: GsubFeatureMask,
+    /// Apply kerning from the `GPOS` table, or the `kern` table if the font has no `GPOS` table.
+    pub kerning: bool,
+}
+
+/// The direction of the text being shaped, which determines the order of the shaped glyphs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShapeDirection {
+    /// Glyphs are returned in logical order, which is also visual order for left-to-right text.
+    LeftToRight,
+    /// Glyphs are returned in visual order for right-to-left text, as if by `reorder_rtl`.
+    RightToLeft,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features {
+            gsub_features: GsubFeatureMask::default(),
+            kerning: true,
+        }
+    }
+}
+
+/// Shape `text` with the font supplied by `provider`.
+///
+/// The characters are first preprocessed as the shaper for the script requires, such as
+/// decomposing the split vowels of Indic scripts and Khmer. They are then mapped to glyphs with
+/// the best available `cmap` subtable. Characters that the font does not map are given glyph 0,
+/// `.notdef`. The `GSUB` features for `script_tag` and
+/// `opt_lang_tag` are then applied, followed by `GPOS` positioning. The returned `Info` values
+/// hold the glyphs along with their kerning adjustments and placements. For Thai text, when the
+/// font has no `GSUB` features for Thai, marks are instead substituted with the forms the font
+/// maps in the Windows private use area.
+///
+/// The `cluster` of each glyph is the byte offset in `text` of the first character it was
+/// shaped from, which can be used to map glyphs back to ranges of `text`. When preprocessing
+/// changes the characters of a base and its marks, their glyphs all have the offset of the base.
+///
+/// `text` must be in logical order and all lookups are applied in logical order. When
+/// `direction` is `RightToLeft` the glyphs are then reordered into visual order, and the base
+/// glyph indices of mark placements are updated to match. Cursive attachments still refer to the
+/// logically next glyph, which is the preceding glyph in visual order.
+pub fn shape(
+    provider: &impl FontTableProvider,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    features: Features,
+    direction: ShapeDirection,
+    text: &str,
+) -> Result<Vec<Info>, ShapingError> {
+    shape_with_variations(
+        provider,
+        script_tag,
+        opt_lang_tag,
+        features,
+        direction,
+        &[],
+        text,
+    )
+}
+
+/// Shape `text` with the instance of the variable font supplied by `provider` at the normalized
+/// axis coordinates `coords`.
+///
+/// This is the same as `shape`, except the lookups of the `rvrn` feature are selected by
+/// matching `coords` against the feature variations of the `GSUB` table. Coordinates are in the
+/// range -1.0 to 1.0 and given in the order of the axes of the font. Axes without a coordinate
+/// are at their default, 0.
+pub fn shape_with_variations(
+    provider: &impl FontTableProvider,
+    script_tag: u32,
+    opt_lang_tag: Option<u32>,
+    features: Features,
+    direction: ShapeDirection,
+    coords: &[f32],
+    text: &str,
+) -> Result<Vec<Info>, ShapingError> {
+    let cmap_data = provider.read_table_data(tag::CMAP)?;
+    let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>()?;
+    let cmap_cache = CmapCache::new(&cmap)?.ok_or(ParseError::MissingValue)?;
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+
+    let mut glyphs: Vec<RawGlyph<()>> = Vec::with_capacity(text.len());
+    for (cluster, ch) in preprocess_text(ScriptType::from(script_tag), text) {
+        if let Ok(variation) = VariationSelector::try_from(ch) {
+            if let Some(glyph) = glyphs.last_mut() {
+                glyph.variation = Some(variation);
+                continue;
+            }
+        }
+        let glyph_index = cmap_cache.map_glyph(ch)?.unwrap_or(0);
+        glyphs.push(make_glyph(ch, glyph_index, cluster));
+    }
+
+    let gdef_data = provider.table_data(tag::GDEF)?;
+    let opt_gdef_table = gdef_data
+        .as_ref()
+        .map(|data| ReadScope::new(data).read::<GDEFTable>())
+        .transpose()?;
+
+    let mut has_script_features = false;
+    if let Some(gsub_data) = provider.table_data(tag::GSUB)? {
+        let gsub_table = ReadScope::new(&gsub_data).read::<LayoutTable<GSUB>>()?;
+        has_script_features = gsub_table.find_script(script_tag)?.is_some();
+        let gsub_cache = new_layout_cache(gsub_table);
+        gsub_apply_rvrn(
+            &gsub_cache,
+            opt_gdef_table.as_ref(),
+            script_tag,
+            opt_lang_tag,
+            coords,
+            &mut glyphs,
+        )?;
+        let make_dotted_circle = || match cmap_cache.map_glyph('\u{25CC}') {
+            Ok(Some(glyph_index)) => vec![make_glyph('\u{25CC}', glyph_index, 0)],
+            _ => Vec::new(),
+        };
+        gsub_apply_default(
+            &make_dotted_circle,
+            &gsub_cache,
+            opt_gdef_table.as_ref(),
+            script_tag,
+            opt_lang_tag,
+            features.gsub_features,
+            maxp.num_glyphs,
+            &mut glyphs,
+        )?;
+    }
+    if !has_script_features && ScriptType::from(script_tag) == ScriptType::Thai {
+        pua_fallback_thai(&mut glyphs, |ch| cmap_cache.map_glyph(ch).unwrap_or(None));
+    }
+
+    let mut infos = Info::init_from_glyphs(opt_gdef_table.as_ref(), glyphs)?;
+    if let Some(gpos_data) = provider.table_data(tag::GPOS)? {
+        let gpos_table = ReadScope::new(&gpos_data).read::<LayoutTable<GPOS>>()?;
+        let gpos_cache = new_layout_cache(gpos_table);
+        gpos_apply(
+            &gpos_cache,
+            opt_gdef_table.as_ref(),
+            features.kerning,
+            None,
+            script_tag,
+            opt_lang_tag,
+            &mut infos,
+        )?;
+    } else if features.kerning {
+        if let Some(kern_data) = provider.table_data(tag::KERN)? {
+            let kern_table = ReadScope::new(&kern_data).read::<KernTable<'_>>()?;
+            kern_apply(&kern_table, &mut infos);
+        }
+    }
+
+    if direction == ShapeDirection::RightToLeft {
+        let order = visual_order(infos.iter().map(|info| info.glyph.cluster));
+        let mut new_indices = vec![0; order.len()];
+        for (new_index, &old_index) in order.iter().enumerate() {
+            new_indices[old_index] = new_index;
+        }
+        permute(&mut infos, &order);
+        for info in &mut infos {
+            match &mut info.mark_placement {
+                MarkPlacement::None => {}
+                MarkPlacement::MarkAnchor(base_index, _, _)
+                | MarkPlacement::MarkOverprint(base_index) => {
+                    *base_index = new_indices[*base_index];
+                }
+            }
+        }
+    }
+
+    Ok(infos)
+}
+
+/// Apply the character preprocessing of the shaper for `script_type` to `text`.
+///
+/// Returns each preprocessed character with the byte offset in `text` of the character it came
+/// from. The text is preprocessed a segment at a time, where a segment is a character and the
+/// marks that follow it. As preprocessing may insert, remove, or reorder characters, all of the
+/// characters of a segment that it changes are given the offset of the start of the segment.
+fn preprocess_text(script_type: ScriptType, text: &str) -> Vec<(usize, char)> {
+    let preprocess: fn(&mut Vec<char>) = match script_type {
+        ScriptType::Indic => preprocess_indic,
+        ScriptType::Khmer => preprocess_khmer,
+        ScriptType::Thai => preprocess_thai,
+        _ => return text.char_indices().collect(),
+    };
+
+    let char_indices = text.char_indices().collect::<Vec<_>>();
+    let mut chars = Vec::with_capacity(char_indices.len());
+    let mut start = 0;
+    while start < char_indices.len() {
+        let len = char_indices[start + 1..]
+            .iter()
+            .zip(&char_indices[start..])
+            .position(|(&(_, ch), &(_, prev))| starts_segment(prev, ch))
+            .map_or(char_indices.len() - start, |len| len + 1);
+        let segment = &char_indices[start..start + len];
+        let mut cs = segment.iter().map(|&(_, ch)| ch).collect::<Vec<_>>();
+        preprocess(&mut cs);
+        if cs.len() == segment.len() && cs.iter().zip(segment).all(|(&ch, &(_, c))| ch == c) {
+            chars.extend_from_slice(segment);
+        } else {
+            let cluster = segment[0].0;
+            chars.extend(cs.into_iter().map(|ch| (cluster, ch)));
+        }
+        start += len;
+    }
+    chars
+}
+
+/// Returns whether `ch`, following `prev`, starts a new segment for preprocessing.
+///
+/// Marks and format characters, such as ZWJ, continue the segment of the preceding character, as
+/// do characters following a virama so that conjuncts are preprocessed together. Thai Sara Am and
+/// Lao Am also continue the segment, as their Nikhahit is moved before the preceding marks.
+fn starts_segment(prev: char, ch: char) -> bool {
+    if canonical_combining_class(prev) == 9 {
+        return false;
+    }
+    match ch {
+        '\u{0E33}' | '\u{0EB3}' => return false,
+        _ => {}
+    }
+    match get_general_category(ch) {
+        GeneralCategory::NonspacingMark
+        | GeneralCategory::SpacingMark
+        | GeneralCategory::EnclosingMark
+        | GeneralCategory::Format => false,
+        _ => true,
+    }
+}
+
+/// Reorder `glyphs`, shaped from right-to-left text in logical order, into visual order.
+///
+/// The order of the clusters is reversed, while the glyphs within each cluster keep their
+/// logical order. The glyphs that a multiple substitution produced from one character therefore
+/// stay in the order they were substituted, and each ligature remains a single glyph whose
+/// `unicodes` are in logical order.
+pub fn reorder_rtl<T>(glyphs: &mut Vec<RawGlyph<T>>) {
+    let order = visual_order(glyphs.iter().map(|glyph| glyph.cluster));
+    permute(glyphs, &order);
+}
+
+/// The logical indices of glyphs with the supplied `clusters`, in right-to-left visual order.
+fn visual_order(clusters: impl Iterator<Item = usize>) -> Vec<usize> {
+    let clusters = clusters.collect::<Vec<_>>();
+    let mut order = Vec::with_capacity(clusters.len());
+    let mut end = clusters.len();
+    while end > 0 {
+        let mut start = end - 1;
+        while start > 0 && clusters[start - 1] == clusters[end - 1] {
+            start -= 1;
+        }
+        order.extend(start..end);
+        end = start;
+    }
+    order
+}
+
+/// Rearrange `items` so that item `i` is the item previously at `order[i]`.
+fn permute<G>(items: &mut Vec<G>, order: &[usize]) {
+    let mut slots = items.drain(..).map(Some).collect::<Vec<_>>();
+    items.extend(order.iter().filter_map(|&index| slots[index].take()));
+}
+
+pub(crate) fn make_glyph(ch: char, glyph_index: u16, cluster: usize) -> RawGlyph<()> {
+    RawGlyph {
+        unicodes: tiny_vec![[char; 1] => ch],
+        glyph_index,
+        liga_component_pos: 0,
+        cluster,
+        glyph_origin: GlyphOrigin::Char(ch),
+        small_caps: false,
+        multi_subst_dup: false,
+        is_vert_alt: false,
+        fake_bold: false,
+        fake_italic: false,
+        variation: None,
+        extra_data: (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glyph_info;
+    use crate::tables::{HheaTable, OpenTypeFile};
+    use crate::tests::read_fixture;
+
+    #[test]
+    fn shape_ligature() {
+        let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        let infos = shape(
+            &provider,
+            tag::LATN,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "Office",
+        )
+        .unwrap();
+        let glyphs = infos
+            .iter()
+            .map(|info| info.glyph.glyph_index)
+            .collect::<Vec<_>>();
+        let unligated = shape(
+            &provider,
+            tag::LATN,
+            None,
+            Features {
+                gsub_features: GsubFeatureMask::empty(),
+                kerning: false,
+            },
+            ShapeDirection::LeftToRight,
+            "Office",
+        )
+        .unwrap();
+
+        // O ffi c e
+        assert_eq!(glyphs.len(), 4);
+        assert_eq!(unligated.len(), 6);
+        assert_eq!(glyphs[0], unligated[0].glyph.glyph_index);
+        assert_eq!(
+            glyphs[2..],
+            [
+                unligated[4].glyph.glyph_index,
+                unligated[5].glyph.glyph_index
+            ]
+        );
+        assert_eq!(infos[1].glyph.unicodes.as_slice(), &['f', 'f', 'i']);
+
+        let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP).unwrap())
+            .read::<MaxpTable>()
+            .unwrap();
+        let hhea = ReadScope::new(&provider.read_table_data(tag::HHEA).unwrap())
+            .read::<HheaTable>()
+            .unwrap();
+        let hmtx_data = provider.read_table_data(tag::HMTX).unwrap();
+        let advance = |info: &Info| {
+            let advance = glyph_info::advance(&maxp, &hhea, &hmtx_data, info.glyph.glyph_index);
+            i32::from(advance.unwrap()) + i32::from(info.kerning)
+        };
+        // The ligature is narrower than the three glyphs it replaces (616 + 616 + 540)
+        assert_eq!(advance(&infos[1]), 1630);
+    }
+
+    #[test]
+    fn shape_clusters() {
+        let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        // ffi ligature
+        let infos = shape(
+            &provider,
+            tag::LATN,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "ffi",
+        )
+        .unwrap();
+        let clusters = infos
+            .iter()
+            .map(|info| info.glyph.cluster)
+            .collect::<Vec<_>>();
+        assert_eq!(clusters, [0]);
+
+        let infos = shape(
+            &provider,
+            tag::LATN,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "Office",
+        )
+        .unwrap();
+        let clusters = infos
+            .iter()
+            .map(|info| info.glyph.cluster)
+            .collect::<Vec<_>>();
+        assert_eq!(clusters, [0, 1, 4, 5]);
+
+        // The vowel sign I is reordered before the ka it follows, and keeps the cluster of the ka
+        let buffer = read_fixture("tests/fonts/noto/NotoSansDevanagari-Regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let infos = shape(
+            &provider,
+            tag::DEVA,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "\u{0915}\u{093F}\u{0915}",
+        )
+        .unwrap();
+        let chars = infos
+            .iter()
+            .map(|info| (info.glyph.unicodes[0], info.glyph.cluster))
+            .collect::<Vec<_>>();
+        assert_eq!(chars, [('\u{093F}', 0), ('\u{0915}', 0), ('\u{0915}', 6)]);
+    }
+
+    #[test]
+    fn shape_rtl() {
+        let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        // beh with fatha, alef, beh
+        let text = "\u{0628}\u{064E}\u{0627}\u{0628}";
+        let logical = shape(
+            &provider,
+            tag::ARAB,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            text,
+        )
+        .unwrap();
+        let visual = shape(
+            &provider,
+            tag::ARAB,
+            None,
+            Features::default(),
+            ShapeDirection::RightToLeft,
+            text,
+        )
+        .unwrap();
+
+        let glyphs = |infos: &[Info]| {
+            infos
+                .iter()
+                .map(|info| (info.glyph.glyph_index, info.glyph.cluster))
+                .collect::<Vec<_>>()
+        };
+        let mut reversed = glyphs(&logical);
+        reversed.reverse();
+        assert_eq!(glyphs(&visual), reversed);
+
+        // The fatha remains attached to the first beh, which is now last
+        match visual[2].mark_placement {
+            MarkPlacement::MarkAnchor(base_index, _, _) => assert_eq!(base_index, 3),
+            _ => panic!("fatha was not attached"),
+        }
+    }
+
+    #[test]
+    fn shape_split_vowel() {
+        let buffer = read_fixture("tests/fonts/noto/NotoSansTamil-Regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        let shape_text = |text| {
+            shape(
+                &provider,
+                tag::TAML,
+                None,
+                Features::default(),
+                ShapeDirection::LeftToRight,
+                text,
+            )
+            .unwrap()
+            .iter()
+            .map(|info| (info.glyph.glyph_index, info.glyph.cluster))
+            .collect::<Vec<_>>()
+        };
+        // Ko, with the vowel sign O, is shaped as ka followed by the two parts of the vowel sign,
+        // which take the cluster of the ka. The following ka keeps its own cluster.
+        let glyphs = shape_text("\u{0B95}\u{0BCA}\u{0B95}");
+        let decomposed = shape_text("\u{0B95}\u{0BC6}\u{0BBE}\u{0B95}");
+        let glyph_indices = |glyphs: &[(u16, usize)]| {
+            glyphs
+                .iter()
+                .map(|&(glyph_index, _)| glyph_index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(glyph_indices(&glyphs), glyph_indices(&decomposed));
+        assert_eq!(glyphs, [(46, 0), (18, 0), (41, 0), (18, 6)]);
+    }
+
+    #[test]
+    fn shape_sara_am() {
+        // None of the test fonts cover Thai, so the glyphs are `.notdef` and the characters they
+        // were shaped from are checked instead
+        let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        // Kam with tone mark Mai Tho, followed by ka
+        let infos = shape(
+            &provider,
+            tag::THAI,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "\u{0E01}\u{0E49}\u{0E33}\u{0E01}",
+        )
+        .unwrap();
+        let chars = infos
+            .iter()
+            .map(|info| (info.glyph.unicodes[0], info.glyph.cluster))
+            .collect::<Vec<_>>();
+        // Sara Am is decomposed, and its Nikhahit moved before the tone mark
+        assert_eq!(
+            chars,
+            [
+                ('\u{0E01}', 0),
+                ('\u{0E4D}', 0),
+                ('\u{0E49}', 0),
+                ('\u{0E32}', 0),
+                ('\u{0E01}', 9)
+            ]
+        );
+    }
+
+    #[test]
+    fn reorder_rtl_keeps_clusters() {
+        let mut glyphs = vec![
+            make_glyph('a', 1, 0),
+            make_glyph('a', 2, 0),
+            make_glyph('b', 3, 1),
+            make_glyph('c', 4, 2),
+        ];
+        reorder_rtl(&mut glyphs);
+        let glyph_indices = glyphs
+            .iter()
+            .map(|glyph| glyph.glyph_index)
+            .collect::<Vec<_>>();
+        assert_eq!(glyph_indices, [4, 3, 1, 2]);
+    }
+}
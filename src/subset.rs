@@ -2,7 +2,8 @@
 
 //! Font subsetting.
 
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::num::Wrapping;
 
@@ -14,12 +15,18 @@ use crate::binary::write::{WriteBinaryDep, WriteBuffer, WriteContext};
 use crate::binary::{long_align, U16Be, U32Be};
 use crate::cff::CFF;
 use crate::error::{ParseError, ReadWriteError, WriteError};
+use crate::font_data_impl::CmapCache;
+use crate::gsub::GsubFeatureMask;
+use crate::layout::{new_layout_cache, LayoutTable, GSUB};
+use crate::macroman::macroman_to_char;
 use crate::post::PostTable;
+use crate::tables::cmap::Cmap;
 use crate::tables::glyf::GlyfTable;
 use crate::tables::loca::{self, LocaTable};
+use crate::tables::os2::{self, Os2};
 use crate::tables::{
     self, cmap, FontTableProvider, HeadTable, HheaTable, HmtxTable, IndexToLocFormat, MaxpTable,
-    TableRecord,
+    OpenTypeFile, OpenTypeFont, TableRecord, VheaTable, VmtxTable,
 };
 use crate::{checksum, tag};
 
@@ -44,22 +51,114 @@ struct OrderedTables {
     checksum: Wrapping<u32>,
 }
 
+/// Options that control how a font is subset.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SubsetOptions {
+    /// Copy the `GDEF`, `GPOS`, and `GSUB` tables into the subset font.
+    ///
+    /// The tables are copied verbatim: glyph ids within them are **not** remapped to the glyph
+    /// ids of the subset font, so they will only be correct if the subset retains the original
+    /// glyph order.
+    pub retain_layout: bool,
+    /// Write a version 2.0 `post` table that retains the names of the glyphs in the subset.
+    ///
+    /// When unset, or the original font does not name its glyphs, a version 3.0 `post` table
+    /// without glyph names is written.
+    pub retain_glyph_names: bool,
+    /// Make the `.notdef` glyph, glyph 0, the first glyph of the subset font.
+    ///
+    /// When set, glyph 0 is moved to the front of the glyph ids if it is not first, or added if
+    /// it is missing. When unset, subsetting fails with `WriteError::BadValue` unless the first
+    /// glyph id is 0. The glyph ids of a Mac Roman `cmap0` refer to the subset font, so they must
+    /// take the position of glyph 0 into account.
+    pub ensure_notdef: bool,
+    /// Keep a Type 1 CFF font as Type 1, regardless of the number of glyphs in the subset.
+    ///
+    /// When unset, a Type 1 CFF font is converted to a CID-keyed font if the subset contains more
+    /// than 255 glyphs, which is more broadly compatible when the font is embedded in a PDF. A
+    /// Type 1 CFF font with any number of glyphs is valid in an OpenType font, so set this to
+    /// produce the more compact Type 1 output. CID-keyed fonts are unaffected.
+    pub force_type1: bool,
+}
+
+/// How `remap_glyph_ids` handles glyph ids that are not in the mapping.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UnmappedGlyphs {
+    /// Remove glyph ids that are not mapped from the array.
+    Drop,
+    /// Fail with `ParseError::BadIndex` if a glyph id is not mapped.
+    Error,
+}
+
+/// The source of the cmap table written to a subset font.
+enum CmapSource<'a> {
+    /// A Mac Roman format 0 subtable mapping character codes to new glyph ids.
+    MacRoman(Box<[u8; 256]>),
+    /// A Unicode format 4 or 6 subtable built from char to old glyph id mappings.
+    Unicode(&'a [(char, u16)]),
+}
+
 /// Subset this font so that it only contains the glyphs with the supplied `glyph_ids`.
+///
+/// Returns `WriteError::BadValue` if `glyph_ids` is empty, unless `options.ensure_notdef` is set
+/// in which case the subset contains just the `.notdef` glyph.
 pub fn subset(
     provider: &impl FontTableProvider,
     glyph_ids: &[u16],
     cmap0: Option<Box<[u8; 256]>>,
+    options: SubsetOptions,
 ) -> Result<Vec<u8>, ReadWriteError> {
+    subset_with_mapping(provider, glyph_ids, cmap0, options).map(|(data, _)| data)
+}
+
+/// Subset this font so that it only contains the glyphs with the supplied `glyph_ids`.
+///
+/// Returns the subset font along with the glyph ids of the original font, indexed by the glyph
+/// ids of the subset font.
+pub fn subset_with_mapping(
+    provider: &impl FontTableProvider,
+    glyph_ids: &[u16],
+    cmap0: Option<Box<[u8; 256]>>,
+    options: SubsetOptions,
+) -> Result<(Vec<u8>, Vec<u16>), ReadWriteError> {
+    let cmap = cmap0.map(CmapSource::MacRoman);
+    let glyph_ids = notdef_first(glyph_ids, options);
     if provider.has_table(tag::CFF) {
-        subset_cff(provider, glyph_ids, cmap0, true)
+        subset_cff(provider, 0, &glyph_ids, cmap, !options.force_type1, options)
     } else {
-        subset_ttf(provider, glyph_ids, cmap0)
+        subset_ttf(provider, &glyph_ids, cmap, options)
     }
 }
 
+/// Subset this font so that it only contains the glyphs with the supplied `glyph_ids`.
+///
+/// A Unicode (platform 3, encoding 1) cmap subtable is built from `mappings`, which map
+/// characters to glyph ids in the original font. The subtable is format 6 if the characters form
+/// a range dense enough for it to be smaller than format 4, otherwise format 4. Mappings for
+/// characters outside the Basic Multilingual Plane, or to glyphs that are not retained, are
+/// omitted.
+pub fn subset_with_cmap(
+    provider: &impl FontTableProvider,
+    glyph_ids: &[u16],
+    mappings: &[(char, u16)],
+    options: SubsetOptions,
+) -> Result<Vec<u8>, ReadWriteError> {
+    let cmap = Some(CmapSource::Unicode(mappings));
+    let glyph_ids = notdef_first(glyph_ids, options);
+    let (data, _) = if provider.has_table(tag::CFF) {
+        subset_cff(provider, 0, &glyph_ids, cmap, !options.force_type1, options)?
+    } else {
+        subset_ttf(provider, &glyph_ids, cmap, options)?
+    };
+    Ok(data)
+}
+
 /// Subset this font so that it only contains the glyphs with the supplied `glyph_ids`.
 ///
 /// Returns just the CFF table in the case of a CFF font, not a complete OpenType font.
+///
+/// When `convert_cff_to_cid_if_more_than_255_glyphs` is false a Type 1 CFF font remains Type 1
+/// however many glyphs are retained.
 pub fn prince_subset(
     provider: &impl FontTableProvider,
     glyph_ids: &[u16],
@@ -69,20 +168,233 @@ pub fn prince_subset(
     if provider.has_table(tag::CFF) {
         subset_cff_table(
             provider,
+            0,
             glyph_ids,
             cmap0,
             convert_cff_to_cid_if_more_than_255_glyphs,
         )
     } else {
-        subset_ttf(provider, glyph_ids, cmap0)
+        subset_ttf(
+            provider,
+            glyph_ids,
+            cmap0.map(CmapSource::MacRoman),
+            SubsetOptions::default(),
+        )
+        .map(|(data, _)| data)
+    }
+}
+
+/// Add glyphs to `existing_subset`, a font previously subset from the font supplied by
+/// `provider`.
+///
+/// `existing_glyph_ids` is the mapping of glyph ids returned by `subset_with_mapping` when
+/// `existing_subset` was created. The glyphs of `existing_subset` keep their glyph ids. They are
+/// followed by the `additional_glyph_ids` not already present, then by any glyphs these need as
+/// components of composite glyphs. Returns the new font along with the glyph ids of `provider`,
+/// indexed by the glyph ids of the new font.
+///
+/// The tables that depend on the glyphs, such as `glyf`, `hmtx`, and `post`, are rebuilt from
+/// `provider`. All other tables, such as `cmap`, `name`, and any layout tables, are copied from
+/// `existing_subset` unchanged, so glyphs are only mapped by the `cmap` if they were already. Only
+/// fonts with TrueType outlines are supported.
+pub fn append_glyphs(
+    existing_subset: &[u8],
+    existing_glyph_ids: &[u16],
+    provider: &impl FontTableProvider,
+    additional_glyph_ids: &[u16],
+) -> Result<(Vec<u8>, Vec<u16>), ReadWriteError> {
+    if provider.has_table(tag::CFF) {
+        return Err(ReadWriteError::Read(ParseError::NotImplemented));
+    }
+    let existing_file = ReadScope::new(existing_subset).read::<OpenTypeFile<'_>>()?;
+    let existing_tags = match &existing_file.font {
+        OpenTypeFont::Single(offset_table) => offset_table
+            .table_records
+            .iter()
+            .map(|table_record| table_record.table_tag)
+            .collect::<Vec<_>>(),
+        OpenTypeFont::Collection(_) => return Err(ReadWriteError::Read(ParseError::BadValue)),
+    };
+    let existing = existing_file.font_provider(0)?;
+    let maxp = ReadScope::new(&existing.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+    if usize::from(maxp.num_glyphs) != existing_glyph_ids.len() {
+        return Err(ReadWriteError::Write(WriteError::BadValue));
+    }
+
+    // Subsetting the existing glyphs followed by the additional ones preserves the existing
+    // glyph ids, as components are only added after all the requested glyphs
+    let mut glyph_ids = existing_glyph_ids.to_vec();
+    let mut seen = glyph_ids.iter().copied().collect::<HashSet<_>>();
+    for &glyph_id in additional_glyph_ids {
+        if seen.insert(glyph_id) {
+            glyph_ids.push(glyph_id);
+        }
+    }
+    let post_data = existing.read_table_data(tag::POST)?;
+    let post = ReadScope::new(&post_data).read::<PostTable<'_>>()?;
+    let options = SubsetOptions {
+        retain_glyph_names: post.header.version == 0x00020000,
+        ..SubsetOptions::default()
+    };
+    let (appended_data, new_to_old_glyph_id) = subset_ttf(provider, &glyph_ids, None, options)?;
+    let appended_file = ReadScope::new(&appended_data).read::<OpenTypeFile<'_>>()?;
+    let appended = appended_file.font_provider(0)?;
+
+    let merged = AppendedFont { existing, appended };
+    let mut tags = existing_tags;
+    for &tag in &AppendedFont::<(), ()>::GLYPH_TABLES {
+        if merged.has_table(tag) && !tags.contains(&tag) {
+            tags.push(tag);
+        }
     }
+    let data = whole_font(&merged, &tags)?;
+    Ok((data, new_to_old_glyph_id))
+}
+
+/// A font with the glyph dependent tables of `appended` and the remaining tables of `existing`.
+struct AppendedFont<P, Q> {
+    existing: P,
+    appended: Q,
+}
+
+impl<P, Q> AppendedFont<P, Q> {
+    /// The tables that are rebuilt when glyphs are added. `head` is included as the format of the
+    /// `loca` table may change.
+    const GLYPH_TABLES: [u32; 9] = [
+        tag::GLYF,
+        tag::HEAD,
+        tag::HHEA,
+        tag::HMTX,
+        tag::LOCA,
+        tag::MAXP,
+        tag::POST,
+        tag::VHEA,
+        tag::VMTX,
+    ];
+}
+
+impl<P: FontTableProvider, Q: FontTableProvider> FontTableProvider for AppendedFont<P, Q> {
+    fn table_data<'a>(&'a self, tag: u32) -> Result<Option<Cow<'a, [u8]>>, ParseError> {
+        if Self::GLYPH_TABLES.contains(&tag) {
+            self.appended.table_data(tag)
+        } else {
+            self.existing.table_data(tag)
+        }
+    }
+
+    fn has_table(&self, tag: u32) -> bool {
+        if Self::GLYPH_TABLES.contains(&tag) {
+            self.appended.has_table(tag)
+        } else {
+            self.existing.has_table(tag)
+        }
+    }
+}
+
+/// Determine the glyphs needed to render `chars` with the font supplied by `provider`.
+///
+/// The characters are mapped to glyphs with the best available `cmap` subtable, then the
+/// glyphs that the `GSUB` lookups of the enabled `features` may substitute for them are added,
+/// along with the components of composite glyphs. The result always includes glyph 0, `.notdef`,
+/// and is sorted, ready to be passed to `subset`.
+///
+/// Lookups are applied without regard to their context, so the result may include glyphs that
+/// are never actually substituted. Features that can't be controlled by `GsubFeatureMask`, such
+/// as those applied by the script shapers, are always included.
+pub fn glyph_closure(
+    provider: &impl FontTableProvider,
+    chars: impl IntoIterator<Item = char>,
+    features: GsubFeatureMask,
+) -> Result<Vec<u16>, ReadWriteError> {
+    let cmap_data = provider.read_table_data(tag::CMAP)?;
+    let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>()?;
+    let cmap_cache = CmapCache::new(&cmap)?.ok_or(ParseError::MissingValue)?;
+
+    let mut glyphs = BTreeSet::new();
+    glyphs.insert(0);
+    for ch in chars {
+        if let Some(glyph_index) = cmap_cache.map_glyph(ch)? {
+            glyphs.insert(glyph_index);
+        }
+    }
+
+    if let Some(gsub_data) = provider.table_data(tag::GSUB)? {
+        let gsub_table = ReadScope::new(&gsub_data).read::<LayoutTable<GSUB>>()?;
+        let gsub_cache = new_layout_cache(gsub_table);
+        let layout_table = &gsub_cache.layout_table;
+        if let (Some(feature_list), Some(lookup_list)) = (
+            &layout_table.opt_feature_list,
+            &layout_table.opt_lookup_list,
+        ) {
+            let mut lookup_indices = BTreeSet::new();
+            let mut pending = Vec::new();
+            for feature_record in feature_list.feature_records() {
+                let feature_mask = GsubFeatureMask::from_tag(feature_record.feature_tag);
+                if feature_mask.is_empty() || features.intersects(feature_mask) {
+                    pending.extend_from_slice(&feature_record.feature_table().lookup_indices);
+                }
+            }
+            while let Some(lookup_index) = pending.pop() {
+                if lookup_indices.insert(lookup_index) {
+                    let lookup =
+                        lookup_list.lookup_cache_gsub(&gsub_cache, usize::from(lookup_index))?;
+                    pending.extend(lookup.lookup_subtables.nested_lookup_indices());
+                }
+            }
+
+            loop {
+                let num_glyphs = glyphs.len();
+                for &lookup_index in &lookup_indices {
+                    let lookup =
+                        lookup_list.lookup_cache_gsub(&gsub_cache, usize::from(lookup_index))?;
+                    lookup.lookup_subtables.closure_glyphs(&mut glyphs);
+                }
+                if glyphs.len() == num_glyphs {
+                    break;
+                }
+            }
+        }
+    }
+
+    if provider.has_table(tag::GLYF) {
+        let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
+        let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
+        let loca_data = provider.read_table_data(tag::LOCA)?;
+        let loca = ReadScope::new(&loca_data)
+            .read_dep::<LocaTable<'_>>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
+        let glyf_data = provider.read_table_data(tag::GLYF)?;
+        let glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable<'_>>(&loca)?;
+        let mut pending = glyphs.iter().copied().collect::<Vec<_>>();
+        while let Some(glyph_id) = pending.pop() {
+            for component in glyf.components(usize::from(glyph_id))? {
+                if glyphs.insert(component) {
+                    pending.push(component);
+                }
+            }
+        }
+    }
+
+    Ok(glyphs.into_iter().collect())
+}
+
+/// Move glyph 0 to the front of `glyph_ids` if requested by `options`.
+fn notdef_first(glyph_ids: &[u16], options: SubsetOptions) -> Cow<'_, [u16]> {
+    if !options.ensure_notdef || glyph_ids.first() == Some(&0) {
+        return Cow::Borrowed(glyph_ids);
+    }
+
+    let mut new_glyph_ids = Vec::with_capacity(glyph_ids.len() + 1);
+    new_glyph_ids.push(0);
+    new_glyph_ids.extend(glyph_ids.iter().copied().filter(|&glyph_id| glyph_id != 0));
+    Cow::Owned(new_glyph_ids)
 }
 
 fn subset_ttf(
     provider: &impl FontTableProvider,
     glyph_ids: &[u16],
-    cmap0: Option<Box<[u8; 256]>>,
-) -> Result<Vec<u8>, ReadWriteError> {
+    cmap: Option<CmapSource<'_>>,
+    options: SubsetOptions,
+) -> Result<(Vec<u8>, Vec<u16>), ReadWriteError> {
     if glyph_ids.get(0) != Some(&0) {
         // glyph index 0 is the .notdef glyph, the fallback, it must always be first
         return Err(ReadWriteError::Write(WriteError::BadValue));
@@ -102,19 +414,24 @@ fn subset_ttf(
         usize::from(hhea.num_h_metrics),
     ))?;
 
-    // Build a new post table with version set to 3, which does not contain any additional
-    // PostScript data
     let post_data = provider.read_table_data(tag::POST)?;
-    let mut post = ReadScope::new(&post_data).read::<PostTable<'_>>()?;
-    post.header.version = 0x00030000; // version 3.0
-    post.opt_sub_table = None;
+    let post = ReadScope::new(&post_data).read::<PostTable<'_>>()?;
 
     // Build the new glyf table
     let (glyf, new_to_old_glyph_id) = glyf.subset(glyph_ids)?;
 
+    // Build new vhea and vmtx tables
+    let vertical_metrics =
+        create_vertical_metrics(provider, usize::from(maxp.num_glyphs), &new_to_old_glyph_id)?;
+
     // Build new maxp table
     let num_glyphs = u16::try_from(glyf.records.len()).map_err(ParseError::from)?;
     maxp.num_glyphs = num_glyphs;
+    if let Some(sub_table) = maxp.version1_sub_table.as_mut() {
+        let (max_component_elements, max_component_depth) = glyf.component_limits()?;
+        sub_table.max_component_elements = max_component_elements;
+        sub_table.max_component_depth = max_component_depth;
+    }
 
     // Build new hhea table
     let num_h_metrics = usize::from(hhea.num_h_metrics);
@@ -128,17 +445,26 @@ fn subset_ttf(
         &new_to_old_glyph_id,
     )?;
 
+    // Build new post table
+    let post = create_post_table(post, &new_to_old_glyph_id, options)?;
+
     // Get the remaining tables
     let cvt = provider.table_data(tag::CVT)?;
     let fpgm = provider.table_data(tag::FPGM)?;
+    let gasp = provider.table_data(tag::GASP)?;
     let name = provider.table_data(tag::NAME)?;
+    let os_2 = provider.table_data(tag::OS_2)?;
     let prep = provider.table_data(tag::PREP)?;
 
+    // Build a new cmap table
+    let cmap = cmap
+        .map(|cmap| create_cmap_table(glyph_ids, &new_to_old_glyph_id, cmap))
+        .transpose()?;
+    let char_range = cmap.as_ref().and_then(cmap_char_range);
+
     // Build the new font
     let mut builder = FontBuilder::new(0x00010000_u32);
-    if let Some(cmap0) = cmap0 {
-        // Build a new cmap table
-        let cmap = create_cmap_table(glyph_ids, cmap0)?;
+    if let Some(cmap) = cmap {
         builder.add_table::<_, cmap::owned::Cmap>(tag::CMAP, cmap, ())?;
     }
     if let Some(cvt) = cvt {
@@ -147,33 +473,56 @@ fn subset_ttf(
     if let Some(fpgm) = fpgm {
         builder.add_table::<_, ReadScope<'_>>(tag::FPGM, ReadScope::new(&fpgm), ())?;
     }
+    if let Some(gasp) = gasp {
+        builder.add_table::<_, ReadScope<'_>>(tag::GASP, ReadScope::new(&gasp), ())?;
+    }
     builder.add_table::<_, HheaTable>(tag::HHEA, &hhea, ())?;
     builder.add_table::<_, HmtxTable<'_>>(tag::HMTX, &hmtx, ())?;
     builder.add_table::<_, MaxpTable>(tag::MAXP, &maxp, ())?;
     if let Some(name) = name {
         builder.add_table::<_, ReadScope<'_>>(tag::NAME, ReadScope::new(&name), ())?;
     }
+    match os_2 {
+        Some(os_2) => {
+            builder.add_table::<_, ReadScope<'_>>(tag::OS_2, ReadScope::new(&os_2), ())?;
+        }
+        None => {
+            let os_2 = create_os2_table(&head, &hhea, &hmtx, char_range);
+            builder.add_table::<_, Os2>(tag::OS_2, &os_2, ())?;
+        }
+    }
     builder.add_table::<_, PostTable<'_>>(tag::POST, &post, ())?;
     if let Some(prep) = prep {
         builder.add_table::<_, ReadScope<'_>>(tag::PREP, ReadScope::new(&prep), ())?;
     }
+    if let Some((vhea, vmtx)) = vertical_metrics {
+        builder.add_table::<_, VheaTable>(tag::VHEA, &vhea, ())?;
+        builder.add_table::<_, VmtxTable<'_>>(tag::VMTX, &vmtx, ())?;
+    }
+    if options.retain_layout {
+        add_layout_tables(provider, &mut builder)?;
+    }
     let mut builder = builder.add_head_table(&head)?;
     builder.add_glyf_table(glyf)?;
-    builder.data()
+    Ok((builder.data()?, new_to_old_glyph_id))
 }
 
 fn subset_cff(
     provider: &impl FontTableProvider,
+    font_index: usize,
     glyph_ids: &[u16],
-    cmap0: Option<Box<[u8; 256]>>,
+    cmap: Option<CmapSource<'_>>,
     convert_cff_to_cid_if_more_than_255_glyphs: bool,
-) -> Result<Vec<u8>, ReadWriteError> {
+    options: SubsetOptions,
+) -> Result<(Vec<u8>, Vec<u16>), ReadWriteError> {
+    if glyph_ids.is_empty() {
+        // A font must contain at least the .notdef glyph
+        return Err(ReadWriteError::Write(WriteError::BadValue));
+    }
+
     let cff_data = provider.read_table_data(tag::CFF)?;
     let scope = ReadScope::new(&cff_data);
     let cff: CFF<'_> = scope.read::<CFF<'_>>()?;
-    if cff.name_index.count != 1 || cff.fonts.len() != 1 {
-        return Err(ReadWriteError::from(ParseError::BadIndex));
-    }
 
     let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
     let mut maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
@@ -184,16 +533,19 @@ fn subset_cff(
         usize::from(hhea.num_h_metrics),
     ))?;
 
-    // Build a new post table with version set to 3, which does not contain any additional
-    // PostScript data
     let post_data = provider.read_table_data(tag::POST)?;
-    let mut post = ReadScope::new(&post_data).read::<PostTable<'_>>()?;
-    post.header.version = 0x00030000; // version 3.0
-    post.opt_sub_table = None;
+    let post = ReadScope::new(&post_data).read::<PostTable<'_>>()?;
 
     // Build the new CFF table
-    let (cff, new_to_old_glyph_id) =
-        cff.subset(glyph_ids, convert_cff_to_cid_if_more_than_255_glyphs)?;
+    let (cff, new_to_old_glyph_id) = cff.subset(
+        font_index,
+        glyph_ids,
+        convert_cff_to_cid_if_more_than_255_glyphs,
+    )?;
+
+    // Build new vhea and vmtx tables
+    let vertical_metrics =
+        create_vertical_metrics(provider, usize::from(maxp.num_glyphs), &new_to_old_glyph_id)?;
 
     // Build new maxp table
     let num_glyphs = u16::try_from(new_to_old_glyph_id.len()).map_err(ParseError::from)?;
@@ -211,18 +563,22 @@ fn subset_cff(
         &new_to_old_glyph_id,
     )?;
 
+    // Build new post table
+    let post = create_post_table(post, &new_to_old_glyph_id, options)?;
+
     // Get the remaining tables
     let cvt = provider.table_data(tag::CVT)?;
     let fpgm = provider.table_data(tag::FPGM)?;
+    let gasp = provider.table_data(tag::GASP)?;
     let name = provider.table_data(tag::NAME)?;
     let prep = provider.table_data(tag::PREP)?;
-    let os_2 = provider.read_table_data(tag::OS_2)?;
+    let os_2 = provider.table_data(tag::OS_2)?;
 
     // Build the new font
     let mut builder = FontBuilder::new(tag::OTTO);
-    if let Some(cmap0) = cmap0 {
+    if let Some(cmap) = cmap {
         // Build a new cmap table
-        let cmap = create_cmap_table(glyph_ids, cmap0)?;
+        let cmap = create_cmap_table(glyph_ids, &new_to_old_glyph_id, cmap)?;
         builder.add_table::<_, cmap::owned::Cmap>(tag::CMAP, cmap, ())?;
     }
     if let Some(cvt) = cvt {
@@ -231,38 +587,56 @@ fn subset_cff(
     if let Some(fpgm) = fpgm {
         builder.add_table::<_, ReadScope<'_>>(tag::FPGM, ReadScope::new(&fpgm), ())?;
     }
+    if let Some(gasp) = gasp {
+        builder.add_table::<_, ReadScope<'_>>(tag::GASP, ReadScope::new(&gasp), ())?;
+    }
     builder.add_table::<_, HheaTable>(tag::HHEA, &hhea, ())?;
     builder.add_table::<_, HmtxTable<'_>>(tag::HMTX, &hmtx, ())?;
     builder.add_table::<_, MaxpTable>(tag::MAXP, &maxp, ())?;
     if let Some(name) = name {
         builder.add_table::<_, ReadScope<'_>>(tag::NAME, ReadScope::new(&name), ())?;
     }
-    builder.add_table::<_, ReadScope<'_>>(tag::OS_2, ReadScope::new(&os_2), ())?;
+    if let Some(os_2) = os_2 {
+        builder.add_table::<_, ReadScope<'_>>(tag::OS_2, ReadScope::new(&os_2), ())?;
+    }
     builder.add_table::<_, PostTable<'_>>(tag::POST, &post, ())?;
     if let Some(prep) = prep {
         builder.add_table::<_, ReadScope<'_>>(tag::PREP, ReadScope::new(&prep), ())?;
     }
+    if let Some((vhea, vmtx)) = vertical_metrics {
+        builder.add_table::<_, VheaTable>(tag::VHEA, &vhea, ())?;
+        builder.add_table::<_, VmtxTable<'_>>(tag::VMTX, &vmtx, ())?;
+    }
+    if options.retain_layout {
+        add_layout_tables(provider, &mut builder)?;
+    }
     builder.add_table::<_, CFF<'_>>(tag::CFF, &cff, ())?;
     let builder = builder.add_head_table(&head)?;
-    builder.data()
+    Ok((builder.data()?, new_to_old_glyph_id))
 }
 
 fn subset_cff_table(
     provider: &impl FontTableProvider,
+    font_index: usize,
     glyph_ids: &[u16],
     _cmap0: Option<Box<[u8; 256]>>,
     convert_cff_to_cid_if_more_than_255_glyphs: bool,
 ) -> Result<Vec<u8>, ReadWriteError> {
+    if glyph_ids.is_empty() {
+        // A font must contain at least the .notdef glyph
+        return Err(ReadWriteError::Write(WriteError::BadValue));
+    }
+
     let cff_data = provider.read_table_data(tag::CFF)?;
     let scope = ReadScope::new(&cff_data);
     let cff: CFF<'_> = scope.read::<CFF<'_>>()?;
-    if cff.name_index.count != 1 || cff.fonts.len() != 1 {
-        return Err(ReadWriteError::from(ParseError::BadIndex));
-    }
 
     // Build the new CFF table
-    let (cff, _new_to_old_glyph_id) =
-        cff.subset(glyph_ids, convert_cff_to_cid_if_more_than_255_glyphs)?;
+    let (cff, _new_to_old_glyph_id) = cff.subset(
+        font_index,
+        glyph_ids,
+        convert_cff_to_cid_if_more_than_255_glyphs,
+    )?;
 
     let mut buffer = WriteBuffer::new();
     CFF::write(&mut buffer, &cff)?;
@@ -270,24 +644,54 @@ fn subset_cff_table(
     Ok(buffer.into_inner())
 }
 
+/// Build the `post` table for a subset font.
+fn create_post_table<'a>(
+    mut post: PostTable<'a>,
+    new_to_old_glyph_id: &[u16],
+    options: SubsetOptions,
+) -> Result<PostTable<'a>, ParseError> {
+    match post.header.version {
+        0x00010000 | 0x00020000 if options.retain_glyph_names => post.subset(new_to_old_glyph_id),
+        _ => {
+            // Version 3 does not contain any additional PostScript data
+            post.header.version = 0x00030000; // version 3.0
+            post.opt_sub_table = None;
+            Ok(post)
+        }
+    }
+}
+
+/// Copy the layout tables present in `provider` into `builder` verbatim.
+fn add_layout_tables(
+    provider: &impl FontTableProvider,
+    builder: &mut FontBuilder,
+) -> Result<(), ReadWriteError> {
+    for &tag in &[tag::GDEF, tag::GPOS, tag::GSUB] {
+        if let Some(data) = provider.table_data(tag)? {
+            builder.add_table::<_, ReadScope<'_>>(tag, ReadScope::new(&data), ())?;
+        }
+    }
+    Ok(())
+}
+
 /// Construct a complete font from the supplied provider and tags.
+///
+/// A `DSIG` table is not copied, as its signature would not be valid for the new font. Fonts with
+/// a `CFF ` table are built as OpenType fonts with CFF outlines, otherwise the `glyf`
+/// and `loca` tables are rebuilt from the glyphs of the font.
 pub fn whole_font<F: FontTableProvider>(
     provider: &F,
     tags: &[u32],
 ) -> Result<Vec<u8>, ReadWriteError> {
     let head = ReadScope::new(&provider.read_table_data(tag::HEAD)?).read::<HeadTable>()?;
     let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP)?).read::<MaxpTable>()?;
-    let loca_data = provider.read_table_data(tag::LOCA)?;
-    let loca = ReadScope::new(&loca_data)
-        .read_dep::<LocaTable<'_>>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
-    let glyf_data = provider.read_table_data(tag::GLYF)?;
-    let glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable<'_>>(&loca)?;
 
-    let sfnt_version = tags
-        .iter()
-        .position(|&tag| tag == tag::CFF)
-        .map(|_| tables::CFF_MAGIC)
-        .unwrap_or(tables::TTF_MAGIC);
+    let is_cff = provider.has_table(tag::CFF);
+    let sfnt_version = if is_cff {
+        tables::CFF_MAGIC
+    } else {
+        tables::TTF_MAGIC
+    };
     let mut builder = FontBuilder::new(sfnt_version);
     let skip = [tag::HEAD, tag::MAXP, tag::LOCA, tag::GLYF];
     for &tag in tags {
@@ -301,30 +705,387 @@ pub fn whole_font<F: FontTableProvider>(
     }
     builder.add_table::<_, MaxpTable>(tag::MAXP, &maxp, ())?;
     let mut builder = builder.add_head_table(&head)?;
-    builder.add_glyf_table(glyf)?;
+    if !is_cff {
+        let loca_data = provider.read_table_data(tag::LOCA)?;
+        let loca = ReadScope::new(&loca_data)
+            .read_dep::<LocaTable<'_>>((usize::from(maxp.num_glyphs), head.index_to_loc_format))?;
+        let glyf_data = provider.read_table_data(tag::GLYF)?;
+        let glyf = ReadScope::new(&glyf_data).read_dep::<GlyfTable<'_>>(&loca)?;
+        builder.add_glyf_table(glyf)?;
+    }
     builder.data()
 }
 
+/// Construct an OpenType font with CFF outlines from the supplied tables.
+///
+/// The font contains just the `CFF `, `OS/2`, `cmap`, `head`, `hhea`, `hmtx`, and `maxp` tables.
+/// The number of glyphs in `maxp` must match the number of glyphs in `cff`.
+pub fn build_otf_from_cff(
+    cff: &CFF<'_>,
+    head: &HeadTable,
+    hhea: &HheaTable,
+    hmtx: &HmtxTable<'_>,
+    maxp: &MaxpTable,
+    os2: &Os2,
+    cmap: cmap::owned::Cmap,
+) -> Result<Vec<u8>, ReadWriteError> {
+    let num_glyphs = cff
+        .fonts
+        .first()
+        .map(|font| font.char_strings_index.len())
+        .ok_or(ParseError::MissingValue)?;
+    if num_glyphs != usize::from(maxp.num_glyphs) {
+        return Err(ReadWriteError::Write(WriteError::BadValue));
+    }
+
+    let mut builder = FontBuilder::new(tag::OTTO);
+    builder.add_table::<_, cmap::owned::Cmap>(tag::CMAP, cmap, ())?;
+    builder.add_table::<_, HheaTable>(tag::HHEA, hhea, ())?;
+    builder.add_table::<_, HmtxTable<'_>>(tag::HMTX, hmtx, ())?;
+    builder.add_table::<_, MaxpTable>(tag::MAXP, maxp, ())?;
+    builder.add_table::<_, Os2>(tag::OS_2, os2, ())?;
+    builder.add_table::<_, CFF<'_>>(tag::CFF, cff, ())?;
+    let builder = builder.add_head_table(head)?;
+    builder.data()
+}
+
+/// Replace the glyph ids in `array` with their new glyph ids from `old_to_new_glyph_id`.
+///
+/// The array is converted to `ReadArrayCow::Owned`. Glyph ids without an entry in
+/// `old_to_new_glyph_id` are handled according to `unmapped`. The array is left unchanged if an
+/// error is returned.
+pub fn remap_glyph_ids(
+    array: &mut ReadArrayCow<'_, U16Be>,
+    old_to_new_glyph_id: &HashMap<u16, u16>,
+    unmapped: UnmappedGlyphs,
+) -> Result<(), ParseError> {
+    let mut new_glyph_ids = Vec::with_capacity(array.len());
+    for old_glyph_id in array.iter() {
+        match (old_to_new_glyph_id.get(&old_glyph_id), unmapped) {
+            (Some(&new_glyph_id), _) => new_glyph_ids.push(new_glyph_id),
+            (None, UnmappedGlyphs::Drop) => {}
+            (None, UnmappedGlyphs::Error) => return Err(ParseError::BadIndex),
+        }
+    }
+    *array = ReadArrayCow::Owned(new_glyph_ids);
+    Ok(())
+}
+
 fn create_cmap_table(
     glyph_ids: &[u16],
-    cmap0: Box<[u8; 256]>,
+    new_to_old_glyph_id: &[u16],
+    source: CmapSource<'_>,
 ) -> Result<cmap::owned::Cmap, ReadWriteError> {
     use cmap::owned::{Cmap, CmapSubtable, EncodingRecord};
 
-    if glyph_ids.len() > 256 {
-        return Err(ReadWriteError::Write(WriteError::BadValue));
+    match source {
+        CmapSource::MacRoman(cmap0) => {
+            if glyph_ids.len() > 256 {
+                return Err(ReadWriteError::Write(WriteError::BadValue));
+            }
+
+            Ok(Cmap {
+                encoding_records: vec![EncodingRecord {
+                    platform_id: 1, // Macintosh platform
+                    encoding_id: 0, // Roman
+                    sub_table: CmapSubtable::Format0 {
+                        language: 0, // the subtable is language independent
+                        glyph_id_array: cmap0,
+                    },
+                }],
+            })
+        }
+        CmapSource::Unicode(mappings) => Ok(Cmap {
+            encoding_records: vec![EncodingRecord {
+                platform_id: 3, // Windows platform
+                encoding_id: 1, // Unicode BMP
+                sub_table: create_cmap_unicode_subtable(new_to_old_glyph_id, mappings),
+            }],
+        }),
     }
+}
 
-    Ok(Cmap {
-        encoding_records: vec![EncodingRecord {
-            platform_id: 1, // Macintosh platform
-            encoding_id: 0, // Roman
-            sub_table: CmapSubtable::Format0 {
-                language: 0, // the subtable is language independent
-                glyph_id_array: cmap0,
-            },
-        }],
-    })
+/// Build the smaller of a format 4 or format 6 subtable from char to old glyph id `mappings`,
+/// mapping to new glyph ids.
+///
+/// Format 6 is chosen when the characters form a dense range that would otherwise need many
+/// format 4 segments.
+fn create_cmap_unicode_subtable(
+    new_to_old_glyph_id: &[u16],
+    mappings: &[(char, u16)],
+) -> cmap::owned::CmapSubtable {
+    let format4 = create_cmap_format4(new_to_old_glyph_id, mappings);
+    let format6 = create_cmap_format6(new_to_old_glyph_id, mappings);
+    match (&format4, &format6) {
+        (
+            cmap::owned::CmapSubtable::Format4 { start_codes, .. },
+            cmap::owned::CmapSubtable::Format6 { glyph_id_array, .. },
+        ) if !glyph_id_array.is_empty() => {
+            // Subtable header plus four u16 arrays per segment, and a trimmed glyph id array
+            let format4_size = 16 + 8 * start_codes.len();
+            let format6_size = 10 + 2 * glyph_id_array.len();
+            if format6_size < format4_size {
+                format6
+            } else {
+                format4
+            }
+        }
+        _ => format4,
+    }
+}
+
+/// Map char to old glyph id `mappings` to BMP character codes and new glyph ids.
+///
+/// Characters outside the Basic Multilingual Plane, 0xFFFF, and glyphs that are not retained are
+/// omitted.
+fn bmp_mappings(new_to_old_glyph_id: &[u16], mappings: &[(char, u16)]) -> BTreeMap<u16, u16> {
+    let old_to_new_glyph_id = new_to_old_glyph_id
+        .iter()
+        .enumerate()
+        .map(|(new_id, &old_id)| (old_id, new_id as u16))
+        .collect::<BTreeMap<_, _>>();
+    mappings
+        .iter()
+        .filter_map(|&(ch, old_id)| {
+            let code = u16::try_from(u32::from(ch)).ok()?;
+            // 0xFFFF is reserved for the final segment of format 4 subtables
+            if code == 0xFFFF {
+                return None;
+            }
+            old_to_new_glyph_id
+                .get(&old_id)
+                .map(|&new_id| (code, new_id))
+        })
+        .collect()
+}
+
+/// Build a format 6 subtable from char to old glyph id `mappings`, mapping to new glyph ids.
+///
+/// Codes between the first and last mapped character that are not mapped are given glyph 0.
+fn create_cmap_format6(
+    new_to_old_glyph_id: &[u16],
+    mappings: &[(char, u16)],
+) -> cmap::owned::CmapSubtable {
+    let mappings = bmp_mappings(new_to_old_glyph_id, mappings);
+    let first_code = mappings.keys().next().copied().unwrap_or(0);
+    let mut glyph_id_array = Vec::new();
+    for (code, glyph_id) in mappings {
+        glyph_id_array.resize(usize::from(code - first_code), 0);
+        glyph_id_array.push(glyph_id);
+    }
+
+    cmap::owned::CmapSubtable::Format6 {
+        language: 0, // the subtable is language independent
+        first_code,
+        glyph_id_array,
+    }
+}
+
+/// Build a format 4 subtable from char to old glyph id `mappings`, mapping to new glyph ids.
+fn create_cmap_format4(
+    new_to_old_glyph_id: &[u16],
+    mappings: &[(char, u16)],
+) -> cmap::owned::CmapSubtable {
+    let mappings = bmp_mappings(new_to_old_glyph_id, mappings);
+
+    // Each segment is a run of consecutive characters mapping to consecutive glyph ids so that it
+    // can be expressed with just an `id_delta`
+    let mut start_codes = Vec::new();
+    let mut end_codes = Vec::new();
+    let mut id_deltas = Vec::new();
+    for (code, glyph_id) in mappings {
+        let id_delta = glyph_id.wrapping_sub(code) as i16;
+        match (end_codes.last_mut(), id_deltas.last()) {
+            (Some(end_code), Some(&last_delta))
+                if *end_code + 1 == code && last_delta == id_delta =>
+            {
+                *end_code = code;
+            }
+            _ => {
+                start_codes.push(code);
+                end_codes.push(code);
+                id_deltas.push(id_delta);
+            }
+        }
+    }
+
+    // The last segment must map 0xFFFF to the missing glyph
+    start_codes.push(0xFFFF);
+    end_codes.push(0xFFFF);
+    id_deltas.push(1);
+    let id_range_offsets = vec![0; start_codes.len()];
+
+    cmap::owned::CmapSubtable::Format4 {
+        language: 0, // the subtable is language independent
+        end_codes,
+        start_codes,
+        id_deltas,
+        id_range_offsets,
+        glyph_id_array: Vec::new(),
+    }
+}
+
+/// Find the first and last character codes mapped by `cmap`.
+fn cmap_char_range(cmap: &cmap::owned::Cmap) -> Option<(u16, u16)> {
+    use cmap::owned::CmapSubtable;
+
+    let record = cmap.encoding_records.first()?;
+    match &record.sub_table {
+        CmapSubtable::Format0 { glyph_id_array, .. } => {
+            // The codes are Mac OS Roman, whereas the range is of Unicode code points
+            (0..=255u8)
+                .zip(glyph_id_array.iter())
+                .filter(|&(_, &glyph_id)| glyph_id != 0)
+                .filter_map(|(code, _)| macroman_to_char(code))
+                .filter_map(|ch| u16::try_from(u32::from(ch)).ok())
+                .minmax()
+                .into_option()
+        }
+        CmapSubtable::Format4 {
+            start_codes,
+            end_codes,
+            ..
+        } => {
+            // The last segment is the one mapping 0xFFFF, which is not a real character
+            if start_codes.len() < 2 {
+                return None;
+            }
+            Some((start_codes[0], end_codes[end_codes.len() - 2]))
+        }
+        CmapSubtable::Format6 {
+            first_code,
+            glyph_id_array,
+            ..
+        } => {
+            let last_code = usize::from(*first_code) + glyph_id_array.len().checked_sub(1)?;
+            Some((*first_code, u16::try_from(last_code).ok()?))
+        }
+        _ => None,
+    }
+}
+
+/// Build a minimal version 4 `OS/2` table for fonts that lack one.
+fn create_os2_table(
+    head: &HeadTable,
+    hhea: &HheaTable,
+    hmtx: &HmtxTable<'_>,
+    char_range: Option<(u16, u16)>,
+) -> Os2 {
+    let em_fraction = |per_mille: i32| (i32::from(head.units_per_em) * per_mille / 1000) as i16;
+
+    // Average of the non-zero advance widths as defined for OS/2 version 3 and later
+    let advance_widths = hmtx
+        .h_metrics
+        .iter()
+        .map(|metric| u32::from(metric.advance_width))
+        .filter(|&advance_width| advance_width != 0)
+        .collect::<Vec<_>>();
+    let x_avg_char_width = if advance_widths.is_empty() {
+        0
+    } else {
+        (advance_widths.iter().sum::<u32>() / advance_widths.len() as u32) as i16
+    };
+
+    let bold = head.is_bold();
+    let italic = head.is_italic();
+    let mut fs_selection = 0;
+    if italic {
+        fs_selection |= 1 << 0;
+    }
+    if bold {
+        fs_selection |= 1 << 5;
+    }
+    if !bold && !italic {
+        fs_selection |= 1 << 6; // REGULAR
+    }
+
+    let (us_first_char_index, us_last_char_index) = char_range.unwrap_or((0, 0));
+
+    Os2 {
+        version: 4,
+        x_avg_char_width,
+        us_weight_class: if bold { 700 } else { 400 },
+        us_width_class: 5, // Medium (normal)
+        fs_type: 0,        // Installable embedding
+        y_subscript_x_size: em_fraction(650),
+        y_subscript_y_size: em_fraction(600),
+        y_subscript_x_offset: 0,
+        y_subscript_y_offset: em_fraction(75),
+        y_superscript_x_size: em_fraction(650),
+        y_superscript_y_size: em_fraction(600),
+        y_superscript_x_offset: 0,
+        y_superscript_y_offset: em_fraction(350),
+        y_strikeout_size: em_fraction(50),
+        y_strikeout_position: em_fraction(220),
+        s_family_class: 0, // No classification
+        panose: [0; 10],   // Any
+        ul_unicode_range1: 0,
+        ul_unicode_range2: 0,
+        ul_unicode_range3: 0,
+        ul_unicode_range4: 0,
+        ach_vend_id: u32::from_be_bytes(*b"NONE"),
+        fs_selection,
+        us_first_char_index,
+        us_last_char_index,
+        version0: Some(os2::Version0 {
+            s_typo_ascender: hhea.ascender,
+            s_typo_descender: hhea.descender,
+            s_typo_line_gap: hhea.line_gap,
+            us_win_ascent: head.y_max.max(0) as u16,
+            us_win_descent: (-i32::from(head.y_min)).max(0) as u16,
+        }),
+        version1: Some(os2::Version1 {
+            ul_code_page_range1: 0,
+            ul_code_page_range2: 0,
+        }),
+        version2to4: Some(os2::Version2to4 {
+            sx_height: 0,
+            s_cap_height: 0,
+            us_default_char: 0,
+            us_break_char: 0x20,
+            us_max_context: 0,
+        }),
+        version5: None,
+    }
+}
+
+/// Build new `vhea` and `vmtx` tables if `provider` has vertical metrics.
+fn create_vertical_metrics(
+    provider: &impl FontTableProvider,
+    num_glyphs: usize,
+    new_to_old_glyph_id: &[u16],
+) -> Result<Option<(VheaTable, VmtxTable<'static>)>, ReadWriteError> {
+    let (vhea_data, vmtx_data) = match (
+        provider.table_data(tag::VHEA)?,
+        provider.table_data(tag::VMTX)?,
+    ) {
+        (Some(vhea_data), Some(vmtx_data)) => (vhea_data, vmtx_data),
+        _ => return Ok(None),
+    };
+    let mut vhea = ReadScope::new(&vhea_data).read::<VheaTable>()?;
+    let vmtx = ReadScope::new(&vmtx_data)
+        .read_dep::<VmtxTable<'_>>((num_glyphs, usize::from(vhea.num_v_metrics)))?;
+
+    let num_v_metrics = usize::from(vhea.num_v_metrics);
+    vhea.num_v_metrics = u16::try_from(new_to_old_glyph_id.len()).map_err(ParseError::from)?;
+    let vmtx = create_vmtx_table(
+        &vmtx,
+        new_to_old_glyph_id.len(),
+        num_v_metrics,
+        new_to_old_glyph_id,
+    )?;
+
+    Ok(Some((vhea, vmtx)))
+}
+
+fn create_vmtx_table<'b>(
+    vmtx: &VmtxTable<'_>,
+    glyph_count: usize,
+    num_v_metrics: usize,
+    new_to_old_id: &[u16],
+) -> Result<VmtxTable<'b>, ReadWriteError> {
+    // vmtx has the same structure as hmtx, with vertical advances and top side bearings
+    create_hmtx_table(vmtx, glyph_count, num_v_metrics, new_to_old_id)
 }
 
 fn create_hmtx_table<'b>(
@@ -333,6 +1094,9 @@ fn create_hmtx_table<'b>(
     num_h_metrics: usize,
     new_to_old_id: &[u16],
 ) -> Result<HmtxTable<'b>, ReadWriteError> {
+    if num_h_metrics == 0 {
+        return Err(ReadWriteError::Read(ParseError::BadValue));
+    }
     let mut h_metrics = Vec::with_capacity(num_h_metrics);
 
     for glyph_id in 0..glyph_count {
@@ -376,6 +1140,11 @@ impl FontBuilder {
         self.add_table_inner::<HostType, T>(tag, table, args)
     }
 
+    /// Remove the table with `tag`, returning whether it was present.
+    pub fn remove_table(&mut self, tag: u32) -> bool {
+        self.tables.remove(&tag).is_some()
+    }
+
     fn add_table_inner<HostType, T: WriteBinaryDep<HostType>>(
         &mut self,
         tag: u32,
@@ -421,6 +1190,9 @@ impl FontBuilderWithHead {
 
     /// Returns a `Vec<u8>` containing the built font
     pub fn data(mut self) -> Result<Vec<u8>, ReadWriteError> {
+        // The digital signature of the original font does not apply to the built font
+        self.inner.remove_table(tag::DSIG);
+
         let mut font = WriteBuffer::new();
 
         self.write_offset_table(&mut font)?;
@@ -523,6 +1295,8 @@ fn max_power_of_2(num: u16) -> u16 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shape::{shape, Features, ShapeDirection};
+    use crate::tables::gasp::GaspTable;
     use crate::tables::glyf::GlyphData;
     use crate::tables::glyf::{
         BoundingBox, CompositeGlyph, CompositeGlyphArgument, CompositeGlyphFlag, GlyfRecord, Glyph,
@@ -961,6 +1735,749 @@ mod tests {
         }
     }
 
+    #[test]
+    fn create_cmap_format4_round_trip() {
+        use crate::tables::cmap::CmapSubtable;
+
+        // Old glyph ids 0, 10, 11, 12, 20 become 0, 1, 2, 3, 4
+        let new_to_old_glyph_id = [0, 10, 11, 12, 20];
+        let mappings = [
+            ('a', 10),
+            ('b', 11),
+            ('c', 12),
+            ('z', 20),
+            ('\u{20AC}', 11),
+            ('x', 99),         // not retained
+            ('\u{1F600}', 10), // outside the BMP
+        ];
+        let sub_table = create_cmap_format4(&new_to_old_glyph_id, &mappings);
+
+        let mut buffer = WriteBuffer::new();
+        cmap::owned::CmapSubtable::write(&mut buffer, sub_table).unwrap();
+        let data = buffer.into_inner();
+        let sub_table = ReadScope::new(&data).read::<CmapSubtable<'_>>().unwrap();
+
+        assert_eq!(sub_table.map_glyph('a' as u32).unwrap(), Some(1));
+        assert_eq!(sub_table.map_glyph('b' as u32).unwrap(), Some(2));
+        assert_eq!(sub_table.map_glyph('c' as u32).unwrap(), Some(3));
+        assert_eq!(sub_table.map_glyph('z' as u32).unwrap(), Some(4));
+        assert_eq!(sub_table.map_glyph(0x20AC).unwrap(), Some(2));
+        assert_eq!(sub_table.map_glyph('x' as u32).unwrap(), None);
+        assert_eq!(sub_table.map_glyph('d' as u32).unwrap(), None);
+    }
+
+    #[test]
+    fn create_cmap_format6_dense_range() {
+        use crate::tables::cmap::CmapSubtable;
+
+        // Cyrillic capital letters mapped to glyphs in reverse order, which can't be expressed with
+        // format 4 id deltas
+        let new_to_old_glyph_id = (0..=64).map(|glyph_id| glyph_id * 2).collect::<Vec<_>>();
+        let mappings = (0x0410_u32..0x0430)
+            .zip((2_u16..=64).rev().step_by(2))
+            .map(|(code, glyph_id)| (char::try_from(code).unwrap(), glyph_id))
+            .collect::<Vec<_>>();
+        let cmap =
+            create_cmap_table(&[], &new_to_old_glyph_id, CmapSource::Unicode(&mappings)).unwrap();
+        let sub_table = cmap.encoding_records.into_iter().next().unwrap().sub_table;
+        match &sub_table {
+            cmap::owned::CmapSubtable::Format6 {
+                first_code,
+                glyph_id_array,
+                ..
+            } => {
+                assert_eq!(*first_code, 0x0410);
+                assert_eq!(glyph_id_array.len(), 32);
+            }
+            _ => panic!("expected format 6 subtable"),
+        }
+
+        let mut buffer = WriteBuffer::new();
+        cmap::owned::CmapSubtable::write(&mut buffer, sub_table).unwrap();
+        let data = buffer.into_inner();
+        let sub_table = ReadScope::new(&data).read::<CmapSubtable<'_>>().unwrap();
+
+        assert_eq!(sub_table.map_glyph(0x0410).unwrap(), Some(32));
+        assert_eq!(sub_table.map_glyph(0x041F).unwrap(), Some(17));
+        assert_eq!(sub_table.map_glyph(0x042F).unwrap(), Some(1));
+        assert_eq!(sub_table.map_glyph(0x0430).unwrap(), None);
+        assert_eq!(sub_table.map_glyph('A' as u32).unwrap(), None);
+
+        // Sparse characters are more compact as format 4 segments
+        let mappings = [('A', 2), ('B', 4), ('\u{0410}', 6)];
+        let cmap =
+            create_cmap_table(&[], &new_to_old_glyph_id, CmapSource::Unicode(&mappings)).unwrap();
+        match cmap.encoding_records[0].sub_table {
+            cmap::owned::CmapSubtable::Format4 { .. } => {}
+            _ => panic!("expected format 4 subtable"),
+        }
+    }
+
+    #[test]
+    fn subset_with_unicode_cmap() {
+        use crate::tables::cmap::{Cmap, CmapSubtable, EncodingId, PlatformId};
+
+        let buffer = read_fixture("tests/fonts/opentype/test-font.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let subset_data = subset_with_cmap(
+            &provider,
+            &[0, 2, 1],
+            &[('A', 1), ('B', 2)],
+            SubsetOptions::default(),
+        )
+        .unwrap();
+
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        let cmap_data = subset_provider.read_table_data(tag::CMAP).unwrap();
+        let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>().unwrap();
+        let encoding_record = cmap
+            .find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_UNICODE_BMP_UCS2)
+            .unwrap();
+        let sub_table = ReadScope::new(&cmap_data)
+            .offset(usize::try_from(encoding_record.offset).unwrap())
+            .read::<CmapSubtable<'_>>()
+            .unwrap();
+
+        assert_eq!(sub_table.map_glyph('A' as u32).unwrap(), Some(2));
+        assert_eq!(sub_table.map_glyph('B' as u32).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn subset_retain_layout() {
+        let buffer = read_fixture("tests/fonts/noto/NotoSansSyriacEastern-Regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let layout_tags = [tag::GDEF, tag::GPOS, tag::GSUB];
+
+        let options = SubsetOptions {
+            retain_layout: true,
+            ..SubsetOptions::default()
+        };
+        let subset_data = subset(&provider, &[0, 1, 2], None, options).unwrap();
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        for &tag in &layout_tags {
+            assert_eq!(
+                subset_provider.read_table_data(tag).unwrap(),
+                provider.read_table_data(tag).unwrap()
+            );
+        }
+
+        let subset_data = subset(&provider, &[0, 1, 2], None, SubsetOptions::default()).unwrap();
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        for &tag in &layout_tags {
+            assert!(!subset_provider.has_table(tag));
+        }
+    }
+
+    #[test]
+    fn subset_copies_gasp() {
+        let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let gasp_data = provider.read_table_data(tag::GASP).unwrap();
+
+        let subset_data = subset(&provider, &[0, 1, 2], None, SubsetOptions::default()).unwrap();
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        let subset_gasp_data = subset_provider.read_table_data(tag::GASP).unwrap();
+        assert_eq!(subset_gasp_data, gasp_data);
+
+        let gasp = ReadScope::new(&subset_gasp_data)
+            .read::<GaspTable>()
+            .unwrap();
+        let mut buffer = WriteBuffer::new();
+        GaspTable::write(&mut buffer, &gasp).unwrap();
+        assert_eq!(buffer.bytes(), &*gasp_data);
+    }
+
+    #[test]
+    fn append_glyphs_matches_union_subset() {
+        let buffer = read_fixture("tests/fonts/opentype/SFNT-TTF-Composite.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        let (existing_data, existing_glyph_ids) =
+            subset_with_mapping(&provider, &[0, 4], None, SubsetOptions::default()).unwrap();
+        let (appended_data, glyph_ids) =
+            append_glyphs(&existing_data, &existing_glyph_ids, &provider, &[2, 4]).unwrap();
+        let (union_data, union_glyph_ids) =
+            subset_with_mapping(&provider, &[0, 4, 2], None, SubsetOptions::default()).unwrap();
+        assert_eq!(glyph_ids, union_glyph_ids);
+        assert_eq!(
+            &glyph_ids[..existing_glyph_ids.len()],
+            &existing_glyph_ids[..]
+        );
+
+        let appended_file = ReadScope::new(&appended_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let appended = appended_file.font_provider(0).unwrap();
+        let union_file = ReadScope::new(&union_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let union = union_file.font_provider(0).unwrap();
+        let existing_file = ReadScope::new(&existing_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let existing = existing_file.font_provider(0).unwrap();
+
+        for &tag in &[
+            tag::GLYF,
+            tag::LOCA,
+            tag::HMTX,
+            tag::HHEA,
+            tag::MAXP,
+            tag::POST,
+        ] {
+            assert_eq!(
+                appended.table_data(tag).unwrap(),
+                union.table_data(tag).unwrap()
+            );
+        }
+        // Other tables are copied from the existing subset
+        for &tag in &[tag::CMAP, tag::NAME, tag::OS_2] {
+            assert_eq!(
+                appended.table_data(tag).unwrap(),
+                existing.table_data(tag).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn append_glyphs_wrong_mapping() {
+        let buffer = read_fixture("tests/fonts/opentype/SFNT-TTF-Composite.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        let existing_data = subset(&provider, &[0, 4], None, SubsetOptions::default()).unwrap();
+        match append_glyphs(&existing_data, &[0], &provider, &[2]) {
+            Err(ReadWriteError::Write(WriteError::BadValue)) => {}
+            _ => panic!("expected WriteError::BadValue"),
+        }
+    }
+
+    /// A `FontTableProvider` that hides the `OS/2` table of the wrapped provider.
+    struct WithoutOs2<P: FontTableProvider>(P);
+
+    impl<P: FontTableProvider> FontTableProvider for WithoutOs2<P> {
+        fn table_data<'a>(
+            &'a self,
+            tag: u32,
+        ) -> Result<Option<std::borrow::Cow<'a, [u8]>>, ParseError> {
+            if tag == tag::OS_2 {
+                Ok(None)
+            } else {
+                self.0.table_data(tag)
+            }
+        }
+
+        fn has_table(&self, tag: u32) -> bool {
+            tag != tag::OS_2 && self.0.has_table(tag)
+        }
+    }
+
+    #[test]
+    fn subset_copies_os2() {
+        let buffer = read_fixture("tests/fonts/opentype/test-font.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let subset_data = subset(&provider, &[0, 1, 2], None, SubsetOptions::default()).unwrap();
+
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        assert_eq!(
+            subset_provider.read_table_data(tag::OS_2).unwrap(),
+            provider.read_table_data(tag::OS_2).unwrap()
+        );
+    }
+
+    #[test]
+    fn subset_creates_os2() {
+        let buffer = read_fixture("tests/fonts/opentype/test-font.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = WithoutOs2(opentype_file.font_provider(0).unwrap());
+        let subset_data = subset_with_cmap(
+            &provider,
+            &[0, 1, 2],
+            &[('A', 1), ('B', 2)],
+            SubsetOptions::default(),
+        )
+        .unwrap();
+
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        let head = ReadScope::new(&subset_provider.read_table_data(tag::HEAD).unwrap())
+            .read::<HeadTable>()
+            .unwrap();
+        let hhea = ReadScope::new(&subset_provider.read_table_data(tag::HHEA).unwrap())
+            .read::<HheaTable>()
+            .unwrap();
+        let os_2_data = subset_provider.read_table_data(tag::OS_2).unwrap();
+        let os_2 = ReadScope::new(&os_2_data)
+            .read_dep::<Os2>(os_2_data.len())
+            .unwrap();
+
+        assert_eq!(os_2_data.len(), 96);
+        assert_eq!(os_2.version, 4);
+        assert_eq!(os_2.us_first_char_index, 'A' as u16);
+        assert_eq!(os_2.us_last_char_index, 'B' as u16);
+        let version0 = os_2.version0.unwrap();
+        assert_eq!(version0.s_typo_ascender, hhea.ascender);
+        assert_eq!(version0.s_typo_descender, hhea.descender);
+        assert_eq!(i32::from(version0.us_win_ascent), i32::from(head.y_max));
+        assert!(os_2.version1.is_some());
+        assert!(os_2.version2to4.is_some());
+        assert!(os_2.version5.is_none());
+    }
+
+    #[test]
+    fn subset_retain_glyph_names() {
+        let buffer = read_fixture("tests/fonts/noto/NotoNaskhArabic-Regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let post_data = provider.read_table_data(tag::POST).unwrap();
+        let post = ReadScope::new(&post_data).read::<PostTable<'_>>().unwrap();
+        let glyph_ids = [0, 3, 68, 513];
+        let expected = glyph_ids
+            .iter()
+            .map(|&glyph_id| post.glyph_name(glyph_id).unwrap())
+            .collect::<Vec<_>>();
+
+        let options = SubsetOptions {
+            retain_glyph_names: true,
+            ..SubsetOptions::default()
+        };
+        let subset_data = subset(&provider, &glyph_ids, None, options).unwrap();
+
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        let subset_post_data = subset_provider.read_table_data(tag::POST).unwrap();
+        let subset_post = ReadScope::new(&subset_post_data)
+            .read::<PostTable<'_>>()
+            .unwrap();
+        assert_eq!(subset_post.header.version, 0x00020000);
+        let names = (0..glyph_ids.len() as u16)
+            .map(|glyph_id| subset_post.glyph_name(glyph_id).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, expected);
+        assert!(names.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn subset_ensure_notdef() {
+        let buffer = read_fixture("tests/fonts/noto/NotoNaskhArabic-Regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        match subset(&provider, &[4, 2], None, SubsetOptions::default()) {
+            Err(ReadWriteError::Write(WriteError::BadValue)) => {}
+            _ => panic!("expected ReadWriteError::Write(WriteError::BadValue)"),
+        }
+
+        let options = SubsetOptions {
+            retain_glyph_names: true,
+            ensure_notdef: true,
+            ..SubsetOptions::default()
+        };
+        let (subset_data, new_to_old_glyph_id) =
+            subset_with_mapping(&provider, &[4, 2], None, options).unwrap();
+        assert_eq!(new_to_old_glyph_id, &[0, 4, 2]);
+
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        let subset_post_data = subset_provider.read_table_data(tag::POST).unwrap();
+        let subset_post = ReadScope::new(&subset_post_data)
+            .read::<PostTable<'_>>()
+            .unwrap();
+        assert_eq!(subset_post.glyph_name(0).unwrap(), Some(".notdef"));
+    }
+
+    fn vertical_advances(provider: &impl FontTableProvider, glyph_ids: &[u16]) -> Vec<u16> {
+        let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP).unwrap())
+            .read::<MaxpTable>()
+            .unwrap();
+        let vhea = ReadScope::new(&provider.read_table_data(tag::VHEA).unwrap())
+            .read::<VheaTable>()
+            .unwrap();
+        let vmtx_data = provider.read_table_data(tag::VMTX).unwrap();
+        let vmtx = ReadScope::new(&vmtx_data)
+            .read_dep::<VmtxTable<'_>>((
+                usize::from(maxp.num_glyphs),
+                usize::from(vhea.num_v_metrics),
+            ))
+            .unwrap();
+        glyph_ids
+            .iter()
+            .map(|&glyph_id| {
+                vmtx.horizontal_advance(glyph_id, vhea.num_v_metrics)
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn subset_vertical_metrics() {
+        let buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let glyph_ids = [0, 1, 1000, 5000];
+        let expected = vertical_advances(&provider, &glyph_ids);
+
+        let subset_data = subset(&provider, &glyph_ids, None, SubsetOptions::default()).unwrap();
+
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        assert_eq!(vertical_advances(&subset_provider, &[0, 1, 2, 3]), expected);
+    }
+
+    #[test]
+    fn subset_recomputes_maxp_component_limits() {
+        let buffer = read_fixture("tests/fonts/arabic/ae_Arab.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let component_limits = |glyph_ids: &[u16]| {
+            let subset_data = subset(&provider, glyph_ids, None, SubsetOptions::default()).unwrap();
+            let subset_file = ReadScope::new(&subset_data)
+                .read::<OpenTypeFile<'_>>()
+                .unwrap();
+            let subset_provider = subset_file.font_provider(0).unwrap();
+            let maxp = ReadScope::new(&subset_provider.read_table_data(tag::MAXP).unwrap())
+                .read::<MaxpTable>()
+                .unwrap();
+            let sub_table = maxp.version1_sub_table.unwrap();
+            (
+                sub_table.max_component_elements,
+                sub_table.max_component_depth,
+            )
+        };
+
+        // Glyph 407 is a composite of composite glyph 160 and glyph 115
+        assert_eq!(component_limits(&[0, 407]), (2, 2));
+        // Glyph 160 is a composite of two simple glyphs
+        assert_eq!(component_limits(&[0, 160]), (2, 1));
+        assert_eq!(component_limits(&[0, 1, 2]), (0, 0));
+    }
+
+    #[test]
+    fn subset_collection_face() {
+        let buffer = read_fixture("tests/fonts/opentype/collection.ttc");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(1).unwrap();
+        let glyph_ids = [0, 2, 4];
+        let subset_data = subset(&provider, &glyph_ids, None, SubsetOptions::default()).unwrap();
+
+        // Face 1 of the collection is SFNT-TTF-Composite.ttf
+        let font_buffer = read_fixture("tests/fonts/opentype/SFNT-TTF-Composite.ttf");
+        let font_file = ReadScope::new(&font_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let font_provider = font_file.font_provider(0).unwrap();
+        let expected = subset(&font_provider, &glyph_ids, None, SubsetOptions::default()).unwrap();
+        assert_eq!(subset_data, expected);
+    }
+
+    #[test]
+    fn glyph_closure_ligature() {
+        let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let infos = shape(
+            &provider,
+            tag::LATN,
+            None,
+            Features::default(),
+            ShapeDirection::LeftToRight,
+            "ffi",
+        )
+        .unwrap();
+        assert_eq!(infos.len(), 1);
+        let ffi = infos[0].glyph.glyph_index;
+
+        let closure = glyph_closure(&provider, "ffi".chars(), GsubFeatureMask::LIGA).unwrap();
+        assert_eq!(closure.first(), Some(&0));
+        assert!(closure.contains(&ffi));
+
+        let closure = glyph_closure(&provider, "ffi".chars(), GsubFeatureMask::empty()).unwrap();
+        assert!(!closure.contains(&ffi));
+    }
+
+    #[test]
+    fn cmap_char_range_mac_roman() {
+        use cmap::owned::{Cmap, CmapSubtable, EncodingRecord};
+
+        let mac_roman_cmap = |codes: &[u8]| {
+            let mut glyph_id_array = Box::new([0; 256]);
+            for (glyph_id, &code) in codes.iter().enumerate() {
+                glyph_id_array[usize::from(code)] = glyph_id as u8 + 1;
+            }
+            Cmap {
+                encoding_records: vec![EncodingRecord {
+                    platform_id: 1,
+                    encoding_id: 0,
+                    sub_table: CmapSubtable::Format0 {
+                        language: 0,
+                        glyph_id_array,
+                    },
+                }],
+            }
+        };
+
+        // A, non-breaking space, and bullet
+        let cmap = mac_roman_cmap(&[0x41, 0xCA, 0xA5]);
+        assert_eq!(cmap_char_range(&cmap), Some((0x41, 0x2022)));
+        let cmap = mac_roman_cmap(&[0xCA]);
+        assert_eq!(cmap_char_range(&cmap), Some((0xA0, 0xA0)));
+        assert_eq!(cmap_char_range(&mac_roman_cmap(&[])), None);
+    }
+
+    #[test]
+    fn subset_cff_without_os2() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = WithoutOs2(opentype_file.font_provider(0).unwrap());
+        let subset_data = subset(&provider, &[0, 1, 2], None, SubsetOptions::default()).unwrap();
+
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = subset_file.font_provider(0).unwrap();
+        assert!(subset_provider.has_table(tag::CFF));
+        assert!(!subset_provider.has_table(tag::OS_2));
+    }
+
+    #[test]
+    fn subset_empty_glyph_ids() {
+        for path in &[
+            "tests/fonts/opentype/test-font.ttf",
+            "tests/fonts/opentype/Klei.otf",
+        ] {
+            let buffer = read_fixture(path);
+            let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+            let provider = opentype_file.font_provider(0).unwrap();
+
+            match subset(&provider, &[], None, SubsetOptions::default()) {
+                Err(ReadWriteError::Write(WriteError::BadValue)) => {}
+                _ => panic!("expected WriteError::BadValue for {}", path),
+            }
+            match prince_subset(&provider, &[], None, false) {
+                Err(ReadWriteError::Write(WriteError::BadValue)) => {}
+                _ => panic!("expected WriteError::BadValue for {}", path),
+            }
+
+            // With ensure_notdef the subset contains just the .notdef glyph
+            let options = SubsetOptions {
+                ensure_notdef: true,
+                ..SubsetOptions::default()
+            };
+            let (_, new_to_old_glyph_id) =
+                subset_with_mapping(&provider, &[], None, options).unwrap();
+            assert_eq!(new_to_old_glyph_id, vec![0]);
+        }
+    }
+
+    #[test]
+    fn create_hmtx_table_zero_num_h_metrics() {
+        let hmtx = HmtxTable {
+            h_metrics: ReadArrayCow::Owned(vec![]),
+            left_side_bearings: ReadArrayCow::Owned(vec![10, 20]),
+        };
+        match create_hmtx_table(&hmtx, 2, 0, &[0, 1]) {
+            Err(ReadWriteError::Read(ParseError::BadValue)) => {}
+            _ => panic!("expected ParseError::BadValue"),
+        }
+    }
+
+    #[test]
+    fn subset_strips_dsig() {
+        let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        assert!(provider.has_table(tag::DSIG));
+
+        let subset_data = subset(&provider, &[0, 1, 2], None, SubsetOptions::default()).unwrap();
+        let subset_file = ReadScope::new(&subset_data)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        assert!(!subset_file.font_provider(0).unwrap().has_table(tag::DSIG));
+
+        let tags = match &opentype_file.font {
+            OpenTypeFont::Single(font) => font
+                .table_records
+                .iter()
+                .map(|record| record.table_tag)
+                .collect::<Vec<_>>(),
+            OpenTypeFont::Collection(_) => unreachable!(),
+        };
+        let data = whole_font(&provider, &tags).unwrap();
+        let fontfile = ReadScope::new(&data).read::<OpenTypeFile<'_>>().unwrap();
+        let new_provider = fontfile.font_provider(0).unwrap();
+        assert!(new_provider.has_table(tag::GSUB));
+        assert!(!new_provider.has_table(tag::DSIG));
+    }
+
+    #[test]
+    fn subset_mapping() {
+        for (path, glyph_ids) in &[
+            ("tests/fonts/opentype/test-font.ttf", [0, 2, 1]),
+            ("tests/fonts/opentype/Klei.otf", [0, 36, 37]),
+        ] {
+            let buffer = read_fixture(path);
+            let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+            let provider = opentype_file.font_provider(0).unwrap();
+            let (subset_data, new_to_old_glyph_id) =
+                subset_with_mapping(&provider, glyph_ids, None, SubsetOptions::default()).unwrap();
+
+            let subset_file = ReadScope::new(&subset_data)
+                .read::<OpenTypeFile<'_>>()
+                .unwrap();
+            let subset_provider = subset_file.font_provider(0).unwrap();
+            let maxp = ReadScope::new(&subset_provider.read_table_data(tag::MAXP).unwrap())
+                .read::<MaxpTable>()
+                .unwrap();
+            assert_eq!(new_to_old_glyph_id.len(), usize::from(maxp.num_glyphs));
+            assert_eq!(new_to_old_glyph_id[..glyph_ids.len()], glyph_ids[..]);
+        }
+    }
+
+    #[test]
+    fn build_otf_from_cff_tables() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let fontfile = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let font = match fontfile.font {
+            OpenTypeFont::Single(font) => font,
+            OpenTypeFont::Collection(_) => unreachable!(),
+        };
+        let cff = read_table!(font, fontfile.scope, tag::CFF, CFF<'_>);
+        let head = read_table!(font, fontfile.scope, tag::HEAD, HeadTable);
+        let maxp = read_table!(font, fontfile.scope, tag::MAXP, MaxpTable);
+        let hhea = read_table!(font, fontfile.scope, tag::HHEA, HheaTable);
+        let hmtx = read_table!(
+            font,
+            fontfile.scope,
+            tag::HMTX,
+            HmtxTable<'_>,
+            (
+                usize::from(maxp.num_glyphs),
+                usize::from(hhea.num_h_metrics),
+            )
+        );
+        let os2_data = font
+            .read_table(&fontfile.scope, tag::OS_2)
+            .unwrap()
+            .unwrap();
+        let os2 = os2_data.read_dep::<Os2>(os2_data.data().len()).unwrap();
+        let glyph_ids = (0..maxp.num_glyphs).collect::<Vec<_>>();
+        let cmap = cmap::owned::Cmap {
+            encoding_records: vec![cmap::owned::EncodingRecord {
+                platform_id: 3, // Windows platform
+                encoding_id: 1, // Unicode BMP
+                sub_table: create_cmap_format4(&glyph_ids, &[('A', 36)]),
+            }],
+        };
+
+        let data = build_otf_from_cff(&cff, &head, &hhea, &hmtx, &maxp, &os2, cmap).unwrap();
+
+        let new_fontfile = ReadScope::new(&data).read::<OpenTypeFile<'_>>().unwrap();
+        let new_font = match new_fontfile.font {
+            OpenTypeFont::Single(font) => font,
+            OpenTypeFont::Collection(_) => unreachable!(),
+        };
+        let tags = new_font
+            .table_records
+            .iter()
+            .map(|record| DisplayTag(record.table_tag).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(new_font.sfnt_version, tag::OTTO);
+        assert_eq!(
+            tags,
+            &["CFF ", "OS/2", "cmap", "head", "hhea", "hmtx", "maxp"]
+        );
+        let new_cff = read_table!(new_font, new_fontfile.scope, tag::CFF, CFF<'_>);
+        assert_eq!(
+            new_cff.fonts[0].char_strings_index.len(),
+            usize::from(maxp.num_glyphs)
+        );
+    }
+
+    #[test]
+    fn remap_glyph_ids_owned() {
+        let old_to_new_glyph_id = [(0, 0), (5, 1), (9, 2)].iter().cloned().collect();
+        let mut array = ReadArrayCow::<U16Be>::Owned(vec![9, 7, 5]);
+
+        assert_eq!(
+            remap_glyph_ids(&mut array, &old_to_new_glyph_id, UnmappedGlyphs::Error),
+            Err(ParseError::BadIndex)
+        );
+        assert_eq!(array.iter().collect::<Vec<_>>(), vec![9, 7, 5]);
+
+        remap_glyph_ids(&mut array, &old_to_new_glyph_id, UnmappedGlyphs::Drop).unwrap();
+        assert_eq!(array.iter().collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn whole_font_cff() {
+        let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+        let fontfile = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = fontfile.font_provider(0).unwrap();
+        let tags = [
+            tag::CFF,
+            tag::CMAP,
+            tag::HEAD,
+            tag::HHEA,
+            tag::HMTX,
+            tag::MAXP,
+            tag::NAME,
+            tag::OS_2,
+            tag::POST,
+        ];
+
+        let data = whole_font(&provider, &tags).unwrap();
+
+        let new_fontfile = ReadScope::new(&data).read::<OpenTypeFile<'_>>().unwrap();
+        let new_font = match new_fontfile.font {
+            OpenTypeFont::Single(font) => font,
+            OpenTypeFont::Collection(_) => unreachable!(),
+        };
+        let new_tags = new_font
+            .table_records
+            .iter()
+            .map(|record| DisplayTag(record.table_tag).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(new_font.sfnt_version, tag::OTTO);
+        assert_eq!(
+            new_tags,
+            &["CFF ", "OS/2", "cmap", "head", "hhea", "hmtx", "maxp", "name", "post"]
+        );
+        let cff = read_table!(new_font, new_fontfile.scope, tag::CFF, CFF<'_>);
+        let maxp = read_table!(new_font, new_fontfile.scope, tag::MAXP, MaxpTable);
+        assert_eq!(
+            cff.fonts[0].char_strings_index.len(),
+            usize::from(maxp.num_glyphs)
+        );
+    }
+
     #[test]
     #[cfg(feature = "prince")]
     fn invalid_glyph_id() {
@@ -969,7 +2486,12 @@ mod tests {
         let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
         let glyph_ids = [0, 9999];
 
-        match subset(&opentype_file.font_provider(0).unwrap(), &glyph_ids, None) {
+        match subset(
+            &opentype_file.font_provider(0).unwrap(),
+            &glyph_ids,
+            None,
+            SubsetOptions::default(),
+        ) {
             Err(ReadWriteError::Read(ParseError::BadIndex)) => {}
             _ => panic!("expected ReadWriteError::Read(ParseError::BadIndex) got somthing else"),
         }
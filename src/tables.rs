@@ -1,11 +1,19 @@
 //! OpenType font table parsing and writing.
 
 pub mod cmap;
+pub mod colr;
+pub mod cpal;
+pub mod gasp;
 pub mod glyf;
+pub mod kern;
 pub mod loca;
 pub mod os2;
+pub mod stat;
+#[cfg(feature = "std")]
 pub mod svg;
 
+use bitflags::bitflags;
+
 use crate::binary::read::{
     CheckIndex, ReadArray, ReadArrayCow, ReadBinary, ReadBinaryDep, ReadCtxt, ReadFrom, ReadScope,
 };
@@ -15,8 +23,8 @@ use crate::error::{ParseError, WriteError};
 use crate::size;
 use crate::tag;
 
-use std::borrow::Cow;
-use std::convert::TryFrom;
+use alloc::borrow::Cow;
+use core::convert::TryFrom;
 
 /// Magic value identifying a CFF font (`OTTO`)
 pub const CFF_MAGIC: u32 = tag::OTTO;
@@ -137,13 +145,33 @@ pub struct HeadTable {
     pub glyph_data_format: i16,
 }
 
+bitflags! {
+    /// Style flags of the `macStyle` field of the `head` table
+    ///
+    /// <https://docs.microsoft.com/en-us/typography/opentype/spec/head>
+    pub struct MacStyleFlags: u16 {
+        /// Bit 0: Bold
+        const BOLD = 1 << 0;
+        /// Bit 1: Italic
+        const ITALIC = 1 << 1;
+        /// Bit 2: Underline
+        const UNDERLINE = 1 << 2;
+        /// Bit 3: Outline
+        const OUTLINE = 1 << 3;
+        /// Bit 4: Shadow
+        const SHADOW = 1 << 4;
+        /// Bit 5: Condensed
+        const CONDENSED = 1 << 5;
+        /// Bit 6: Extended
+        const EXTENDED = 1 << 6;
+    }
+}
+
 /// `hhea` horizontal header table
 ///
 /// > This table contains information for horizontal layout.
 ///
 /// <https://docs.microsoft.com/en-us/typography/opentype/spec/hhea>
-///
-/// This struct is also used for the `vhea` table.
 pub struct HheaTable {
     pub ascender: i16,
     pub descender: i16,
@@ -169,6 +197,38 @@ pub struct HmtxTable<'a> {
     pub left_side_bearings: ReadArrayCow<'a, I16Be>,
 }
 
+/// `vhea` vertical header table
+///
+/// > This table contains information for vertical layout.
+///
+/// <https://docs.microsoft.com/en-us/typography/opentype/spec/vhea>
+pub struct VheaTable {
+    /// Either 0x00010000 for version 1.0 or 0x00011000 for version 1.1
+    pub version: Fixed,
+    /// `ascent` in version 1.0, `vertTypoAscender` in version 1.1
+    pub ascender: i16,
+    /// `descent` in version 1.0, `vertTypoDescender` in version 1.1
+    pub descender: i16,
+    /// `lineGap` in version 1.0, `vertTypoLineGap` in version 1.1
+    pub line_gap: i16,
+    pub advance_height_max: u16,
+    pub min_top_side_bearing: i16,
+    pub min_bottom_side_bearing: i16,
+    pub y_max_extent: i16,
+    pub caret_slope_rise: i16,
+    pub caret_slope_run: i16,
+    pub caret_offset: i16,
+    pub num_v_metrics: u16,
+}
+
+/// `vmtx` vertical metrics table
+///
+/// <https://docs.microsoft.com/en-us/typography/opentype/spec/vmtx>
+///
+/// The `vmtx` table shares the layout of `hmtx`: `h_metrics` holds the vertical advances and top
+/// side bearings, and `left_side_bearings` holds the trailing top side bearings.
+pub type VmtxTable<'a> = HmtxTable<'a>;
+
 /// A `longHorMetric` record in the `hmtx` table.
 ///
 /// <https://docs.microsoft.com/en-us/typography/opentype/spec/hmtx>
@@ -252,6 +312,10 @@ pub struct LangTagRecord {
 }
 
 impl<'a> OpenTypeFile<'a> {
+    /// Returns a `FontTableProvider` for the font at `index`.
+    ///
+    /// For a TrueType Collection `index` selects the face, and the tables that the faces share
+    /// are read from the same data. `index` is ignored for a single font.
     pub fn font_provider(
         &'a self,
         index: usize,
@@ -497,22 +561,22 @@ impl<'a> WriteBinary<&Self> for HeadTable {
 }
 
 impl HeadTable {
-    // macStyle:
-    // Bit 0: Bold (if set to 1);
-    // Bit 1: Italic (if set to 1)
-    // Bit 2: Underline (if set to 1)
-    // Bit 3: Outline (if set to 1)
-    // Bit 4: Shadow (if set to 1)
-    // Bit 5: Condensed (if set to 1)
-    // Bit 6: Extended (if set to 1)
-    // Bits 7–15: Reserved (set to 0).
-    // https://docs.microsoft.com/en-us/typography/opentype/spec/head
+    /// The number of font design units per em.
+    pub fn units_per_em(&self) -> u16 {
+        self.units_per_em
+    }
+
+    /// The `macStyle` field as flags, reserved bits are discarded.
+    pub fn mac_style(&self) -> MacStyleFlags {
+        MacStyleFlags::from_bits_truncate(self.mac_style)
+    }
+
     pub fn is_bold(&self) -> bool {
-        self.mac_style & 1 != 0
+        self.mac_style().contains(MacStyleFlags::BOLD)
     }
 
     pub fn is_italic(&self) -> bool {
-        self.mac_style & 2 != 0
+        self.mac_style().contains(MacStyleFlags::ITALIC)
     }
 }
 
@@ -557,6 +621,77 @@ impl<'a> ReadBinary<'a> for HheaTable {
     }
 }
 
+impl<'a> ReadBinary<'a> for VheaTable {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let version = ctxt.read_u32be()?;
+        ctxt.check(version == 0x00010000 || version == 0x00011000)?;
+        let ascender = ctxt.read_i16be()?;
+        let descender = ctxt.read_i16be()?;
+        let line_gap = ctxt.read_i16be()?;
+        let advance_height_max = ctxt.read_u16be()?;
+        let min_top_side_bearing = ctxt.read_i16be()?;
+        let min_bottom_side_bearing = ctxt.read_i16be()?;
+        let y_max_extent = ctxt.read_i16be()?;
+        let caret_slope_rise = ctxt.read_i16be()?;
+        let caret_slope_run = ctxt.read_i16be()?;
+        let caret_offset = ctxt.read_i16be()?;
+        let _reserved1 = ctxt.read_i16be()?;
+        let _reserved2 = ctxt.read_i16be()?;
+        let _reserved3 = ctxt.read_i16be()?;
+        let _reserved4 = ctxt.read_i16be()?;
+        let metric_data_format = ctxt.read_i16be()?;
+        ctxt.check(metric_data_format == 0)?;
+        let num_v_metrics = ctxt.read_u16be()?;
+
+        Ok(VheaTable {
+            version,
+            ascender,
+            descender,
+            line_gap,
+            advance_height_max,
+            min_top_side_bearing,
+            min_bottom_side_bearing,
+            y_max_extent,
+            caret_slope_rise,
+            caret_slope_run,
+            caret_offset,
+            num_v_metrics,
+        })
+    }
+}
+
+impl WriteBinary<&Self> for VheaTable {
+    type Output = ();
+
+    fn write<C: WriteContext>(ctxt: &mut C, table: &VheaTable) -> Result<(), WriteError> {
+        U32Be::write(ctxt, table.version)?;
+
+        I16Be::write(ctxt, table.ascender)?;
+        I16Be::write(ctxt, table.descender)?;
+        I16Be::write(ctxt, table.line_gap)?;
+        U16Be::write(ctxt, table.advance_height_max)?;
+        I16Be::write(ctxt, table.min_top_side_bearing)?;
+        I16Be::write(ctxt, table.min_bottom_side_bearing)?;
+        I16Be::write(ctxt, table.y_max_extent)?;
+        I16Be::write(ctxt, table.caret_slope_rise)?;
+        I16Be::write(ctxt, table.caret_slope_run)?;
+        I16Be::write(ctxt, table.caret_offset)?;
+
+        I16Be::write(ctxt, 0i16)?; // reserved
+        I16Be::write(ctxt, 0i16)?; // reserved
+        I16Be::write(ctxt, 0i16)?; // reserved
+        I16Be::write(ctxt, 0i16)?; // reserved
+
+        I16Be::write(ctxt, 0i16)?; // metric_data_format
+
+        U16Be::write(ctxt, table.num_v_metrics)?;
+
+        Ok(())
+    }
+}
+
 impl<'a> WriteBinary<&Self> for HheaTable {
     type Output = ();
 
@@ -596,6 +731,8 @@ impl<'a> ReadBinaryDep<'a> for HmtxTable<'a> {
         ctxt: &mut ReadCtxt<'a>,
         (num_glyphs, num_h_metrics): (usize, usize),
     ) -> Result<Self, ParseError> {
+        // There must be at least one record as glyphs past the last one take its advance
+        ctxt.check(num_h_metrics > 0)?;
         let h_metrics = ctxt.read_array::<LongHorMetric>(num_h_metrics)?;
         let left_side_bearings =
             ctxt.read_array::<I16Be>(num_glyphs.saturating_sub(num_h_metrics))?;
@@ -633,6 +770,25 @@ impl<'a> HmtxTable<'a> {
             .and_then(|_| self.h_metrics.read_item(index))
             .map(|long_hor_metric| long_hor_metric.advance_width)
     }
+
+    /// The left side bearing of `glyph_id`.
+    ///
+    /// Glyphs past the last of the `num_h_metrics` records take their left side bearing from
+    /// `left_side_bearings`.
+    pub fn left_side_bearing(&self, glyph_id: u16, num_h_metrics: u16) -> Result<i16, ParseError> {
+        if glyph_id < num_h_metrics {
+            let index = usize::from(glyph_id);
+            self.h_metrics
+                .check_index(index)
+                .and_then(|_| self.h_metrics.read_item(index))
+                .map(|long_hor_metric| long_hor_metric.lsb)
+        } else {
+            let index = usize::from(glyph_id - num_h_metrics);
+            self.left_side_bearings
+                .check_index(index)
+                .and_then(|_| self.left_side_bearings.read_item(index))
+        }
+    }
 }
 
 impl<'a> ReadFrom<'a> for LongHorMetric {
@@ -783,7 +939,7 @@ impl<'a> WriteBinary<&Self> for NameTable<'a> {
         let format = name.opt_langtag_records.as_ref().map_or(0u16, |_| 1);
         U16Be::write(ctxt, format)?;
         U16Be::write(ctxt, u16::try_from(name.name_records.len())?)?; // count
-        let string_offset = ctxt.placeholder::<U16Be, _>()?;
+        let string_offset = ctxt.reserve_u16()?;
         <&ReadArray<'a, _>>::write(ctxt, &name.name_records)?;
 
         if let Some(lang_tag_records) = &name.opt_langtag_records {
@@ -791,7 +947,7 @@ impl<'a> WriteBinary<&Self> for NameTable<'a> {
             <&ReadArray<'a, _>>::write(ctxt, lang_tag_records)?;
         }
 
-        ctxt.write_placeholder(string_offset, u16::try_from(ctxt.bytes_written())?)?;
+        ctxt.patch_u16(string_offset, u16::try_from(ctxt.bytes_written())?)?;
         ctxt.write_bytes(name.string_storage.data())?;
 
         Ok(())
@@ -897,11 +1053,24 @@ impl F2Dot14 {
     }
 }
 
+impl From<F2Dot14> for f32 {
+    fn from(value: F2Dot14) -> Self {
+        f32::from(value.0 as i16) / 16384.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{HeadTable, HmtxTable, NameTable};
-    use crate::binary::read::ReadScope;
+    use super::{
+        FontTableProvider, HeadTable, HmtxTable, LongHorMetric, MacStyleFlags, NameTable,
+        OpenTypeFile, OpenTypeFont, VheaTable,
+    };
+    use crate::binary::read::{ReadArrayCow, ReadScope};
     use crate::binary::write::{WriteBinary, WriteBuffer, WriteContext};
+    use crate::error::ParseError;
+    use crate::tag;
+    use crate::tests::read_fixture;
+    use std::borrow::Cow;
 
     #[test]
     fn test_write_head_table() {
@@ -918,6 +1087,47 @@ mod tests {
         assert_eq!(ctxt.bytes(), &head_data[..]);
     }
 
+    #[test]
+    fn test_write_vhea_table_version_1_1() {
+        #[rustfmt::skip]
+        let vhea_data = [
+            0x00, 0x01, 0x10, 0x00, // version 1.1
+            0x03, 0x70, 0xFC, 0x90, 0x00, 0x00, // vertTypoAscender, Descender, LineGap
+            0x04, 0x00, 0xFF, 0xC0, 0x00, 0x10, 0x03, 0xE8, // advanceHeightMax to yMaxExtent
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, // caret slope rise, run, offset
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // reserved
+            0x00, 0x00, // metricDataFormat
+            0x00, 0x05, // numOfLongVerMetrics
+        ];
+        let vhea = ReadScope::new(&vhea_data).read::<VheaTable>().unwrap();
+        assert_eq!(vhea.version, 0x00011000);
+        assert_eq!(vhea.ascender, 880);
+        assert_eq!(vhea.descender, -880);
+        assert_eq!(vhea.num_v_metrics, 5);
+
+        let mut ctxt = WriteBuffer::new();
+        VheaTable::write(&mut ctxt, &vhea).unwrap();
+        assert_eq!(ctxt.bytes(), &vhea_data[..]);
+    }
+
+    #[test]
+    fn test_head_units_per_em_and_mac_style() {
+        let head_data = include_bytes!("../tests/fonts/opentype/head.bin");
+        let mut head = ReadScope::new(head_data).read::<HeadTable>().unwrap();
+        assert_eq!(head.units_per_em(), 2048);
+        assert_eq!(head.mac_style(), MacStyleFlags::empty());
+        assert!(!head.is_bold());
+
+        // Bold, Italic, and a reserved bit
+        head.mac_style = 0x8003;
+        assert_eq!(
+            head.mac_style(),
+            MacStyleFlags::BOLD | MacStyleFlags::ITALIC
+        );
+        assert!(head.is_bold());
+        assert!(head.is_italic());
+    }
+
     #[test]
     fn test_write_hmtx_table() {
         // Read a hmtx table in, then write it back out and compare it
@@ -934,6 +1144,41 @@ mod tests {
         assert_eq!(ctxt.bytes(), &hmtx_data[..]);
     }
 
+    #[test]
+    fn test_hmtx_metrics() {
+        let hmtx = HmtxTable {
+            h_metrics: ReadArrayCow::Owned(vec![
+                LongHorMetric {
+                    advance_width: 500,
+                    lsb: 10,
+                },
+                LongHorMetric {
+                    advance_width: 600,
+                    lsb: 20,
+                },
+            ]),
+            left_side_bearings: ReadArrayCow::Owned(vec![30, 40]),
+        };
+        let num_h_metrics = 2;
+
+        assert_eq!(hmtx.horizontal_advance(1, num_h_metrics), Ok(600));
+        assert_eq!(hmtx.left_side_bearing(1, num_h_metrics), Ok(20));
+        // Glyphs past num_h_metrics use the advance of the last record
+        assert_eq!(hmtx.horizontal_advance(3, num_h_metrics), Ok(600));
+        assert_eq!(hmtx.left_side_bearing(3, num_h_metrics), Ok(40));
+        assert_eq!(
+            hmtx.left_side_bearing(4, num_h_metrics),
+            Err(ParseError::BadIndex)
+        );
+    }
+
+    #[test]
+    fn test_read_hmtx_zero_num_h_metrics() {
+        let hmtx_data = [0, 10, 0, 20];
+        let hmtx = ReadScope::new(&hmtx_data).read_dep::<HmtxTable<'_>>((2, 0));
+        assert_eq!(hmtx.err(), Some(ParseError::BadValue));
+    }
+
     #[test]
     fn test_write_name_table() {
         // Read a name table in, then write it back out and compare it
@@ -945,4 +1190,26 @@ mod tests {
 
         assert_eq!(ctxt.bytes(), &name_data[..]);
     }
+
+    #[test]
+    fn test_table_data_borrowed() {
+        let buffer = read_fixture("tests/fonts/opentype/test-font.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+
+        // The table data should borrow from the font data rather than be a copy of it
+        let head = match provider.table_data(tag::HEAD).unwrap().unwrap() {
+            Cow::Borrowed(data) => data,
+            Cow::Owned(_) => panic!("expected borrowed table data"),
+        };
+        let record = match &opentype_file.font {
+            OpenTypeFont::Single(offset_table) => offset_table.find_table_record(tag::HEAD),
+            OpenTypeFont::Collection(_) => None,
+        }
+        .unwrap();
+        let offset = record.offset as usize;
+        assert_eq!(head.as_ptr(), buffer[offset..].as_ptr());
+        assert_eq!(head.len(), record.length as usize);
+        assert!(provider.table_data(tag::CFF).unwrap().is_none());
+    }
 }
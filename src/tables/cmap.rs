@@ -6,14 +6,20 @@
 //!
 //! — <https://docs.microsoft.com/en-us/typography/opentype/spec/cmap>
 
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::convert::TryFrom;
 
+#[cfg(feature = "std")]
 use itertools::izip;
 
-use crate::binary::read::{CheckIndex, ReadArray, ReadBinary, ReadCtxt, ReadFrom, ReadScope};
+use crate::binary::read::{
+    CheckIndex, ReadArray, ReadBinary, ReadCtxt, ReadFixedSizeDep, ReadFrom, ReadScope,
+    ReadUnchecked,
+};
 use crate::binary::write::{WriteBinary, WriteContext};
-use crate::binary::{I16Be, U16Be, U32Be, U8};
+use crate::binary::{I16Be, U16Be, U24Be, U32Be, U8};
 use crate::error::{ParseError, WriteError};
 use crate::size;
 
@@ -47,6 +53,7 @@ impl EncodingId {
 
     pub const MACINTOSH_APPLE_ROMAN: EncodingId = EncodingId(0);
     pub const MACINTOSH_UNICODE_UCS4: EncodingId = EncodingId(4);
+    pub const MACINTOSH_UNICODE_VARIATION_SEQUENCES: EncodingId = EncodingId(5);
 }
 
 pub struct Cmap<'a> {
@@ -61,6 +68,14 @@ pub struct EncodingRecord {
     pub offset: u32,
 }
 
+#[derive(Copy, Clone)]
+pub enum Encoding {
+    Unicode = 1,
+    Symbol = 2,
+    AppleRoman = 3,
+    Big5 = 4,
+}
+
 pub enum CmapSubtable<'a> {
     Format0 {
         language: u16,
@@ -94,6 +109,19 @@ pub enum CmapSubtable<'a> {
         language: u32,
         groups: ReadArray<'a, SequentialMapGroup>,
     },
+    Format14 {
+        scope: ReadScope<'a>,
+        var_selector_records: ReadArray<'a, VariationSelectorRecord>,
+    },
+}
+
+/// The glyph that a Unicode variation sequence maps to in a format 14 sub-table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VariationGlyph {
+    /// The sequence maps to the default glyph of the base character.
+    Default,
+    /// The sequence maps to the glyph with the given id.
+    NonDefault(u16),
 }
 
 // cmap subtable format 2 sub-header
@@ -115,6 +143,25 @@ pub struct SequentialMapGroup {
     start_glyph_id: u32,
 }
 
+// cmap subtable format 14 variation selector record
+pub struct VariationSelectorRecord {
+    var_selector: u32,
+    default_uvs_offset: u32,
+    non_default_uvs_offset: u32,
+}
+
+// Range of characters in a format 14 default UVS table
+struct UnicodeRange {
+    start_unicode_value: u32,
+    additional_count: u8,
+}
+
+// Mapping from a character to a glyph in a format 14 non-default UVS table
+struct UvsMapping {
+    unicode_value: u32,
+    glyph_id: u16,
+}
+
 impl<'a> ReadBinary<'a> for Cmap<'a> {
     type HostType = Self;
 
@@ -146,6 +193,7 @@ impl<'a> ReadBinary<'a> for CmapSubtable<'a> {
     type HostType = Self;
 
     fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
         let subtable_format = ctxt.read_u16be()?;
         match subtable_format {
             0 => {
@@ -241,6 +289,16 @@ impl<'a> ReadBinary<'a> for CmapSubtable<'a> {
                 let groups = ctxt.read_array::<SequentialMapGroup>(num_groups)?;
                 Ok(CmapSubtable::Format12 { language, groups })
             }
+            14 => {
+                let length = usize::try_from(ctxt.read_u32be()?)?;
+                let num_var_selector_records = usize::try_from(ctxt.read_u32be()?)?;
+                let var_selector_records =
+                    ctxt.read_array::<VariationSelectorRecord>(num_var_selector_records)?;
+                Ok(CmapSubtable::Format14 {
+                    scope: scope.offset_length(0, length)?,
+                    var_selector_records,
+                })
+            }
             _ => Err(ParseError::BadVersion),
         }
     }
@@ -279,7 +337,7 @@ impl<'a> WriteBinary<&Self> for CmapSubtable<'a> {
                 };
 
                 U16Be::write(ctxt, 4u16)?; // format
-                let length = ctxt.placeholder::<U16Be, _>()?;
+                let length = ctxt.reserve_u16()?;
                 U16Be::write(ctxt, *language)?;
                 U16Be::write(ctxt, calc.seg_count_x2())?;
                 U16Be::write(ctxt, calc.search_range())?;
@@ -291,7 +349,7 @@ impl<'a> WriteBinary<&Self> for CmapSubtable<'a> {
                 <&ReadArray<'_, _>>::write(ctxt, id_deltas)?;
                 <&ReadArray<'_, _>>::write(ctxt, id_range_offsets)?;
                 <&ReadArray<'_, _>>::write(ctxt, glyph_id_array)?;
-                ctxt.write_placeholder(length, u16::try_from(ctxt.bytes_written() - start)?)?;
+                ctxt.patch_u16(length, u16::try_from(ctxt.bytes_written() - start)?)?;
             }
             CmapSubtable::Format6 {
                 language,
@@ -301,12 +359,12 @@ impl<'a> WriteBinary<&Self> for CmapSubtable<'a> {
                 let start = ctxt.bytes_written();
 
                 U16Be::write(ctxt, 6u16)?; // format
-                let length = ctxt.placeholder::<U16Be, _>()?;
+                let length = ctxt.reserve_u16()?;
                 U16Be::write(ctxt, *language)?;
                 U16Be::write(ctxt, *first_code)?;
                 U16Be::write(ctxt, u16::try_from(glyph_id_array.len())?)?;
                 <&ReadArray<'_, _>>::write(ctxt, glyph_id_array)?;
-                ctxt.write_placeholder(length, u16::try_from(ctxt.bytes_written() - start)?)?;
+                ctxt.patch_u16(length, u16::try_from(ctxt.bytes_written() - start)?)?;
             }
             CmapSubtable::Format10 {
                 language,
@@ -317,23 +375,28 @@ impl<'a> WriteBinary<&Self> for CmapSubtable<'a> {
 
                 U16Be::write(ctxt, 10u16)?; // format
                 U16Be::write(ctxt, 0u16)?; // reserved
-                let length = ctxt.placeholder::<U32Be, _>()?;
+                let length = ctxt.reserve_u32()?;
                 U32Be::write(ctxt, *language)?;
                 U32Be::write(ctxt, *start_char_code)?;
                 U32Be::write(ctxt, u32::try_from(glyph_id_array.len())?)?;
                 <&ReadArray<'_, _>>::write(ctxt, glyph_id_array)?;
-                ctxt.write_placeholder(length, u32::try_from(ctxt.bytes_written() - start)?)?;
+                ctxt.patch_u32(length, u32::try_from(ctxt.bytes_written() - start)?)?;
             }
             CmapSubtable::Format12 { language, groups } => {
                 let start = ctxt.bytes_written();
 
                 U16Be::write(ctxt, 12u16)?; // format
                 U16Be::write(ctxt, 0u16)?; // reserved
-                let length = ctxt.placeholder::<U32Be, _>()?;
+                let length = ctxt.reserve_u32()?;
                 U32Be::write(ctxt, *language)?;
                 U32Be::write(ctxt, u32::try_from(groups.len())?)?;
                 <&ReadArray<'_, _>>::write(ctxt, groups)?;
-                ctxt.write_placeholder(length, u32::try_from(ctxt.bytes_written() - start)?)?;
+                ctxt.patch_u32(length, u32::try_from(ctxt.bytes_written() - start)?)?;
+            }
+            CmapSubtable::Format14 { scope, .. } => {
+                // The sub-table is written unchanged as the offsets within it are relative to
+                // its start
+                ctxt.write_bytes(scope.data())?;
             }
         }
 
@@ -347,11 +410,12 @@ impl Format4Calculator {
     }
 
     fn search_range(self) -> u16 {
-        2 * (2u16.pow((self.seg_count as f64).log2().floor() as u32))
+        2 * (1 << self.entry_selector())
     }
 
     fn entry_selector(self) -> u16 {
-        (self.search_range() as f64 / 2.).log2() as u16
+        // floor(log2(seg_count)), computed without floating point so it's available in `no_std`
+        15u32.saturating_sub(self.seg_count.leading_zeros()) as u16
     }
 
     fn range_shift(self) -> u16 {
@@ -410,6 +474,37 @@ impl SubHeader {
     }
 }
 
+impl<'a> ReadFrom<'a> for VariationSelectorRecord {
+    type ReadType = (U24Be, U32Be, U32Be);
+    fn from((var_selector, default_uvs_offset, non_default_uvs_offset): (u32, u32, u32)) -> Self {
+        VariationSelectorRecord {
+            var_selector,
+            default_uvs_offset,
+            non_default_uvs_offset,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for UnicodeRange {
+    type ReadType = (U24Be, U8);
+    fn from((start_unicode_value, additional_count): (u32, u8)) -> Self {
+        UnicodeRange {
+            start_unicode_value,
+            additional_count,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for UvsMapping {
+    type ReadType = (U24Be, U16Be);
+    fn from((unicode_value, glyph_id): (u32, u16)) -> Self {
+        UvsMapping {
+            unicode_value,
+            glyph_id,
+        }
+    }
+}
+
 impl<'a> ReadFrom<'a> for SequentialMapGroup {
     type ReadType = (U32Be, U32Be, U32Be);
     fn from((start_char_code, end_char_code, start_glyph_id): (u32, u32, u32)) -> Self {
@@ -455,6 +550,59 @@ impl<'a> Cmap<'a> {
     pub fn encoding_records(&self) -> impl Iterator<Item = EncodingRecord> + 'a {
         self.encoding_records.iter()
     }
+
+    /// Map the Unicode variation sequence of `base` followed by `selector` to a glyph id.
+    ///
+    /// The sequence is looked up in the Unicode Variation Sequences (format 14) sub-table. Default
+    /// variation sequences map to the glyph for `base` in the best Unicode sub-table. Returns
+    /// `None` if the font has no format 14 sub-table or does not support the sequence.
+    #[cfg(feature = "std")]
+    pub fn map_variation_glyph(
+        &self,
+        base: char,
+        selector: char,
+    ) -> Result<Option<u16>, ParseError> {
+        let encoding_record = match self.find_subtable(
+            PlatformId::UNICODE,
+            EncodingId::MACINTOSH_UNICODE_VARIATION_SEQUENCES,
+        ) {
+            Some(encoding_record) => encoding_record,
+            None => return Ok(None),
+        };
+        let variation_subtable = self
+            .scope
+            .offset(usize::try_from(encoding_record.offset)?)
+            .read::<CmapSubtable<'_>>()?;
+
+        match variation_subtable.map_variation_sequence(u32::from(base), u32::from(selector))? {
+            Some(VariationGlyph::Default) => match read_cmap_subtable(self)? {
+                Some((Encoding::Unicode, cmap_subtable)) => {
+                    cmap_subtable.map_glyph(u32::from(base))
+                }
+                _ => Ok(None),
+            },
+            Some(VariationGlyph::NonDefault(glyph_id)) => Ok(Some(glyph_id)),
+            None => Ok(None),
+        }
+    }
+
+    /// Build a map of glyph ids to the characters that map to them in the Unicode `subtable`.
+    ///
+    /// This is the inverse of `CmapSubtable::map_glyph`, for recovering the text of glyphs such as
+    /// when building a PDF ToUnicode CMap. It is best-effort: if more than one character maps to
+    /// the same glyph the lowest is kept, and glyphs formed by `GSUB`, such as ligatures, are
+    /// absent, as is `.notdef`. `subtable` must use a Unicode encoding.
+    #[cfg(feature = "std")]
+    pub fn build_reverse_map(
+        subtable: &CmapSubtable<'_>,
+    ) -> Result<HashMap<u16, char>, ParseError> {
+        let mappings = subtable.mappings()?;
+        Ok(mappings
+            .into_iter()
+            .filter(|&(glyph_id, _)| glyph_id != 0)
+            .filter_map(|(glyph_id, ch)| char::try_from(ch).ok().map(|ch| (glyph_id, ch)))
+            .collect())
+    }
 }
 
 impl<'a> CmapSubtable<'a> {
@@ -599,17 +747,80 @@ impl<'a> CmapSubtable<'a> {
                 }
                 Ok(None)
             }
+            // Format 14 maps variation sequences rather than single characters, see
+            // `map_variation_sequence`
+            CmapSubtable::Format14 { .. } => Ok(None),
+        }
+    }
+
+    /// Look up the Unicode variation sequence of `base` followed by `selector`.
+    ///
+    /// Only format 14 sub-tables contain variation sequences, `None` is returned for sub-tables in
+    /// other formats and for sequences that are not in the sub-table.
+    pub fn map_variation_sequence(
+        &self,
+        base: u32,
+        selector: u32,
+    ) -> Result<Option<VariationGlyph>, ParseError> {
+        let (scope, var_selector_records) = match self {
+            CmapSubtable::Format14 {
+                scope,
+                var_selector_records,
+            } => (scope, var_selector_records),
+            _ => return Ok(None),
+        };
+        let record = match binary_search(var_selector_records, |record| {
+            record.var_selector.cmp(&selector)
+        }) {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+
+        if record.default_uvs_offset != 0 {
+            let mut ctxt = scope
+                .offset(usize::try_from(record.default_uvs_offset)?)
+                .ctxt();
+            let num_ranges = usize::try_from(ctxt.read_u32be()?)?;
+            let ranges = ctxt.read_array::<UnicodeRange>(num_ranges)?;
+            let found = binary_search(&ranges, |range| {
+                if range.start_unicode_value > base {
+                    Ordering::Greater
+                } else if range.start_unicode_value + u32::from(range.additional_count) < base {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            });
+            if found.is_some() {
+                return Ok(Some(VariationGlyph::Default));
+            }
+        }
+
+        if record.non_default_uvs_offset != 0 {
+            let mut ctxt = scope
+                .offset(usize::try_from(record.non_default_uvs_offset)?)
+                .ctxt();
+            let num_mappings = usize::try_from(ctxt.read_u32be()?)?;
+            let mappings = ctxt.read_array::<UvsMapping>(num_mappings)?;
+            if let Some(mapping) =
+                binary_search(&mappings, |mapping| mapping.unicode_value.cmp(&base))
+            {
+                return Ok(Some(VariationGlyph::NonDefault(mapping.glyph_id)));
+            }
         }
+
+        Ok(None)
     }
 
     /// Extract all the mappings from the sub-table.
     ///
     /// The returned `HashMap` maps glyph indexes to char codes. If more than one char code maps to
-    /// the same glyph, the `HashMap` will contain the **first** mapping encountered. Also note that
+    /// the same glyph, the `HashMap` will contain the **lowest** char code. Also note that
     /// the char code is not necessarily Unicode. It depends on on the encoding of the cmap
     /// sub-table.
     ///
     /// This method primarily exists to support [GlyphNames](crate::glyph_info::GlyphNames).
+    #[cfg(feature = "std")]
     pub(crate) fn mappings(&self) -> Result<HashMap<u16, u32>, ParseError> {
         match self {
             CmapSubtable::Format0 {
@@ -619,7 +830,7 @@ impl<'a> CmapSubtable<'a> {
                 let mut mappings = HashMap::with_capacity(glyph_id_array.len());
                 for (ch, gid) in glyph_id_array.iter().enumerate() {
                     // cast is safe as format 0 can only contain 256 glyphs
-                    mappings.entry(u16::from(gid)).or_insert(ch as u32);
+                    insert_mapping(&mut mappings, u16::from(gid), ch as u32);
                 }
                 Ok(mappings)
             }
@@ -658,7 +869,7 @@ impl<'a> CmapSubtable<'a> {
                             ((i32::from(glyph_id_array.get_item(index)) + i32::from(id_delta))
                                 & 0xFFFF) as u16
                         };
-                        mappings.entry(glyph_id).or_insert(u32::from(ch));
+                        insert_mapping(&mut mappings, glyph_id, u32::from(ch));
                     }
                 }
                 Ok(mappings)
@@ -671,9 +882,7 @@ impl<'a> CmapSubtable<'a> {
                 let mut mappings = HashMap::with_capacity(glyph_id_array.len());
                 for (index, gid) in glyph_id_array.iter().enumerate() {
                     // cast is safe as the entryCount of the glyphIdArray is a 16-bit value
-                    mappings
-                        .entry(gid)
-                        .or_insert(u32::from(*first_code) + index as u32);
+                    insert_mapping(&mut mappings, gid, u32::from(*first_code) + index as u32);
                 }
                 Ok(mappings)
             }
@@ -685,7 +894,7 @@ impl<'a> CmapSubtable<'a> {
                 let mut mappings = HashMap::with_capacity(glyph_id_array.len());
                 for (index, gid) in glyph_id_array.iter().enumerate() {
                     let index = u32::try_from(index)?;
-                    mappings.entry(gid).or_insert(*start_char_code + index);
+                    insert_mapping(&mut mappings, gid, *start_char_code + index);
                 }
                 Ok(mappings)
             }
@@ -693,15 +902,138 @@ impl<'a> CmapSubtable<'a> {
                 let mut mappings = HashMap::new();
                 for record in groups.iter() {
                     for (i, ch) in (record.start_char_code..=record.end_char_code).enumerate() {
-                        mappings
-                            .entry(u16::try_from(record.start_glyph_id)? + u16::try_from(i)?)
-                            .or_insert(ch);
+                        let glyph_id = u16::try_from(record.start_glyph_id)? + u16::try_from(i)?;
+                        insert_mapping(&mut mappings, glyph_id, ch);
                     }
                 }
                 Ok(mappings)
             }
+            CmapSubtable::Format14 { .. } => Ok(HashMap::new()),
+        }
+    }
+}
+
+/// Insert the mapping of `glyph_id` to `ch`, keeping the lowest char code if it is already mapped
+pub fn read_cmap_subtable<'a>(
+    cmap: &Cmap<'a>,
+) -> Result<Option<(Encoding, CmapSubtable<'a>)>, ParseError> {
+    if let Some((encoding, encoding_record)) = find_good_cmap_subtable(cmap) {
+        let subtable = cmap
+            .scope
+            .offset(usize::try_from(encoding_record.offset)?)
+            .read::<CmapSubtable<'_>>()?;
+        Ok(Some((encoding, subtable)))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn find_good_cmap_subtable(cmap: &Cmap<'_>) -> Option<(Encoding, EncodingRecord)> {
+    // Any UNICODE table in format 12, as it can map characters outside the BMP
+    if let Some(encoding_record) = cmap.encoding_records().find(|record| {
+        is_unicode_encoding(record) && cmap_subtable_format(cmap, record) == Some(12)
+    }) {
+        return Some((Encoding::Unicode, encoding_record));
+    }
+
+    // MS UNICODE, UCS-4 (32 bit)
+    if let Some(encoding_record) =
+        cmap.find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_UNICODE_UCS4)
+    {
+        return Some((Encoding::Unicode, encoding_record));
+    }
+
+    // MS UNICODE, UCS-2 (16 bit)
+    if let Some(encoding_record) =
+        cmap.find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_UNICODE_BMP_UCS2)
+    {
+        return Some((Encoding::Unicode, encoding_record));
+    }
+
+    // Apple UNICODE, UCS-4 (32 bit)
+    if let Some(encoding_record) =
+        cmap.find_subtable(PlatformId::UNICODE, EncodingId::MACINTOSH_UNICODE_UCS4)
+    {
+        return Some((Encoding::Unicode, encoding_record));
+    }
+
+    // Any UNICODE table, other than the variation sequences table, which does not map single
+    // characters
+    if let Some(encoding_record) = cmap.encoding_records().find(|record| {
+        record.platform_id == PlatformId::UNICODE.0
+            && record.encoding_id != EncodingId::MACINTOSH_UNICODE_VARIATION_SEQUENCES.0
+    }) {
+        return Some((Encoding::Unicode, encoding_record));
+    }
+
+    // MS Symbol
+    if let Some(encoding_record) =
+        cmap.find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_SYMBOL)
+    {
+        return Some((Encoding::Symbol, encoding_record));
+    }
+
+    // Apple Roman
+    if let Some(encoding_record) =
+        cmap.find_subtable(PlatformId::MACINTOSH, EncodingId::MACINTOSH_APPLE_ROMAN)
+    {
+        return Some((Encoding::AppleRoman, encoding_record));
+    }
+
+    // Big5
+    if let Some(encoding_record) = cmap.find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_BIG5)
+    {
+        return Some((Encoding::Big5, encoding_record));
+    }
+
+    None
+}
+
+fn is_unicode_encoding(record: &EncodingRecord) -> bool {
+    match PlatformId(record.platform_id) {
+        PlatformId::UNICODE => {
+            record.encoding_id != EncodingId::MACINTOSH_UNICODE_VARIATION_SEQUENCES.0
+        }
+        PlatformId::WINDOWS => {
+            record.encoding_id == EncodingId::WINDOWS_UNICODE_BMP_UCS2.0
+                || record.encoding_id == EncodingId::WINDOWS_UNICODE_UCS4.0
+        }
+        _ => false,
+    }
+}
+
+fn cmap_subtable_format(cmap: &Cmap<'_>, record: &EncodingRecord) -> Option<u16> {
+    let offset = usize::try_from(record.offset).ok()?;
+    cmap.scope.offset(offset).ctxt().read_u16be().ok()
+}
+
+#[cfg(feature = "std")]
+fn insert_mapping(mappings: &mut HashMap<u16, u32>, glyph_id: u16, ch: u32) {
+    let entry = mappings.entry(glyph_id).or_insert(ch);
+    *entry = (*entry).min(ch);
+}
+
+// Find an item in a sorted array. `compare` orders an item relative to the one being sought.
+fn binary_search<'a, T, F>(
+    array: &ReadArray<'a, T>,
+    compare: F,
+) -> Option<<T as ReadUnchecked<'a>>::HostType>
+where
+    T: ReadFixedSizeDep<'a> + ReadUnchecked<'a>,
+    F: Fn(&<T as ReadUnchecked<'a>>::HostType) -> Ordering,
+{
+    let mut low = 0;
+    let mut high = array.len();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let item = array.get_item(mid);
+        match compare(&item) {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return Some(item),
         }
     }
+    None
 }
 
 // For converting cmap format 4 offsets to indexes into the glyph id array.
@@ -726,6 +1058,9 @@ fn offset_to_index(
 }
 
 pub mod owned {
+    use alloc::boxed::Box;
+    use alloc::vec::Vec;
+
     use super::{
         size, Format4Calculator, I16Be, SequentialMapGroup, TryFrom, U16Be, U32Be, WriteBinary,
         WriteContext, WriteError,
@@ -783,7 +1118,7 @@ pub mod owned {
             for record in &table.encoding_records {
                 U16Be::write(ctxt, record.platform_id)?;
                 U16Be::write(ctxt, record.encoding_id)?;
-                let offset = ctxt.placeholder::<U32Be, _>()?;
+                let offset = ctxt.reserve_u32()?;
                 offsets.push(offset);
             }
 
@@ -792,7 +1127,7 @@ pub mod owned {
             {
                 let offset = u32::try_from(ctxt.bytes_written() - start)?;
                 CmapSubtable::write(ctxt, record.sub_table)?;
-                ctxt.write_placeholder(placeholder, offset)?;
+                ctxt.patch_u32(placeholder, offset)?;
             }
 
             Ok(())
@@ -827,7 +1162,7 @@ pub mod owned {
                     };
 
                     U16Be::write(ctxt, 4u16)?; // format
-                    let length = ctxt.placeholder::<U16Be, _>()?;
+                    let length = ctxt.reserve_u16()?;
                     U16Be::write(ctxt, language)?;
                     U16Be::write(ctxt, calc.seg_count_x2())?;
                     U16Be::write(ctxt, calc.search_range())?;
@@ -839,7 +1174,7 @@ pub mod owned {
                     ctxt.write_vec::<I16Be>(id_deltas)?;
                     ctxt.write_vec::<U16Be>(id_range_offsets)?;
                     ctxt.write_vec::<U16Be>(glyph_id_array)?;
-                    ctxt.write_placeholder(length, u16::try_from(ctxt.bytes_written() - start)?)?;
+                    ctxt.patch_u16(length, u16::try_from(ctxt.bytes_written() - start)?)?;
                 }
                 CmapSubtable::Format6 {
                     language,
@@ -849,12 +1184,12 @@ pub mod owned {
                     let start = ctxt.bytes_written();
 
                     U16Be::write(ctxt, 6u16)?; // format
-                    let length = ctxt.placeholder::<U16Be, _>()?;
+                    let length = ctxt.reserve_u16()?;
                     U16Be::write(ctxt, language)?;
                     U16Be::write(ctxt, first_code)?;
                     U16Be::write(ctxt, u16::try_from(glyph_id_array.len())?)?;
                     ctxt.write_vec::<U16Be>(glyph_id_array)?;
-                    ctxt.write_placeholder(length, u16::try_from(ctxt.bytes_written() - start)?)?;
+                    ctxt.patch_u16(length, u16::try_from(ctxt.bytes_written() - start)?)?;
                 }
                 CmapSubtable::Format10 {
                     language,
@@ -865,23 +1200,23 @@ pub mod owned {
 
                     U16Be::write(ctxt, 10u16)?; // format
                     U16Be::write(ctxt, 0u16)?; // reserved
-                    let length = ctxt.placeholder::<U32Be, _>()?;
+                    let length = ctxt.reserve_u32()?;
                     U32Be::write(ctxt, language)?;
                     U32Be::write(ctxt, start_char_code)?;
                     U32Be::write(ctxt, u32::try_from(glyph_id_array.len())?)?;
                     ctxt.write_vec::<U16Be>(glyph_id_array)?;
-                    ctxt.write_placeholder(length, u32::try_from(ctxt.bytes_written() - start)?)?;
+                    ctxt.patch_u32(length, u32::try_from(ctxt.bytes_written() - start)?)?;
                 }
                 CmapSubtable::Format12 { language, groups } => {
                     let start = ctxt.bytes_written();
 
                     U16Be::write(ctxt, 12u16)?; // format
                     U16Be::write(ctxt, 0u16)?; // reserved
-                    let length = ctxt.placeholder::<U32Be, _>()?;
+                    let length = ctxt.reserve_u32()?;
                     U32Be::write(ctxt, language)?;
                     U32Be::write(ctxt, u32::try_from(groups.len())?)?;
                     ctxt.write_vec::<SequentialMapGroup>(groups)?;
-                    ctxt.write_placeholder(length, u32::try_from(ctxt.bytes_written() - start)?)?;
+                    ctxt.patch_u32(length, u32::try_from(ctxt.bytes_written() - start)?)?;
                 }
             }
 
@@ -893,7 +1228,7 @@ pub mod owned {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tables::{OpenTypeFile, OpenTypeFont};
+    use crate::tables::{FontTableProvider, OpenTypeFile, OpenTypeFont};
     use crate::tag;
     use crate::tests::read_fixture;
     use std::path::Path;
@@ -1049,4 +1384,65 @@ mod tests {
             },
         );
     }
+
+    // A format 4 sub-table mapping A-C to glyphs 1-3 with a delta, and a-b to glyphs 1 and 4
+    // through the glyph id array
+    #[rustfmt::skip]
+    const FORMAT4_SUBTABLE: &[u8] = &[
+        0x00, 0x04, // format
+        0x00, 0x2C, // length
+        0x00, 0x00, // language
+        0x00, 0x06, 0x00, 0x04, 0x00, 0x01, 0x00, 0x02, // segCountX2 and search parameters
+        0x00, 0x43, 0x00, 0x62, 0xFF, 0xFF, // endCode
+        0x00, 0x00, // reservedPad
+        0x00, 0x41, 0x00, 0x61, 0xFF, 0xFF, // startCode
+        0xFF, 0xC0, 0x00, 0x00, 0x00, 0x01, // idDelta
+        0x00, 0x00, 0x00, 0x04, 0x00, 0x00, // idRangeOffset
+        0x00, 0x01, 0x00, 0x04, // glyphIdArray
+    ];
+
+    #[test]
+    fn test_build_reverse_map() {
+        let cmap_subtable = ReadScope::new(FORMAT4_SUBTABLE)
+            .read::<CmapSubtable<'_>>()
+            .unwrap();
+        assert_eq!(cmap_subtable.map_glyph('a' as u32).unwrap(), Some(1));
+
+        let reverse_map = Cmap::build_reverse_map(&cmap_subtable).unwrap();
+        let mut mappings = reverse_map.into_iter().collect::<Vec<_>>();
+        mappings.sort();
+        // 'a' also maps to glyph 1 but 'A' is lower, and U+FFFF maps to .notdef
+        assert_eq!(mappings, vec![(1, 'A'), (2, 'B'), (3, 'C'), (4, 'b')]);
+    }
+
+    #[test]
+    fn test_map_variation_glyph() {
+        let font_buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+        let opentype_file = ReadScope::new(&font_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let cmap_data = provider.read_table_data(tag::CMAP).unwrap();
+        let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>().unwrap();
+        let (_, cmap_subtable) = read_cmap_subtable(&cmap).unwrap().unwrap();
+
+        // Non-default variation sequence
+        assert_eq!(
+            cmap.map_variation_glyph('\u{4FAE}', '\u{FE00}').unwrap(),
+            Some(6808)
+        );
+        assert_ne!(cmap_subtable.map_glyph(0x4FAE).unwrap(), Some(6808));
+        // Default variation sequence
+        let default_glyph = cmap_subtable.map_glyph(0x4E00).unwrap();
+        assert!(default_glyph.is_some());
+        assert_eq!(
+            cmap.map_variation_glyph('\u{4E00}', '\u{E0100}').unwrap(),
+            default_glyph
+        );
+        // Unsupported variation sequence
+        assert_eq!(
+            cmap.map_variation_glyph('\u{4E00}', '\u{FE00}').unwrap(),
+            None
+        );
+    }
 }
@@ -0,0 +1,168 @@
+#![deny(missing_docs)]
+
+//! `COLR` table parsing.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/colr>
+//!
+//! Only the version 0 layer records are read. Version 1 tables are accepted but the additional
+//! paint data is ignored.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom};
+use crate::binary::U16Be;
+use crate::error::ParseError;
+
+/// Holds the version 0 records from the `COLR` table.
+pub struct ColrTable<'a> {
+    /// The version of the table.
+    pub version: u16,
+    /// Base glyph records sorted by glyph id.
+    pub base_glyph_records: ReadArray<'a, BaseGlyphRecord>,
+    /// Layer records referred to by the base glyph records.
+    pub layer_records: ReadArray<'a, LayerRecord>,
+}
+
+/// Associates a base glyph with a run of layer records.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BaseGlyphRecord {
+    /// The glyph id of the base glyph.
+    pub glyph_id: u16,
+    /// The index of the first layer record for the base glyph.
+    pub first_layer_index: u16,
+    /// The number of layer records for the base glyph.
+    pub num_layers: u16,
+}
+
+/// A glyph to draw in a color from the `CPAL` table.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LayerRecord {
+    /// The glyph id of the layer glyph.
+    pub glyph_id: u16,
+    /// Index of the color in the `CPAL` palette.
+    ///
+    /// The value `0xFFFF` indicates that the text foreground color should be used.
+    pub palette_index: u16,
+}
+
+impl<'a> ReadBinary<'a> for ColrTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let version = ctxt.read_u16be()?;
+        ctxt.check(version <= 1)?;
+        let num_base_glyph_records = usize::from(ctxt.read_u16be()?);
+        let base_glyph_records_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let layer_records_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let num_layer_records = usize::from(ctxt.read_u16be()?);
+
+        let base_glyph_records = scope
+            .offset(base_glyph_records_offset)
+            .ctxt()
+            .read_array(num_base_glyph_records)?;
+        let layer_records = scope
+            .offset(layer_records_offset)
+            .ctxt()
+            .read_array(num_layer_records)?;
+
+        Ok(ColrTable {
+            version,
+            base_glyph_records,
+            layer_records,
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for BaseGlyphRecord {
+    type ReadType = (U16Be, U16Be, U16Be);
+    fn from((glyph_id, first_layer_index, num_layers): (u16, u16, u16)) -> Self {
+        BaseGlyphRecord {
+            glyph_id,
+            first_layer_index,
+            num_layers,
+        }
+    }
+}
+
+impl<'a> ReadFrom<'a> for LayerRecord {
+    type ReadType = (U16Be, U16Be);
+    fn from((glyph_id, palette_index): (u16, u16)) -> Self {
+        LayerRecord {
+            glyph_id,
+            palette_index,
+        }
+    }
+}
+
+impl<'a> ColrTable<'a> {
+    /// Look up the layers of the color glyph for `base_glyph`.
+    ///
+    /// Returns the layers as `(glyph id, palette index)` pairs in drawing order, bottom-most
+    /// first. Returns `None` if `base_glyph` is not a color glyph or its layers are out of range.
+    pub fn layers(&self, base_glyph: u16) -> Option<Vec<(u16, u16)>> {
+        let record = self.find_base_glyph_record(base_glyph)?;
+        let start = usize::from(record.first_layer_index);
+        let end = start + usize::from(record.num_layers);
+        if end > self.layer_records.len() {
+            return None;
+        }
+
+        let layers = (start..end)
+            .map(|index| {
+                let layer = self.layer_records.get_item(index);
+                (layer.glyph_id, layer.palette_index)
+            })
+            .collect();
+        Some(layers)
+    }
+
+    fn find_base_glyph_record(&self, base_glyph: u16) -> Option<BaseGlyphRecord> {
+        let mut low = 0;
+        let mut high = self.base_glyph_records.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let record = self.base_glyph_records.get_item(mid);
+            if record.glyph_id < base_glyph {
+                low = mid + 1;
+            } else if record.glyph_id > base_glyph {
+                high = mid;
+            } else {
+                return Some(record);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+
+    #[rustfmt::skip]
+    const COLR: &[u8] = &[
+        0, 0,       // version
+        0, 2,       // numBaseGlyphRecords
+        0, 0, 0, 14, // baseGlyphRecordsOffset
+        0, 0, 0, 26, // layerRecordsOffset
+        0, 3,       // numLayerRecords
+        // BaseGlyphRecords
+        0, 5, 0, 0, 0, 2, // glyph 5, layers 0..2
+        0, 9, 0, 2, 0, 1, // glyph 9, layers 2..3
+        // LayerRecords
+        0, 20, 0, 0,
+        0, 21, 0, 1,
+        0, 22, 0xFF, 0xFF,
+    ];
+
+    #[test]
+    fn test_layers() {
+        let colr = ReadScope::new(COLR).read::<ColrTable<'_>>().unwrap();
+
+        assert_eq!(colr.layers(5), Some(vec![(20, 0), (21, 1)]));
+        assert_eq!(colr.layers(9), Some(vec![(22, 0xFFFF)]));
+        assert_eq!(colr.layers(7), None);
+    }
+}
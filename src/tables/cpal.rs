@@ -0,0 +1,137 @@
+#![deny(missing_docs)]
+
+//! `CPAL` table parsing.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/cpal>
+//!
+//! The palette types, labels, and entry labels of version 1 tables are ignored.
+
+use core::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom};
+use crate::binary::{U16Be, U8};
+use crate::error::ParseError;
+
+/// Holds the palettes from the `CPAL` table.
+pub struct CpalTable<'a> {
+    /// The version of the table.
+    pub version: u16,
+    /// The number of colors in each palette.
+    pub num_palette_entries: u16,
+    /// The index of the first color record of each palette.
+    pub color_record_indices: ReadArray<'a, U16Be>,
+    /// The colors of all palettes.
+    pub color_records: ReadArray<'a, ColorRecord>,
+}
+
+/// A color in the sRGB color space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColorRecord {
+    /// Blue component.
+    pub blue: u8,
+    /// Green component.
+    pub green: u8,
+    /// Red component.
+    pub red: u8,
+    /// Alpha component.
+    pub alpha: u8,
+}
+
+impl<'a> ReadBinary<'a> for CpalTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let version = ctxt.read_u16be()?;
+        ctxt.check(version <= 1)?;
+        let num_palette_entries = ctxt.read_u16be()?;
+        let num_palettes = usize::from(ctxt.read_u16be()?);
+        let num_color_records = usize::from(ctxt.read_u16be()?);
+        let color_records_array_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let color_record_indices = ctxt.read_array(num_palettes)?;
+
+        let color_records = scope
+            .offset(color_records_array_offset)
+            .ctxt()
+            .read_array(num_color_records)?;
+
+        Ok(CpalTable {
+            version,
+            num_palette_entries,
+            color_record_indices,
+            color_records,
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for ColorRecord {
+    type ReadType = ((U8, U8), (U8, U8));
+    fn from(((blue, green), (red, alpha)): ((u8, u8), (u8, u8))) -> Self {
+        ColorRecord {
+            blue,
+            green,
+            red,
+            alpha,
+        }
+    }
+}
+
+impl<'a> CpalTable<'a> {
+    /// The number of palettes in the table.
+    pub fn num_palettes(&self) -> usize {
+        self.color_record_indices.len()
+    }
+
+    /// Look up the color at `index` in `palette`.
+    ///
+    /// Returns the color as `(red, green, blue, alpha)`, or `None` if `palette` or `index` are out
+    /// of range.
+    pub fn color(&self, palette: u16, index: u16) -> Option<(u8, u8, u8, u8)> {
+        if usize::from(palette) >= self.num_palettes() || index >= self.num_palette_entries {
+            return None;
+        }
+
+        let first_index = self.color_record_indices.get_item(usize::from(palette));
+        let record_index = usize::from(first_index) + usize::from(index);
+        if record_index >= self.color_records.len() {
+            return None;
+        }
+
+        let record = self.color_records.get_item(record_index);
+        Some((record.red, record.green, record.blue, record.alpha))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+
+    #[rustfmt::skip]
+    const CPAL: &[u8] = &[
+        0, 0,       // version
+        0, 2,       // numPaletteEntries
+        0, 2,       // numPalettes
+        0, 4,       // numColorRecords
+        0, 0, 0, 16, // colorRecordsArrayOffset
+        0, 0,       // colorRecordIndices[0]
+        0, 2,       // colorRecordIndices[1]
+        // ColorRecords (BGRA)
+        0x00, 0x00, 0xFF, 0xFF,
+        0xFF, 0x00, 0x00, 0x80,
+        0x00, 0xFF, 0x00, 0xFF,
+        0x10, 0x20, 0x30, 0x40,
+    ];
+
+    #[test]
+    fn test_color() {
+        let cpal = ReadScope::new(CPAL).read::<CpalTable<'_>>().unwrap();
+
+        assert_eq!(cpal.num_palettes(), 2);
+        assert_eq!(cpal.color(0, 0), Some((0xFF, 0x00, 0x00, 0xFF)));
+        assert_eq!(cpal.color(0, 1), Some((0x00, 0x00, 0xFF, 0x80)));
+        assert_eq!(cpal.color(1, 1), Some((0x30, 0x20, 0x10, 0x40)));
+        assert_eq!(cpal.color(0, 2), None);
+        assert_eq!(cpal.color(2, 0), None);
+    }
+}
@@ -0,0 +1,139 @@
+#![deny(missing_docs)]
+
+//! `gasp` table parsing and writing.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/gasp>
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom};
+use crate::binary::write::{WriteBinary, WriteContext};
+use crate::binary::U16Be;
+use crate::error::{ParseError, WriteError};
+
+/// Holds the grid-fitting and scan conversion behaviors from the `gasp` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GaspTable {
+    /// The version of the table, 0 or 1.
+    pub version: u16,
+    /// The ranges, sorted by increasing `range_max_ppem`.
+    pub gasp_ranges: Vec<GaspRange>,
+}
+
+/// The rendering behavior for sizes up to and including `range_max_ppem`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GaspRange {
+    /// Upper limit of the range in pixels per em.
+    pub range_max_ppem: u16,
+    /// Flags describing the desired rendering behavior, see the `GASP_*` constants.
+    pub range_gasp_behavior: u16,
+}
+
+/// Use grid-fitting.
+pub const GASP_GRIDFIT: u16 = 0x0001;
+/// Use grayscale rendering.
+pub const GASP_DOGRAY: u16 = 0x0002;
+/// Use grid-fitting with ClearType symmetric smoothing. Only valid in version 1 tables.
+pub const GASP_SYMMETRIC_GRIDFIT: u16 = 0x0004;
+/// Use smoothing along multiple axes with ClearType. Only valid in version 1 tables.
+pub const GASP_SYMMETRIC_SMOOTHING: u16 = 0x0008;
+
+impl<'a> ReadBinary<'a> for GaspTable {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let version = ctxt.read_u16be()?;
+        ctxt.check(version <= 1)?;
+        let num_ranges = usize::from(ctxt.read_u16be()?);
+        let gasp_ranges: ReadArray<'_, GaspRange> = ctxt.read_array(num_ranges)?;
+
+        Ok(GaspTable {
+            version,
+            gasp_ranges: gasp_ranges.to_vec(),
+        })
+    }
+}
+
+impl<'a> ReadFrom<'a> for GaspRange {
+    type ReadType = (U16Be, U16Be);
+    fn from((range_max_ppem, range_gasp_behavior): (u16, u16)) -> Self {
+        GaspRange {
+            range_max_ppem,
+            range_gasp_behavior,
+        }
+    }
+}
+
+impl WriteBinary<&Self> for GaspTable {
+    type Output = ();
+
+    fn write<C: WriteContext>(ctxt: &mut C, table: &GaspTable) -> Result<(), WriteError> {
+        U16Be::write(ctxt, table.version)?;
+        U16Be::write(ctxt, u16::try_from(table.gasp_ranges.len())?)?;
+        for range in &table.gasp_ranges {
+            U16Be::write(ctxt, range.range_max_ppem)?;
+            U16Be::write(ctxt, range.range_gasp_behavior)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl GaspTable {
+    /// The rendering behavior flags for text drawn at `ppem` pixels per em.
+    ///
+    /// Returns `None` if `ppem` is larger than the last range of the table.
+    pub fn behavior(&self, ppem: u16) -> Option<u16> {
+        self.gasp_ranges
+            .iter()
+            .find(|range| ppem <= range.range_max_ppem)
+            .map(|range| range.range_gasp_behavior)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::binary::write::WriteBuffer;
+
+    #[rustfmt::skip]
+    const GASP: &[u8] = &[
+        0, 1,       // version
+        0, 3,       // numRanges
+        0, 8,       // rangeMaxPPEM
+        0, 0x0A,    // rangeGaspBehavior
+        0, 16,      // rangeMaxPPEM
+        0, 0x05,    // rangeGaspBehavior
+        0xFF, 0xFF, // rangeMaxPPEM
+        0, 0x0F,    // rangeGaspBehavior
+    ];
+
+    #[test]
+    fn round_trip() {
+        let gasp = ReadScope::new(GASP).read::<GaspTable>().unwrap();
+        assert_eq!(gasp.version, 1);
+        assert_eq!(gasp.gasp_ranges.len(), 3);
+        assert_eq!(
+            gasp.behavior(8),
+            Some(GASP_DOGRAY | GASP_SYMMETRIC_SMOOTHING)
+        );
+        assert_eq!(
+            gasp.behavior(9),
+            Some(GASP_GRIDFIT | GASP_SYMMETRIC_GRIDFIT)
+        );
+        assert_eq!(gasp.behavior(0xFFFF), Some(0x0F));
+
+        let mut buffer = WriteBuffer::new();
+        GaspTable::write(&mut buffer, &gasp).unwrap();
+        assert_eq!(buffer.bytes(), GASP);
+    }
+
+    #[test]
+    fn bad_version() {
+        let mut data = GASP.to_vec();
+        data[1] = 2;
+        assert!(ReadScope::new(&data).read::<GaspTable>().is_err());
+    }
+}
@@ -5,8 +5,9 @@
 //!
 //! — <https://docs.microsoft.com/en-us/typography/opentype/spec/glyf>
 
-use std::convert::TryFrom;
-use std::iter;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::iter;
 
 use bitflags::bitflags;
 use itertools::Itertools;
@@ -142,6 +143,22 @@ pub struct CompositeGlyphs {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Point(pub i16, pub i16);
 
+/// A closed contour of a TrueType glyph outline, made up of quadratic Bézier curves.
+///
+/// Two consecutive off-curve points have an implied on-curve point midway between them. The last
+/// point connects back to the first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contour {
+    pub points: Vec<ContourPoint>,
+}
+
+/// A point of a `Contour`, which is either on the outline or a control point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContourPoint {
+    pub point: Point,
+    pub on_curve: bool,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct BoundingBox {
     pub x_min: i16,
@@ -744,6 +761,193 @@ impl<'a> GlyfTable<'a> {
 
         Ok((GlyfTable { records }, new_to_old_id))
     }
+
+    /// The maximum number of top level components of the composite glyphs in the table, and the
+    /// maximum levels of nesting of the composite glyphs.
+    ///
+    /// These are the `max_component_elements` and `max_component_depth` fields of `maxp`. A
+    /// composite glyph made up of simple glyphs has a depth of 1. Both values are 0 if there are
+    /// no composite glyphs.
+    pub fn component_limits(&self) -> Result<(u16, u16), ParseError> {
+        let mut depths = vec![None; self.records.len()];
+        let mut max_component_elements = 0;
+        let mut max_component_depth = 0;
+        for glyph_id in 0..self.records.len() {
+            let components = self.components(glyph_id)?;
+            let num_components = u16::try_from(components.len())?;
+            max_component_elements = max_component_elements.max(num_components);
+            let depth = self.component_depth(glyph_id, &mut depths, 0)?;
+            max_component_depth = max_component_depth.max(depth);
+        }
+        Ok((max_component_elements, max_component_depth))
+    }
+
+    /// Returns a copy of this table that only contains the glyphs specified by `glyph_ids`, with
+    /// composite glyphs flattened into simple glyphs.
+    ///
+    /// The outlines of the components of each composite glyph are transformed by the scale and
+    /// offset of the component and combined into a single simple glyph. As no composite glyphs
+    /// remain the glyph ids are not changed, and the returned mapping of new to old glyph ids is a
+    /// copy of `glyph_ids`. Hinting instructions of composite glyphs are dropped.
+    pub fn subset_flattened(
+        &self,
+        glyph_ids: &[u16],
+    ) -> Result<(GlyfTable<'a>, Vec<u16>), ParseError> {
+        let records = glyph_ids
+            .iter()
+            .map(|&glyph_id| {
+                let record = self
+                    .records
+                    .get(usize::from(glyph_id))
+                    .ok_or(ParseError::BadIndex)?;
+                if !record.is_composite()? {
+                    return Ok(record.clone());
+                }
+                let simple_glyph = self.flatten_glyph(usize::from(glyph_id), 0)?;
+                if simple_glyph.coordinates.is_empty() {
+                    return Ok(GlyfRecord::Empty);
+                }
+                Ok(GlyfRecord::Parsed(Glyph {
+                    number_of_contours: i16::try_from(simple_glyph.end_pts_of_contours.len())?,
+                    bounding_box: simple_glyph.bounding_box(),
+                    data: GlyphData::Simple(simple_glyph),
+                }))
+            })
+            .collect::<Result<_, ParseError>>()?;
+
+        Ok((GlyfTable { records }, glyph_ids.to_vec()))
+    }
+
+    /// The contours of the outline of `glyph_id`.
+    ///
+    /// The components of composite glyphs are resolved recursively, with the scale and offset of
+    /// each component applied to its points. Empty glyphs have no contours.
+    pub fn glyph_contours(&self, glyph_id: u16) -> Result<Vec<Contour>, ParseError> {
+        self.flatten_glyph(usize::from(glyph_id), 0)?.contours()
+    }
+
+    fn flatten_glyph(
+        &self,
+        glyph_id: usize,
+        recursion_depth: usize,
+    ) -> Result<SimpleGlyph, ParseError> {
+        // Components that refer back to a composite glyph would recurse forever
+        if recursion_depth > self.records.len() {
+            return Err(ParseError::LimitExceeded);
+        }
+        let mut record = self
+            .records
+            .get(glyph_id)
+            .ok_or(ParseError::BadIndex)?
+            .clone();
+        record.parse()?;
+        let components = match record {
+            GlyfRecord::Empty => return Ok(SimpleGlyph::empty()),
+            GlyfRecord::Present(_) => unreachable!(),
+            GlyfRecord::Parsed(Glyph {
+                data: GlyphData::Simple(simple_glyph),
+                ..
+            }) => return Ok(simple_glyph),
+            GlyfRecord::Parsed(Glyph {
+                data: GlyphData::Composite { glyphs, .. },
+                ..
+            }) => glyphs,
+        };
+
+        let mut flattened = SimpleGlyph::empty();
+        for component in components {
+            let component_glyph =
+                self.flatten_glyph(usize::from(component.glyph_index), recursion_depth + 1)?;
+            let points = component_glyph
+                .coordinates
+                .iter()
+                .map(|&Point(x, y)| component.transform(f32::from(x), f32::from(y)))
+                .collect::<Vec<_>>();
+
+            let (dx, dy) = if component.flags.args_are_xy_values() {
+                let dx = f32::from(component.argument1.value());
+                let dy = f32::from(component.argument2.value());
+                if component.flags.scaled_component_offset() {
+                    component.transform(dx, dy)
+                } else {
+                    (dx, dy)
+                }
+            } else {
+                // The arguments are the points of the glyph so far and the component that are
+                // positioned on top of each other
+                let parent_point = flattened
+                    .coordinates
+                    .get(component.argument1.point_index()?)
+                    .ok_or(ParseError::BadIndex)?;
+                let (x, y) = points
+                    .get(component.argument2.point_index()?)
+                    .ok_or(ParseError::BadIndex)?;
+                (f32::from(parent_point.0) - x, f32::from(parent_point.1) - y)
+            };
+
+            let base = u16::try_from(flattened.coordinates.len())?;
+            for end_point in component_glyph.end_pts_of_contours {
+                let end_point = end_point
+                    .checked_add(base)
+                    .ok_or(ParseError::LimitExceeded)?;
+                flattened.end_pts_of_contours.push(end_point);
+            }
+            flattened.flags.extend(component_glyph.flags);
+            flattened.coordinates.extend(
+                points
+                    .into_iter()
+                    .map(|(x, y)| Point(round_to_i16(x + dx), round_to_i16(y + dy))),
+            );
+        }
+
+        Ok(flattened)
+    }
+
+    pub(crate) fn components(&self, glyph_id: usize) -> Result<Vec<u16>, ParseError> {
+        let mut record = self
+            .records
+            .get(glyph_id)
+            .ok_or(ParseError::BadIndex)?
+            .clone();
+        if !record.is_composite()? {
+            return Ok(Vec::new());
+        }
+        record.parse()?;
+        match record {
+            GlyfRecord::Parsed(Glyph {
+                data: GlyphData::Composite { glyphs, .. },
+                ..
+            }) => Ok(glyphs
+                .iter()
+                .map(|composite_glyph| composite_glyph.glyph_index)
+                .collect()),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn component_depth(
+        &self,
+        glyph_id: usize,
+        depths: &mut [Option<u16>],
+        recursion_depth: usize,
+    ) -> Result<u16, ParseError> {
+        // Components that refer back to a composite glyph would recurse forever
+        if recursion_depth > self.records.len() {
+            return Err(ParseError::LimitExceeded);
+        }
+        if let Some(Some(depth)) = depths.get(glyph_id) {
+            return Ok(*depth);
+        }
+        let components = self.components(glyph_id)?;
+        let mut depth = 0;
+        for component in components {
+            let component_depth =
+                self.component_depth(usize::from(component), depths, recursion_depth + 1)?;
+            depth = depth.max(component_depth + 1);
+        }
+        depths[glyph_id] = Some(depth);
+        Ok(depth)
+    }
 }
 
 impl<'a> GlyfRecord<'a> {
@@ -755,6 +959,19 @@ impl<'a> GlyfRecord<'a> {
         }
     }
 
+    /// The bounding box of the glyph, or `None` if it has no outline.
+    pub fn bounding_box(&self) -> Result<Option<BoundingBox>, ParseError> {
+        match self {
+            GlyfRecord::Empty => Ok(None),
+            GlyfRecord::Present(scope) => {
+                let mut ctxt = scope.ctxt();
+                let _number_of_contours = ctxt.read_i16be()?;
+                ctxt.read::<BoundingBox>().map(Some)
+            }
+            GlyfRecord::Parsed(glyph) => Ok(Some(glyph.bounding_box.clone())),
+        }
+    }
+
     pub fn is_composite(&self) -> Result<bool, ParseError> {
         self.number_of_contours()
             .map(|number_of_contours| number_of_contours < 0)
@@ -796,6 +1013,68 @@ impl CompositeGlyphFlag {
     pub fn we_have_instructions(self) -> bool {
         self & Self::WE_HAVE_INSTRUCTIONS == Self::WE_HAVE_INSTRUCTIONS
     }
+
+    pub fn scaled_component_offset(self) -> bool {
+        self & Self::SCALED_COMPONENT_OFFSET == Self::SCALED_COMPONENT_OFFSET
+    }
+}
+
+impl CompositeGlyph {
+    /// Apply the scale of this component to the point (`x`, `y`).
+    fn transform(&self, x: f32, y: f32) -> (f32, f32) {
+        match self.scale {
+            None => (x, y),
+            Some(CompositeGlyphScale::Scale(scale)) => {
+                let scale = f32::from(scale);
+                (x * scale, y * scale)
+            }
+            Some(CompositeGlyphScale::XY { x_scale, y_scale }) => {
+                (x * f32::from(x_scale), y * f32::from(y_scale))
+            }
+            Some(CompositeGlyphScale::Matrix(matrix)) => (
+                x * f32::from(matrix[0][0]) + y * f32::from(matrix[1][0]),
+                x * f32::from(matrix[0][1]) + y * f32::from(matrix[1][1]),
+            ),
+        }
+    }
+}
+
+impl CompositeGlyphArgument {
+    fn value(&self) -> i16 {
+        match *self {
+            CompositeGlyphArgument::U8(value) => i16::from(value),
+            CompositeGlyphArgument::I8(value) => i16::from(value),
+            // Offsets are always read as signed values
+            CompositeGlyphArgument::U16(value) => value as i16,
+            CompositeGlyphArgument::I16(value) => value,
+        }
+    }
+
+    fn point_index(&self) -> Result<usize, ParseError> {
+        match *self {
+            CompositeGlyphArgument::U8(value) => Ok(usize::from(value)),
+            CompositeGlyphArgument::U16(value) => Ok(usize::from(value)),
+            CompositeGlyphArgument::I8(_) | CompositeGlyphArgument::I16(_) => {
+                Err(ParseError::BadValue)
+            }
+        }
+    }
+}
+
+fn round_to_i16(value: f32) -> i16 {
+    // Round half away from zero; `f32::round` is not available in `no_std`
+    let value = if value < 0.0 {
+        value - 0.5
+    } else {
+        value + 0.5
+    };
+    if value < f32::from(i16::min_value()) {
+        i16::min_value()
+    } else if value > f32::from(i16::max_value()) {
+        i16::max_value()
+    } else {
+        value as i16
+    }
 }
 
 impl BoundingBox {
@@ -835,9 +1114,41 @@ impl BoundingBox {
 }
 
 impl SimpleGlyph {
+    fn empty() -> Self {
+        SimpleGlyph {
+            end_pts_of_contours: Vec::new(),
+            instructions: Vec::new(),
+            flags: Vec::new(),
+            coordinates: Vec::new(),
+        }
+    }
+
     pub fn bounding_box(&self) -> BoundingBox {
         BoundingBox::from_points(&self.coordinates)
     }
+
+    /// Split the points of the glyph into its contours.
+    pub fn contours(&self) -> Result<Vec<Contour>, ParseError> {
+        let mut contours = Vec::with_capacity(self.end_pts_of_contours.len());
+        let mut start = 0;
+        for &end_point in &self.end_pts_of_contours {
+            let end = usize::from(end_point) + 1;
+            if end <= start || end > self.coordinates.len() || end > self.flags.len() {
+                return Err(ParseError::BadValue);
+            }
+            let points = self.coordinates[start..end]
+                .iter()
+                .zip(&self.flags[start..end])
+                .map(|(&point, flag)| ContourPoint {
+                    point,
+                    on_curve: flag.is_on_curve(),
+                })
+                .collect();
+            contours.push(Contour { points });
+            start = end;
+        }
+        Ok(contours)
+    }
 }
 
 #[cfg(test)]
@@ -846,10 +1157,13 @@ mod tests {
     use crate::binary::read::ReadScope;
     use crate::binary::write::{WriteBinary, WriteBinaryDep, WriteBuffer, WriteContext};
     use crate::tables::glyf::{
-        CompositeGlyph, CompositeGlyphArgument, CompositeGlyphFlag, Glyph, GlyphData, SimpleGlyph,
-        SimpleGlyphFlag,
+        CompositeGlyph, CompositeGlyphArgument, CompositeGlyphFlag, CompositeGlyphScale,
+        ContourPoint, Glyph, GlyphData, SimpleGlyph, SimpleGlyphFlag,
     };
     use crate::tables::loca::{owned, LocaTable};
+    use crate::tables::{F2Dot14, FontTableProvider, HeadTable, MaxpTable, OpenTypeFile};
+    use crate::tag;
+    use crate::tests::read_fixture;
 
     fn simple_glyph_fixture() -> Glyph<'static> {
         let simple_glyph = SimpleGlyph {
@@ -981,6 +1295,171 @@ mod tests {
         assert_eq!(BoundingBox::from_points(&points), expected);
     }
 
+    #[test]
+    fn glyph_contours() {
+        let buffer = read_fixture("tests/fonts/opentype/SFNT-TTF-Composite.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let head = ReadScope::new(&provider.read_table_data(tag::HEAD).unwrap())
+            .read::<HeadTable>()
+            .unwrap();
+        let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP).unwrap())
+            .read::<MaxpTable>()
+            .unwrap();
+        let loca_data = provider.read_table_data(tag::LOCA).unwrap();
+        let loca = ReadScope::new(&loca_data)
+            .read_dep::<LocaTable<'_>>((usize::from(maxp.num_glyphs), head.index_to_loc_format))
+            .unwrap();
+        let glyf_data = provider.read_table_data(tag::GLYF).unwrap();
+        let glyf = ReadScope::new(&glyf_data)
+            .read_dep::<GlyfTable<'_>>(&loca)
+            .unwrap();
+
+        // Glyph 2 is a composite whose first component is glyph 7 offset horizontally by 3453
+        let contours = glyf.glyph_contours(2).unwrap();
+        let component_contours = glyf.glyph_contours(7).unwrap();
+        let first = component_contours[0].points[0];
+        assert_eq!(
+            contours[0].points[0],
+            ContourPoint {
+                point: Point(first.point.0 + 3453, first.point.1),
+                on_curve: first.on_curve
+            }
+        );
+        assert_eq!(contours[0].points.len(), component_contours[0].points.len());
+        assert!(glyf.glyph_contours(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn glyph_contours_transformed() {
+        // A triangle, and a composite of the triangle rotated 90° anticlockwise and offset
+        let triangle = SimpleGlyph {
+            end_pts_of_contours: vec![2],
+            instructions: vec![],
+            flags: vec![
+                SimpleGlyphFlag::ON_CURVE_POINT,
+                SimpleGlyphFlag::empty(),
+                SimpleGlyphFlag::ON_CURVE_POINT,
+            ],
+            coordinates: vec![Point(0, 0), Point(100, 200), Point(200, 0)],
+        };
+        let rotated = CompositeGlyph {
+            flags: CompositeGlyphFlag::ARG_1_AND_2_ARE_WORDS
+                | CompositeGlyphFlag::ARGS_ARE_XY_VALUES
+                | CompositeGlyphFlag::WE_HAVE_A_TWO_BY_TWO,
+            glyph_index: 0,
+            argument1: CompositeGlyphArgument::I16(1000),
+            argument2: CompositeGlyphArgument::I16(-50),
+            // x' = -y, y' = x
+            scale: Some(CompositeGlyphScale::Matrix([
+                [F2Dot14::new(0), F2Dot14::new(0x4000)],
+                [F2Dot14::new(0xC000), F2Dot14::new(0)],
+            ])),
+        };
+        let glyf = GlyfTable {
+            records: vec![
+                GlyfRecord::Parsed(Glyph {
+                    number_of_contours: 1,
+                    bounding_box: triangle.bounding_box(),
+                    data: GlyphData::Simple(triangle),
+                }),
+                GlyfRecord::Parsed(Glyph {
+                    number_of_contours: -1,
+                    bounding_box: BoundingBox {
+                        x_min: 800,
+                        x_max: 1000,
+                        y_min: -50,
+                        y_max: 150,
+                    },
+                    data: GlyphData::Composite {
+                        glyphs: vec![rotated],
+                        instructions: &[],
+                    },
+                }),
+            ],
+        };
+
+        let contours = glyf.glyph_contours(1).unwrap();
+        let points = contours[0]
+            .points
+            .iter()
+            .map(|contour_point| (contour_point.point, contour_point.on_curve))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            points,
+            [
+                (Point(1000, -50), true),
+                (Point(800, 50), false),
+                (Point(1000, 150), true)
+            ]
+        );
+    }
+
+    #[test]
+    fn subset_flattened() {
+        let buffer = read_fixture("tests/fonts/opentype/SFNT-TTF-Composite.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let head = ReadScope::new(&provider.read_table_data(tag::HEAD).unwrap())
+            .read::<HeadTable>()
+            .unwrap();
+        let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP).unwrap())
+            .read::<MaxpTable>()
+            .unwrap();
+        let loca_data = provider.read_table_data(tag::LOCA).unwrap();
+        let loca = ReadScope::new(&loca_data)
+            .read_dep::<LocaTable<'_>>((usize::from(maxp.num_glyphs), head.index_to_loc_format))
+            .unwrap();
+        let glyf_data = provider.read_table_data(tag::GLYF).unwrap();
+        let glyf = ReadScope::new(&glyf_data)
+            .read_dep::<GlyfTable<'_>>(&loca)
+            .unwrap();
+        let simple_glyph = |glyf: &GlyfTable<'_>, glyph_id: usize| {
+            let mut record = glyf.records[glyph_id].clone();
+            record.parse().unwrap();
+            match record {
+                GlyfRecord::Parsed(Glyph {
+                    data: GlyphData::Simple(simple_glyph),
+                    ..
+                }) => simple_glyph,
+                _ => panic!("expected a simple glyph"),
+            }
+        };
+
+        // Glyph 2 is a composite of glyphs 7, 6, 5, and 4 offset horizontally by 3453, 2773,
+        // 1182, and 205
+        let (flattened, new_to_old_glyph_id) = glyf.subset_flattened(&[0, 2]).unwrap();
+        assert_eq!(new_to_old_glyph_id, vec![0, 2]);
+        assert_eq!(flattened.records[0], GlyfRecord::Empty);
+        let glyph = match &flattened.records[1] {
+            GlyfRecord::Parsed(glyph) => glyph,
+            _ => panic!("expected a parsed glyph"),
+        };
+        let composite_bounding_box = glyf.records[2].bounding_box().unwrap().unwrap();
+        assert_eq!(glyph.bounding_box, composite_bounding_box);
+
+        let mut expected_points = Vec::new();
+        let mut expected_contours = 0;
+        for &(component, dx) in &[(7, 3453), (6, 2773), (5, 1182), (4, 205)] {
+            let component = simple_glyph(&glyf, component);
+            expected_contours += component.end_pts_of_contours.len();
+            expected_points.extend(
+                component
+                    .coordinates
+                    .iter()
+                    .map(|&Point(x, y)| Point(x + dx, y)),
+            );
+        }
+        let flattened_glyph = simple_glyph(&flattened, 1);
+        assert_eq!(glyph.number_of_contours as usize, expected_contours);
+        assert_eq!(flattened_glyph.end_pts_of_contours.len(), expected_contours);
+        assert_eq!(flattened_glyph.coordinates, expected_points);
+        assert_eq!(
+            usize::from(*flattened_glyph.end_pts_of_contours.last().unwrap()) + 1,
+            expected_points.len()
+        );
+    }
+
     #[test]
     fn write_glyf_table_loca_sanity_check() {
         let glyf = GlyfTable {
@@ -0,0 +1,202 @@
+#![deny(missing_docs)]
+
+//! `kern` table parsing.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/kern>
+//!
+//! Both the OpenType (version 0) and Apple (version 1) table headers are supported. Only format 0
+//! subtables are read, subtables in other formats are skipped.
+
+use alloc::vec::Vec;
+
+use crate::binary::read::{ReadArray, ReadBinary, ReadCtxt, ReadFrom};
+use crate::binary::{I16Be, U16Be};
+use crate::error::ParseError;
+
+/// Holds the format 0 subtables from the `kern` table.
+pub struct KernTable<'a> {
+    /// The format 0 kerning subtables.
+    pub sub_tables: Vec<KernSubtable<'a>>,
+}
+
+/// A format 0 `kern` subtable.
+pub struct KernSubtable<'a> {
+    /// The subtable holds horizontal kerning values.
+    pub is_horizontal: bool,
+    /// The subtable holds minimum values rather than kerning values.
+    pub is_minimum: bool,
+    /// The values are perpendicular to the flow of the text.
+    pub is_cross_stream: bool,
+    /// The values replace those accumulated from previous subtables.
+    pub is_override: bool,
+    /// Kerning pairs sorted by left then right glyph id.
+    pub pairs: ReadArray<'a, KernPair>,
+}
+
+/// A kerning value for a pair of glyphs.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KernPair {
+    /// The glyph id of the left glyph.
+    pub left: u16,
+    /// The glyph id of the right glyph.
+    pub right: u16,
+    /// The kerning value in font design units.
+    pub value: i16,
+}
+
+impl<'a> ReadBinary<'a> for KernTable<'a> {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let version = ctxt.read_u16be()?;
+        let apple = match version {
+            0 => false,
+            1 => {
+                // The Apple header has a 32-bit version, 0x00010000
+                let minor_version = ctxt.read_u16be()?;
+                ctxt.check(minor_version == 0)?;
+                true
+            }
+            _ => return Err(ParseError::BadVersion),
+        };
+        let num_tables = if apple {
+            ctxt.read_u32be()?
+        } else {
+            u32::from(ctxt.read_u16be()?)
+        };
+
+        let mut sub_tables = Vec::new();
+        for _ in 0..num_tables {
+            // Apple subtable headers have a 32-bit length, a coverage field with the format in
+            // the low byte, and a tuple index. OpenType headers have a version, a 16-bit length,
+            // and a coverage field with the format in the high byte.
+            let (length, coverage, header_size) = if apple {
+                let length = ctxt.read_u32be()?;
+                let coverage = ctxt.read_u16be()?;
+                let _tuple_index = ctxt.read_u16be()?;
+                (length, coverage, 8)
+            } else {
+                let _version = ctxt.read_u16be()?;
+                let length = u32::from(ctxt.read_u16be()?);
+                let coverage = ctxt.read_u16be()?;
+                (length, coverage, 6)
+            };
+            let format = if apple {
+                coverage & 0xFF
+            } else {
+                coverage >> 8
+            };
+
+            if format != 0 {
+                let body_length = length
+                    .checked_sub(header_size)
+                    .ok_or(ParseError::BadValue)?;
+                let _body = ctxt.read_slice(body_length as usize)?;
+                continue;
+            }
+
+            // The length of format 0 subtables is ignored as some fonts contain more pairs than
+            // the 16-bit length field can describe
+            let num_pairs = ctxt.read_u16be()?;
+            let _search_range = ctxt.read_u16be()?;
+            let _entry_selector = ctxt.read_u16be()?;
+            let _range_shift = ctxt.read_u16be()?;
+            let pairs = ctxt.read_array::<KernPair>(usize::from(num_pairs))?;
+
+            let sub_table = if apple {
+                KernSubtable {
+                    is_horizontal: coverage & 0x8000 == 0,
+                    is_minimum: false,
+                    is_cross_stream: coverage & 0x4000 != 0,
+                    is_override: false,
+                    pairs,
+                }
+            } else {
+                KernSubtable {
+                    is_horizontal: coverage & 0x1 != 0,
+                    is_minimum: coverage & 0x2 != 0,
+                    is_cross_stream: coverage & 0x4 != 0,
+                    is_override: coverage & 0x8 != 0,
+                    pairs,
+                }
+            };
+            sub_tables.push(sub_table);
+        }
+
+        Ok(KernTable { sub_tables })
+    }
+}
+
+impl<'a> ReadFrom<'a> for KernPair {
+    type ReadType = (U16Be, U16Be, I16Be);
+    fn from((left, right, value): (u16, u16, i16)) -> Self {
+        KernPair { left, right, value }
+    }
+}
+
+impl<'a> KernTable<'a> {
+    /// Look up the horizontal kerning value for the pair of glyphs `left` and `right`.
+    ///
+    /// Values from all horizontal subtables that apply along the flow of the text are combined.
+    /// Returns `None` if no subtable contains the pair.
+    pub fn kerning(&self, left: u16, right: u16) -> Option<i16> {
+        let mut kerning = None;
+        for sub_table in &self.sub_tables {
+            if !sub_table.is_horizontal || sub_table.is_minimum || sub_table.is_cross_stream {
+                continue;
+            }
+            if let Some(value) = sub_table.kerning(left, right) {
+                kerning = match kerning {
+                    Some(total) if !sub_table.is_override => Some(value.saturating_add(total)),
+                    _ => Some(value),
+                };
+            }
+        }
+        kerning
+    }
+}
+
+impl<'a> KernSubtable<'a> {
+    /// Look up the value for the pair of glyphs `left` and `right` in this subtable.
+    pub fn kerning(&self, left: u16, right: u16) -> Option<i16> {
+        let key = (left, right);
+        let mut low = 0;
+        let mut high = self.pairs.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let pair = self.pairs.get_item(mid);
+            if (pair.left, pair.right) < key {
+                low = mid + 1;
+            } else if (pair.left, pair.right) > key {
+                high = mid;
+            } else {
+                return Some(pair.value);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::read::ReadScope;
+    use crate::tables::{FontTableProvider, OpenTypeFile};
+    use crate::tag;
+    use crate::tests::read_fixture;
+
+    #[test]
+    fn test_kerning() {
+        let buffer = read_fixture("tests/fonts/arabic/ae_Arab.ttf");
+        let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+        let provider = opentype_file.font_provider(0).unwrap();
+        let kern_data = provider.read_table_data(tag::KERN).unwrap();
+        let kern = ReadScope::new(&kern_data).read::<KernTable<'_>>().unwrap();
+
+        assert_eq!(kern.sub_tables.len(), 1);
+        assert_eq!(kern.sub_tables[0].pairs.len(), 765);
+        assert_eq!(kern.kerning(18, 57), Some(-64));
+        assert_eq!(kern.kerning(18, 59), Some(-131));
+        assert_eq!(kern.kerning(18, 58), None);
+    }
+}
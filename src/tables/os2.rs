@@ -1,14 +1,15 @@
-//! Parsing of the `OS/2` table.
+//! Parsing and writing of the `OS/2` table.
 //!
 //! > The OS/2 table consists of a set of metrics and other data that are required in OpenType fonts.
 //!
 //! — <https://docs.microsoft.com/en-us/typography/opentype/spec/os2>
 
-use std::convert::TryInto;
+use core::convert::TryInto;
 
 use crate::binary::read::{ReadBinaryDep, ReadCtxt};
+use crate::binary::write::{WriteBinary, WriteContext};
 use crate::binary::{I16Be, U16Be, U32Be};
-use crate::error::ParseError;
+use crate::error::{ParseError, WriteError};
 
 /// `OS/2` table
 ///
@@ -203,6 +204,66 @@ impl<'a> ReadBinaryDep<'a> for Os2 {
     }
 }
 
+impl WriteBinary<&Self> for Os2 {
+    type Output = ();
+
+    fn write<C: WriteContext>(ctxt: &mut C, table: &Os2) -> Result<(), WriteError> {
+        U16Be::write(ctxt, table.version)?;
+        I16Be::write(ctxt, table.x_avg_char_width)?;
+        U16Be::write(ctxt, table.us_weight_class)?;
+        U16Be::write(ctxt, table.us_width_class)?;
+        U16Be::write(ctxt, table.fs_type)?;
+        I16Be::write(ctxt, table.y_subscript_x_size)?;
+        I16Be::write(ctxt, table.y_subscript_y_size)?;
+        I16Be::write(ctxt, table.y_subscript_x_offset)?;
+        I16Be::write(ctxt, table.y_subscript_y_offset)?;
+        I16Be::write(ctxt, table.y_superscript_x_size)?;
+        I16Be::write(ctxt, table.y_superscript_y_size)?;
+        I16Be::write(ctxt, table.y_superscript_x_offset)?;
+        I16Be::write(ctxt, table.y_superscript_y_offset)?;
+        I16Be::write(ctxt, table.y_strikeout_size)?;
+        I16Be::write(ctxt, table.y_strikeout_position)?;
+        I16Be::write(ctxt, table.s_family_class)?;
+        ctxt.write_bytes(&table.panose)?;
+        U32Be::write(ctxt, table.ul_unicode_range1)?;
+        U32Be::write(ctxt, table.ul_unicode_range2)?;
+        U32Be::write(ctxt, table.ul_unicode_range3)?;
+        U32Be::write(ctxt, table.ul_unicode_range4)?;
+        U32Be::write(ctxt, table.ach_vend_id)?;
+        U16Be::write(ctxt, table.fs_selection)?;
+        U16Be::write(ctxt, table.us_first_char_index)?;
+        U16Be::write(ctxt, table.us_last_char_index)?;
+
+        if let Some(version0) = &table.version0 {
+            I16Be::write(ctxt, version0.s_typo_ascender)?;
+            I16Be::write(ctxt, version0.s_typo_descender)?;
+            I16Be::write(ctxt, version0.s_typo_line_gap)?;
+            U16Be::write(ctxt, version0.us_win_ascent)?;
+            U16Be::write(ctxt, version0.us_win_descent)?;
+        }
+
+        if let Some(version1) = &table.version1 {
+            U32Be::write(ctxt, version1.ul_code_page_range1)?;
+            U32Be::write(ctxt, version1.ul_code_page_range2)?;
+        }
+
+        if let Some(version2to4) = &table.version2to4 {
+            I16Be::write(ctxt, version2to4.sx_height)?;
+            I16Be::write(ctxt, version2to4.s_cap_height)?;
+            U16Be::write(ctxt, version2to4.us_default_char)?;
+            U16Be::write(ctxt, version2to4.us_break_char)?;
+            U16Be::write(ctxt, version2to4.us_max_context)?;
+        }
+
+        if let Some(version5) = &table.version5 {
+            U16Be::write(ctxt, version5.us_lower_optical_point_size)?;
+            U16Be::write(ctxt, version5.us_upper_optical_point_size)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,397 @@
+#![deny(missing_docs)]
+
+//! `STAT` table parsing.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/stat>
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::binary::read::{ReadBinary, ReadCtxt, ReadScope};
+use crate::binary::U16Be;
+use crate::error::ParseError;
+use crate::size;
+
+/// The style attributes from the `STAT` table.
+///
+/// The names of the axes and values are given as ids of strings in the `name` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatTable {
+    /// The major version of the table, always 1.
+    pub major_version: u16,
+    /// The minor version of the table.
+    pub minor_version: u16,
+    /// The design axes, which axis values refer to by index.
+    pub design_axes: Vec<AxisRecord>,
+    /// The axis value tables. Tables with an unknown format are skipped.
+    pub axis_values: Vec<AxisValue>,
+    /// Name id of the name to use when all the axis value names are elided. Present from version
+    /// 1.1.
+    pub elided_fallback_name_id: Option<u16>,
+}
+
+/// A design axis, such as weight or width.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AxisRecord {
+    /// The tag identifying the axis, such as `wght`.
+    pub axis_tag: u32,
+    /// Name id of the name of the axis.
+    pub axis_name_id: u16,
+    /// The position of the axis when building names from the axis values.
+    pub axis_ordering: u16,
+}
+
+/// A named value or range on one or more design axes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AxisValue {
+    /// A single value on an axis.
+    Format1 {
+        /// The index of the axis in `StatTable::design_axes`.
+        axis_index: u16,
+        /// See the `AXIS_VALUE_*` flags.
+        flags: u16,
+        /// Name id of the name of the value.
+        value_name_id: u16,
+        /// The value on the axis.
+        value: f32,
+    },
+    /// A range of values on an axis.
+    Format2 {
+        /// The index of the axis in `StatTable::design_axes`.
+        axis_index: u16,
+        /// See the `AXIS_VALUE_*` flags.
+        flags: u16,
+        /// Name id of the name of the range.
+        value_name_id: u16,
+        /// The nominal value within the range.
+        nominal_value: f32,
+        /// The minimum value of the range.
+        range_min_value: f32,
+        /// The maximum value of the range.
+        range_max_value: f32,
+    },
+    /// A value on an axis along with a linked value, such as the bold counterpart of a regular
+    /// weight.
+    Format3 {
+        /// The index of the axis in `StatTable::design_axes`.
+        axis_index: u16,
+        /// See the `AXIS_VALUE_*` flags.
+        flags: u16,
+        /// Name id of the name of the value.
+        value_name_id: u16,
+        /// The value on the axis.
+        value: f32,
+        /// The linked value on the axis.
+        linked_value: f32,
+    },
+    /// A combination of values on several axes.
+    Format4 {
+        /// See the `AXIS_VALUE_*` flags.
+        flags: u16,
+        /// Name id of the name of the combination.
+        value_name_id: u16,
+        /// The value on each axis of the combination.
+        axis_values: Vec<AxisValueRecord>,
+    },
+}
+
+/// A value on an axis within a format 4 axis value table.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AxisValueRecord {
+    /// The index of the axis in `StatTable::design_axes`.
+    pub axis_index: u16,
+    /// The value on the axis.
+    pub value: f32,
+}
+
+/// The axis value applies to older versions of the font family that lack some axes.
+pub const AXIS_VALUE_OLDER_SIBLING_FONT_ATTRIBUTE: u16 = 0x0001;
+/// The name of the axis value can be omitted when building a name, such as `Regular`.
+pub const AXIS_VALUE_ELIDABLE_AXIS_VALUE_NAME: u16 = 0x0002;
+
+/// The size of an axis record in version 1 tables, larger records may be used by later minor
+/// versions.
+const AXIS_RECORD_SIZE: usize = size::U32 + 2 * size::U16;
+
+impl<'a> ReadBinary<'a> for StatTable {
+    type HostType = Self;
+
+    fn read(ctxt: &mut ReadCtxt<'a>) -> Result<Self, ParseError> {
+        let scope = ctxt.scope();
+        let major_version = ctxt.read_u16be()?;
+        ctxt.check(major_version == 1)?;
+        let minor_version = ctxt.read_u16be()?;
+        let design_axis_size = usize::from(ctxt.read_u16be()?);
+        ctxt.check(design_axis_size >= AXIS_RECORD_SIZE)?;
+        let design_axis_count = usize::from(ctxt.read_u16be()?);
+        let design_axes_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let axis_value_count = usize::from(ctxt.read_u16be()?);
+        let axis_value_offsets_offset = usize::try_from(ctxt.read_u32be()?)?;
+        let elided_fallback_name_id = if minor_version >= 1 {
+            Some(ctxt.read_u16be()?)
+        } else {
+            None
+        };
+
+        let mut design_axes = Vec::with_capacity(design_axis_count);
+        if design_axis_count > 0 {
+            let axes_scope = scope.offset_checked(design_axes_offset)?;
+            for i in 0..design_axis_count {
+                let mut axis_ctxt = axes_scope.offset(i * design_axis_size).ctxt();
+                design_axes.push(AxisRecord {
+                    axis_tag: axis_ctxt.read_u32be()?,
+                    axis_name_id: axis_ctxt.read_u16be()?,
+                    axis_ordering: axis_ctxt.read_u16be()?,
+                });
+            }
+        }
+
+        let mut axis_values = Vec::with_capacity(axis_value_count);
+        if axis_value_count > 0 {
+            // The offsets to the axis value tables are from the start of the array of offsets
+            let offsets_scope = scope.offset_checked(axis_value_offsets_offset)?;
+            let offsets = offsets_scope.ctxt().read_array::<U16Be>(axis_value_count)?;
+            for offset in &offsets {
+                let value_scope = offsets_scope.offset_checked(usize::from(offset))?;
+                if let Some(axis_value) = read_axis_value(&value_scope)? {
+                    axis_values.push(axis_value);
+                }
+            }
+        }
+
+        Ok(StatTable {
+            major_version,
+            minor_version,
+            design_axes,
+            axis_values,
+            elided_fallback_name_id,
+        })
+    }
+}
+
+/// Read an axis value table, returning `None` if its format is unknown.
+fn read_axis_value(scope: &ReadScope<'_>) -> Result<Option<AxisValue>, ParseError> {
+    let mut ctxt = scope.ctxt();
+    let axis_value = match ctxt.read_u16be()? {
+        1 => AxisValue::Format1 {
+            axis_index: ctxt.read_u16be()?,
+            flags: ctxt.read_u16be()?,
+            value_name_id: ctxt.read_u16be()?,
+            value: read_fixed(&mut ctxt)?,
+        },
+        2 => AxisValue::Format2 {
+            axis_index: ctxt.read_u16be()?,
+            flags: ctxt.read_u16be()?,
+            value_name_id: ctxt.read_u16be()?,
+            nominal_value: read_fixed(&mut ctxt)?,
+            range_min_value: read_fixed(&mut ctxt)?,
+            range_max_value: read_fixed(&mut ctxt)?,
+        },
+        3 => AxisValue::Format3 {
+            axis_index: ctxt.read_u16be()?,
+            flags: ctxt.read_u16be()?,
+            value_name_id: ctxt.read_u16be()?,
+            value: read_fixed(&mut ctxt)?,
+            linked_value: read_fixed(&mut ctxt)?,
+        },
+        4 => {
+            let axis_count = usize::from(ctxt.read_u16be()?);
+            let flags = ctxt.read_u16be()?;
+            let value_name_id = ctxt.read_u16be()?;
+            let mut axis_values = Vec::with_capacity(axis_count);
+            for _ in 0..axis_count {
+                axis_values.push(AxisValueRecord {
+                    axis_index: ctxt.read_u16be()?,
+                    value: read_fixed(&mut ctxt)?,
+                });
+            }
+            AxisValue::Format4 {
+                flags,
+                value_name_id,
+                axis_values,
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(axis_value))
+}
+
+/// Read a 16.16 fixed-point number.
+fn read_fixed(ctxt: &mut ReadCtxt<'_>) -> Result<f32, ParseError> {
+    Ok(ctxt.read_i32be()? as f32 / 65536.0)
+}
+
+impl AxisValue {
+    /// The flags of the axis value, see the `AXIS_VALUE_*` constants.
+    pub fn flags(&self) -> u16 {
+        match *self {
+            AxisValue::Format1 { flags, .. }
+            | AxisValue::Format2 { flags, .. }
+            | AxisValue::Format3 { flags, .. }
+            | AxisValue::Format4 { flags, .. } => flags,
+        }
+    }
+
+    /// Name id of the name of the axis value.
+    pub fn value_name_id(&self) -> u16 {
+        match *self {
+            AxisValue::Format1 { value_name_id, .. }
+            | AxisValue::Format2 { value_name_id, .. }
+            | AxisValue::Format3 { value_name_id, .. }
+            | AxisValue::Format4 { value_name_id, .. } => value_name_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_name::fontcode_get_name;
+    use std::ffi::CString;
+
+    #[rustfmt::skip]
+    const STAT: &[u8] = &[
+        0, 1,          // majorVersion
+        0, 1,          // minorVersion
+        0, 8,          // designAxisSize
+        0, 2,          // designAxisCount
+        0, 0, 0, 20,   // designAxesOffset
+        0, 5,          // axisValueCount
+        0, 0, 0, 36,   // offsetToAxisValueOffsets
+        1, 4,          // elidedFallbackNameID
+        // Design axes
+        b'w', b'g', b'h', b't', // axisTag
+        1, 0,                   // axisNameID
+        0, 0,                   // axisOrdering
+        b'w', b'd', b't', b'h', // axisTag
+        1, 1,                   // axisNameID
+        0, 1,                   // axisOrdering
+        // Axis value offsets
+        0, 10,
+        0, 22,
+        0, 42,
+        0, 58,
+        0, 78,
+        // Format 1: Bold
+        0, 1,          // format
+        0, 0,          // axisIndex
+        0, 0,          // flags
+        1, 2,          // valueNameID
+        2, 188, 0, 0,  // value 700
+        // Format 2: Condensed
+        0, 2,          // format
+        0, 1,          // axisIndex
+        0, 0,          // flags
+        1, 3,          // valueNameID
+        0, 75, 0, 0,   // nominalValue 75
+        0, 62, 128, 0, // rangeMinValue 62.5
+        0, 87, 128, 0, // rangeMaxValue 87.5
+        // Format 3: Regular
+        0, 3,          // format
+        0, 0,          // axisIndex
+        0, 2,          // flags
+        1, 4,          // valueNameID
+        1, 144, 0, 0,  // value 400
+        2, 188, 0, 0,  // linkedValue 700
+        // Format 4: Bold Condensed
+        0, 4,          // format
+        0, 2,          // axisCount
+        0, 0,          // flags
+        1, 5,          // valueNameID
+        0, 0,          // axisIndex
+        2, 188, 0, 0,  // value 700
+        0, 1,          // axisIndex
+        0, 75, 0, 0,   // value 75
+        // Unknown format
+        0, 9,
+    ];
+
+    #[rustfmt::skip]
+    const NAME: &[u8] = &[
+        0, 0,  // format
+        0, 3,  // count
+        0, 42, // stringOffset
+        // platformID, encodingID, languageID, nameID, length, offset
+        0, 3, 0, 1, 4, 9, 1, 0, 0, 12, 0, 0,  // Weight
+        0, 3, 0, 1, 4, 9, 1, 1, 0, 10, 0, 12, // Width
+        0, 3, 0, 1, 4, 9, 1, 2, 0, 8, 0, 22,  // Bold
+        // String storage
+        0, b'W', 0, b'e', 0, b'i', 0, b'g', 0, b'h', 0, b't',
+        0, b'W', 0, b'i', 0, b'd', 0, b't', 0, b'h',
+        0, b'B', 0, b'o', 0, b'l', 0, b'd',
+    ];
+
+    #[test]
+    fn read_stat() {
+        let stat = ReadScope::new(STAT).read::<StatTable>().unwrap();
+        assert_eq!(stat.elided_fallback_name_id, Some(260));
+        let axis_tags = stat
+            .design_axes
+            .iter()
+            .map(|axis| axis.axis_tag)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            axis_tags,
+            [u32::from_be_bytes(*b"wght"), u32::from_be_bytes(*b"wdth")]
+        );
+        assert_eq!(stat.design_axes[1].axis_ordering, 1);
+
+        assert_eq!(stat.axis_values.len(), 4);
+        assert_eq!(
+            stat.axis_values[1],
+            AxisValue::Format2 {
+                axis_index: 1,
+                flags: 0,
+                value_name_id: 259,
+                nominal_value: 75.0,
+                range_min_value: 62.5,
+                range_max_value: 87.5,
+            }
+        );
+        assert_eq!(
+            stat.axis_values[2].flags(),
+            AXIS_VALUE_ELIDABLE_AXIS_VALUE_NAME
+        );
+        match &stat.axis_values[3] {
+            AxisValue::Format4 { axis_values, .. } => assert_eq!(
+                axis_values,
+                &[
+                    AxisValueRecord {
+                        axis_index: 0,
+                        value: 700.0
+                    },
+                    AxisValueRecord {
+                        axis_index: 1,
+                        value: 75.0
+                    },
+                ]
+            ),
+            axis_value => panic!("expected format 4 got {:?}", axis_value),
+        }
+    }
+
+    #[test]
+    fn resolve_names() {
+        let stat = ReadScope::new(STAT).read::<StatTable>().unwrap();
+        let weight_axis = &stat.design_axes[0];
+        assert_eq!(
+            fontcode_get_name(NAME, weight_axis.axis_name_id).unwrap(),
+            Some(CString::new("Weight").unwrap())
+        );
+        assert_eq!(
+            fontcode_get_name(NAME, stat.design_axes[1].axis_name_id).unwrap(),
+            Some(CString::new("Width").unwrap())
+        );
+        assert_eq!(
+            fontcode_get_name(NAME, stat.axis_values[0].value_name_id()).unwrap(),
+            Some(CString::new("Bold").unwrap())
+        );
+    }
+
+    #[test]
+    fn bad_design_axis_size() {
+        let mut data = STAT.to_vec();
+        data[5] = 6;
+        assert!(ReadScope::new(&data).read::<StatTable>().is_err());
+    }
+}
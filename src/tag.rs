@@ -1,7 +1,8 @@
 //! Utilities and constants for OpenType tags.
 
 use crate::error::ParseError;
-use std::fmt;
+use alloc::string::{String, ToString};
+use core::fmt;
 
 /// Generate a 4-byte font table tag from byte string
 ///
@@ -135,6 +136,8 @@ pub const CCMP: u32 = tag!(b"ccmp");
 pub const CFAR: u32 = tag!(b"cfar");
 /// `CFF `
 pub const CFF: u32 = tag!(b"CFF ");
+/// `CFF2`
+pub const CFF2: u32 = tag!(b"CFF2");
 /// `cjct`
 pub const CJCT: u32 = tag!(b"cjct");
 /// `clig`
@@ -163,6 +166,8 @@ pub const DFLT: u32 = tag!(b"DFLT");
 pub const DIST: u32 = tag!(b"dist");
 /// `dlig`
 pub const DLIG: u32 = tag!(b"dlig");
+/// `DSIG`
+pub const DSIG: u32 = tag!(b"DSIG");
 /// `dupe`
 pub const DUPE: u32 = tag!(b"dupe");
 /// `EBDT`
@@ -227,6 +232,8 @@ pub const HALN: u32 = tag!(b"haln");
 pub const HDMX: u32 = tag!(b"hdmx");
 /// `head`
 pub const HEAD: u32 = tag!(b"head");
+/// `hebr`
+pub const HEBR: u32 = tag!(b"hebr");
 /// `hhea`
 pub const HHEA: u32 = tag!(b"hhea");
 /// `hlig`
@@ -247,10 +254,14 @@ pub const JSTF: u32 = tag!(b"JSTF");
 pub const JUST: u32 = tag!(b"just");
 /// `kern`
 pub const KERN: u32 = tag!(b"kern");
+/// `khmr`
+pub const KHMR: u32 = tag!(b"khmr");
 /// `knd2`
 pub const KND2: u32 = tag!(b"knd2");
 /// `knda`
 pub const KNDA: u32 = tag!(b"knda");
+/// `lao `
+pub const LAO: u32 = tag!(b"lao ");
 /// `latn`
 pub const LATN: u32 = tag!(b"latn");
 /// `lcar`
@@ -281,12 +292,18 @@ pub const MKMK: u32 = tag!(b"mkmk");
 pub const MLM2: u32 = tag!(b"mlm2");
 /// `mlym`
 pub const MLYM: u32 = tag!(b"mlym");
+/// `mong`
+pub const MONG: u32 = tag!(b"mong");
 /// `mort`
 pub const MORT: u32 = tag!(b"mort");
 /// `morx`
 pub const MORX: u32 = tag!(b"morx");
 /// `mset`
 pub const MSET: u32 = tag!(b"mset");
+/// `mym2`
+pub const MYM2: u32 = tag!(b"mym2");
+/// `mymr`
+pub const MYMR: u32 = tag!(b"mymr");
 /// `name`
 pub const NAME: u32 = tag!(b"name");
 /// `nukt`
@@ -333,6 +350,8 @@ pub const RKRF: u32 = tag!(b"rkrf");
 pub const RLIG: u32 = tag!(b"rlig");
 /// `rphf`
 pub const RPHF: u32 = tag!(b"rphf");
+/// `rvrn`
+pub const RVRN: u32 = tag!(b"rvrn");
 /// `sbix`
 pub const SBIX: u32 = tag!(b"sbix");
 /// `Silf`
@@ -345,6 +364,8 @@ pub const SINH: u32 = tag!(b"sinh");
 pub const SMCP: u32 = tag!(b"smcp");
 /// `SND`
 pub const SND: u32 = tag!(b"SND ");
+/// `STAT`
+pub const STAT: u32 = tag!(b"STAT");
 /// `SVG `
 pub const SVG: u32 = tag!(b"SVG ");
 /// `syrc`
@@ -355,6 +376,10 @@ pub const TAML: u32 = tag!(b"taml");
 pub const TEL2: u32 = tag!(b"tel2");
 /// `telu`
 pub const TELU: u32 = tag!(b"telu");
+/// `thai`
+pub const THAI: u32 = tag!(b"thai");
+/// `tibt`
+pub const TIBT: u32 = tag!(b"tibt");
 /// `tiff`
 pub const TIFF: u32 = tag!(b"tiff");
 /// `tml2`
@@ -363,6 +388,8 @@ pub const TML2: u32 = tag!(b"tml2");
 pub const TNUM: u32 = tag!(b"tnum");
 /// `trak`
 pub const TRAK: u32 = tag!(b"trak");
+/// `TRK`
+pub const TRK: u32 = tag!(b"TRK ");
 /// `ttcf`
 pub const TTCF: u32 = tag!(b"ttcf");
 /// `URD`
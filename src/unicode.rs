@@ -1,3 +1,6 @@
+mod ccc;
+
+use std::cmp::Ordering;
 use std::convert::TryFrom;
 
 /// A Unicode variation selector.
@@ -31,3 +34,36 @@ impl TryFrom<char> for VariationSelector {
         }
     }
 }
+
+/// Look up the Canonical_Combining_Class of a character.
+///
+/// Returns 0 (Not_Reordered) for characters that are not combining marks.
+pub fn canonical_combining_class(ch: char) -> u8 {
+    ccc::CANONICAL_COMBINING_CLASS
+        .binary_search_by(|&(start, end, _)| {
+            if end < ch {
+                Ordering::Less
+            } else if start > ch {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .map(|index| ccc::CANONICAL_COMBINING_CLASS[index].2)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_combining_class() {
+        assert_eq!(canonical_combining_class('a'), 0);
+        assert_eq!(canonical_combining_class('\u{0300}'), 230);
+        assert_eq!(canonical_combining_class('\u{064E}'), 30); // ARABIC FATHA
+        assert_eq!(canonical_combining_class('\u{0651}'), 33); // ARABIC SHADDA
+        assert_eq!(canonical_combining_class('\u{0655}'), 220); // ARABIC HAMZA BELOW
+        assert_eq!(canonical_combining_class('\u{1E94A}'), 7); // ADLAM NUKTA
+    }
+}
@@ -0,0 +1,387 @@
+// Generated from the Unicode 14.0.0 Character Database (UnicodeData.txt).
+
+/// Ranges of characters with a non-zero Canonical_Combining_Class, sorted by code point.
+pub(super) const CANONICAL_COMBINING_CLASS: &[(char, char, u8)] = &[
+    ('\u{0300}', '\u{0314}', 230),
+    ('\u{0315}', '\u{0315}', 232),
+    ('\u{0316}', '\u{0319}', 220),
+    ('\u{031A}', '\u{031A}', 232),
+    ('\u{031B}', '\u{031B}', 216),
+    ('\u{031C}', '\u{0320}', 220),
+    ('\u{0321}', '\u{0322}', 202),
+    ('\u{0323}', '\u{0326}', 220),
+    ('\u{0327}', '\u{0328}', 202),
+    ('\u{0329}', '\u{0333}', 220),
+    ('\u{0334}', '\u{0338}', 1),
+    ('\u{0339}', '\u{033C}', 220),
+    ('\u{033D}', '\u{0344}', 230),
+    ('\u{0345}', '\u{0345}', 240),
+    ('\u{0346}', '\u{0346}', 230),
+    ('\u{0347}', '\u{0349}', 220),
+    ('\u{034A}', '\u{034C}', 230),
+    ('\u{034D}', '\u{034E}', 220),
+    ('\u{0350}', '\u{0352}', 230),
+    ('\u{0353}', '\u{0356}', 220),
+    ('\u{0357}', '\u{0357}', 230),
+    ('\u{0358}', '\u{0358}', 232),
+    ('\u{0359}', '\u{035A}', 220),
+    ('\u{035B}', '\u{035B}', 230),
+    ('\u{035C}', '\u{035C}', 233),
+    ('\u{035D}', '\u{035E}', 234),
+    ('\u{035F}', '\u{035F}', 233),
+    ('\u{0360}', '\u{0361}', 234),
+    ('\u{0362}', '\u{0362}', 233),
+    ('\u{0363}', '\u{036F}', 230),
+    ('\u{0483}', '\u{0487}', 230),
+    ('\u{0591}', '\u{0591}', 220),
+    ('\u{0592}', '\u{0595}', 230),
+    ('\u{0596}', '\u{0596}', 220),
+    ('\u{0597}', '\u{0599}', 230),
+    ('\u{059A}', '\u{059A}', 222),
+    ('\u{059B}', '\u{059B}', 220),
+    ('\u{059C}', '\u{05A1}', 230),
+    ('\u{05A2}', '\u{05A7}', 220),
+    ('\u{05A8}', '\u{05A9}', 230),
+    ('\u{05AA}', '\u{05AA}', 220),
+    ('\u{05AB}', '\u{05AC}', 230),
+    ('\u{05AD}', '\u{05AD}', 222),
+    ('\u{05AE}', '\u{05AE}', 228),
+    ('\u{05AF}', '\u{05AF}', 230),
+    ('\u{05B0}', '\u{05B0}', 10),
+    ('\u{05B1}', '\u{05B1}', 11),
+    ('\u{05B2}', '\u{05B2}', 12),
+    ('\u{05B3}', '\u{05B3}', 13),
+    ('\u{05B4}', '\u{05B4}', 14),
+    ('\u{05B5}', '\u{05B5}', 15),
+    ('\u{05B6}', '\u{05B6}', 16),
+    ('\u{05B7}', '\u{05B7}', 17),
+    ('\u{05B8}', '\u{05B8}', 18),
+    ('\u{05B9}', '\u{05BA}', 19),
+    ('\u{05BB}', '\u{05BB}', 20),
+    ('\u{05BC}', '\u{05BC}', 21),
+    ('\u{05BD}', '\u{05BD}', 22),
+    ('\u{05BF}', '\u{05BF}', 23),
+    ('\u{05C1}', '\u{05C1}', 24),
+    ('\u{05C2}', '\u{05C2}', 25),
+    ('\u{05C4}', '\u{05C4}', 230),
+    ('\u{05C5}', '\u{05C5}', 220),
+    ('\u{05C7}', '\u{05C7}', 18),
+    ('\u{0610}', '\u{0617}', 230),
+    ('\u{0618}', '\u{0618}', 30),
+    ('\u{0619}', '\u{0619}', 31),
+    ('\u{061A}', '\u{061A}', 32),
+    ('\u{064B}', '\u{064B}', 27),
+    ('\u{064C}', '\u{064C}', 28),
+    ('\u{064D}', '\u{064D}', 29),
+    ('\u{064E}', '\u{064E}', 30),
+    ('\u{064F}', '\u{064F}', 31),
+    ('\u{0650}', '\u{0650}', 32),
+    ('\u{0651}', '\u{0651}', 33),
+    ('\u{0652}', '\u{0652}', 34),
+    ('\u{0653}', '\u{0654}', 230),
+    ('\u{0655}', '\u{0656}', 220),
+    ('\u{0657}', '\u{065B}', 230),
+    ('\u{065C}', '\u{065C}', 220),
+    ('\u{065D}', '\u{065E}', 230),
+    ('\u{065F}', '\u{065F}', 220),
+    ('\u{0670}', '\u{0670}', 35),
+    ('\u{06D6}', '\u{06DC}', 230),
+    ('\u{06DF}', '\u{06E2}', 230),
+    ('\u{06E3}', '\u{06E3}', 220),
+    ('\u{06E4}', '\u{06E4}', 230),
+    ('\u{06E7}', '\u{06E8}', 230),
+    ('\u{06EA}', '\u{06EA}', 220),
+    ('\u{06EB}', '\u{06EC}', 230),
+    ('\u{06ED}', '\u{06ED}', 220),
+    ('\u{0711}', '\u{0711}', 36),
+    ('\u{0730}', '\u{0730}', 230),
+    ('\u{0731}', '\u{0731}', 220),
+    ('\u{0732}', '\u{0733}', 230),
+    ('\u{0734}', '\u{0734}', 220),
+    ('\u{0735}', '\u{0736}', 230),
+    ('\u{0737}', '\u{0739}', 220),
+    ('\u{073A}', '\u{073A}', 230),
+    ('\u{073B}', '\u{073C}', 220),
+    ('\u{073D}', '\u{073D}', 230),
+    ('\u{073E}', '\u{073E}', 220),
+    ('\u{073F}', '\u{0741}', 230),
+    ('\u{0742}', '\u{0742}', 220),
+    ('\u{0743}', '\u{0743}', 230),
+    ('\u{0744}', '\u{0744}', 220),
+    ('\u{0745}', '\u{0745}', 230),
+    ('\u{0746}', '\u{0746}', 220),
+    ('\u{0747}', '\u{0747}', 230),
+    ('\u{0748}', '\u{0748}', 220),
+    ('\u{0749}', '\u{074A}', 230),
+    ('\u{07EB}', '\u{07F1}', 230),
+    ('\u{07F2}', '\u{07F2}', 220),
+    ('\u{07F3}', '\u{07F3}', 230),
+    ('\u{07FD}', '\u{07FD}', 220),
+    ('\u{0816}', '\u{0819}', 230),
+    ('\u{081B}', '\u{0823}', 230),
+    ('\u{0825}', '\u{0827}', 230),
+    ('\u{0829}', '\u{082D}', 230),
+    ('\u{0859}', '\u{085B}', 220),
+    ('\u{0898}', '\u{0898}', 230),
+    ('\u{0899}', '\u{089B}', 220),
+    ('\u{089C}', '\u{089F}', 230),
+    ('\u{08CA}', '\u{08CE}', 230),
+    ('\u{08CF}', '\u{08D3}', 220),
+    ('\u{08D4}', '\u{08E1}', 230),
+    ('\u{08E3}', '\u{08E3}', 220),
+    ('\u{08E4}', '\u{08E5}', 230),
+    ('\u{08E6}', '\u{08E6}', 220),
+    ('\u{08E7}', '\u{08E8}', 230),
+    ('\u{08E9}', '\u{08E9}', 220),
+    ('\u{08EA}', '\u{08EC}', 230),
+    ('\u{08ED}', '\u{08EF}', 220),
+    ('\u{08F0}', '\u{08F0}', 27),
+    ('\u{08F1}', '\u{08F1}', 28),
+    ('\u{08F2}', '\u{08F2}', 29),
+    ('\u{08F3}', '\u{08F5}', 230),
+    ('\u{08F6}', '\u{08F6}', 220),
+    ('\u{08F7}', '\u{08F8}', 230),
+    ('\u{08F9}', '\u{08FA}', 220),
+    ('\u{08FB}', '\u{08FF}', 230),
+    ('\u{093C}', '\u{093C}', 7),
+    ('\u{094D}', '\u{094D}', 9),
+    ('\u{0951}', '\u{0951}', 230),
+    ('\u{0952}', '\u{0952}', 220),
+    ('\u{0953}', '\u{0954}', 230),
+    ('\u{09BC}', '\u{09BC}', 7),
+    ('\u{09CD}', '\u{09CD}', 9),
+    ('\u{09FE}', '\u{09FE}', 230),
+    ('\u{0A3C}', '\u{0A3C}', 7),
+    ('\u{0A4D}', '\u{0A4D}', 9),
+    ('\u{0ABC}', '\u{0ABC}', 7),
+    ('\u{0ACD}', '\u{0ACD}', 9),
+    ('\u{0B3C}', '\u{0B3C}', 7),
+    ('\u{0B4D}', '\u{0B4D}', 9),
+    ('\u{0BCD}', '\u{0BCD}', 9),
+    ('\u{0C3C}', '\u{0C3C}', 7),
+    ('\u{0C4D}', '\u{0C4D}', 9),
+    ('\u{0C55}', '\u{0C55}', 84),
+    ('\u{0C56}', '\u{0C56}', 91),
+    ('\u{0CBC}', '\u{0CBC}', 7),
+    ('\u{0CCD}', '\u{0CCD}', 9),
+    ('\u{0D3B}', '\u{0D3C}', 9),
+    ('\u{0D4D}', '\u{0D4D}', 9),
+    ('\u{0DCA}', '\u{0DCA}', 9),
+    ('\u{0E38}', '\u{0E39}', 103),
+    ('\u{0E3A}', '\u{0E3A}', 9),
+    ('\u{0E48}', '\u{0E4B}', 107),
+    ('\u{0EB8}', '\u{0EB9}', 118),
+    ('\u{0EBA}', '\u{0EBA}', 9),
+    ('\u{0EC8}', '\u{0ECB}', 122),
+    ('\u{0F18}', '\u{0F19}', 220),
+    ('\u{0F35}', '\u{0F35}', 220),
+    ('\u{0F37}', '\u{0F37}', 220),
+    ('\u{0F39}', '\u{0F39}', 216),
+    ('\u{0F71}', '\u{0F71}', 129),
+    ('\u{0F72}', '\u{0F72}', 130),
+    ('\u{0F74}', '\u{0F74}', 132),
+    ('\u{0F7A}', '\u{0F7D}', 130),
+    ('\u{0F80}', '\u{0F80}', 130),
+    ('\u{0F82}', '\u{0F83}', 230),
+    ('\u{0F84}', '\u{0F84}', 9),
+    ('\u{0F86}', '\u{0F87}', 230),
+    ('\u{0FC6}', '\u{0FC6}', 220),
+    ('\u{1037}', '\u{1037}', 7),
+    ('\u{1039}', '\u{103A}', 9),
+    ('\u{108D}', '\u{108D}', 220),
+    ('\u{135D}', '\u{135F}', 230),
+    ('\u{1714}', '\u{1715}', 9),
+    ('\u{1734}', '\u{1734}', 9),
+    ('\u{17D2}', '\u{17D2}', 9),
+    ('\u{17DD}', '\u{17DD}', 230),
+    ('\u{18A9}', '\u{18A9}', 228),
+    ('\u{1939}', '\u{1939}', 222),
+    ('\u{193A}', '\u{193A}', 230),
+    ('\u{193B}', '\u{193B}', 220),
+    ('\u{1A17}', '\u{1A17}', 230),
+    ('\u{1A18}', '\u{1A18}', 220),
+    ('\u{1A60}', '\u{1A60}', 9),
+    ('\u{1A75}', '\u{1A7C}', 230),
+    ('\u{1A7F}', '\u{1A7F}', 220),
+    ('\u{1AB0}', '\u{1AB4}', 230),
+    ('\u{1AB5}', '\u{1ABA}', 220),
+    ('\u{1ABB}', '\u{1ABC}', 230),
+    ('\u{1ABD}', '\u{1ABD}', 220),
+    ('\u{1ABF}', '\u{1AC0}', 220),
+    ('\u{1AC1}', '\u{1AC2}', 230),
+    ('\u{1AC3}', '\u{1AC4}', 220),
+    ('\u{1AC5}', '\u{1AC9}', 230),
+    ('\u{1ACA}', '\u{1ACA}', 220),
+    ('\u{1ACB}', '\u{1ACE}', 230),
+    ('\u{1B34}', '\u{1B34}', 7),
+    ('\u{1B44}', '\u{1B44}', 9),
+    ('\u{1B6B}', '\u{1B6B}', 230),
+    ('\u{1B6C}', '\u{1B6C}', 220),
+    ('\u{1B6D}', '\u{1B73}', 230),
+    ('\u{1BAA}', '\u{1BAB}', 9),
+    ('\u{1BE6}', '\u{1BE6}', 7),
+    ('\u{1BF2}', '\u{1BF3}', 9),
+    ('\u{1C37}', '\u{1C37}', 7),
+    ('\u{1CD0}', '\u{1CD2}', 230),
+    ('\u{1CD4}', '\u{1CD4}', 1),
+    ('\u{1CD5}', '\u{1CD9}', 220),
+    ('\u{1CDA}', '\u{1CDB}', 230),
+    ('\u{1CDC}', '\u{1CDF}', 220),
+    ('\u{1CE0}', '\u{1CE0}', 230),
+    ('\u{1CE2}', '\u{1CE8}', 1),
+    ('\u{1CED}', '\u{1CED}', 220),
+    ('\u{1CF4}', '\u{1CF4}', 230),
+    ('\u{1CF8}', '\u{1CF9}', 230),
+    ('\u{1DC0}', '\u{1DC1}', 230),
+    ('\u{1DC2}', '\u{1DC2}', 220),
+    ('\u{1DC3}', '\u{1DC9}', 230),
+    ('\u{1DCA}', '\u{1DCA}', 220),
+    ('\u{1DCB}', '\u{1DCC}', 230),
+    ('\u{1DCD}', '\u{1DCD}', 234),
+    ('\u{1DCE}', '\u{1DCE}', 214),
+    ('\u{1DCF}', '\u{1DCF}', 220),
+    ('\u{1DD0}', '\u{1DD0}', 202),
+    ('\u{1DD1}', '\u{1DF5}', 230),
+    ('\u{1DF6}', '\u{1DF6}', 232),
+    ('\u{1DF7}', '\u{1DF8}', 228),
+    ('\u{1DF9}', '\u{1DF9}', 220),
+    ('\u{1DFA}', '\u{1DFA}', 218),
+    ('\u{1DFB}', '\u{1DFB}', 230),
+    ('\u{1DFC}', '\u{1DFC}', 233),
+    ('\u{1DFD}', '\u{1DFD}', 220),
+    ('\u{1DFE}', '\u{1DFE}', 230),
+    ('\u{1DFF}', '\u{1DFF}', 220),
+    ('\u{20D0}', '\u{20D1}', 230),
+    ('\u{20D2}', '\u{20D3}', 1),
+    ('\u{20D4}', '\u{20D7}', 230),
+    ('\u{20D8}', '\u{20DA}', 1),
+    ('\u{20DB}', '\u{20DC}', 230),
+    ('\u{20E1}', '\u{20E1}', 230),
+    ('\u{20E5}', '\u{20E6}', 1),
+    ('\u{20E7}', '\u{20E7}', 230),
+    ('\u{20E8}', '\u{20E8}', 220),
+    ('\u{20E9}', '\u{20E9}', 230),
+    ('\u{20EA}', '\u{20EB}', 1),
+    ('\u{20EC}', '\u{20EF}', 220),
+    ('\u{20F0}', '\u{20F0}', 230),
+    ('\u{2CEF}', '\u{2CF1}', 230),
+    ('\u{2D7F}', '\u{2D7F}', 9),
+    ('\u{2DE0}', '\u{2DFF}', 230),
+    ('\u{302A}', '\u{302A}', 218),
+    ('\u{302B}', '\u{302B}', 228),
+    ('\u{302C}', '\u{302C}', 232),
+    ('\u{302D}', '\u{302D}', 222),
+    ('\u{302E}', '\u{302F}', 224),
+    ('\u{3099}', '\u{309A}', 8),
+    ('\u{A66F}', '\u{A66F}', 230),
+    ('\u{A674}', '\u{A67D}', 230),
+    ('\u{A69E}', '\u{A69F}', 230),
+    ('\u{A6F0}', '\u{A6F1}', 230),
+    ('\u{A806}', '\u{A806}', 9),
+    ('\u{A82C}', '\u{A82C}', 9),
+    ('\u{A8C4}', '\u{A8C4}', 9),
+    ('\u{A8E0}', '\u{A8F1}', 230),
+    ('\u{A92B}', '\u{A92D}', 220),
+    ('\u{A953}', '\u{A953}', 9),
+    ('\u{A9B3}', '\u{A9B3}', 7),
+    ('\u{A9C0}', '\u{A9C0}', 9),
+    ('\u{AAB0}', '\u{AAB0}', 230),
+    ('\u{AAB2}', '\u{AAB3}', 230),
+    ('\u{AAB4}', '\u{AAB4}', 220),
+    ('\u{AAB7}', '\u{AAB8}', 230),
+    ('\u{AABE}', '\u{AABF}', 230),
+    ('\u{AAC1}', '\u{AAC1}', 230),
+    ('\u{AAF6}', '\u{AAF6}', 9),
+    ('\u{ABED}', '\u{ABED}', 9),
+    ('\u{FB1E}', '\u{FB1E}', 26),
+    ('\u{FE20}', '\u{FE26}', 230),
+    ('\u{FE27}', '\u{FE2D}', 220),
+    ('\u{FE2E}', '\u{FE2F}', 230),
+    ('\u{101FD}', '\u{101FD}', 220),
+    ('\u{102E0}', '\u{102E0}', 220),
+    ('\u{10376}', '\u{1037A}', 230),
+    ('\u{10A0D}', '\u{10A0D}', 220),
+    ('\u{10A0F}', '\u{10A0F}', 230),
+    ('\u{10A38}', '\u{10A38}', 230),
+    ('\u{10A39}', '\u{10A39}', 1),
+    ('\u{10A3A}', '\u{10A3A}', 220),
+    ('\u{10A3F}', '\u{10A3F}', 9),
+    ('\u{10AE5}', '\u{10AE5}', 230),
+    ('\u{10AE6}', '\u{10AE6}', 220),
+    ('\u{10D24}', '\u{10D27}', 230),
+    ('\u{10EAB}', '\u{10EAC}', 230),
+    ('\u{10F46}', '\u{10F47}', 220),
+    ('\u{10F48}', '\u{10F4A}', 230),
+    ('\u{10F4B}', '\u{10F4B}', 220),
+    ('\u{10F4C}', '\u{10F4C}', 230),
+    ('\u{10F4D}', '\u{10F50}', 220),
+    ('\u{10F82}', '\u{10F82}', 230),
+    ('\u{10F83}', '\u{10F83}', 220),
+    ('\u{10F84}', '\u{10F84}', 230),
+    ('\u{10F85}', '\u{10F85}', 220),
+    ('\u{11046}', '\u{11046}', 9),
+    ('\u{11070}', '\u{11070}', 9),
+    ('\u{1107F}', '\u{1107F}', 9),
+    ('\u{110B9}', '\u{110B9}', 9),
+    ('\u{110BA}', '\u{110BA}', 7),
+    ('\u{11100}', '\u{11102}', 230),
+    ('\u{11133}', '\u{11134}', 9),
+    ('\u{11173}', '\u{11173}', 7),
+    ('\u{111C0}', '\u{111C0}', 9),
+    ('\u{111CA}', '\u{111CA}', 7),
+    ('\u{11235}', '\u{11235}', 9),
+    ('\u{11236}', '\u{11236}', 7),
+    ('\u{112E9}', '\u{112E9}', 7),
+    ('\u{112EA}', '\u{112EA}', 9),
+    ('\u{1133B}', '\u{1133C}', 7),
+    ('\u{1134D}', '\u{1134D}', 9),
+    ('\u{11366}', '\u{1136C}', 230),
+    ('\u{11370}', '\u{11374}', 230),
+    ('\u{11442}', '\u{11442}', 9),
+    ('\u{11446}', '\u{11446}', 7),
+    ('\u{1145E}', '\u{1145E}', 230),
+    ('\u{114C2}', '\u{114C2}', 9),
+    ('\u{114C3}', '\u{114C3}', 7),
+    ('\u{115BF}', '\u{115BF}', 9),
+    ('\u{115C0}', '\u{115C0}', 7),
+    ('\u{1163F}', '\u{1163F}', 9),
+    ('\u{116B6}', '\u{116B6}', 9),
+    ('\u{116B7}', '\u{116B7}', 7),
+    ('\u{1172B}', '\u{1172B}', 9),
+    ('\u{11839}', '\u{11839}', 9),
+    ('\u{1183A}', '\u{1183A}', 7),
+    ('\u{1193D}', '\u{1193E}', 9),
+    ('\u{11943}', '\u{11943}', 7),
+    ('\u{119E0}', '\u{119E0}', 9),
+    ('\u{11A34}', '\u{11A34}', 9),
+    ('\u{11A47}', '\u{11A47}', 9),
+    ('\u{11A99}', '\u{11A99}', 9),
+    ('\u{11C3F}', '\u{11C3F}', 9),
+    ('\u{11D42}', '\u{11D42}', 7),
+    ('\u{11D44}', '\u{11D45}', 9),
+    ('\u{11D97}', '\u{11D97}', 9),
+    ('\u{16AF0}', '\u{16AF4}', 1),
+    ('\u{16B30}', '\u{16B36}', 230),
+    ('\u{16FF0}', '\u{16FF1}', 6),
+    ('\u{1BC9E}', '\u{1BC9E}', 1),
+    ('\u{1D165}', '\u{1D166}', 216),
+    ('\u{1D167}', '\u{1D169}', 1),
+    ('\u{1D16D}', '\u{1D16D}', 226),
+    ('\u{1D16E}', '\u{1D172}', 216),
+    ('\u{1D17B}', '\u{1D182}', 220),
+    ('\u{1D185}', '\u{1D189}', 230),
+    ('\u{1D18A}', '\u{1D18B}', 220),
+    ('\u{1D1AA}', '\u{1D1AD}', 230),
+    ('\u{1D242}', '\u{1D244}', 230),
+    ('\u{1E000}', '\u{1E006}', 230),
+    ('\u{1E008}', '\u{1E018}', 230),
+    ('\u{1E01B}', '\u{1E021}', 230),
+    ('\u{1E023}', '\u{1E024}', 230),
+    ('\u{1E026}', '\u{1E02A}', 230),
+    ('\u{1E130}', '\u{1E136}', 230),
+    ('\u{1E2AE}', '\u{1E2AE}', 230),
+    ('\u{1E2EC}', '\u{1E2EF}', 230),
+    ('\u{1E8D0}', '\u{1E8D6}', 220),
+    ('\u{1E944}', '\u{1E949}', 230),
+    ('\u{1E94A}', '\u{1E94A}', 7),
+];
@@ -326,6 +326,7 @@ fn make_direct_glyph(glyph_index: u16) -> RawGlyph<()> {
         unicodes: tiny_vec![],
         glyph_index: glyph_index,
         liga_component_pos: 0,
+        cluster: 0,
         glyph_origin: GlyphOrigin::Direct,
         small_caps: false,
         multi_subst_dup: false,
@@ -337,6 +338,73 @@ fn make_direct_glyph(glyph_index: u16) -> RawGlyph<()> {
     }
 }
 
+#[test]
+fn test_gsub_apply_alternates() {
+    let font_buffer = read_fixture("tests/aots/gsub3_1_simple_f1.otf");
+    let font_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile>()
+        .expect("error reading font file");
+    let ttf = match font_file.font {
+        OpenTypeFont::Single(offset_table) => offset_table,
+        OpenTypeFont::Collection(_) => panic!("expected a TTF font"),
+    };
+    let gsub_table = ttf
+        .read_table(&font_file.scope, tag::GSUB)
+        .unwrap()
+        .unwrap()
+        .read::<LayoutTable<GSUB>>()
+        .unwrap();
+    let cache = new_layout_cache(gsub_table);
+    let script_tag = tag::from_string("latn").unwrap();
+    let feature_tag = tag::from_string("test").unwrap();
+
+    // Glyph 18 has the alternates 20, 21, and 22
+    for &(alternate, expected) in &[(0, 20), (2, 22), (3, 18)] {
+        let mut glyphs = vec![make_direct_glyph(18), make_direct_glyph(19)];
+        gsub::gsub_apply_alternates(
+            &cache,
+            None,
+            script_tag,
+            None,
+            &[(feature_tag, alternate)],
+            &mut glyphs,
+        )
+        .unwrap();
+        let glyph_indices = glyphs.into_iter().map(|g| g.glyph_index).collect_vec();
+        assert_eq!(glyph_indices, vec![expected, 19]);
+    }
+}
+
+#[test]
+fn test_gsub_query_alternates() {
+    let font_buffer = read_fixture("tests/aots/gsub3_1_simple_f1.otf");
+    let font_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile>()
+        .expect("error reading font file");
+    let ttf = match font_file.font {
+        OpenTypeFont::Single(offset_table) => offset_table,
+        OpenTypeFont::Collection(_) => panic!("expected a TTF font"),
+    };
+    let gsub_table = ttf
+        .read_table(&font_file.scope, tag::GSUB)
+        .unwrap()
+        .unwrap()
+        .read::<LayoutTable<GSUB>>()
+        .unwrap();
+    let cache = new_layout_cache(gsub_table);
+    let script_tag = tag::from_string("latn").unwrap();
+    let feature_tag = tag::from_string("test").unwrap();
+    let glyphs = vec![make_direct_glyph(18), make_direct_glyph(19)];
+
+    let alternates = |i| {
+        gsub::gsub_query_alternates(&cache, None, script_tag, None, feature_tag, &glyphs, i)
+            .unwrap()
+    };
+    assert_eq!(alternates(0), vec![20, 21, 22]);
+    assert_eq!(alternates(1), Vec::<u16>::new());
+    assert_eq!(alternates(2), Vec::<u16>::new());
+}
+
 mod aots {
     use super::*;
 
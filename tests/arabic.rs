@@ -8,7 +8,7 @@ mod arabic_tests {
 
     use allsorts::binary::read::ReadScope;
     use allsorts::font_data_impl::FontDataImpl;
-    use allsorts::gsub::RawGlyph;
+    use allsorts::gsub::{FeatureInfo, RawGlyph};
     use allsorts::scripts::arabic::gsub_apply_arabic;
     use allsorts::tables::cmap::CmapSubtable;
     use allsorts::tables::OpenTypeFile;
@@ -223,6 +223,27 @@ mod arabic_tests {
         )
     }
 
+    #[test]
+    fn rlig_mark_filtering_set() {
+        // Each test contains a mark between the components of an `RLIG` ligature that is
+        // skipped because it is not in the lookup's mark filtering set
+        test(
+            None,
+            vec![
+                (
+                    "tests/fonts/arabic/Scheherazade-Regular.ttf",
+                    "\u{648}\u{64e}\u{654}",
+                    vec![280, 1075],
+                ),
+                (
+                    "tests/fonts/arabic/Scheherazade-Regular.ttf",
+                    "\u{6d5}\u{64f}\u{654}",
+                    vec![512, 1076],
+                ),
+            ],
+        )
+    }
+
     #[test]
     fn calt() {
         // Each test contains at least one glyph that should trigger `GSUB`'s `CALT` feature
@@ -323,6 +344,42 @@ mod arabic_tests {
         )
     }
 
+    #[test]
+    fn zwj_zwnj() {
+        // ZWJ should join and ZWNJ should break the joining of its neighbours, then both should
+        // be removed
+        test(
+            None,
+            vec![
+                (
+                    "tests/fonts/noto/NotoNaskhArabic-Regular.ttf",
+                    "\u{644}\u{627}",
+                    vec![513],
+                ),
+                (
+                    "tests/fonts/noto/NotoNaskhArabic-Regular.ttf",
+                    "\u{644}\u{200c}\u{627}",
+                    vec![68, 39],
+                ),
+                (
+                    "tests/fonts/noto/NotoNaskhArabic-Regular.ttf",
+                    "\u{644}\u{200d}",
+                    vec![491],
+                ),
+                (
+                    "tests/fonts/noto/NotoNaskhArabic-Regular.ttf",
+                    "\u{200d}\u{627}",
+                    vec![433],
+                ),
+                (
+                    "tests/fonts/noto/NotoNaskhArabic-Regular.ttf",
+                    "\u{644}\u{200c}",
+                    vec![68],
+                ),
+            ],
+        )
+    }
+
     trait IIMF {
         fn add(&mut self, input_shorthand: &str, expected_shorthand: &str);
     }
@@ -460,7 +517,33 @@ mod arabic_tests {
         )
     }
 
+    #[test]
+    fn extra_features() {
+        // ss01 replaces the initial form of beh, so is applied after init
+        let ss01 = FeatureInfo {
+            feature_tag: tag::from_string("ss01").unwrap(),
+            alternate: None,
+        };
+        test_with_features(
+            None,
+            &[ss01],
+            vec![(
+                "tests/fonts/arabic/amiri-regular.ttf",
+                "\u{624}\u{628}\u{627}",
+                vec![385, 5511, 2020],
+            )],
+        )
+    }
+
     fn test(lang_tag: Option<u32>, test_cases: Vec<(&str, &str, Vec<u16>)>) {
+        test_with_features(lang_tag, &[], test_cases)
+    }
+
+    fn test_with_features(
+        lang_tag: Option<u32>,
+        extra_features: &[FeatureInfo],
+        test_cases: Vec<(&str, &str, Vec<u16>)>,
+    ) {
         for (font_path, text, expected) in test_cases {
             let font_contents = common::read_fixture(font_path);
             let opentype_file = ReadScope::new(&font_contents)
@@ -502,6 +585,7 @@ mod arabic_tests {
                     .map(Rc::as_ref),
                 tag::ARAB,
                 lang_tag,
+                extra_features,
                 &mut raw_glyphs,
             )
             .unwrap();
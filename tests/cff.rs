@@ -6,15 +6,19 @@
 #[path = "common.rs"]
 mod common;
 
+use std::convert::TryFrom;
 use std::fmt::Debug;
 
 use itertools::Itertools;
 
 use allsorts::binary::read::ReadScope;
 use allsorts::binary::write::{WriteBinary, WriteBuffer};
-use allsorts::cff::{CFFVariant, Charset, Dict, DictDefault, FontDict, Operand, CFF};
-use allsorts::subset::subset;
-use allsorts::tables::{OpenTypeFile, OpenTypeFont};
+use allsorts::cff::charstring::PathSegment;
+use allsorts::cff::{
+    CFFVariant, Charset, Dict, DictDefault, FontDict, GlyphNameMap, Operand, Operator, CFF,
+};
+use allsorts::subset::{subset, SubsetOptions};
+use allsorts::tables::{FontTableProvider, MaxpTable, OpenTypeFile, OpenTypeFont};
 use allsorts::tag;
 
 use crate::common::read_fixture;
@@ -46,8 +50,12 @@ fn test_read_write_cff_cid() {
         .expect("error parsing written CFF table");
 
     // Compare
-    assert_eq!(cff2.header, cff.header);
-    assert_eq!(cff2.name_index.count, cff.name_index.count);
+    assert_eq!(cff2.header.major, cff.header.major);
+    assert_eq!(cff2.header.minor, cff.header.minor);
+    // The header is always written with 4 byte offsets
+    assert_eq!(cff2.header.hdr_size, 4);
+    assert_eq!(cff2.header.off_size, 4);
+    assert_eq!(cff2.name_index.len(), cff.name_index.len());
     assert_eq!(cff2.string_index.len(), cff.string_index.len());
     assert_eq!(cff2.global_subr_index.count, cff.global_subr_index.count);
     assert_eq!(cff2.fonts.len(), cff.fonts.len());
@@ -136,7 +144,7 @@ fn test_read_write_cff_type_1() {
 
     // Compare
     assert_eq!(cff2.header, cff.header);
-    assert_eq!(cff2.name_index.count, cff.name_index.count);
+    assert_eq!(cff2.name_index.len(), cff.name_index.len());
     assert_eq!(cff2.string_index.len(), cff.string_index.len());
     assert_eq!(cff2.global_subr_index.count, cff.global_subr_index.count);
     assert_eq!(cff2.fonts.len(), cff.fonts.len());
@@ -193,7 +201,8 @@ fn test_subset_cff_cid() {
     assert!(subset(
         &opentype_file.font_provider(0).unwrap(),
         &glyph_ids,
-        Some(Box::new(cmap))
+        Some(Box::new(cmap)),
+        SubsetOptions::default()
     )
     .is_ok());
 }
@@ -218,7 +227,8 @@ fn test_subset_cff_type1() {
     assert!(subset(
         &opentype_file.font_provider(0).unwrap(),
         &glyph_ids,
-        Some(Box::new(cmap0))
+        Some(Box::new(cmap0)),
+        SubsetOptions::default()
     )
     .is_ok());
 }
@@ -246,6 +256,7 @@ fn test_subset_cff_type1_iso_adobe() {
         &opentype_file.font_provider(0).unwrap(),
         &glyph_ids,
         Some(Box::new(cmap)),
+        SubsetOptions::default(),
     )
     .unwrap();
     let scope = ReadScope::new(&subset_buffer);
@@ -267,6 +278,321 @@ fn test_subset_cff_type1_iso_adobe() {
     }
 }
 
+#[test]
+fn test_subset_cff_force_type1() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let glyph_ids = (0..300).collect_vec();
+
+    let is_cid = |options| {
+        let subset_buffer = subset(&provider, &glyph_ids, None, options).unwrap();
+        let otf = ReadScope::new(&subset_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        let subset_provider = otf.font_provider(0).unwrap();
+        let cff_table_data = subset_provider.read_table_data(tag::CFF).unwrap();
+        let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+        assert_eq!(cff.glyph_count(0), Some(300));
+        cff.is_cid(0).unwrap()
+    };
+
+    assert!(is_cid(SubsetOptions::default()));
+    let options = SubsetOptions {
+        force_type1: true,
+        ..SubsetOptions::default()
+    };
+    assert!(!is_cid(options));
+}
+
+#[test]
+fn test_subset_cff_type1_string_index() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+    let glyph_ids = [0, 1, 53, 66, 67, 70, 72, 73, 74, 79, 84, 85, 86];
+    let expected_names = glyph_ids
+        .iter()
+        .map(|&glyph_id| {
+            let sid = cff.fonts[0].charset.id_for_glyph(glyph_id).unwrap();
+            cff.read_string(sid).unwrap()
+        })
+        .collect_vec();
+    let expected_notice = cff.fonts[0]
+        .top_dict
+        .get_i32(Operator::Notice)
+        .map(|sid| cff.read_string(sid.unwrap() as u16).unwrap());
+
+    let (subset_cff, _) = cff.subset(0, &glyph_ids, true).unwrap();
+    let mut buffer = WriteBuffer::new();
+    CFF::write(&mut buffer, &subset_cff).unwrap();
+    let subset_buffer = buffer.into_inner();
+    let subset_cff = ReadScope::new(&subset_buffer).read::<CFF<'_>>().unwrap();
+
+    assert!(subset_cff.string_index.len() < cff.string_index.len());
+    let actual_names = (0..glyph_ids.len() as u16)
+        .map(|glyph_id| {
+            let sid = subset_cff.fonts[0].charset.id_for_glyph(glyph_id).unwrap();
+            subset_cff.read_string(sid).unwrap()
+        })
+        .collect_vec();
+    assert_eq!(actual_names, expected_names);
+    let actual_notice = subset_cff.fonts[0]
+        .top_dict
+        .get_i32(Operator::Notice)
+        .map(|sid| subset_cff.read_string(sid.unwrap() as u16).unwrap());
+    assert_eq!(actual_notice, expected_notice);
+}
+
+#[test]
+fn test_glyph_path_type1() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+    let font = &cff.fonts[0];
+
+    let mut glyph_o = None;
+    for glyph_id in 0..font.char_strings_index.len() as u16 {
+        let path = cff.glyph_path(0, glyph_id).unwrap();
+        let sid = font.charset.id_for_glyph(glyph_id).unwrap();
+        if cff.read_string(sid).unwrap() == "O" {
+            glyph_o = Some(path);
+        }
+    }
+
+    // O has an outer and inner contour
+    let path = glyph_o.expect("glyph O not found");
+    let contours = path
+        .segments
+        .iter()
+        .filter(|segment| match segment {
+            PathSegment::MoveTo(_) => true,
+            _ => false,
+        })
+        .count();
+    assert_eq!(contours, 2);
+}
+
+#[test]
+fn test_glyph_path_cid() {
+    let buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+    let font = &cff.fonts[0];
+
+    for glyph_id in 0..font.char_strings_index.len() as u16 {
+        assert!(cff.glyph_path(0, glyph_id).is_ok());
+    }
+}
+
+#[test]
+fn test_compute_font_bbox() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+    let font = &cff.fonts[0];
+
+    let bbox = cff.compute_font_bbox(0).unwrap();
+    assert_eq!(
+        font.top_dict.get(Operator::FontBBox),
+        Some(
+            [
+                Operand::Integer(i32::from(bbox.x_min)),
+                Operand::Integer(i32::from(bbox.y_min)),
+                Operand::Integer(i32::from(bbox.x_max)),
+                Operand::Integer(i32::from(bbox.y_max)),
+            ]
+            .as_ref()
+        )
+    );
+}
+
+#[test]
+fn test_ros() {
+    let buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+
+    assert_eq!(
+        cff.ros(0).unwrap().unwrap(),
+        (String::from("Adobe"), String::from("Identity"), 0)
+    );
+    assert!(cff.ros(1).is_none());
+}
+
+#[test]
+fn test_ros_type1() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+
+    assert!(cff.ros(0).is_none());
+}
+
+#[test]
+fn test_font_metadata() {
+    let buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP).unwrap())
+        .read::<MaxpTable>()
+        .unwrap();
+
+    assert_eq!(cff.glyph_count(0), Some(usize::from(maxp.num_glyphs)));
+    assert_eq!(cff.is_cid(0), Some(true));
+    assert_eq!(cff.font_name(0), Some(String::from("NotoSansJP-Regular")));
+    assert_eq!(cff.glyph_count(1), None);
+    assert_eq!(cff.is_cid(1), None);
+    assert_eq!(cff.font_name(1), None);
+}
+
+#[test]
+fn test_font_metadata_type1() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP).unwrap())
+        .read::<MaxpTable>()
+        .unwrap();
+
+    assert_eq!(cff.glyph_count(0), Some(usize::from(maxp.num_glyphs)));
+    assert_eq!(cff.is_cid(0), Some(false));
+    assert_eq!(cff.font_name(0), Some(String::from("Klei")));
+}
+
+#[test]
+fn test_font_info() {
+    let buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let mut cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+
+    let info = cff.fonts[0].font_info(&cff).unwrap();
+    assert_eq!(info.family_name, Some(String::from("Noto Sans JP")));
+    assert_eq!(info.full_name, Some(String::from("Noto Sans JP Regular")));
+    assert_eq!(info.weight, Some(String::from("Regular")));
+    assert_eq!(info.version, None);
+    assert!(!info.is_fixed_pitch);
+    assert_eq!(info.italic_angle, 0.);
+    assert_eq!(info.underline_position, -150.);
+    assert_eq!(info.font_matrix, [0.001, 0., 0., 0.001, 0., 0.]);
+
+    cff.fonts[0]
+        .top_dict
+        .set(Operator::ItalicAngle, vec![Operand::Integer(-12)]);
+    let info = cff.fonts[0].font_info(&cff).unwrap();
+    assert_eq!(info.italic_angle, -12.);
+}
+
+#[test]
+fn test_set_notice() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let mut cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+
+    cff.set_notice("Subset for testing").unwrap();
+    cff.set_copyright("copyright").unwrap();
+    let mut buffer = WriteBuffer::new();
+    CFF::write(&mut buffer, &cff).unwrap();
+    let data = buffer.into_inner();
+    let cff = ReadScope::new(&data).read::<CFF<'_>>().unwrap();
+
+    let top_dict = &cff.fonts[0].top_dict;
+    let notice = top_dict.get_i32(Operator::Notice).unwrap().unwrap();
+    assert_eq!(
+        cff.read_string(u16::try_from(notice).unwrap()).unwrap(),
+        "Subset for testing"
+    );
+    // Copyright is a standard string
+    let copyright = top_dict.get_i32(Operator::Copyright).unwrap().unwrap();
+    assert!(copyright < 391);
+    assert_eq!(
+        cff.read_string(u16::try_from(copyright).unwrap()).unwrap(),
+        "copyright"
+    );
+}
+
+#[test]
+fn test_clear_notice() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let mut cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+
+    cff.set_notice("").unwrap();
+    assert!(cff.fonts[0].top_dict.get(Operator::Notice).is_none());
+}
+
+#[test]
+fn test_convert_type1_to_cid_ros() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+
+    let glyph_ids = (0..300).collect::<Vec<_>>();
+    let (subset_cff, _) = cff.subset(0, &glyph_ids, true).unwrap();
+    assert_eq!(
+        subset_cff.ros(0).unwrap().unwrap(),
+        (String::from("Adobe"), String::from("Identity"), 0)
+    );
+}
+
+#[test]
+fn test_gid_for_name() {
+    let buffer = read_fixture("tests/fonts/opentype/Klei.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+    let font = &cff.fonts[0];
+
+    let glyph_id = cff.gid_for_name("O").expect("glyph O not found");
+    let sid = font.charset.id_for_glyph(glyph_id).unwrap();
+    assert_eq!(cff.read_string(sid).unwrap(), "O");
+    assert_eq!(cff.gid_for_name(".notdef"), Some(0));
+    assert_eq!(cff.gid_for_name("not-a-glyph-name"), None);
+
+    let names = GlyphNameMap::new(&cff, 0).unwrap();
+    assert_eq!(names.gid_for_name("O"), Some(glyph_id));
+    assert_eq!(names.gid_for_name(".notdef"), Some(0));
+    assert_eq!(names.gid_for_name("not-a-glyph-name"), None);
+    assert!(GlyphNameMap::new(&cff, 1).is_none());
+}
+
+#[test]
+fn test_gid_for_name_cid() {
+    let buffer = read_fixture("tests/fonts/noto/NotoSansJP-Regular.otf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+    let cff_table_data = provider.read_table_data(tag::CFF).unwrap();
+    let cff = ReadScope::new(&cff_table_data).read::<CFF<'_>>().unwrap();
+
+    assert_eq!(cff.gid_for_name(".notdef"), None);
+    assert!(GlyphNameMap::new(&cff, 0).is_none());
+}
+
 // Compare two Dicts for equality but allow Operands that are Offsets to differ
 fn compare_dicts<T: DictDefault + Debug>(actual: &Dict<T>, expected: &Dict<T>) {
     let same = actual.len() == expected.len()
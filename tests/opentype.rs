@@ -10,9 +10,12 @@ use std::path::Path;
 use std::rc::Rc;
 
 use allsorts::binary::read::ReadScope;
-use allsorts::error::ShapingError;
+use allsorts::error::{ParseError, ShapingError};
 use allsorts::font_data_impl::FontDataImpl;
-use allsorts::gsub::{gsub_apply_default, GsubFeatureMask};
+use allsorts::gsub::{
+    gsub_apply_default, gsub_apply_default_generic, gsub_would_substitute, list_features,
+    FeatureInfo, GsubFeatureMask, GsubOptions, RawGlyph,
+};
 use allsorts::tables::cmap::{Cmap, CmapSubtable, EncodingId, PlatformId};
 use allsorts::tables::glyf::{
     BoundingBox, GlyfRecord, GlyfTable, Glyph, GlyphData, Point, SimpleGlyph, SimpleGlyphFlag,
@@ -21,7 +24,7 @@ use allsorts::tables::loca::LocaTable;
 use allsorts::tables::{
     FontTableProvider, HeadTable, IndexToLocFormat, MaxpTable, OpenTypeFile, OpenTypeFont,
 };
-use allsorts::tag;
+use allsorts::tag::{self, DisplayTag};
 
 use crate::common::read_fixture;
 
@@ -212,6 +215,7 @@ fn shape<'a, T: FontTableProvider>(
     font: &mut FontDataImpl<T>,
     script_tag: u32,
     opt_lang_tag: Option<u32>,
+    extra_features: &[FeatureInfo],
     text: &str,
 ) -> Result<Vec<u16>, ShapingError> {
     let cmap_subtable_data = font.cmap_subtable_data().to_vec();
@@ -238,7 +242,10 @@ fn shape<'a, T: FontTableProvider>(
         gdef_table.as_ref().map(Rc::as_ref),
         script_tag,
         opt_lang_tag,
-        GsubFeatureMask::default(),
+        GsubOptions {
+            feature_mask: GsubFeatureMask::default(),
+            extra_features,
+        },
         font.num_glyphs(),
         &mut glyphs,
     )?;
@@ -262,7 +269,7 @@ fn test_shape_emoji(text: &str, expected: &[u16]) {
         .expect("error reading font data")
         .expect("missing required font tables");
 
-    let glyph_ids = shape(&mut font_data_impl, tag::LATN, None, text).unwrap();
+    let glyph_ids = shape(&mut font_data_impl, tag::LATN, None, &[], text).unwrap();
     assert_eq!(glyph_ids, expected);
 }
 
@@ -286,6 +293,308 @@ fn test_shape_emoji_zwj_sequence() {
     test_shape_emoji("👨‍👨‍👧‍👦", &[1759]);
 }
 
+#[test]
+fn test_shape_arabic_stylistic_set() {
+    let font_buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let mut font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+    let ss01 = FeatureInfo {
+        feature_tag: tag::from_string("ss01").unwrap(),
+        alternate: None,
+    };
+
+    // uni0624 uni0628.init uni0627.fina
+    let text = "\u{624}\u{628}\u{627}";
+    assert_eq!(
+        shape(&mut font, tag::ARAB, None, &[], text).unwrap(),
+        vec![385, 2089, 2020]
+    );
+    // uni0624 uni0628.init_LD uni0627.fina
+    assert_eq!(
+        shape(&mut font, tag::ARAB, None, &[ss01], text).unwrap(),
+        vec![385, 5511, 2020]
+    );
+}
+
+#[test]
+fn test_shape_localized_forms() {
+    let font_buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let mut font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+
+    // f_i
+    assert_eq!(
+        shape(&mut font, tag::LATN, None, &[], "fi").unwrap(),
+        vec![784]
+    );
+    // The Turkish locl feature substitutes i.TRK, which is applied before liga so the ligature
+    // does not form: f i.TRK
+    assert_eq!(
+        shape(&mut font, tag::LATN, Some(tag::TRK), &[], "fi").unwrap(),
+        vec![73, 6669]
+    );
+}
+
+#[test]
+fn test_shape_required_feature() {
+    let mut font_buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+    let gsub_offset = {
+        let opentype_file = ReadScope::new(&font_buffer)
+            .read::<OpenTypeFile<'_>>()
+            .unwrap();
+        match opentype_file.font {
+            OpenTypeFont::Single(offset_table) => {
+                offset_table.find_table_record(tag::GSUB).unwrap().offset as usize
+            }
+            OpenTypeFont::Collection(_) => unreachable!(),
+        }
+    };
+    // Make ss03 (feature index 18) the required feature of the default LangSys of the latn
+    // script, which is at offset 232 in the GSUB table
+    let required_feature_index = gsub_offset + 232 + 2;
+    assert_eq!(font_buffer[required_feature_index..][..2], [0xFF, 0xFF]);
+    font_buffer[required_feature_index..][..2].copy_from_slice(&[0, 18]);
+
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let mut font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+
+    // ampersand.ara
+    assert_eq!(
+        shape(&mut font, tag::LATN, None, &[], "&").unwrap(),
+        vec![6633]
+    );
+}
+
+/// The indices of the characters a glyph was shaped from
+#[derive(Clone, Debug)]
+struct SourceIndices(Vec<usize>);
+
+impl allsorts::gsub::GlyphData for SourceIndices {
+    fn merge(mut data1: SourceIndices, data2: SourceIndices) -> SourceIndices {
+        data1.0.extend(data2.0);
+        data1
+    }
+}
+
+#[test]
+fn test_shape_generic_glyph_data() {
+    let font_buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let mut font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+    let cmap_subtable_data = font.cmap_subtable_data().to_vec();
+    let cmap_subtable = ReadScope::new(&cmap_subtable_data)
+        .read::<CmapSubtable<'_>>()
+        .unwrap();
+
+    let mut glyphs = "Office"
+        .chars()
+        .enumerate()
+        .map(|(i, ch)| {
+            let glyph = shape::map_glyph(&cmap_subtable, ch).unwrap().unwrap();
+            RawGlyph {
+                unicodes: glyph.unicodes,
+                glyph_index: glyph.glyph_index,
+                liga_component_pos: glyph.liga_component_pos,
+                cluster: glyph.cluster,
+                glyph_origin: glyph.glyph_origin,
+                small_caps: glyph.small_caps,
+                multi_subst_dup: glyph.multi_subst_dup,
+                is_vert_alt: glyph.is_vert_alt,
+                fake_bold: glyph.fake_bold,
+                fake_italic: glyph.fake_italic,
+                variation: glyph.variation,
+                extra_data: SourceIndices(vec![i]),
+            }
+        })
+        .collect::<Vec<_>>();
+    let gsub_cache = font.gsub_cache().unwrap().unwrap();
+    let gdef_table = font.gdef_table().unwrap();
+
+    gsub_apply_default_generic(
+        &gsub_cache,
+        gdef_table.as_ref().map(Rc::as_ref),
+        tag::LATN,
+        None,
+        GsubFeatureMask::default(),
+        font.num_glyphs(),
+        &mut glyphs,
+    )
+    .unwrap();
+
+    // O ffi c e
+    let source_indices = glyphs
+        .iter()
+        .map(|g| g.extra_data.0.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        source_indices,
+        vec![vec![0], vec![1, 2, 3], vec![4], vec![5]]
+    );
+
+    // Arabic needs joining analysis, which is not done for glyphs with other data
+    let res = gsub_apply_default_generic(
+        &gsub_cache,
+        gdef_table.as_ref().map(Rc::as_ref),
+        tag::ARAB,
+        None,
+        GsubFeatureMask::default(),
+        font.num_glyphs(),
+        &mut glyphs,
+    );
+    match res {
+        Err(ShapingError::Parse(ParseError::NotImplemented)) => {}
+        _ => panic!("expected ParseError::NotImplemented, got {:?}", res),
+    }
+}
+
+#[test]
+fn test_shape_fraction_slash() {
+    let font_buffer = read_fixture("tests/fonts/gujarati/padmaa.ttf");
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let mut font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+    let gsub_cache = font.gsub_cache().unwrap().unwrap();
+    let gdef_table = font.gdef_table().unwrap();
+
+    // The font does not map U+2044 FRACTION SLASH, so map it to the glyph for '/'
+    let mut glyphs = vec![
+        shape::make_glyph('1', 20),
+        shape::make_glyph('\u{2044}', 18),
+        shape::make_glyph('2', 21),
+    ];
+    gsub_apply_default(
+        &|| Vec::new(),
+        &gsub_cache,
+        gdef_table.as_ref().map(Rc::as_ref),
+        tag::LATN,
+        None,
+        GsubFeatureMask::default() | GsubFeatureMask::FRAC,
+        font.num_glyphs(),
+        &mut glyphs,
+    )
+    .unwrap();
+
+    // onehalf
+    let glyph_ids = glyphs.iter().map(|g| g.glyph_index).collect::<Vec<_>>();
+    assert_eq!(glyph_ids, vec![134]);
+}
+
+#[test]
+fn test_gsub_would_substitute() {
+    let font_buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let mut font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+    let cmap_subtable_data = font.cmap_subtable_data().to_vec();
+    let cmap_subtable = ReadScope::new(&cmap_subtable_data)
+        .read::<CmapSubtable<'_>>()
+        .unwrap();
+    let gsub_cache = font.gsub_cache().unwrap().unwrap();
+    let gdef_table = font.gdef_table().unwrap();
+
+    let would_substitute = |text: &str, feature_tag: u32| {
+        let glyphs = text
+            .chars()
+            .map(|ch| shape::map_glyph(&cmap_subtable, ch).unwrap().unwrap())
+            .collect::<Vec<_>>();
+        gsub_would_substitute(
+            &gsub_cache,
+            gdef_table.as_ref().map(Rc::as_ref),
+            tag::LATN,
+            None,
+            feature_tag,
+            &glyphs,
+        )
+        .unwrap()
+    };
+
+    // The ffi ligature is found anywhere in the glyph run
+    assert!(would_substitute("Office", tag::LIGA));
+    assert!(would_substitute("ffi", tag::LIGA));
+    assert!(!would_substitute("Oce", tag::LIGA));
+    assert!(!would_substitute("", tag::LIGA));
+    // The font has no dlig feature
+    assert!(!would_substitute("Office", tag::DLIG));
+}
+
+#[test]
+fn test_list_features() {
+    let font_buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+    let opentype_file = ReadScope::new(&font_buffer)
+        .read::<OpenTypeFile<'_>>()
+        .unwrap();
+    let font_table_provider = opentype_file
+        .font_provider(0)
+        .expect("error reading font file");
+    let mut font = FontDataImpl::new(Box::new(font_table_provider))
+        .expect("error reading font data")
+        .expect("missing required font tables");
+    let gsub_cache = font
+        .gsub_cache()
+        .expect("unable to get gsub cache")
+        .expect("missing gsub table");
+
+    let features = list_features(&gsub_cache, tag::LATN, None).unwrap();
+    let tags = features
+        .iter()
+        .map(|&feature_tag| DisplayTag(feature_tag).to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tags,
+        vec![
+            "calt", "ccmp", "dnom", "fina", "init", "liga", "medi", "numr", "pnum", "rlig", "rtlm",
+            "ss01", "ss02", "ss03", "ss04", "ss05", "ss06"
+        ]
+    );
+
+    // The Turkish language system adds locl
+    let features = list_features(&gsub_cache, tag::LATN, Some(tag::TRK)).unwrap();
+    assert!(features.contains(&tag::LOCL));
+    assert!(!list_features(&gsub_cache, tag::LATN, None)
+        .unwrap()
+        .contains(&tag::LOCL));
+}
+
 #[test]
 fn test_reverse_chaining_contextual_single_substitution() {
     let font_buffer = read_fixture("tests/fonts/opentype/Ubuntu Mono with Numderline.ttf");
@@ -436,7 +745,7 @@ fn test_reverse_chaining_contextual_single_substitution() {
 
     for (input, output) in test_cases {
         assert_eq!(
-            shape(&mut font, script_tag, opt_lang_tag, input).unwrap(),
+            shape(&mut font, script_tag, opt_lang_tag, &[], input).unwrap(),
             output,
         );
     }
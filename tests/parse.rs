@@ -0,0 +1,78 @@
+//! Parsing with just the table parsing core, which is all that's available without the `std`
+//! feature.
+//!
+//! Run with `cargo test --no-default-features --test parse`.
+
+// Workaround rustfmt bug:
+// https://github.com/rust-lang/rustfmt/issues/3794
+#[path = "common.rs"]
+mod common;
+
+use std::convert::TryFrom;
+
+use allsorts::binary::read::ReadScope;
+use allsorts::tables::cmap::{Cmap, CmapSubtable, EncodingId, PlatformId};
+use allsorts::tables::glyf::GlyfTable;
+use allsorts::tables::loca::LocaTable;
+use allsorts::tables::{
+    FontTableProvider, HeadTable, HheaTable, HmtxTable, MaxpTable, OpenTypeFile,
+};
+use allsorts::tag;
+
+use crate::common::read_fixture;
+
+#[test]
+fn parse_core_tables() {
+    let buffer = read_fixture("tests/fonts/arabic/amiri-regular.ttf");
+    let opentype_file = ReadScope::new(&buffer).read::<OpenTypeFile<'_>>().unwrap();
+    let provider = opentype_file.font_provider(0).unwrap();
+
+    let cmap_data = provider.read_table_data(tag::CMAP).unwrap();
+    let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>().unwrap();
+    let encoding_record = cmap
+        .find_subtable(PlatformId::WINDOWS, EncodingId::WINDOWS_UNICODE_BMP_UCS2)
+        .unwrap();
+    let cmap_subtable = ReadScope::new(&cmap_data)
+        .offset(usize::try_from(encoding_record.offset).unwrap())
+        .read::<CmapSubtable<'_>>()
+        .unwrap();
+    // A
+    let glyph_id = cmap_subtable.map_glyph('A' as u32).unwrap().unwrap();
+    assert_eq!(glyph_id, 36);
+
+    let head = ReadScope::new(&provider.read_table_data(tag::HEAD).unwrap())
+        .read::<HeadTable>()
+        .unwrap();
+    let maxp = ReadScope::new(&provider.read_table_data(tag::MAXP).unwrap())
+        .read::<MaxpTable>()
+        .unwrap();
+    let loca_data = provider.read_table_data(tag::LOCA).unwrap();
+    let loca = ReadScope::new(&loca_data)
+        .read_dep::<LocaTable<'_>>((usize::from(maxp.num_glyphs), head.index_to_loc_format))
+        .unwrap();
+    let glyf_data = provider.read_table_data(tag::GLYF).unwrap();
+    let glyf = ReadScope::new(&glyf_data)
+        .read_dep::<GlyfTable<'_>>(&loca)
+        .unwrap();
+    let bounding_box = glyf.records[usize::from(glyph_id)]
+        .bounding_box()
+        .unwrap()
+        .unwrap();
+    assert_eq!(bounding_box.x_max, 1264);
+
+    let hhea = ReadScope::new(&provider.read_table_data(tag::HHEA).unwrap())
+        .read::<HheaTable>()
+        .unwrap();
+    let hmtx_data = provider.read_table_data(tag::HMTX).unwrap();
+    let hmtx = ReadScope::new(&hmtx_data)
+        .read_dep::<HmtxTable<'_>>((
+            usize::from(maxp.num_glyphs),
+            usize::from(hhea.num_h_metrics),
+        ))
+        .unwrap();
+    assert_eq!(
+        hmtx.horizontal_advance(glyph_id, hhea.num_h_metrics)
+            .unwrap(),
+        1254
+    );
+}
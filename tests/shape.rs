@@ -40,6 +40,7 @@ pub fn make_glyph(ch: char, glyph_index: u16) -> RawGlyph<()> {
         unicodes: tiny_vec![[char; 1] => ch],
         glyph_index: glyph_index,
         liga_component_pos: 0,
+        cluster: 0,
         glyph_origin: GlyphOrigin::Char(ch),
         small_caps: false,
         multi_subst_dup: false,